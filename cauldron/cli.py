@@ -1488,8 +1488,11 @@ def _build_control_block(
     input_len: int,
     output_ptr: int,
     output_len: int,
+    vm_seed: int = 0,
 ) -> bytes:
-    return _h.build_control_block(control_size, input_ptr, input_len, output_ptr, output_len)
+    return _h.build_control_block(
+        control_size, input_ptr, input_len, output_ptr, output_len, vm_seed
+    )
 
 
 def _write_account(
@@ -1551,14 +1554,6 @@ def _cmd_input_write(args: argparse.Namespace) -> int:
     if output_max <= 0:
         raise ValueError("abi.output_max must be positive")
 
-    control_bytes = _build_control_block(
-        control_size,
-        input_offset,
-        len(payload_bytes),
-        output_offset,
-        0,
-    )
-
     info, _ = _accounts_segment_metas(
         args.accounts,
         program_id_override=args.program_id,
@@ -1568,6 +1563,16 @@ def _cmd_input_write(args: argparse.Namespace) -> int:
     if not vm_pubkey:
         raise ValueError("accounts file missing vm pubkey")
 
+    vm_seed = info.get("vm_seed")
+    control_bytes = _build_control_block(
+        control_size,
+        input_offset,
+        len(payload_bytes),
+        output_offset,
+        0,
+        int(vm_seed) if isinstance(vm_seed, str) and vm_seed else 0,
+    )
+
     env = os.environ.copy()
     if args.rpc_url:
         env["FROSTBITE_RPC_URL"] = args.rpc_url
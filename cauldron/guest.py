@@ -10,7 +10,7 @@ from typing import Any, Dict, Optional
 from .constants import DEFAULT_SCRATCH_MIN, MIN_RESERVED_TAIL
 from .convert import infer_template
 from .manifest import load_manifest
-from .schema import SCHEMA_IDS, parse_hash32, schema_hash32
+from .schema import SCHEMA_IDS, parse_hash32, schema_canonical_bytes, schema_hash32
 from .util import product
 
 
@@ -71,6 +71,7 @@ class GuestConfig:
     stride: Optional[int] = None
     out_channels: Optional[int] = None
     conv_offset: Optional[int] = None
+    schema_canonical: Optional[bytes] = None
 
 
 def _get_table(manifest: Dict[str, Any], name: str) -> Dict[str, Any]:
@@ -267,6 +268,8 @@ def generate_guest_config(
         expected_schema_id=schema_id,
         expected_schema_hash=expected_hash,
     )
+    if schema_hash_mode == "auto":
+        config.schema_canonical = schema_canonical_bytes(manifest)
 
     if resolved_template in ("linear", "mlp", "mlp2", "mlp3", "softmax", "naive_bayes", "tree", "cnn1d", "tiny_cnn"):
         if input_dim is None or output_dim is None:
@@ -463,6 +466,19 @@ def generate_guest_config(
     return config
 
 
+def _rust_byte_string(data: bytes) -> str:
+    out = []
+    for b in data:
+        ch = chr(b)
+        if ch in ('"', "\\"):
+            out.append("\\" + ch)
+        elif 0x20 <= b < 0x7F:
+            out.append(ch)
+        else:
+            out.append(f"\\x{b:02x}")
+    return 'b"' + "".join(out) + '"'
+
+
 def render_config(config: GuestConfig) -> str:
     lines = ["//! Auto-generated config constants (patched by Cauldron).", ""]
     lines.append(f"pub const CONTROL_OFFSET: usize = 0x{config.control_offset:04X};")
@@ -587,7 +603,15 @@ def render_config(config: GuestConfig) -> str:
         lines.append(f"pub const OUTPUT_BLOB_SIZE: usize = {config.output_blob_size};")
 
     lines.append("")
-    lines.append(f"pub const EXPECTED_SCHEMA_HASH: u32 = 0x{config.expected_schema_hash:08X};")
+    if config.schema_canonical is not None:
+        # Derive the hash in the guest so it cannot drift from the SDK's algorithm.
+        lines.append(f"pub const SCHEMA_CANONICAL: &[u8] = {_rust_byte_string(config.schema_canonical)};")
+        lines.append(
+            "pub const EXPECTED_SCHEMA_HASH: u32 = frostbite_sdk::schema_hash(SCHEMA_CANONICAL);"
+            f" // 0x{config.expected_schema_hash:08X}"
+        )
+    else:
+        lines.append(f"pub const EXPECTED_SCHEMA_HASH: u32 = 0x{config.expected_schema_hash:08X};")
     lines.append(f"pub const EXPECTED_SCHEMA_ID: u32 = {config.expected_schema_id};")
     lines.append("")
     return "\n".join(lines)
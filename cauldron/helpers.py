@@ -269,7 +269,7 @@ def fetch_account_data(
 def parse_control_block(scratch: bytes, control_offset: int) -> dict[str, int]:
     if control_offset < 0 or control_offset + 64 > len(scratch):
         raise ValueError("control block out of bounds")
-    fields = struct.unpack_from("<IIIIIIIIIIIIQ", scratch, control_offset)
+    fields = struct.unpack_from("<IIIIIIIIIIIIQQ", scratch, control_offset)
     keys = [
         "magic",
         "abi_version",
@@ -284,6 +284,7 @@ def parse_control_block(scratch: bytes, control_offset: int) -> dict[str, int]:
         "user_ptr",
         "user_len",
         "reserved0",
+        "vm_seed",
     ]
     return dict(zip(keys, fields))
 
@@ -353,6 +354,7 @@ def build_control_block(
     input_len: int,
     output_ptr: int,
     output_len: int,
+    vm_seed: int = 0,
 ) -> bytes:
     if control_size < 64:
         raise ValueError("abi.control_size must be >= 64")
@@ -364,10 +366,12 @@ def build_control_block(
     ):
         if value < 0 or value > 0xFFFF_FFFF:
             raise ValueError(f"{name} must fit in u32")
+    if vm_seed < 0 or vm_seed > 0xFFFF_FFFF_FFFF_FFFF:
+        raise ValueError("vm_seed must fit in u64")
 
     buf = bytearray(control_size)
     struct.pack_into(
-        "<IIIIIIIIIIIIQ",
+        "<IIIIIIIIIIIIQQ",
         buf,
         0,
         FBM1_MAGIC,
@@ -383,6 +387,7 @@ def build_control_block(
         0,
         0,
         0,
+        vm_seed,
     )
     return bytes(buf)
 
@@ -0,0 +1,97 @@
+use frostbite_modelkit_tools::cli_config::detect_program_id;
+use frostbite_modelkit_tools::seeds::{
+    derive_seeded_address, kind_name, parse_segment_kind, parse_u64_value, segment_seed_string,
+    vm_seed_string,
+};
+use solana_sdk::pubkey::Pubkey;
+use std::env;
+use std::str::FromStr;
+
+struct DeriveArgs {
+    vm_seed: u64,
+    authority: Pubkey,
+    program_id: Option<Pubkey>,
+    segment: Option<(u8, u8)>,
+}
+
+/// Prints the seeded VM (and optionally segment) address for a seed, without
+/// touching the network, to compare against what another tool derived.
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let args = parse_args()?;
+    let program_id = match args.program_id {
+        Some(program_id) => program_id,
+        None => detect_program_id()?,
+    };
+
+    println!("Program: {}", program_id);
+    println!("Authority: {}", args.authority);
+
+    let vm_seed = vm_seed_string(args.vm_seed);
+    let vm = derive_seeded_address(&args.authority, &vm_seed, &program_id)?;
+    println!("VM: {} (seed {})", vm, vm_seed);
+
+    if let Some((kind, slot)) = args.segment {
+        let segment_seed = segment_seed_string(args.vm_seed, kind, slot);
+        let segment = derive_seeded_address(&args.authority, &segment_seed, &program_id)?;
+        println!(
+            "Segment {} slot {}: {} (seed {})",
+            kind_name(kind),
+            slot,
+            segment,
+            segment_seed
+        );
+    }
+    Ok(())
+}
+
+fn parse_args() -> Result<DeriveArgs, Box<dyn std::error::Error>> {
+    let args: Vec<String> = env::args().collect();
+    if args.len() < 5 {
+        eprintln!(
+            "Usage: derive_addr --vm-seed <u64> --authority <pubkey> [--program-id <pubkey>] [--kind <weights|ram> --slot <u8>]"
+        );
+        return Err("missing required args".into());
+    }
+
+    let mut vm_seed: Option<u64> = None;
+    let mut authority: Option<Pubkey> = None;
+    let mut program_id: Option<Pubkey> = None;
+    let mut kind: Option<u8> = None;
+    let mut slot: Option<u8> = None;
+
+    let mut idx = 1usize;
+    while idx < args.len() {
+        let flag = args[idx].as_str();
+        idx += 1;
+        let value = args
+            .get(idx)
+            .ok_or_else(|| format!("missing value for {}", flag))?;
+        match flag {
+            "--vm-seed" => vm_seed = Some(parse_u64_value(value)?),
+            "--authority" => authority = Some(Pubkey::from_str(value.trim())?),
+            "--program-id" => program_id = Some(Pubkey::from_str(value.trim())?),
+            "--kind" => kind = Some(parse_segment_kind(value)?),
+            "--slot" => {
+                let parsed_slot = parse_u64_value(value)?;
+                if !(1..=15).contains(&parsed_slot) {
+                    return Err("slot must be in 1..=15".into());
+                }
+                slot = Some(parsed_slot as u8);
+            }
+            other => return Err(format!("unknown argument: {}", other).into()),
+        }
+        idx += 1;
+    }
+
+    let segment = match (kind, slot) {
+        (Some(kind), Some(slot)) => Some((kind, slot)),
+        (None, None) => None,
+        _ => return Err("--kind and --slot must be given together".into()),
+    };
+    Ok(DeriveArgs {
+        vm_seed: vm_seed.ok_or("missing --vm-seed")?,
+        authority: authority.ok_or("missing --authority")?,
+        program_id,
+        segment,
+    })
+}
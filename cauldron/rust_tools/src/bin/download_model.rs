@@ -0,0 +1,159 @@
+use frostbite_modelkit_tools::cli_config::{
+    detect_program_id, expand_path, load_solana_cli_config, CliConfig, DEFAULT_PAYER_KEYPAIR,
+    DEFAULT_RPC_URL, DEFAULT_SOLANA_CONFIG,
+};
+use frostbite_modelkit_tools::seeds::{
+    derive_segment_pda, kind_name, parse_segment_kind, parse_u64_value, SEGMENT_KIND_WEIGHTS,
+};
+use frostbite_modelkit_tools::upload::{segment_payload, sha256_hex};
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::{commitment_config::CommitmentConfig, pubkey::Pubkey, signature::Signer};
+use std::env;
+use std::str::FromStr;
+
+enum Source {
+    Seeded { vm_seed: u64, kind: u8, slot: u8 },
+    Pubkey(Pubkey),
+}
+
+struct Args {
+    source: Source,
+    authority: Option<Pubkey>,
+    out: String,
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let args: Vec<String> = env::args().collect();
+    let parsed = match parse_args(&args)? {
+        Some(parsed) => parsed,
+        None => {
+            print_usage();
+            return Ok(());
+        }
+    };
+
+    let solana_config_path =
+        env::var("SOLANA_CONFIG").unwrap_or_else(|_| DEFAULT_SOLANA_CONFIG.to_string());
+    let cli_config = load_solana_cli_config(&solana_config_path);
+    let rpc_url = env::var("FROSTBITE_RPC_URL")
+        .ok()
+        .or_else(|| cli_config.as_ref().and_then(|cfg| cfg.rpc_url.clone()))
+        .unwrap_or_else(|| DEFAULT_RPC_URL.to_string());
+    let client = RpcClient::new_with_commitment(rpc_url.clone(), CommitmentConfig::confirmed());
+    println!("RPC: {}", rpc_url);
+
+    let (account, label) = match parsed.source {
+        Source::Pubkey(pubkey) => (pubkey, "Account".to_string()),
+        Source::Seeded {
+            vm_seed,
+            kind,
+            slot,
+        } => {
+            let authority = match parsed.authority {
+                Some(pubkey) => pubkey,
+                None => resolve_authority(cli_config.as_ref())?,
+            };
+            let program_id = detect_program_id()?;
+            println!("Authority: {}", authority);
+            println!("Program ID: {}", program_id);
+            let segment = derive_segment_pda(&program_id, &authority, vm_seed, kind, slot)?;
+            (
+                segment,
+                format!("Segment PDA (kind={}, slot={})", kind_name(kind), slot),
+            )
+        }
+    };
+    println!("{}: {}", label, account);
+
+    let data = client
+        .get_account_data(&account)
+        .await
+        .map_err(|err| format!("failed to fetch {}: {}", account, err))?;
+    let payload = segment_payload(&data).map_err(|err| format!("{}: {}", account, err))?;
+
+    let out = expand_path(&parsed.out);
+    std::fs::write(&out, payload)?;
+    println!("Wrote {} bytes to {}", payload.len(), out);
+    println!("SHA-256: {}", sha256_hex(payload));
+    Ok(())
+}
+
+fn print_usage() {
+    println!("Usage:");
+    println!("  cargo run --bin download_model -- --vm-seed <u64> [--kind weights|ram] [--slot <1..15>] [--authority <pubkey>] --out <file>");
+    println!("  cargo run --bin download_model -- --pubkey <account> --out <file>");
+}
+
+fn parse_args(args: &[String]) -> Result<Option<Args>, Box<dyn std::error::Error>> {
+    if args.len() <= 1 || args.iter().any(|arg| arg == "-h" || arg == "--help") {
+        return Ok(None);
+    }
+
+    let mut vm_seed = None;
+    let mut kind = SEGMENT_KIND_WEIGHTS;
+    let mut slot = 1u8;
+    let mut pubkey = None;
+    let mut authority = None;
+    let mut out = None;
+
+    let mut idx = 1;
+    while idx < args.len() {
+        let flag = args[idx].as_str();
+        idx += 1;
+        if idx >= args.len() {
+            return Err(format!("missing value for {}", flag).into());
+        }
+        let value = args[idx].as_str();
+        match flag {
+            "--vm-seed" => vm_seed = Some(parse_u64_value(value)?),
+            "--kind" => kind = parse_segment_kind(value)?,
+            "--slot" => {
+                let parsed = parse_u64_value(value)?;
+                if !(1..=15).contains(&parsed) {
+                    return Err("--slot must be in range 1..=15".into());
+                }
+                slot = parsed as u8;
+            }
+            "--pubkey" => pubkey = Some(Pubkey::from_str(value)?),
+            "--authority" => authority = Some(Pubkey::from_str(value)?),
+            "--out" => out = Some(value.to_string()),
+            _ => return Err(format!("unknown argument: {}", flag).into()),
+        }
+        idx += 1;
+    }
+
+    let source = match (vm_seed, pubkey) {
+        (Some(vm_seed), None) => Source::Seeded {
+            vm_seed,
+            kind,
+            slot,
+        },
+        (None, Some(pubkey)) => Source::Pubkey(pubkey),
+        (Some(_), Some(_)) => return Err("use either --vm-seed or --pubkey, not both".into()),
+        (None, None) => return Err("--vm-seed or --pubkey is required".into()),
+    };
+    let out = out.ok_or("--out is required")?;
+    Ok(Some(Args {
+        source,
+        authority,
+        out,
+    }))
+}
+
+/// Seeded addresses only need the authority pubkey, so a hint or either
+/// keypair file is enough; nothing is signed.
+fn resolve_authority(cli_config: Option<&CliConfig>) -> Result<Pubkey, Box<dyn std::error::Error>> {
+    if let Ok(hint) = env::var("FROSTBITE_AUTHORITY_PUBKEY") {
+        return Ok(Pubkey::from_str(&hint)?);
+    }
+    let path = env::var("FROSTBITE_AUTHORITY_KEYPAIR")
+        .or_else(|_| env::var("FROSTBITE_PAYER_KEYPAIR"))
+        .ok()
+        .or_else(|| cli_config.and_then(|cfg| cfg.keypair_path.clone()))
+        .unwrap_or_else(|| DEFAULT_PAYER_KEYPAIR.to_string());
+    let path = expand_path(&path);
+    let keypair = solana_sdk::signature::read_keypair_file(&path)
+        .map_err(|_| format!("Could not find authority keypair at {}", path))?;
+    Ok(keypair.pubkey())
+}
@@ -1,3 +1,14 @@
+use frostbite_modelkit_tools::cli_config::{
+    detect_program_id, expand_path, load_solana_cli_config, DEFAULT_PAYER_KEYPAIR, DEFAULT_RPC_URL,
+    DEFAULT_SOLANA_CONFIG,
+};
+use frostbite_modelkit_tools::retry::RetryPolicy;
+use frostbite_modelkit_tools::seeds::{
+    derive_seeded_address, kind_name, parse_u64_value, segment_seed_string, vm_seed_string,
+};
+use frostbite_modelkit_tools::segments::{parse_segment_spec, validate_segment_specs, SegmentSpec};
+use frostbite_modelkit_tools::upload::BINARY_HEADER_SIZE;
+use frostbite_modelkit_tools::vm::VM_ACCOUNT_SIZE;
 use solana_client::nonblocking::rpc_client::RpcClient;
 use solana_sdk::{
     commitment_config::CommitmentConfig,
@@ -8,36 +19,12 @@ use solana_sdk::{
     transaction::Transaction,
 };
 use std::env;
-use std::path::PathBuf;
 use std::str::FromStr;
 use std::sync::Arc;
 
-const DEFAULT_SOLANA_CONFIG: &str = "~/.config/solana/cli/config.yml";
-const DEFAULT_RPC_URL: &str = "http://127.0.0.1:8899";
-const DEFAULT_PAYER_KEYPAIR: &str = "~/.config/solana/id.json";
-const DEFAULT_PROGRAM_ID: &str = "FRsToriMLgDc1Ud53ngzHUZvCRoazCaGeGUuzkwoha7m";
-
 const OP_INIT_VM_SEEDED: u8 = 40;
 const OP_INIT_SEGMENT_SEEDED: u8 = 41;
 
-const SEEDED_VM_PREFIX: &str = "fbv1:vm:";
-const SEEDED_SEG_PREFIX: &str = "fbv1:sg:";
-
-const SEGMENT_KIND_WEIGHTS: u8 = 1;
-const SEGMENT_KIND_RAM: u8 = 2;
-
-const VM_MEMORY_SIZE: usize = 262_144;
-const VM_MEMORY_OFFSET: usize = 552;
-const VM_ACCOUNT_SIZE: usize = VM_MEMORY_OFFSET + VM_MEMORY_SIZE;
-const SEGMENT_HEADER_SIZE: usize = 12;
-
-#[derive(Clone, Copy)]
-struct SegmentSpec {
-    kind: u8,
-    slot: u8,
-    payload_len: u32,
-}
-
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let (vm_seed, segments) = parse_args()?;
@@ -126,7 +113,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         let segment_pubkey =
             derive_seeded_address(&authority.pubkey(), &segment_seed, &program_id)?;
         let payload_len = segment.payload_len as usize;
-        let required_space = SEGMENT_HEADER_SIZE
+        let required_space = BINARY_HEADER_SIZE
             .checked_add(payload_len)
             .ok_or("segment size overflow")?;
 
@@ -211,80 +198,10 @@ fn parse_args() -> Result<(u64, Vec<SegmentSpec>), Box<dyn std::error::Error>> {
     }
 
     let vm_seed = vm_seed.ok_or("missing --vm-seed")?;
+    validate_segment_specs(&segments)?;
     Ok((vm_seed, segments))
 }
 
-fn parse_segment_spec(raw: &str) -> Result<SegmentSpec, Box<dyn std::error::Error>> {
-    let parts: Vec<&str> = raw.split(':').collect();
-    if parts.len() != 3 {
-        return Err("segment spec must be kind:slot:bytes".into());
-    }
-    let kind = parse_segment_kind(parts[0])?;
-    let slot = parse_u64_value(parts[1])?;
-    if !(1..=15).contains(&slot) {
-        return Err("segment slot must be in 1..=15".into());
-    }
-    let payload_len = parse_u64_value(parts[2])?;
-    if payload_len > u32::MAX as u64 {
-        return Err("segment payload bytes exceed u32::MAX".into());
-    }
-    Ok(SegmentSpec {
-        kind,
-        slot: slot as u8,
-        payload_len: payload_len as u32,
-    })
-}
-
-fn parse_segment_kind(raw: &str) -> Result<u8, Box<dyn std::error::Error>> {
-    let lowered = raw.trim().to_ascii_lowercase();
-    match lowered.as_str() {
-        "1" | "weights" => Ok(SEGMENT_KIND_WEIGHTS),
-        "2" | "ram" => Ok(SEGMENT_KIND_RAM),
-        _ => Err(format!("unsupported segment kind '{}'", raw).into()),
-    }
-}
-
-fn kind_name(kind: u8) -> &'static str {
-    match kind {
-        SEGMENT_KIND_WEIGHTS => "weights",
-        SEGMENT_KIND_RAM => "ram",
-        _ => "unknown",
-    }
-}
-
-fn parse_u64_value(raw: &str) -> Result<u64, Box<dyn std::error::Error>> {
-    let trimmed = raw.trim();
-    if trimmed.is_empty() {
-        return Err("numeric value cannot be empty".into());
-    }
-    if let Some(hex) = trimmed
-        .strip_prefix("0x")
-        .or_else(|| trimmed.strip_prefix("0X"))
-    {
-        return Ok(u64::from_str_radix(hex, 16)?);
-    }
-    Ok(trimmed.parse::<u64>()?)
-}
-
-fn vm_seed_string(vm_seed: u64) -> String {
-    format!("{}{vm_seed:016x}", SEEDED_VM_PREFIX)
-}
-
-fn segment_seed_string(vm_seed: u64, kind: u8, slot: u8) -> String {
-    format!("{}{vm_seed:016x}:{kind:02x}{slot:02x}", SEEDED_SEG_PREFIX)
-}
-
-fn derive_seeded_address(
-    authority: &Pubkey,
-    seed: &str,
-    program_id: &Pubkey,
-) -> Result<Pubkey, Box<dyn std::error::Error>> {
-    if seed.len() > 32 {
-        return Err(format!("seed exceeds 32 bytes: {}", seed).into());
-    }
-    Ok(Pubkey::create_with_seed(authority, seed, program_id)?)
-}
-
 async fn ensure_seeded_program_account(
     client: &RpcClient,
     fee_payer: &Keypair,
@@ -334,13 +251,17 @@ async fn send_instruction(
     instruction: Instruction,
 ) -> Result<(), Box<dyn std::error::Error>> {
     let signers = build_signers(fee_payer, authority);
-    let tx = Transaction::new_signed_with_payer(
-        &[instruction],
-        Some(&fee_payer.pubkey()),
-        &signers,
-        client.get_latest_blockhash().await?,
-    );
-    client.send_and_confirm_transaction(&tx).await?;
+    RetryPolicy::from_env()
+        .run_async(|| async {
+            let tx = Transaction::new_signed_with_payer(
+                std::slice::from_ref(&instruction),
+                Some(&fee_payer.pubkey()),
+                &signers,
+                client.get_latest_blockhash().await?,
+            );
+            client.send_and_confirm_transaction(&tx).await
+        })
+        .await?;
     Ok(())
 }
 
@@ -351,99 +272,3 @@ fn build_signers<'a>(fee_payer: &'a Keypair, authority: &'a Keypair) -> Vec<&'a
     }
     signers
 }
-
-fn detect_program_id() -> Result<Pubkey, Box<dyn std::error::Error>> {
-    if let Ok(id) = env::var("FROSTBITE_PROGRAM_ID") {
-        return Ok(Pubkey::from_str(&id)?);
-    }
-    if let Ok(path) = env::var("FROSTBITE_PROGRAM_KEYPAIR") {
-        return Ok(read_program_keypair(&path)?);
-    }
-    if let Some(path) = find_program_keypair() {
-        return Ok(read_program_keypair(path.to_str().unwrap_or_default())?);
-    }
-    Ok(Pubkey::from_str(DEFAULT_PROGRAM_ID)?)
-}
-
-fn read_program_keypair(path: &str) -> Result<Pubkey, Box<dyn std::error::Error>> {
-    let data = std::fs::read_to_string(path)?;
-    let bytes: Vec<u8> = serde_json::from_str(&data)?;
-    let keypair = Keypair::from_bytes(&bytes)?;
-    Ok(keypair.pubkey())
-}
-
-fn find_program_keypair() -> Option<PathBuf> {
-    let mut candidates = Vec::new();
-    if let Ok(home) = env::var("FROSTBITE_HOME") {
-        candidates.push(PathBuf::from(format!(
-            "{}/target/deploy/frostbite-keypair.json",
-            home.trim_end_matches('/')
-        )));
-    }
-
-    if let Ok(cwd) = env::current_dir() {
-        for rel in [
-            "target/deploy/frostbite-keypair.json",
-            "../target/deploy/frostbite-keypair.json",
-            "../../target/deploy/frostbite-keypair.json",
-            "../../../target/deploy/frostbite-keypair.json",
-        ] {
-            candidates.push(cwd.join(rel));
-        }
-    }
-
-    for path in candidates {
-        if path.exists() {
-            return Some(path);
-        }
-    }
-    None
-}
-
-#[derive(Default)]
-struct CliConfig {
-    rpc_url: Option<String>,
-    keypair_path: Option<String>,
-}
-
-fn load_solana_cli_config(path: &str) -> Option<CliConfig> {
-    let path = expand_path(path);
-    let contents = std::fs::read_to_string(&path).ok()?;
-    let mut cfg = CliConfig::default();
-    for raw_line in contents.lines() {
-        let line = raw_line.trim();
-        if line.is_empty() || line.starts_with('#') {
-            continue;
-        }
-        if let Some(value) = parse_yaml_value(line, "json_rpc_url") {
-            cfg.rpc_url = Some(value);
-            continue;
-        }
-        if let Some(value) = parse_yaml_value(line, "keypair_path") {
-            cfg.keypair_path = Some(value);
-        }
-    }
-    Some(cfg)
-}
-
-fn parse_yaml_value(line: &str, key: &str) -> Option<String> {
-    let mut parts = line.splitn(2, ':');
-    let left = parts.next()?.trim();
-    if left != key {
-        return None;
-    }
-    let value = parts.next()?.trim();
-    if value.is_empty() {
-        return None;
-    }
-    Some(value.trim_matches('"').trim_matches('\'').to_string())
-}
-
-fn expand_path(path: &str) -> String {
-    if let Some(stripped) = path.strip_prefix("~/") {
-        if let Ok(home) = std::env::var("HOME") {
-            return format!("{}/{}", home, stripped);
-        }
-    }
-    path.to_string()
-}
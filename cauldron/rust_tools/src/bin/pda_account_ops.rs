@@ -1,3 +1,13 @@
+use frostbite_modelkit_tools::cli_config::{
+    detect_program_id, expand_path, load_solana_cli_config, DEFAULT_PAYER_KEYPAIR, DEFAULT_RPC_URL,
+    DEFAULT_SOLANA_CONFIG,
+};
+use frostbite_modelkit_tools::retry::RetryPolicy;
+use frostbite_modelkit_tools::seeds::{
+    derive_segment_pda, derive_vm_pda, kind_name, parse_segment_kind, parse_u64_value,
+    SEGMENT_KIND_RAM, SEGMENT_KIND_WEIGHTS,
+};
+use frostbite_modelkit_tools::segments::{check_clear_range, probe_segment};
 use solana_client::nonblocking::rpc_client::RpcClient;
 use solana_sdk::{
     commitment_config::CommitmentConfig,
@@ -7,25 +17,13 @@ use solana_sdk::{
     transaction::Transaction,
 };
 use std::env;
-use std::path::PathBuf;
 use std::str::FromStr;
 use std::sync::Arc;
 
-const DEFAULT_SOLANA_CONFIG: &str = "~/.config/solana/cli/config.yml";
-const DEFAULT_RPC_URL: &str = "http://127.0.0.1:8899";
-const DEFAULT_PAYER_KEYPAIR: &str = "~/.config/solana/id.json";
-const DEFAULT_PROGRAM_ID: &str = "FRsToriMLgDc1Ud53ngzHUZvCRoazCaGeGUuzkwoha7m";
-
 const OP_CLEAR_SEGMENT_SEEDED: u8 = 46;
 const OP_CLOSE_SEGMENT_SEEDED: u8 = 47;
 const OP_CLOSE_VM_SEEDED: u8 = 48;
 
-const SEEDED_VM_PREFIX: &str = "fbv1:vm:";
-const SEEDED_SEG_PREFIX: &str = "fbv1:sg:";
-
-const SEGMENT_KIND_WEIGHTS: u8 = 1;
-const SEGMENT_KIND_RAM: u8 = 2;
-
 enum Command {
     ClearSegment {
         vm_seed: u64,
@@ -44,6 +42,9 @@ enum Command {
         vm_seed: u64,
         recipient: Pubkey,
     },
+    Inspect {
+        vm_seed: u64,
+    },
 }
 
 #[tokio::main]
@@ -99,6 +100,9 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     println!("Authority: {}", authority.pubkey());
 
     let instruction = match command {
+        Command::Inspect { vm_seed } => {
+            return inspect(&client, &program_id, &authority.pubkey(), vm_seed).await;
+        }
         Command::ClearSegment {
             vm_seed,
             kind,
@@ -119,6 +123,13 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                 clear_len
             );
 
+            let segment = client
+                .get_account(&segment_pda)
+                .await
+                .map_err(|err| format!("failed to fetch segment {}: {}", segment_pda, err))?;
+            check_clear_range(segment.data.len(), payload_offset, clear_len)
+                .map_err(|err| format!("segment {}:{}: {}", kind_name(kind), slot, err))?;
+
             let mut data = Vec::with_capacity(1 + 8 + 1 + 1 + 4 + 4);
             data.push(OP_CLEAR_SEGMENT_SEEDED);
             data.extend_from_slice(&vm_seed.to_le_bytes());
@@ -207,7 +218,7 @@ fn parse_args(default_recipient: Pubkey) -> Result<Command, Box<dyn std::error::
     let args: Vec<String> = env::args().collect();
     if args.len() < 4 {
         eprintln!(
-            "Usage:\n  pda_account_ops clear-segment --vm-seed <u64> --kind <weights|ram> --slot <u8> [--offset <u32>] [--len <u32>]\n  pda_account_ops close-segment --vm-seed <u64> --kind <weights|ram> --slot <u8> [--recipient <pubkey>]\n  pda_account_ops close-vm --vm-seed <u64> [--recipient <pubkey>]"
+            "Usage:\n  pda_account_ops clear-segment --vm-seed <u64> --kind <weights|ram> --slot <u8> [--offset <u32>] [--len <u32>]\n  pda_account_ops close-segment --vm-seed <u64> --kind <weights|ram> --slot <u8> [--recipient <pubkey>]\n  pda_account_ops close-vm --vm-seed <u64> [--recipient <pubkey>]\n  pda_account_ops inspect --vm-seed <u64>"
         );
         return Err("missing required args".into());
     }
@@ -298,78 +309,63 @@ fn parse_args(default_recipient: Pubkey) -> Result<Command, Box<dyn std::error::
             recipient,
         }),
         "close-vm" => Ok(Command::CloseVm { vm_seed, recipient }),
+        "inspect" => Ok(Command::Inspect { vm_seed }),
         _ => Err(format!("unknown action '{}'", action).into()),
     }
 }
 
-fn parse_segment_kind(raw: &str) -> Result<u8, Box<dyn std::error::Error>> {
-    let lowered = raw.trim().to_ascii_lowercase();
-    match lowered.as_str() {
-        "1" | "weights" => Ok(SEGMENT_KIND_WEIGHTS),
-        "2" | "ram" => Ok(SEGMENT_KIND_RAM),
-        _ => Err(format!("unsupported segment kind '{}'", raw).into()),
-    }
-}
-
-fn kind_name(kind: u8) -> &'static str {
-    match kind {
-        SEGMENT_KIND_WEIGHTS => "weights",
-        SEGMENT_KIND_RAM => "ram",
-        _ => "unknown",
-    }
-}
-
-fn parse_u64_value(raw: &str) -> Result<u64, Box<dyn std::error::Error>> {
-    let trimmed = raw.trim();
-    if trimmed.is_empty() {
-        return Err("numeric value cannot be empty".into());
-    }
-    if let Some(hex) = trimmed
-        .strip_prefix("0x")
-        .or_else(|| trimmed.strip_prefix("0X"))
-    {
-        return Ok(u64::from_str_radix(hex, 16)?);
-    }
-    Ok(trimmed.parse::<u64>()?)
-}
-
-fn vm_seed_string(vm_seed: u64) -> String {
-    format!("{}{vm_seed:016x}", SEEDED_VM_PREFIX)
-}
-
-fn derive_segment_pda(
-    program_id: &Pubkey,
-    authority: &Pubkey,
-    vm_seed: u64,
-    kind: u8,
-    slot: u8,
-) -> Result<Pubkey, Box<dyn std::error::Error>> {
-    let seed = segment_seed_string(vm_seed, kind, slot);
-    derive_seeded_address(authority, &seed, program_id)
-}
-
-fn segment_seed_string(vm_seed: u64, kind: u8, slot: u8) -> String {
-    format!("{}{vm_seed:016x}:{kind:02x}{slot:02x}", SEEDED_SEG_PREFIX)
-}
-
-fn derive_vm_pda(
+/// Print the VM account and every weights/ram slot that could exist for the seed.
+async fn inspect(
+    client: &RpcClient,
     program_id: &Pubkey,
     authority: &Pubkey,
     vm_seed: u64,
-) -> Result<Pubkey, Box<dyn std::error::Error>> {
-    let seed = vm_seed_string(vm_seed);
-    derive_seeded_address(authority, &seed, program_id)
-}
-
-fn derive_seeded_address(
-    authority: &Pubkey,
-    seed: &str,
-    program_id: &Pubkey,
-) -> Result<Pubkey, Box<dyn std::error::Error>> {
-    if seed.len() > 32 {
-        return Err(format!("seed exceeds 32 bytes: {}", seed).into());
+) -> Result<(), Box<dyn std::error::Error>> {
+    let vm_pda = derive_vm_pda(program_id, authority, vm_seed)?;
+    let mut pubkeys = vec![vm_pda];
+    let mut slots = Vec::new();
+    for kind in [SEGMENT_KIND_WEIGHTS, SEGMENT_KIND_RAM] {
+        for slot in 1..=15u8 {
+            pubkeys.push(derive_segment_pda(
+                program_id, authority, vm_seed, kind, slot,
+            )?);
+            slots.push((kind, slot));
+        }
+    }
+    let accounts = RetryPolicy::from_env()
+        .run_async(|| client.get_multiple_accounts(&pubkeys))
+        .await?;
+
+    println!("INSPECT vm_seed={}", vm_seed);
+    // The VM account has no RVCD header, so only its size and owner apply.
+    match &accounts[0] {
+        Some(account) => println!(
+            "  vm         {} size={} owner={}",
+            vm_pda,
+            account.data.len(),
+            account.owner
+        ),
+        None => println!("  vm         {} missing", vm_pda),
     }
-    Ok(Pubkey::create_with_seed(authority, seed, program_id)?)
+    let mut missing = 0usize;
+    for ((kind, slot), (pubkey, account)) in slots
+        .iter()
+        .zip(pubkeys[1..].iter().zip(accounts[1..].iter()))
+    {
+        if account.is_none() {
+            missing += 1;
+            continue;
+        }
+        let label = format!("{}:{}", kind_name(*kind), slot);
+        println!(
+            "  {:<10} {} {}",
+            label,
+            pubkey,
+            probe_segment(account.as_ref())
+        );
+    }
+    println!("  ({} of 30 segment slots not created)", missing);
+    Ok(())
 }
 
 async fn send_instruction(
@@ -379,13 +375,17 @@ async fn send_instruction(
     instruction: Instruction,
 ) -> Result<(), Box<dyn std::error::Error>> {
     let signers = build_signers(fee_payer, authority);
-    let tx = Transaction::new_signed_with_payer(
-        &[instruction],
-        Some(&fee_payer.pubkey()),
-        &signers,
-        client.get_latest_blockhash().await?,
-    );
-    client.send_and_confirm_transaction(&tx).await?;
+    RetryPolicy::from_env()
+        .run_async(|| async {
+            let tx = Transaction::new_signed_with_payer(
+                std::slice::from_ref(&instruction),
+                Some(&fee_payer.pubkey()),
+                &signers,
+                client.get_latest_blockhash().await?,
+            );
+            client.send_and_confirm_transaction(&tx).await
+        })
+        .await?;
     Ok(())
 }
 
@@ -396,99 +396,3 @@ fn build_signers<'a>(fee_payer: &'a Keypair, authority: &'a Keypair) -> Vec<&'a
     }
     signers
 }
-
-fn detect_program_id() -> Result<Pubkey, Box<dyn std::error::Error>> {
-    if let Ok(id) = env::var("FROSTBITE_PROGRAM_ID") {
-        return Ok(Pubkey::from_str(&id)?);
-    }
-    if let Ok(path) = env::var("FROSTBITE_PROGRAM_KEYPAIR") {
-        return Ok(read_program_keypair(&path)?);
-    }
-    if let Some(path) = find_program_keypair() {
-        return Ok(read_program_keypair(path.to_str().unwrap_or_default())?);
-    }
-    Ok(Pubkey::from_str(DEFAULT_PROGRAM_ID)?)
-}
-
-fn read_program_keypair(path: &str) -> Result<Pubkey, Box<dyn std::error::Error>> {
-    let data = std::fs::read_to_string(path)?;
-    let bytes: Vec<u8> = serde_json::from_str(&data)?;
-    let keypair = Keypair::from_bytes(&bytes)?;
-    Ok(keypair.pubkey())
-}
-
-fn find_program_keypair() -> Option<PathBuf> {
-    let mut candidates = Vec::new();
-    if let Ok(home) = env::var("FROSTBITE_HOME") {
-        candidates.push(PathBuf::from(format!(
-            "{}/target/deploy/frostbite-keypair.json",
-            home.trim_end_matches('/')
-        )));
-    }
-
-    if let Ok(cwd) = env::current_dir() {
-        for rel in [
-            "target/deploy/frostbite-keypair.json",
-            "../target/deploy/frostbite-keypair.json",
-            "../../target/deploy/frostbite-keypair.json",
-            "../../../target/deploy/frostbite-keypair.json",
-        ] {
-            candidates.push(cwd.join(rel));
-        }
-    }
-
-    for path in candidates {
-        if path.exists() {
-            return Some(path);
-        }
-    }
-    None
-}
-
-#[derive(Default)]
-struct CliConfig {
-    rpc_url: Option<String>,
-    keypair_path: Option<String>,
-}
-
-fn load_solana_cli_config(path: &str) -> Option<CliConfig> {
-    let path = expand_path(path);
-    let contents = std::fs::read_to_string(&path).ok()?;
-    let mut cfg = CliConfig::default();
-    for raw_line in contents.lines() {
-        let line = raw_line.trim();
-        if line.is_empty() || line.starts_with('#') {
-            continue;
-        }
-        if let Some(value) = parse_yaml_value(line, "json_rpc_url") {
-            cfg.rpc_url = Some(value);
-            continue;
-        }
-        if let Some(value) = parse_yaml_value(line, "keypair_path") {
-            cfg.keypair_path = Some(value);
-        }
-    }
-    Some(cfg)
-}
-
-fn parse_yaml_value(line: &str, key: &str) -> Option<String> {
-    let mut parts = line.splitn(2, ':');
-    let left = parts.next()?.trim();
-    if left != key {
-        return None;
-    }
-    let value = parts.next()?.trim();
-    if value.is_empty() {
-        return None;
-    }
-    Some(value.trim_matches('"').trim_matches('\'').to_string())
-}
-
-fn expand_path(path: &str) -> String {
-    if let Some(stripped) = path.strip_prefix("~/") {
-        if let Ok(home) = std::env::var("HOME") {
-            return format!("{}/{}", home, stripped);
-        }
-    }
-    path.to_string()
-}
@@ -0,0 +1,265 @@
+use frostbite_modelkit_tools::cli_config::{
+    detect_program_id, expand_path, load_solana_cli_config, DEFAULT_PAYER_KEYPAIR, DEFAULT_RPC_URL,
+    DEFAULT_SOLANA_CONFIG,
+};
+use frostbite_modelkit_tools::retry::RetryPolicy;
+use frostbite_modelkit_tools::seeds::{
+    derive_segment_pda, derive_vm_pda, parse_u64_value, SEGMENT_KIND_RAM,
+};
+use frostbite_modelkit_tools::upload::BINARY_HEADER_SIZE;
+use frostbite_modelkit_tools::vm::{VM_MEMORY_OFFSET, VM_MEMORY_SIZE};
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::{
+    commitment_config::CommitmentConfig,
+    instruction::{AccountMeta, Instruction},
+    pubkey::Pubkey,
+    signature::{Keypair, Signer},
+    transaction::Transaction,
+};
+use std::env;
+use std::str::FromStr;
+use std::sync::Arc;
+
+const OP_WRITE_ACCOUNT: u8 = 5;
+const OP_CLEAR_SEGMENT_SEEDED: u8 = 46;
+
+const MAX_SLOT: u8 = 15;
+
+const WRITE_CHUNK_SIZE: usize = 900;
+
+struct ResetArgs {
+    vm_seed: u64,
+    skip_scratch: bool,
+    skip_segments: bool,
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let args = parse_args()?;
+
+    let solana_config_path =
+        env::var("SOLANA_CONFIG").unwrap_or_else(|_| DEFAULT_SOLANA_CONFIG.to_string());
+    let cli_config = load_solana_cli_config(&solana_config_path);
+    let rpc_url = env::var("FROSTBITE_RPC_URL")
+        .ok()
+        .or_else(|| cli_config.as_ref().and_then(|cfg| cfg.rpc_url.clone()))
+        .unwrap_or_else(|| DEFAULT_RPC_URL.to_string());
+    let payer_keypair_path = env::var("FROSTBITE_PAYER_KEYPAIR")
+        .ok()
+        .or_else(|| cli_config.as_ref().and_then(|cfg| cfg.keypair_path.clone()))
+        .unwrap_or_else(|| DEFAULT_PAYER_KEYPAIR.to_string());
+    let payer_keypair_path = expand_path(&payer_keypair_path);
+
+    let client = RpcClient::new_with_commitment(rpc_url.clone(), CommitmentConfig::confirmed());
+    let payer = Arc::new(
+        solana_sdk::signature::read_keypair_file(&payer_keypair_path)
+            .map_err(|_| format!("Could not find payer keypair at {}", payer_keypair_path))?,
+    );
+
+    let authority_keypair_path = env::var("FROSTBITE_AUTHORITY_KEYPAIR")
+        .ok()
+        .map(|path| expand_path(&path));
+    let authority = if let Some(path) = authority_keypair_path.as_ref() {
+        Arc::new(
+            solana_sdk::signature::read_keypair_file(path)
+                .map_err(|_| format!("Could not find authority keypair at {}", path))?,
+        )
+    } else {
+        payer.clone()
+    };
+    if let Ok(authority_pubkey_hint) = env::var("FROSTBITE_AUTHORITY_PUBKEY") {
+        let hinted = Pubkey::from_str(&authority_pubkey_hint)?;
+        if hinted != authority.pubkey() {
+            return Err(format!(
+                "FROSTBITE_AUTHORITY_PUBKEY mismatch: signer={}, provided={}",
+                authority.pubkey(),
+                hinted
+            )
+            .into());
+        }
+    }
+
+    let program_id = detect_program_id()?;
+    let vm_pda = derive_vm_pda(&program_id, &authority.pubkey(), args.vm_seed)?;
+
+    println!("RPC: {}", rpc_url);
+    println!("Program: {}", program_id);
+    println!("Payer: {}", payer.pubkey());
+    println!("Authority: {}", authority.pubkey());
+    println!("VM: {} (seed {})", vm_pda, args.vm_seed);
+
+    let vm_account = client
+        .get_account(&vm_pda)
+        .await
+        .map_err(|err| format!("VM account {} not found: {}", vm_pda, err))?;
+    if vm_account.owner != program_id {
+        return Err(format!(
+            "VM account {} is owned by {}, expected {}",
+            vm_pda, vm_account.owner, program_id
+        )
+        .into());
+    }
+
+    let mut cleared_segments = 0usize;
+    let mut cleared_segment_bytes = 0usize;
+    if !args.skip_segments {
+        // Slot 1 carries weights and is mapped readonly; only RAM segments are
+        // mutable at execute time, so those are the ones a reset has to zero.
+        for slot in 1..=MAX_SLOT {
+            let segment_pda = derive_segment_pda(
+                &program_id,
+                &authority.pubkey(),
+                args.vm_seed,
+                SEGMENT_KIND_RAM,
+                slot,
+            )?;
+            let segment = match client.get_account(&segment_pda).await {
+                Ok(account) if account.owner == program_id => account,
+                _ => continue,
+            };
+            let payload_len = segment.data.len().saturating_sub(BINARY_HEADER_SIZE);
+
+            let mut data = Vec::with_capacity(1 + 8 + 1 + 1 + 4 + 4);
+            data.push(OP_CLEAR_SEGMENT_SEEDED);
+            data.extend_from_slice(&args.vm_seed.to_le_bytes());
+            data.push(SEGMENT_KIND_RAM);
+            data.push(slot);
+            // offset=0, len=0 clears the entire payload.
+            data.extend_from_slice(&0u32.to_le_bytes());
+            data.extend_from_slice(&0u32.to_le_bytes());
+
+            let instruction = Instruction {
+                program_id,
+                accounts: vec![
+                    AccountMeta::new_readonly(authority.pubkey(), true),
+                    AccountMeta::new_readonly(vm_pda, false),
+                    AccountMeta::new(segment_pda, false),
+                ],
+                data,
+            };
+            send_instruction(&client, payer.as_ref(), authority.as_ref(), instruction).await?;
+            println!(
+                "Cleared ram slot {} ({}): {} bytes",
+                slot, segment_pda, payload_len
+            );
+            cleared_segments += 1;
+            cleared_segment_bytes += payload_len;
+        }
+    }
+
+    let mut scratch_chunks = 0usize;
+    let mut scratch_bytes = 0usize;
+    if !args.skip_scratch {
+        let memory_end = usize::min(vm_account.data.len(), VM_MEMORY_OFFSET + VM_MEMORY_SIZE);
+        if memory_end <= VM_MEMORY_OFFSET {
+            return Err(format!(
+                "VM account {} is too small ({} bytes) to hold scratch memory",
+                vm_pda,
+                vm_account.data.len()
+            )
+            .into());
+        }
+        let memory = &vm_account.data[VM_MEMORY_OFFSET..memory_end];
+        let zeros = [0u8; WRITE_CHUNK_SIZE];
+        // Only rewrite chunks that are not already zero; a freshly initialised
+        // VM is mostly empty and a full sweep would cost ~290 transactions.
+        for (idx, chunk) in memory.chunks(WRITE_CHUNK_SIZE).enumerate() {
+            if chunk.iter().all(|&byte| byte == 0) {
+                continue;
+            }
+            let offset = VM_MEMORY_OFFSET + idx * WRITE_CHUNK_SIZE;
+            let mut data = Vec::with_capacity(1 + 4 + chunk.len());
+            data.push(OP_WRITE_ACCOUNT);
+            data.extend_from_slice(&(offset as u32).to_le_bytes());
+            data.extend_from_slice(&zeros[..chunk.len()]);
+
+            let instruction = Instruction {
+                program_id,
+                accounts: vec![
+                    AccountMeta::new_readonly(authority.pubkey(), true),
+                    AccountMeta::new(vm_pda, false),
+                ],
+                data,
+            };
+            send_instruction(&client, payer.as_ref(), authority.as_ref(), instruction).await?;
+            scratch_chunks += 1;
+            scratch_bytes += chunk.len();
+        }
+        println!(
+            "Cleared VM scratch: {} bytes in {} writes ({} bytes already zero)",
+            scratch_bytes,
+            scratch_chunks,
+            memory.len() - scratch_bytes
+        );
+    }
+
+    println!(
+        "Reset complete: {} ram segment(s), {} segment bytes, {} scratch bytes",
+        cleared_segments, cleared_segment_bytes, scratch_bytes
+    );
+    Ok(())
+}
+
+fn parse_args() -> Result<ResetArgs, Box<dyn std::error::Error>> {
+    let args: Vec<String> = env::args().collect();
+    if args.len() < 3 {
+        eprintln!("Usage: reset_vm --vm-seed <u64> [--skip-scratch] [--skip-segments]");
+        return Err("missing required args".into());
+    }
+
+    let mut vm_seed: Option<u64> = None;
+    let mut skip_scratch = false;
+    let mut skip_segments = false;
+
+    let mut idx = 1usize;
+    while idx < args.len() {
+        match args[idx].as_str() {
+            "--vm-seed" => {
+                idx += 1;
+                if idx >= args.len() {
+                    return Err("missing value for --vm-seed".into());
+                }
+                vm_seed = Some(parse_u64_value(&args[idx])?);
+            }
+            "--skip-scratch" => skip_scratch = true,
+            "--skip-segments" => skip_segments = true,
+            other => return Err(format!("unknown argument: {}", other).into()),
+        }
+        idx += 1;
+    }
+
+    Ok(ResetArgs {
+        vm_seed: vm_seed.ok_or("missing --vm-seed")?,
+        skip_scratch,
+        skip_segments,
+    })
+}
+
+async fn send_instruction(
+    client: &RpcClient,
+    fee_payer: &Keypair,
+    authority: &Keypair,
+    instruction: Instruction,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let signers = build_signers(fee_payer, authority);
+    RetryPolicy::from_env()
+        .run_async(|| async {
+            let tx = Transaction::new_signed_with_payer(
+                std::slice::from_ref(&instruction),
+                Some(&fee_payer.pubkey()),
+                &signers,
+                client.get_latest_blockhash().await?,
+            );
+            client.send_and_confirm_transaction(&tx).await
+        })
+        .await?;
+    Ok(())
+}
+
+fn build_signers<'a>(fee_payer: &'a Keypair, authority: &'a Keypair) -> Vec<&'a dyn Signer> {
+    let mut signers: Vec<&dyn Signer> = vec![fee_payer];
+    if authority.pubkey() != fee_payer.pubkey() {
+        signers.push(authority);
+    }
+    signers
+}
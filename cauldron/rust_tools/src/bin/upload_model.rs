@@ -1,3 +1,18 @@
+use frostbite_modelkit_tools::cli_config::{
+    detect_program_id, expand_path, load_solana_cli_config, DEFAULT_PAYER_KEYPAIR, DEFAULT_RPC_URL,
+    DEFAULT_SOLANA_CONFIG,
+};
+use frostbite_modelkit_tools::retry::RetryPolicy;
+use frostbite_modelkit_tools::seeds::{
+    derive_segment_pda, derive_vm_pda, parse_segment_kind, parse_u64_value, segment_seed_string,
+    vm_seed_string, SEGMENT_KIND_WEIGHTS,
+};
+use frostbite_modelkit_tools::upload::{
+    check_balance, encode_header, estimate_fees, header_is_current, parse_upload_args,
+    resolve_chunk_size, verify_payload_digest, UploadProgress, UploadState, BINARY_HEADER_SIZE,
+    BINARY_MAGIC,
+};
+use frostbite_modelkit_tools::vm::VM_ACCOUNT_SIZE;
 use futures::stream::{FuturesUnordered, StreamExt};
 use solana_client::nonblocking::rpc_client::RpcClient;
 use solana_sdk::{
@@ -12,31 +27,20 @@ use std::env;
 use std::path::{Path, PathBuf};
 use std::str::FromStr;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 use tokio::sync::Semaphore;
 
-const DEFAULT_SOLANA_CONFIG: &str = "~/.config/solana/cli/config.yml";
-const DEFAULT_RPC_URL: &str = "http://127.0.0.1:8899";
-const DEFAULT_PAYER_KEYPAIR: &str = "~/.config/solana/id.json";
-const DEFAULT_PROGRAM_ID: &str = "FRsToriMLgDc1Ud53ngzHUZvCRoazCaGeGUuzkwoha7m";
-const CHUNK_SIZE: usize = 900;
-const CONCURRENCY: usize = 100;
-
-const BINARY_HEADER_SIZE: usize = 12;
-const BINARY_MAGIC: [u8; 4] = *b"RVCD";
+// Sidecar writes during a pass; it is always written when a pass ends.
+const STATE_SAVE_EVERY: usize = 64;
+const PROGRESS_EVERY: Duration = Duration::from_secs(2);
 
 const OP_WRITE_ACCOUNT: u8 = 5;
 const OP_INIT_VM_PDA: u8 = 40;
 const OP_INIT_SEGMENT_PDA: u8 = 41;
 const OP_WRITE_SEGMENT_PDA: u8 = 45;
 
-const SEEDED_VM_PREFIX: &str = "fbv1:vm:";
-const SEEDED_SEG_PREFIX: &str = "fbv1:sg:";
-const VM_MEMORY_SIZE: usize = 262_144;
-const VM_MEMORY_OFFSET: usize = 552;
-const VM_ACCOUNT_SIZE: usize = VM_MEMORY_OFFSET + VM_MEMORY_SIZE;
-
-const SEGMENT_KIND_WEIGHTS: u8 = 1;
-const SEGMENT_KIND_RAM: u8 = 2;
+// Matches frostbite-sdk SPLIT_PIECE_SIZE; guests map piece N to slot start + N.
+const SPLIT_PIECE_SIZE: usize = 256 * 1024;
 
 #[derive(Clone, Copy)]
 enum UploadMode {
@@ -61,6 +65,14 @@ impl UploadMode {
     }
 }
 
+#[derive(Clone, Copy)]
+struct UploadOptions {
+    chunk_size: usize,
+    concurrency: usize,
+    /// Compare the SHA-256 of the uploaded payload with the local file.
+    verify_digest: bool,
+}
+
 #[derive(Clone, Copy)]
 struct PdaUploadConfig {
     vm_seed: u64,
@@ -75,11 +87,15 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     println!("--- Frostbite Parallel Model Upload ---");
 
     let args: Vec<String> = env::args().collect();
-    if args.len() < 2 {
-        println!("Usage: cargo run --bin upload_model -- <chunk_file_path>");
+    let parsed = parse_upload_args(&args)?;
+    let split = parsed.split.as_deref().map(parse_split_spec).transpose()?;
+    let Some(chunk_arg) = parsed.chunk_path else {
+        println!(
+            "Usage: cargo run --bin upload_model -- <chunk_file_path> [--split-across-slots <start>:<count>] [--chunk-size <bytes>] [--concurrency <n>] [--verify-digest]"
+        );
         return Ok(());
-    }
-    let chunk_path = expand_path(&args[1]);
+    };
+    let chunk_path = expand_path(&chunk_arg);
 
     let solana_config_path =
         env::var("SOLANA_CONFIG").unwrap_or_else(|_| DEFAULT_SOLANA_CONFIG.to_string());
@@ -125,12 +141,28 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         }
     }
 
+    let seeded = split.is_some() || pda_mode_enabled();
+    let signers = if authority.pubkey() == payer.pubkey() {
+        1
+    } else {
+        2
+    };
+    let options = UploadOptions {
+        chunk_size: resolve_chunk_size(parsed.chunk_size, seeded, signers > 1)?,
+        concurrency: parsed.concurrency,
+        verify_digest: parsed.verify_digest,
+    };
+
     println!("RPC: {}", rpc_url);
     println!("Payer keypair: {}", payer_keypair_path);
     println!("Authority: {}", authority.pubkey());
     if let Some(path) = authority_keypair_path.as_ref() {
         println!("Authority keypair: {}", path);
     }
+    println!(
+        "Chunk size: {} bytes, concurrency: {}",
+        options.chunk_size, options.concurrency
+    );
 
     let frostbite_id = detect_program_id()?;
 
@@ -141,6 +173,21 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     }
     println!("File size: {} bytes", file_len);
 
+    if let Some((start_slot, slot_count)) = split {
+        return upload_split(
+            &client,
+            &payer,
+            &authority,
+            frostbite_id,
+            &chunk_path,
+            data,
+            start_slot,
+            slot_count,
+            options,
+        )
+        .await;
+    }
+
     let upload_mode = if pda_mode_enabled() {
         let cfg = configure_pda_mode(authority.pubkey(), &frostbite_id)?;
         println!("Upload mode: seeded deterministic");
@@ -149,6 +196,17 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             "Segment PDA: {} (kind={}, slot={})",
             cfg.segment_pda, cfg.kind, cfg.slot
         );
+        preflight_balance(
+            &client,
+            &payer.pubkey(),
+            &[
+                (cfg.vm_pda, VM_ACCOUNT_SIZE),
+                (cfg.segment_pda, BINARY_HEADER_SIZE + file_len),
+            ],
+            file_len.div_ceil(options.chunk_size),
+            signers,
+        )
+        .await?;
         init_vm_pda(
             &client,
             payer.as_ref(),
@@ -194,6 +252,14 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         };
         let chunk_pubkey = chunk_kp.pubkey();
         println!("Target Account: {}", chunk_pubkey);
+        preflight_balance(
+            &client,
+            &payer.pubkey(),
+            &[(chunk_pubkey, file_len + BINARY_HEADER_SIZE)],
+            file_len.div_ceil(options.chunk_size),
+            signers,
+        )
+        .await?;
 
         if let Ok(existing) = client.get_account(&chunk_pubkey).await {
             if existing.owner != frostbite_id {
@@ -203,6 +269,31 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                 )
                 .into());
             }
+            if existing.data.len() < file_len + BINARY_HEADER_SIZE {
+                return Err(format!(
+                    "Target account {} is {} bytes, smaller than header + file ({}); use a new FROSTBITE_CHUNK_KEYPAIR",
+                    chunk_pubkey,
+                    existing.data.len(),
+                    file_len + BINARY_HEADER_SIZE
+                )
+                .into());
+            }
+            if !header_is_current(&existing.data, &data) {
+                println!("Rewriting stale account header...");
+                let header_ix =
+                    header_write_instruction(frostbite_id, payer.pubkey(), chunk_pubkey, &data);
+                RetryPolicy::from_env()
+                    .run_async(|| async {
+                        let tx = Transaction::new_signed_with_payer(
+                            std::slice::from_ref(&header_ix),
+                            Some(&payer.pubkey()),
+                            &[payer.as_ref()],
+                            client.get_latest_blockhash().await?,
+                        );
+                        client.send_and_confirm_transaction(&tx).await
+                    })
+                    .await?;
+            }
         } else {
             let account_size = file_len + BINARY_HEADER_SIZE;
             println!("Creating Account ({} bytes)...", account_size);
@@ -218,28 +309,21 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                 account_size as u64,
                 &frostbite_id,
             );
-            let mut init_data = Vec::with_capacity(1 + 4 + BINARY_HEADER_SIZE);
-            init_data.push(OP_WRITE_ACCOUNT);
-            init_data.extend_from_slice(&0u32.to_le_bytes());
-            init_data.extend_from_slice(&BINARY_MAGIC);
-            init_data.extend_from_slice(&(file_len as u32).to_le_bytes());
-            init_data.extend_from_slice(&0u32.to_le_bytes());
-            let init_ix = Instruction {
-                program_id: frostbite_id,
-                accounts: vec![
-                    AccountMeta::new(payer.pubkey(), true),
-                    AccountMeta::new(chunk_pubkey, false),
-                ],
-                data: init_data,
-            };
-
-            let tx = Transaction::new_signed_with_payer(
-                &[create_ix, init_ix],
-                Some(&payer.pubkey()),
-                &[&payer.as_ref(), &chunk_kp],
-                client.get_latest_blockhash().await?,
-            );
-            client.send_and_confirm_transaction(&tx).await?;
+            let init_ix =
+                header_write_instruction(frostbite_id, payer.pubkey(), chunk_pubkey, &data);
+
+            let instructions = [create_ix, init_ix];
+            RetryPolicy::from_env()
+                .run_async(|| async {
+                    let tx = Transaction::new_signed_with_payer(
+                        &instructions,
+                        Some(&payer.pubkey()),
+                        &[payer.as_ref(), &chunk_kp],
+                        client.get_latest_blockhash().await?,
+                    );
+                    client.send_and_confirm_transaction(&tx).await
+                })
+                .await?;
             println!("Account initialized.");
         }
 
@@ -248,10 +332,57 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         }
     };
 
-    let target_account = upload_mode.target_account();
+    let state_path = PathBuf::from(format!("{}.upload.json", chunk_path));
+    upload_payload(
+        &client,
+        &payer,
+        &authority,
+        frostbite_id,
+        upload_mode,
+        data,
+        &state_path,
+        options,
+    )
+    .await
+}
 
-    let semaphore = Arc::new(Semaphore::new(CONCURRENCY));
+/// Upload `data` to the target of `mode`, re-sending chunks until the on-chain
+/// payload matches byte for byte.
+///
+/// Confirmed chunks are recorded in the sidecar at `state_path`; a restarted
+/// upload does not re-send them unless the final full comparison disagrees.
+/// The sidecar is removed once the payload verifies.
+#[allow(clippy::too_many_arguments)]
+async fn upload_payload(
+    client: &Arc<RpcClient>,
+    payer: &Arc<Keypair>,
+    authority: &Arc<Keypair>,
+    program_id: Pubkey,
+    mode: UploadMode,
+    data: Vec<u8>,
+    state_path: &Path,
+    options: UploadOptions,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let target_account = mode.target_account();
+
+    let chunk_size = options.chunk_size;
+    let semaphore = Arc::new(Semaphore::new(options.concurrency));
+    let retry = RetryPolicy::from_env();
+    let mut state = UploadState::resume(
+        UploadState::new(&target_account.to_string(), &data, options.chunk_size),
+        state_path,
+    );
+    if !state.confirmed.is_empty() {
+        println!(
+            "Resuming: {} chunks already confirmed ({})",
+            state.confirmed.len(),
+            state_path.display()
+        );
+    }
     let data_ref = Arc::new(data);
+    let total_chunks = data_ref.len().div_ceil(chunk_size);
+    let mut progress = UploadProgress::new(total_chunks, data_ref.len(), PROGRESS_EVERY);
+    let started = Instant::now();
 
     loop {
         println!("Verifying on-chain state...");
@@ -273,33 +404,54 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
         let on_chain_data = &acc.data[BINARY_HEADER_SIZE..BINARY_HEADER_SIZE + data_ref.len()];
 
-        let mut dirty_chunks = Vec::new();
-        let total_chunks = (data_ref.len() + CHUNK_SIZE - 1) / CHUNK_SIZE;
+        let mut mismatched = Vec::new();
 
         for i in 0..total_chunks {
-            let start = i * CHUNK_SIZE;
-            let end = std::cmp::min(start + CHUNK_SIZE, data_ref.len());
+            let start = i * chunk_size;
+            let end = std::cmp::min(start + chunk_size, data_ref.len());
             let file_slice = &data_ref[start..end];
             let on_chain_slice = &on_chain_data[start..end];
 
             if file_slice != on_chain_slice {
-                dirty_chunks.push(i);
+                mismatched.push(i);
             }
         }
 
-        if dirty_chunks.is_empty() {
+        if mismatched.is_empty() {
             println!(
                 "SUCCESS: Integrity Verified. All {} chunks match.",
                 total_chunks
             );
+            if options.verify_digest {
+                let digest = verify_payload_digest(&acc.data, &data_ref)?;
+                println!("SHA-256: {}", digest);
+            }
+            if state_path.exists() {
+                std::fs::remove_file(state_path)?;
+            }
             break;
         }
 
+        // Chunks the sidecar says were confirmed may just not be visible yet;
+        // only re-send them once nothing else is left.
+        let mut dirty_chunks: Vec<usize> = mismatched
+            .iter()
+            .copied()
+            .filter(|&i| !state.is_confirmed(i))
+            .collect();
+        if dirty_chunks.is_empty() {
+            for &i in &mismatched {
+                state.forget(i);
+            }
+            dirty_chunks = mismatched;
+        }
+
         println!(
             "Uploading {}/{} dirty chunks...",
             dirty_chunks.len(),
             total_chunks
         );
+        progress.start_pass(dirty_chunks.len());
 
         let mut futures = FuturesUnordered::new();
         for chunk_idx in dirty_chunks {
@@ -308,12 +460,10 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             let payer = payer.clone();
             let authority = authority.clone();
             let data = data_ref.clone();
-            let mode = upload_mode;
-            let program_id = frostbite_id;
 
             futures.push(tokio::spawn(async move {
-                let start = chunk_idx * CHUNK_SIZE;
-                let end = std::cmp::min(start + CHUNK_SIZE, data.len());
+                let start = chunk_idx * chunk_size;
+                let end = std::cmp::min(start + chunk_size, data.len());
                 let chunk_data = &data[start..end];
 
                 let ix = build_chunk_write_instruction(
@@ -323,43 +473,241 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                     start,
                     chunk_data,
                 );
-                let bh = client.get_latest_blockhash().await.unwrap_or_default();
-                let tx = if payer.pubkey() == authority.pubkey() {
-                    Transaction::new_signed_with_payer(
-                        &[ix],
-                        Some(&payer.pubkey()),
-                        &[payer.as_ref()],
-                        bh,
-                    )
-                } else {
-                    Transaction::new_signed_with_payer(
-                        &[ix],
-                        Some(&payer.pubkey()),
-                        &[payer.as_ref(), authority.as_ref()],
-                        bh,
-                    )
-                };
-                let res = client.send_and_confirm_transaction(&tx).await;
+                let res = retry
+                    .run_async(|| async {
+                        let bh = client.get_latest_blockhash().await?;
+                        let tx = if payer.pubkey() == authority.pubkey() {
+                            Transaction::new_signed_with_payer(
+                                std::slice::from_ref(&ix),
+                                Some(&payer.pubkey()),
+                                &[payer.as_ref()],
+                                bh,
+                            )
+                        } else {
+                            Transaction::new_signed_with_payer(
+                                std::slice::from_ref(&ix),
+                                Some(&payer.pubkey()),
+                                &[payer.as_ref(), authority.as_ref()],
+                                bh,
+                            )
+                        };
+                        client.send_and_confirm_transaction(&tx).await
+                    })
+                    .await;
                 drop(permit);
-                res
+                (chunk_idx, res)
             }));
         }
 
         while let Some(res) = futures.next().await {
             match res {
-                Ok(Ok(_)) => print!("."),
-                Ok(Err(_)) => print!("x"),
-                Err(_) => print!("!"),
+                Ok((chunk_idx, Ok(_))) => {
+                    state.confirm(chunk_idx);
+                    if state.confirmed.len().is_multiple_of(STATE_SAVE_EVERY) {
+                        state.save(state_path)?;
+                    }
+                    let start = chunk_idx * chunk_size;
+                    progress.record_sent(chunk_size.min(data_ref.len() - start));
+                }
+                Ok((_, Err(_))) | Err(_) => progress.record_failed(),
+            }
+            let elapsed = started.elapsed();
+            if progress.due(elapsed) {
+                println!("  {}", progress.line(elapsed));
             }
-            use std::io::Write;
-            std::io::stdout().flush().ok();
         }
-        println!();
+        state.save(state_path)?;
+    }
+
+    Ok(())
+}
+
+/// Upload `data` as consecutive `SPLIT_PIECE_SIZE` pieces into weights slots
+/// `start_slot..start_slot + slot_count`, then record the piece table next to
+/// the source file as `<file>.split.json`. The table is for host tooling only;
+/// guests rely on the fixed piece size alone.
+#[allow(clippy::too_many_arguments)]
+async fn upload_split(
+    client: &Arc<RpcClient>,
+    payer: &Arc<Keypair>,
+    authority: &Arc<Keypair>,
+    program_id: Pubkey,
+    chunk_path: &str,
+    data: Vec<u8>,
+    start_slot: u8,
+    slot_count: u8,
+    options: UploadOptions,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if !pda_mode_enabled() {
+        return Err(
+            "--split-across-slots requires seeded upload mode (set FROSTBITE_VM_SEED)".into(),
+        );
+    }
+    let pieces = data.len().div_ceil(SPLIT_PIECE_SIZE).max(1);
+    if pieces > slot_count as usize {
+        return Err(format!(
+            "file needs {} slots of {} bytes but --split-across-slots reserves {}",
+            pieces, SPLIT_PIECE_SIZE, slot_count
+        )
+        .into());
+    }
+
+    let base = configure_pda_mode(authority.pubkey(), &program_id)?;
+    if base.kind != SEGMENT_KIND_WEIGHTS {
+        return Err("--split-across-slots only supports weights segments".into());
+    }
+    println!(
+        "Upload mode: seeded deterministic (split across {} slots)",
+        pieces
+    );
+    println!("VM PDA: {}", base.vm_pda);
+    let mut accounts = vec![(base.vm_pda, VM_ACCOUNT_SIZE)];
+    let mut chunks = 0;
+    for piece in 0..pieces {
+        let start = piece * SPLIT_PIECE_SIZE;
+        let len = std::cmp::min(start + SPLIT_PIECE_SIZE, data.len()) - start;
+        let segment_pda = derive_segment_pda(
+            &program_id,
+            &authority.pubkey(),
+            base.vm_seed,
+            SEGMENT_KIND_WEIGHTS,
+            start_slot + piece as u8,
+        )?;
+        accounts.push((segment_pda, BINARY_HEADER_SIZE + len));
+        chunks += len.div_ceil(options.chunk_size);
+    }
+    let signers = if authority.pubkey() == payer.pubkey() {
+        1
+    } else {
+        2
+    };
+    preflight_balance(client, &payer.pubkey(), &accounts, chunks, signers).await?;
+    init_vm_pda(
+        client,
+        payer.as_ref(),
+        authority.as_ref(),
+        &program_id,
+        base.vm_seed,
+        base.vm_pda,
+    )
+    .await?;
+
+    let mut entries = Vec::with_capacity(pieces);
+    for piece in 0..pieces {
+        let slot = start_slot + piece as u8;
+        let start = piece * SPLIT_PIECE_SIZE;
+        let end = std::cmp::min(start + SPLIT_PIECE_SIZE, data.len());
+        let segment_pda = derive_segment_pda(
+            &program_id,
+            &authority.pubkey(),
+            base.vm_seed,
+            SEGMENT_KIND_WEIGHTS,
+            slot,
+        )?;
+        println!(
+            "Piece {}/{}: bytes {}..{} -> slot {} ({})",
+            piece + 1,
+            pieces,
+            start,
+            end,
+            slot,
+            segment_pda
+        );
+        let cfg = PdaUploadConfig {
+            slot,
+            segment_pda,
+            ..base
+        };
+        ensure_segment_header_for_upload(
+            client,
+            payer.as_ref(),
+            authority.as_ref(),
+            &program_id,
+            cfg,
+            end - start,
+        )
+        .await?;
+        let mode = UploadMode::Pda {
+            target_account: segment_pda,
+            vm_pda: base.vm_pda,
+            vm_seed: base.vm_seed,
+            kind: SEGMENT_KIND_WEIGHTS,
+            slot,
+        };
+        upload_payload(
+            client,
+            payer,
+            authority,
+            program_id,
+            mode,
+            data[start..end].to_vec(),
+            Path::new(&format!("{}.slot{}.upload.json", chunk_path, slot)),
+            options,
+        )
+        .await?;
+        entries.push(serde_json::json!({
+            "slot": slot,
+            "offset": start,
+            "len": end - start,
+            "pubkey": segment_pda.to_string(),
+        }));
     }
 
+    let manifest = serde_json::json!({
+        "version": 1,
+        "source": chunk_path,
+        "total_len": data.len(),
+        "piece_size": SPLIT_PIECE_SIZE,
+        "data_offset": BINARY_HEADER_SIZE,
+        "start_slot": start_slot,
+        "pieces": entries,
+    });
+    let manifest_path = format!("{}.split.json", chunk_path);
+    std::fs::write(&manifest_path, serde_json::to_string_pretty(&manifest)?)?;
+    println!("Split manifest: {}", manifest_path);
     Ok(())
 }
 
+fn parse_split_spec(raw: &str) -> Result<(u8, u8), Box<dyn std::error::Error>> {
+    let (start_raw, count_raw) = raw
+        .split_once(':')
+        .ok_or("--split-across-slots expects <start>:<count>")?;
+    let start = parse_u64_value(start_raw)?;
+    let count = parse_u64_value(count_raw)?;
+    if !(1..=15).contains(&start) {
+        return Err("--split-across-slots start must be in range 1..=15".into());
+    }
+    if count == 0 || start + count - 1 > 15 {
+        return Err(format!(
+            "--split-across-slots {}:{} must cover at least one slot and end at or before slot 15",
+            start, count
+        )
+        .into());
+    }
+    Ok((start as u8, count as u8))
+}
+
+/// WRITE_ACCOUNT at offset 0 with the RVCD header for `payload`.
+fn header_write_instruction(
+    program_id: Pubkey,
+    payer: Pubkey,
+    target_account: Pubkey,
+    payload: &[u8],
+) -> Instruction {
+    let mut ix_data = Vec::with_capacity(1 + 4 + BINARY_HEADER_SIZE);
+    ix_data.push(OP_WRITE_ACCOUNT);
+    ix_data.extend_from_slice(&0u32.to_le_bytes());
+    ix_data.extend_from_slice(&encode_header(payload));
+    Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new(payer, true),
+            AccountMeta::new(target_account, false),
+        ],
+        data: ix_data,
+    }
+}
+
 fn build_chunk_write_instruction(
     program_id: Pubkey,
     authority: Pubkey,
@@ -472,72 +820,6 @@ fn pda_mode_enabled() -> bool {
     }
 }
 
-fn parse_u64_value(raw: &str) -> Result<u64, Box<dyn std::error::Error>> {
-    let trimmed = raw.trim();
-    if trimmed.is_empty() {
-        return Err("numeric value cannot be empty".into());
-    }
-    if let Some(hex) = trimmed
-        .strip_prefix("0x")
-        .or_else(|| trimmed.strip_prefix("0X"))
-    {
-        return Ok(u64::from_str_radix(hex, 16)?);
-    }
-    Ok(trimmed.parse::<u64>()?)
-}
-
-fn parse_segment_kind(raw: &str) -> Result<u8, Box<dyn std::error::Error>> {
-    let lowered = raw.trim().to_ascii_lowercase();
-    match lowered.as_str() {
-        "1" | "weights" => Ok(SEGMENT_KIND_WEIGHTS),
-        "2" | "ram" => Ok(SEGMENT_KIND_RAM),
-        _ => Err(format!(
-            "Unsupported FROSTBITE_SEGMENT_KIND '{}'; expected weights|ram|1|2",
-            raw
-        )
-        .into()),
-    }
-}
-
-fn vm_seed_string(vm_seed: u64) -> String {
-    format!("{}{vm_seed:016x}", SEEDED_VM_PREFIX)
-}
-
-fn derive_segment_pda(
-    program_id: &Pubkey,
-    authority: &Pubkey,
-    vm_seed: u64,
-    kind: u8,
-    slot: u8,
-) -> Result<Pubkey, Box<dyn std::error::Error>> {
-    let seed = segment_seed_string(vm_seed, kind, slot);
-    derive_seeded_address(authority, &seed, program_id)
-}
-
-fn segment_seed_string(vm_seed: u64, kind: u8, slot: u8) -> String {
-    format!("{}{vm_seed:016x}:{kind:02x}{slot:02x}", SEEDED_SEG_PREFIX)
-}
-
-fn derive_vm_pda(
-    program_id: &Pubkey,
-    authority: &Pubkey,
-    vm_seed: u64,
-) -> Result<Pubkey, Box<dyn std::error::Error>> {
-    let seed = vm_seed_string(vm_seed);
-    derive_seeded_address(authority, &seed, program_id)
-}
-
-fn derive_seeded_address(
-    authority: &Pubkey,
-    seed: &str,
-    program_id: &Pubkey,
-) -> Result<Pubkey, Box<dyn std::error::Error>> {
-    if seed.len() > 32 {
-        return Err(format!("seed exceeds 32 bytes: {}", seed).into());
-    }
-    Ok(Pubkey::create_with_seed(authority, seed, program_id)?)
-}
-
 async fn init_vm_pda(
     client: &RpcClient,
     fee_payer: &Keypair,
@@ -680,119 +962,65 @@ async fn ensure_seeded_program_account(
     send_instruction(client, fee_payer, authority, create_ix).await
 }
 
+/// Abort before creating anything when the payer cannot cover rent for the
+/// accounts in `accounts` that do not exist yet plus fees for the setup and
+/// `chunks` write transactions.
+async fn preflight_balance(
+    client: &RpcClient,
+    payer: &Pubkey,
+    accounts: &[(Pubkey, usize)],
+    chunks: usize,
+    signers: usize,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut rent = 0u64;
+    let mut setup_txs = 0usize;
+    for (account, space) in accounts {
+        if client.get_account(account).await.is_err() {
+            rent = rent.saturating_add(
+                client
+                    .get_minimum_balance_for_rent_exemption(*space)
+                    .await?,
+            );
+            // Create plus init.
+            setup_txs += 2;
+        }
+    }
+    let fees = estimate_fees(chunks + setup_txs, signers);
+    let balance = client.get_balance(payer).await?;
+    check_balance(balance, rent, fees)?;
+    println!(
+        "Pre-flight: balance {} lamports, estimated {} rent + {} fees",
+        balance, rent, fees
+    );
+    Ok(())
+}
+
 async fn send_instruction(
     client: &RpcClient,
     fee_payer: &Keypair,
     authority: &Keypair,
     ix: Instruction,
 ) -> Result<(), Box<dyn std::error::Error>> {
-    let bh = client.get_latest_blockhash().await?;
-    let tx = if fee_payer.pubkey() == authority.pubkey() {
-        Transaction::new_signed_with_payer(&[ix], Some(&fee_payer.pubkey()), &[fee_payer], bh)
-    } else {
-        Transaction::new_signed_with_payer(
-            &[ix],
-            Some(&fee_payer.pubkey()),
-            &[fee_payer, authority],
-            bh,
-        )
-    };
-    client.send_and_confirm_transaction(&tx).await?;
+    RetryPolicy::from_env()
+        .run_async(|| async {
+            let bh = client.get_latest_blockhash().await?;
+            let tx = if fee_payer.pubkey() == authority.pubkey() {
+                Transaction::new_signed_with_payer(
+                    std::slice::from_ref(&ix),
+                    Some(&fee_payer.pubkey()),
+                    &[fee_payer],
+                    bh,
+                )
+            } else {
+                Transaction::new_signed_with_payer(
+                    std::slice::from_ref(&ix),
+                    Some(&fee_payer.pubkey()),
+                    &[fee_payer, authority],
+                    bh,
+                )
+            };
+            client.send_and_confirm_transaction(&tx).await
+        })
+        .await?;
     Ok(())
 }
-
-fn detect_program_id() -> Result<Pubkey, Box<dyn std::error::Error>> {
-    if let Ok(id) = env::var("FROSTBITE_PROGRAM_ID") {
-        return Ok(Pubkey::from_str(&id)?);
-    }
-    if let Ok(path) = env::var("FROSTBITE_PROGRAM_KEYPAIR") {
-        return Ok(read_program_keypair(&path)?);
-    }
-    if let Some(path) = find_program_keypair() {
-        return Ok(read_program_keypair(path.to_str().unwrap_or_default())?);
-    }
-    Ok(Pubkey::from_str(DEFAULT_PROGRAM_ID)?)
-}
-
-fn read_program_keypair(path: &str) -> Result<Pubkey, Box<dyn std::error::Error>> {
-    let data = std::fs::read_to_string(path)?;
-    let bytes: Vec<u8> = serde_json::from_str(&data)?;
-    let keypair = Keypair::from_bytes(&bytes)?;
-    Ok(keypair.pubkey())
-}
-
-fn find_program_keypair() -> Option<PathBuf> {
-    let mut candidates = Vec::new();
-    if let Ok(home) = env::var("FROSTBITE_HOME") {
-        candidates.push(PathBuf::from(format!(
-            "{}/target/deploy/frostbite-keypair.json",
-            home.trim_end_matches('/')
-        )));
-    }
-
-    if let Ok(cwd) = env::current_dir() {
-        for rel in [
-            "target/deploy/frostbite-keypair.json",
-            "../target/deploy/frostbite-keypair.json",
-            "../../target/deploy/frostbite-keypair.json",
-            "../../../target/deploy/frostbite-keypair.json",
-        ] {
-            candidates.push(cwd.join(rel));
-        }
-    }
-
-    for path in candidates {
-        if path.exists() {
-            return Some(path);
-        }
-    }
-    None
-}
-
-#[derive(Default)]
-struct CliConfig {
-    rpc_url: Option<String>,
-    keypair_path: Option<String>,
-}
-
-fn load_solana_cli_config(path: &str) -> Option<CliConfig> {
-    let path = expand_path(path);
-    let contents = std::fs::read_to_string(&path).ok()?;
-    let mut cfg = CliConfig::default();
-    for raw_line in contents.lines() {
-        let line = raw_line.trim();
-        if line.is_empty() || line.starts_with('#') {
-            continue;
-        }
-        if let Some(value) = parse_yaml_value(line, "json_rpc_url") {
-            cfg.rpc_url = Some(value);
-            continue;
-        }
-        if let Some(value) = parse_yaml_value(line, "keypair_path") {
-            cfg.keypair_path = Some(value);
-        }
-    }
-    Some(cfg)
-}
-
-fn parse_yaml_value(line: &str, key: &str) -> Option<String> {
-    let mut parts = line.splitn(2, ':');
-    let left = parts.next()?.trim();
-    if left != key {
-        return None;
-    }
-    let value = parts.next()?.trim();
-    if value.is_empty() {
-        return None;
-    }
-    Some(value.trim_matches('"').trim_matches('\'').to_string())
-}
-
-fn expand_path(path: &str) -> String {
-    if let Some(stripped) = path.strip_prefix("~/") {
-        if let Ok(home) = std::env::var("HOME") {
-            return format!("{}/{}", home, stripped);
-        }
-    }
-    path.to_string()
-}
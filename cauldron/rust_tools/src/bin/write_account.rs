@@ -1,72 +1,24 @@
+use frostbite_modelkit_tools::cli_config::{
+    detect_program_id, expand_path, load_solana_cli_config, DEFAULT_PAYER_KEYPAIR, DEFAULT_RPC_URL,
+    DEFAULT_SOLANA_CONFIG,
+};
+use frostbite_modelkit_tools::retry::RetryPolicy;
 use solana_client::rpc_client::RpcClient;
 use solana_sdk::{
     commitment_config::CommitmentConfig,
     instruction::{AccountMeta, Instruction},
     pubkey::Pubkey,
-    signature::{Keypair, Signer},
+    signature::Signer,
     transaction::Transaction,
 };
 use std::env;
 use std::fs;
-use std::path::PathBuf;
 use std::str::FromStr;
 
-const DEFAULT_SOLANA_CONFIG: &str = "~/.config/solana/cli/config.yml";
-const DEFAULT_RPC_URL: &str = "http://127.0.0.1:8899";
-const DEFAULT_PAYER_KEYPAIR: &str = "~/.config/solana/id.json";
-const DEFAULT_PROGRAM_ID: &str = "FRsToriMLgDc1Ud53ngzHUZvCRoazCaGeGUuzkwoha7m";
 const DEFAULT_CHUNK_SIZE: usize = 900;
 
 const WRITE_ACCOUNT: u8 = 5;
 
-#[derive(Default)]
-struct CliConfig {
-    rpc_url: Option<String>,
-    keypair_path: Option<String>,
-}
-
-fn load_solana_cli_config(path: &str) -> Option<CliConfig> {
-    let path = expand_path(path);
-    let contents = fs::read_to_string(&path).ok()?;
-    let mut cfg = CliConfig::default();
-    for raw_line in contents.lines() {
-        let line = raw_line.trim();
-        if line.is_empty() || line.starts_with('#') {
-            continue;
-        }
-        if let Some(value) = parse_yaml_value(line, "json_rpc_url") {
-            cfg.rpc_url = Some(value);
-            continue;
-        }
-        if let Some(value) = parse_yaml_value(line, "keypair_path") {
-            cfg.keypair_path = Some(value);
-        }
-    }
-    Some(cfg)
-}
-
-fn parse_yaml_value(line: &str, key: &str) -> Option<String> {
-    let mut parts = line.splitn(2, ':');
-    let left = parts.next()?.trim();
-    if left != key {
-        return None;
-    }
-    let value = parts.next()?.trim();
-    if value.is_empty() {
-        return None;
-    }
-    Some(value.trim_matches('"').trim_matches('\'').to_string())
-}
-
-fn expand_path(path: &str) -> String {
-    if let Some(stripped) = path.strip_prefix("~/") {
-        if let Ok(home) = env::var("HOME") {
-            return format!("{}/{}", home, stripped);
-        }
-    }
-    path.to_string()
-}
-
 fn parse_offset(value: &str) -> Result<u32, Box<dyn std::error::Error>> {
     if let Some(hex) = value.strip_prefix("0x") {
         Ok(u32::from_str_radix(hex, 16)?)
@@ -130,6 +82,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         return Ok(());
     }
 
+    let retry = RetryPolicy::from_env();
     let mut offset = base_offset as usize;
     let mut start = 0usize;
     let mut _chunk_idx = 0u64;
@@ -152,13 +105,15 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
             data: ix_data,
         };
 
-        let tx = Transaction::new_signed_with_payer(
-            &[ix],
-            Some(&payer.pubkey()),
-            &[&payer as &dyn Signer],
-            client.get_latest_blockhash()?,
-        );
-        client.send_and_confirm_transaction(&tx)?;
+        retry.run(|| -> Result<_, Box<dyn std::error::Error>> {
+            let tx = Transaction::new_signed_with_payer(
+                std::slice::from_ref(&ix),
+                Some(&payer.pubkey()),
+                &[&payer as &dyn Signer],
+                client.get_latest_blockhash()?,
+            );
+            Ok(client.send_and_confirm_transaction(&tx)?)
+        })?;
 
         _chunk_idx += 1;
         start = end;
@@ -168,51 +123,3 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     println!("Wrote {} bytes to {}", total, target_pubkey);
     Ok(())
 }
-
-fn detect_program_id() -> Result<Pubkey, Box<dyn std::error::Error>> {
-    if let Ok(id) = env::var("FROSTBITE_PROGRAM_ID") {
-        return Ok(Pubkey::from_str(&id)?);
-    }
-    if let Ok(path) = env::var("FROSTBITE_PROGRAM_KEYPAIR") {
-        return Ok(read_program_keypair(&path)?);
-    }
-    if let Some(path) = find_program_keypair() {
-        return Ok(read_program_keypair(path.to_str().unwrap_or_default())?);
-    }
-    Ok(Pubkey::from_str(DEFAULT_PROGRAM_ID)?)
-}
-
-fn read_program_keypair(path: &str) -> Result<Pubkey, Box<dyn std::error::Error>> {
-    let data = fs::read_to_string(path)?;
-    let bytes: Vec<u8> = serde_json::from_str(&data)?;
-    let keypair = Keypair::from_bytes(&bytes)?;
-    Ok(keypair.pubkey())
-}
-
-fn find_program_keypair() -> Option<PathBuf> {
-    let mut candidates = Vec::new();
-    if let Ok(home) = env::var("FROSTBITE_HOME") {
-        candidates.push(PathBuf::from(format!(
-            "{}/target/deploy/frostbite-keypair.json",
-            home.trim_end_matches('/')
-        )));
-    }
-
-    if let Ok(cwd) = env::current_dir() {
-        for rel in [
-            "target/deploy/frostbite-keypair.json",
-            "../target/deploy/frostbite-keypair.json",
-            "../../target/deploy/frostbite-keypair.json",
-            "../../../target/deploy/frostbite-keypair.json",
-        ] {
-            candidates.push(cwd.join(rel));
-        }
-    }
-
-    for path in candidates {
-        if path.exists() {
-            return Some(path);
-        }
-    }
-    None
-}
@@ -0,0 +1,146 @@
+//! Solana CLI config, keypair path and program ID resolution shared by the
+//! rust_tools binaries.
+
+use solana_sdk::{
+    pubkey::Pubkey,
+    signature::{Keypair, Signer},
+};
+use std::env;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+pub const DEFAULT_SOLANA_CONFIG: &str = "~/.config/solana/cli/config.yml";
+pub const DEFAULT_RPC_URL: &str = "http://127.0.0.1:8899";
+pub const DEFAULT_PAYER_KEYPAIR: &str = "~/.config/solana/id.json";
+pub const DEFAULT_PROGRAM_ID: &str = "FRsToriMLgDc1Ud53ngzHUZvCRoazCaGeGUuzkwoha7m";
+
+#[derive(Default)]
+pub struct CliConfig {
+    pub rpc_url: Option<String>,
+    pub keypair_path: Option<String>,
+}
+
+pub fn load_solana_cli_config(path: &str) -> Option<CliConfig> {
+    let path = expand_path(path);
+    let contents = std::fs::read_to_string(&path).ok()?;
+    let mut cfg = CliConfig::default();
+    for raw_line in contents.lines() {
+        let line = raw_line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if let Some(value) = parse_yaml_value(line, "json_rpc_url") {
+            cfg.rpc_url = Some(value);
+            continue;
+        }
+        if let Some(value) = parse_yaml_value(line, "keypair_path") {
+            cfg.keypair_path = Some(value);
+        }
+    }
+    Some(cfg)
+}
+
+/// Value of a flat `key: value` line, with surrounding quotes removed.
+pub fn parse_yaml_value(line: &str, key: &str) -> Option<String> {
+    let mut parts = line.splitn(2, ':');
+    let left = parts.next()?.trim();
+    if left != key {
+        return None;
+    }
+    let value = parts.next()?.trim();
+    if value.is_empty() {
+        return None;
+    }
+    Some(value.trim_matches('"').trim_matches('\'').to_string())
+}
+
+pub fn expand_path(path: &str) -> String {
+    if let Some(stripped) = path.strip_prefix("~/") {
+        if let Ok(home) = env::var("HOME") {
+            return format!("{}/{}", home, stripped);
+        }
+    }
+    path.to_string()
+}
+
+/// `FROSTBITE_PROGRAM_ID`, then `FROSTBITE_PROGRAM_KEYPAIR`, then a deploy
+/// keypair under `FROSTBITE_HOME` or the working directory, then the devnet ID.
+pub fn detect_program_id() -> Result<Pubkey, Box<dyn std::error::Error>> {
+    if let Ok(id) = env::var("FROSTBITE_PROGRAM_ID") {
+        return Ok(Pubkey::from_str(&id)?);
+    }
+    if let Ok(path) = env::var("FROSTBITE_PROGRAM_KEYPAIR") {
+        return read_program_keypair(&path);
+    }
+    if let Some(path) = find_program_keypair() {
+        return read_program_keypair(path.to_str().unwrap_or_default());
+    }
+    Ok(Pubkey::from_str(DEFAULT_PROGRAM_ID)?)
+}
+
+pub fn read_program_keypair(path: &str) -> Result<Pubkey, Box<dyn std::error::Error>> {
+    let data = std::fs::read_to_string(path)?;
+    let bytes: Vec<u8> = serde_json::from_str(&data)?;
+    let keypair = Keypair::from_bytes(&bytes)?;
+    Ok(keypair.pubkey())
+}
+
+pub fn find_program_keypair() -> Option<PathBuf> {
+    let mut candidates = Vec::new();
+    if let Ok(home) = env::var("FROSTBITE_HOME") {
+        candidates.push(PathBuf::from(format!(
+            "{}/target/deploy/frostbite-keypair.json",
+            home.trim_end_matches('/')
+        )));
+    }
+
+    if let Ok(cwd) = env::current_dir() {
+        for rel in [
+            "target/deploy/frostbite-keypair.json",
+            "../target/deploy/frostbite-keypair.json",
+            "../../target/deploy/frostbite-keypair.json",
+            "../../../target/deploy/frostbite-keypair.json",
+        ] {
+            candidates.push(cwd.join(rel));
+        }
+    }
+
+    candidates.into_iter().find(|path| path.exists())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_yaml_value_matches_exact_key() {
+        assert_eq!(
+            parse_yaml_value(
+                "json_rpc_url: https://api.devnet.solana.com",
+                "json_rpc_url"
+            ),
+            Some("https://api.devnet.solana.com".to_string())
+        );
+        assert_eq!(
+            parse_yaml_value("keypair_path: '/home/a/id.json'", "keypair_path"),
+            Some("/home/a/id.json".to_string())
+        );
+        assert_eq!(
+            parse_yaml_value("keypair_path: \"~/id.json\"", "keypair_path"),
+            Some("~/id.json".to_string())
+        );
+        assert_eq!(parse_yaml_value("keypair_path:", "keypair_path"), None);
+        assert_eq!(parse_yaml_value("json_rpc_url_2: x", "json_rpc_url"), None);
+        assert_eq!(parse_yaml_value("no separator", "no separator"), None);
+    }
+
+    #[test]
+    fn expand_path_only_rewrites_home_prefix() {
+        assert_eq!(expand_path("/abs/id.json"), "/abs/id.json");
+        assert_eq!(expand_path("rel/~/id.json"), "rel/~/id.json");
+        assert_eq!(expand_path("~user/id.json"), "~user/id.json");
+        if let Ok(home) = env::var("HOME") {
+            assert_eq!(expand_path("~/id.json"), format!("{}/id.json", home));
+        }
+    }
+}
@@ -0,0 +1,180 @@
+//! Host-side builder for the 32-byte FBH1 input header.
+//!
+//! Mirrors the guest SDK's `parse_fbh1` (section 4 of
+//! FROSTBITE_GUEST_CONTRACT.md) and `cauldron input-write`, so anything
+//! producing input off-chain wraps payloads the same way:
+//!
+//! ```text
+//! 0   u32  magic        "FBH1"
+//! 4   u16  version      1
+//! 6   u16  flags        FBH_FLAG_HAS_CRC32 | FBH_FLAG_HAS_SCHEMA_HASH
+//! 8   u32  header_len   32
+//! 12  u32  schema_id
+//! 16  u32  payload_len
+//! 20  u32  crc32        0 unless FBH_FLAG_HAS_CRC32
+//! 24  u32  schema_hash  0 unless FBH_FLAG_HAS_SCHEMA_HASH
+//! 28  u32  reserved     0
+//! ```
+
+pub const FBH1_MAGIC: u32 = 0x3148_4246;
+pub const FBH1_VERSION: u16 = 1;
+pub const FBH1_HEADER_LEN: usize = 32;
+pub const FBH_FLAG_HAS_CRC32: u16 = 1 << 0;
+pub const FBH_FLAG_HAS_SCHEMA_HASH: u16 = 1 << 1;
+
+/// Schema id for a manifest `schema.type`.
+pub fn schema_id(schema_type: &str) -> Option<u32> {
+    match schema_type {
+        "vector" => Some(0),
+        "time_series" => Some(1),
+        "graph" => Some(2),
+        "custom" => Some(3),
+        _ => None,
+    }
+}
+
+/// CRC-32 (IEEE, reflected) of the payload.
+pub fn crc32(data: &[u8]) -> u32 {
+    let mut crc = 0xFFFF_FFFFu32;
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xEDB8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+/// `payload` behind an FBH1 header. A `schema_hash` sets the schema-hash
+/// flag; `with_crc` sets the CRC flag. Panics if the payload does not fit
+/// the u32 length field.
+pub fn build_fbh1(
+    payload: &[u8],
+    schema_id: u32,
+    schema_hash: Option<u32>,
+    with_crc: bool,
+) -> Vec<u8> {
+    let payload_len = u32::try_from(payload.len()).expect("FBH1 payload exceeds u32");
+    let mut flags = 0u16;
+    if with_crc {
+        flags |= FBH_FLAG_HAS_CRC32;
+    }
+    if schema_hash.is_some() {
+        flags |= FBH_FLAG_HAS_SCHEMA_HASH;
+    }
+    let crc = if with_crc { crc32(payload) } else { 0 };
+
+    let mut out = Vec::with_capacity(FBH1_HEADER_LEN + payload.len());
+    out.extend_from_slice(&FBH1_MAGIC.to_le_bytes());
+    out.extend_from_slice(&FBH1_VERSION.to_le_bytes());
+    out.extend_from_slice(&flags.to_le_bytes());
+    out.extend_from_slice(&(FBH1_HEADER_LEN as u32).to_le_bytes());
+    out.extend_from_slice(&schema_id.to_le_bytes());
+    out.extend_from_slice(&payload_len.to_le_bytes());
+    out.extend_from_slice(&crc.to_le_bytes());
+    out.extend_from_slice(&schema_hash.unwrap_or(0).to_le_bytes());
+    out.extend_from_slice(&0u32.to_le_bytes());
+    out.extend_from_slice(payload);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use frostbite_sdk::status::{ERR_INPUT_HEADER, ERR_SCHEMA};
+    use frostbite_sdk::{parse_fbh1, VmAddr};
+
+    fn u32_at(buf: &[u8], offset: usize) -> u32 {
+        u32::from_le_bytes(buf[offset..offset + 4].try_into().unwrap())
+    }
+
+    /// Run the guest parser over `input` as if it sat in VM memory.
+    fn guest_parse(input: &[u8], schema_id: u32, schema_hash: u32) -> Result<Vec<u8>, u32> {
+        let (payload, len) = unsafe {
+            parse_fbh1(
+                VmAddr::from_slice(input),
+                input.len(),
+                schema_id,
+                schema_hash,
+            )
+        }?;
+        let start = (payload.raw() - VmAddr::from_slice(input).raw()) as usize;
+        Ok(input[start..start + len].to_vec())
+    }
+
+    #[test]
+    fn header_fields_match_the_layout() {
+        let payload = [1u8, 2, 3, 4, 5];
+        let wrapped = build_fbh1(&payload, 3, Some(0xA1B2_C3D4), true);
+        assert_eq!(wrapped.len(), FBH1_HEADER_LEN + payload.len());
+        assert_eq!(&wrapped[0..4], b"FBH1");
+        assert_eq!(u16::from_le_bytes([wrapped[4], wrapped[5]]), FBH1_VERSION);
+        assert_eq!(
+            u16::from_le_bytes([wrapped[6], wrapped[7]]),
+            FBH_FLAG_HAS_CRC32 | FBH_FLAG_HAS_SCHEMA_HASH
+        );
+        assert_eq!(u32_at(&wrapped, 8), 32);
+        assert_eq!(u32_at(&wrapped, 12), 3);
+        assert_eq!(u32_at(&wrapped, 16), 5);
+        assert_eq!(u32_at(&wrapped, 20), crc32(&payload));
+        assert_eq!(u32_at(&wrapped, 24), 0xA1B2_C3D4);
+        assert_eq!(u32_at(&wrapped, 28), 0);
+        assert_eq!(&wrapped[32..], &payload);
+
+        let bare = build_fbh1(&[], 0, None, false);
+        assert_eq!(bare.len(), FBH1_HEADER_LEN);
+        assert_eq!(&bare[6..8], &[0, 0]);
+        assert_eq!(&bare[16..28], &[0; 12]);
+    }
+
+    #[test]
+    fn crc32_and_schema_ids_match_the_guest_sdk() {
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+        assert_eq!(crc32(b"123456789"), frostbite_sdk::crc32(b"123456789"));
+        assert_eq!(crc32(&[]), 0);
+        assert_eq!(schema_id("vector"), Some(0));
+        assert_eq!(schema_id("custom"), Some(3));
+        assert_eq!(schema_id("image"), None);
+    }
+
+    #[test]
+    fn guest_parser_accepts_every_flag_combination() {
+        let payload: Vec<u8> = (0u8..37).collect();
+        for (hash, with_crc) in [
+            (None, false),
+            (None, true),
+            (Some(0x1234_5678), false),
+            (Some(0x1234_5678), true),
+        ] {
+            let wrapped = build_fbh1(&payload, 1, hash, with_crc);
+            assert_eq!(
+                guest_parse(&wrapped, 1, 0x1234_5678),
+                Ok(payload.clone()),
+                "hash {hash:?} crc {with_crc}"
+            );
+        }
+        let empty = build_fbh1(&[], 2, None, true);
+        assert_eq!(guest_parse(&empty, 2, 0), Ok(Vec::new()));
+    }
+
+    #[test]
+    fn guest_parser_rejects_mismatches() {
+        let payload = [9u8; 8];
+        let wrapped = build_fbh1(&payload, 0, Some(0xAAAA_AAAA), true);
+        assert_eq!(guest_parse(&wrapped, 1, 0xAAAA_AAAA), Err(ERR_SCHEMA));
+        assert_eq!(guest_parse(&wrapped, 0, 0xBBBB_BBBB), Err(ERR_SCHEMA));
+
+        let mut corrupt = wrapped.clone();
+        corrupt[FBH1_HEADER_LEN] ^= 1;
+        assert_eq!(guest_parse(&corrupt, 0, 0xAAAA_AAAA), Err(ERR_INPUT_HEADER));
+
+        // Trailing bytes beyond payload_len are a header error, not ignored.
+        let mut long = wrapped;
+        long.push(0);
+        assert_eq!(guest_parse(&long, 0, 0xAAAA_AAAA), Err(ERR_INPUT_HEADER));
+    }
+}
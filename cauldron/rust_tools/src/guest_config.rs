@@ -0,0 +1,975 @@
+//! Guest `config.rs` generation.
+//!
+//! Rust port of the "patched by Cauldron" step in `cauldron/guest.py`: given the
+//! model parameters for a template, compute the scratch layout, check that the
+//! regions fit below the program image and the stack, and render the constants
+//! file the guest templates `include`.
+
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+
+pub const DEFAULT_SCRATCH_MIN: usize = 262_144;
+pub const DEFAULT_RESERVED_TAIL: usize = 32;
+pub const DEFAULT_STACK_GUARD: usize = 0x4000;
+pub const DEFAULT_HIDDEN_OFFSET: usize = 0x3000;
+pub const DEFAULT_CONV_OFFSET: usize = 0x3000;
+pub const DEFAULT_Q16: i32 = 1 << 16;
+pub const DEFAULT_ENTRY: usize = 0x4000;
+pub const DEFAULT_INPUT_OFFSET: usize = 0x1000;
+pub const DEFAULT_OUTPUT_OFFSET: usize = 0x2000;
+pub const CONTROL_SIZE: usize = 64;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TemplateKind {
+    Linear,
+    Softmax,
+    NaiveBayes,
+    Mlp,
+    Mlp2,
+    Mlp3,
+    Cnn1d,
+    TinyCnn,
+    TwoTower,
+    Tree,
+    Custom,
+}
+
+impl TemplateKind {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "linear" => Some(Self::Linear),
+            "softmax" => Some(Self::Softmax),
+            "naive_bayes" => Some(Self::NaiveBayes),
+            "mlp" => Some(Self::Mlp),
+            "mlp2" => Some(Self::Mlp2),
+            "mlp3" => Some(Self::Mlp3),
+            "cnn1d" => Some(Self::Cnn1d),
+            "tiny_cnn" => Some(Self::TinyCnn),
+            "two_tower" => Some(Self::TwoTower),
+            "tree" => Some(Self::Tree),
+            "custom" => Some(Self::Custom),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Linear => "linear",
+            Self::Softmax => "softmax",
+            Self::NaiveBayes => "naive_bayes",
+            Self::Mlp => "mlp",
+            Self::Mlp2 => "mlp2",
+            Self::Mlp3 => "mlp3",
+            Self::Cnn1d => "cnn1d",
+            Self::TinyCnn => "tiny_cnn",
+            Self::TwoTower => "two_tower",
+            Self::Tree => "tree",
+            Self::Custom => "custom",
+        }
+    }
+}
+
+/// Manifest `[abi]` / `[build]` values that shape the scratch layout.
+#[derive(Clone, Copy, Debug)]
+pub struct AbiParams {
+    pub entry: usize,
+    pub control_offset: usize,
+    pub input_offset: usize,
+    pub input_max: usize,
+    pub output_offset: usize,
+    pub output_max: usize,
+    pub scratch_min: usize,
+    pub reserved_tail: usize,
+    pub stack_guard: usize,
+}
+
+impl Default for AbiParams {
+    fn default() -> Self {
+        Self {
+            entry: DEFAULT_ENTRY,
+            control_offset: 0,
+            input_offset: DEFAULT_INPUT_OFFSET,
+            input_max: 4096,
+            output_offset: DEFAULT_OUTPUT_OFFSET,
+            output_max: 256,
+            scratch_min: DEFAULT_SCRATCH_MIN,
+            reserved_tail: DEFAULT_RESERVED_TAIL,
+            stack_guard: DEFAULT_STACK_GUARD,
+        }
+    }
+}
+
+/// Where the first weights blob lives.
+#[derive(Clone, Copy, Debug)]
+pub struct WeightsRef {
+    pub seg: u32,
+    pub offset: usize,
+    pub data_offset: usize,
+}
+
+impl Default for WeightsRef {
+    fn default() -> Self {
+        Self {
+            seg: 1,
+            offset: 0,
+            data_offset: 0,
+        }
+    }
+}
+
+/// Per-template parameters. Scratch offsets are absolute offsets in segment 0.
+#[derive(Clone, Debug)]
+pub enum ModelParams {
+    Linear {
+        input_dim: usize,
+        output_dim: usize,
+        w_scale_q16: i32,
+        has_bias: bool,
+    },
+    /// Shared by the `softmax` and `naive_bayes` templates.
+    Softmax {
+        input_dim: usize,
+        output_dim: usize,
+        w_scale_q16: i32,
+        has_bias: bool,
+        apply_softmax: bool,
+    },
+    Mlp {
+        input_dim: usize,
+        hidden_dim: usize,
+        output_dim: usize,
+        w1_scale_q16: i32,
+        w2_scale_q16: i32,
+        hidden_offset: usize,
+    },
+    Mlp2 {
+        input_dim: usize,
+        hidden_dims: [usize; 2],
+        output_dim: usize,
+        scales_q16: [i32; 3],
+        has_bias: bool,
+        hidden_offsets: [usize; 2],
+    },
+    Mlp3 {
+        input_dim: usize,
+        hidden_dims: [usize; 3],
+        output_dim: usize,
+        scales_q16: [i32; 4],
+        has_bias: bool,
+        hidden_offsets: [usize; 3],
+    },
+    Cnn1d {
+        input_len: usize,
+        input_channels: usize,
+        output_dim: usize,
+        kernel_size: usize,
+        stride: usize,
+        out_channels: usize,
+        w1_scale_q16: i32,
+        w2_scale_q16: i32,
+        has_bias: bool,
+        conv_offset: usize,
+    },
+    TinyCnn {
+        input_height: usize,
+        input_width: usize,
+        output_dim: usize,
+        kernel_size: usize,
+        stride: usize,
+        out_channels: usize,
+        w1_scale_q16: i32,
+        w2_scale_q16: i32,
+        has_bias: bool,
+        conv_offset: usize,
+    },
+    TwoTower {
+        input_dim_a: usize,
+        input_dim_b: usize,
+        embed_dim: usize,
+        output_dim: usize,
+        w1_scale_q16: i32,
+        w2_scale_q16: i32,
+        has_bias: bool,
+        dot_shift: u32,
+        embed_a_offset: usize,
+        embed_b_offset: usize,
+    },
+    Tree {
+        input_dim: usize,
+        output_dim: usize,
+        tree_count: usize,
+        tree_node_count: usize,
+        tree_stride: usize,
+    },
+    Custom {
+        input_blob_size: usize,
+        output_blob_size: usize,
+    },
+}
+
+#[derive(Clone, Debug)]
+pub struct GuestConfig {
+    pub template: TemplateKind,
+    pub abi: AbiParams,
+    pub weights: WeightsRef,
+    pub model: ModelParams,
+    pub expected_schema_id: u32,
+    pub expected_schema_hash: u32,
+    /// Canonical schema bytes (`schema_hash_mode = "auto"`). When set, the
+    /// config embeds them and derives `EXPECTED_SCHEMA_HASH` with
+    /// `frostbite_sdk::schema_hash`; `expected_schema_hash` is only echoed in
+    /// a comment.
+    pub schema_canonical: Option<Vec<u8>>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Region {
+    pub name: &'static str,
+    pub start: usize,
+    pub len: usize,
+}
+
+impl Region {
+    pub fn end(&self) -> usize {
+        self.start + self.len
+    }
+
+    fn overlaps(&self, other: &Region) -> bool {
+        self.len != 0 && other.len != 0 && self.start < other.end() && other.start < self.end()
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct GuestLayout {
+    pub stack_ptr: usize,
+    pub regions: Vec<Region>,
+}
+
+/// Chain `dims.len()` i32 buffers back to back starting at `base`, matching the
+/// defaults `guest.py` uses for `hidden_offset2`/`hidden_offset3`.
+pub fn chained_offsets<const N: usize>(base: usize, dims: [usize; N]) -> [usize; N] {
+    let mut out = [0usize; N];
+    let mut cursor = base;
+    for (slot, dim) in out.iter_mut().zip(dims) {
+        *slot = cursor;
+        cursor += dim * 4;
+    }
+    out
+}
+
+fn template_matches(template: TemplateKind, model: &ModelParams) -> bool {
+    matches!(
+        (template, model),
+        (TemplateKind::Linear, ModelParams::Linear { .. })
+            | (TemplateKind::Softmax, ModelParams::Softmax { .. })
+            | (TemplateKind::NaiveBayes, ModelParams::Softmax { .. })
+            | (TemplateKind::Mlp, ModelParams::Mlp { .. })
+            | (TemplateKind::Mlp2, ModelParams::Mlp2 { .. })
+            | (TemplateKind::Mlp3, ModelParams::Mlp3 { .. })
+            | (TemplateKind::Cnn1d, ModelParams::Cnn1d { .. })
+            | (TemplateKind::TinyCnn, ModelParams::TinyCnn { .. })
+            | (TemplateKind::TwoTower, ModelParams::TwoTower { .. })
+            | (TemplateKind::Tree, ModelParams::Tree { .. })
+            | (TemplateKind::Custom, ModelParams::Custom { .. })
+    )
+}
+
+fn scratch_regions(model: &ModelParams) -> Result<Vec<Region>, Box<dyn std::error::Error>> {
+    let mut regions = Vec::new();
+    match *model {
+        ModelParams::Mlp {
+            hidden_dim,
+            hidden_offset,
+            ..
+        } => regions.push(Region {
+            name: "hidden",
+            start: hidden_offset,
+            len: hidden_dim * 4,
+        }),
+        ModelParams::Mlp2 {
+            hidden_dims,
+            hidden_offsets,
+            ..
+        } => {
+            for (idx, name) in ["hidden1", "hidden2"].into_iter().enumerate() {
+                regions.push(Region {
+                    name,
+                    start: hidden_offsets[idx],
+                    len: hidden_dims[idx] * 4,
+                });
+            }
+        }
+        ModelParams::Mlp3 {
+            hidden_dims,
+            hidden_offsets,
+            ..
+        } => {
+            for (idx, name) in ["hidden1", "hidden2", "hidden3"].into_iter().enumerate() {
+                regions.push(Region {
+                    name,
+                    start: hidden_offsets[idx],
+                    len: hidden_dims[idx] * 4,
+                });
+            }
+        }
+        ModelParams::Cnn1d {
+            input_len,
+            kernel_size,
+            stride,
+            out_channels,
+            conv_offset,
+            ..
+        } => {
+            if stride == 0 || kernel_size == 0 || input_len < kernel_size {
+                return Err("cnn1d requires stride >= 1 and input_len >= kernel_size".into());
+            }
+            let out_len = (input_len - kernel_size) / stride + 1;
+            // Conv activations followed by the pooled per-channel vector.
+            regions.push(Region {
+                name: "conv",
+                start: conv_offset,
+                len: (out_len * out_channels + out_channels) * 4,
+            });
+        }
+        ModelParams::TinyCnn {
+            out_channels,
+            conv_offset,
+            ..
+        } => regions.push(Region {
+            name: "conv",
+            start: conv_offset,
+            len: out_channels * 4,
+        }),
+        ModelParams::TwoTower {
+            embed_dim,
+            embed_a_offset,
+            embed_b_offset,
+            ..
+        } => {
+            regions.push(Region {
+                name: "embed_a",
+                start: embed_a_offset,
+                len: embed_dim * 4,
+            });
+            regions.push(Region {
+                name: "embed_b",
+                start: embed_b_offset,
+                len: embed_dim * 4,
+            });
+        }
+        _ => {}
+    }
+    Ok(regions)
+}
+
+/// Compute the stack pointer and every fixed region in segment 0, then check
+/// that no two regions overlap and that all of them end below both the program
+/// entry and the stack guard.
+pub fn compute_layout(cfg: &GuestConfig) -> Result<GuestLayout, Box<dyn std::error::Error>> {
+    if !template_matches(cfg.template, &cfg.model) {
+        return Err(format!("model params do not match template {}", cfg.template.name()).into());
+    }
+    match cfg.model {
+        ModelParams::TwoTower { output_dim, .. } | ModelParams::Tree { output_dim, .. }
+            if output_dim != 1 =>
+        {
+            return Err(format!("{} template requires output_dim = 1", cfg.template.name()).into());
+        }
+        _ => {}
+    }
+    let abi = &cfg.abi;
+    if abi.scratch_min == 0 {
+        return Err("abi.scratch_min must be a positive integer".into());
+    }
+    if abi.scratch_min <= abi.reserved_tail + abi.stack_guard {
+        return Err("scratch_min too small for stack guard and reserved_tail".into());
+    }
+    let stack_ptr = abi.scratch_min - abi.reserved_tail - abi.stack_guard;
+
+    let mut regions = vec![
+        Region {
+            name: "control",
+            start: abi.control_offset,
+            len: CONTROL_SIZE,
+        },
+        Region {
+            name: "input",
+            start: abi.input_offset,
+            len: abi.input_max,
+        },
+        Region {
+            name: "output",
+            start: abi.output_offset,
+            len: abi.output_max,
+        },
+    ];
+    regions.extend(scratch_regions(&cfg.model)?);
+
+    let limit = abi.entry.min(stack_ptr);
+    for region in &regions {
+        if region.end() > limit {
+            return Err(format!(
+                "{} region 0x{:X}..0x{:X} crosses limit 0x{:X}",
+                region.name,
+                region.start,
+                region.end(),
+                limit
+            )
+            .into());
+        }
+    }
+    for (idx, a) in regions.iter().enumerate() {
+        for b in &regions[idx + 1..] {
+            if a.overlaps(b) {
+                return Err(format!(
+                    "{} region 0x{:X}..0x{:X} overlaps {} region 0x{:X}..0x{:X}",
+                    a.name,
+                    a.start,
+                    a.end(),
+                    b.name,
+                    b.start,
+                    b.end()
+                )
+                .into());
+            }
+        }
+    }
+
+    Ok(GuestLayout { stack_ptr, regions })
+}
+
+fn push_weights(out: &mut String, weights: &WeightsRef) {
+    let _ = writeln!(out, "pub const WEIGHTS_SEG: u32 = {};", weights.seg);
+    let _ = writeln!(out, "pub const WEIGHTS_OFFSET: usize = {};", weights.offset);
+    let _ = writeln!(
+        out,
+        "pub const WEIGHTS_DATA_OFFSET: usize = {};",
+        weights.data_offset
+    );
+}
+
+fn push_scales(out: &mut String, scales: &[i32]) {
+    for (idx, scale) in scales.iter().enumerate() {
+        let _ = writeln!(out, "pub const W{}_SCALE_Q16: i32 = {};", idx + 1, scale);
+    }
+}
+
+/// Rust byte-string literal for `data`, escaped like `guest.py::_rust_byte_string`.
+fn rust_byte_string(data: &[u8]) -> String {
+    let mut out = String::from("b\"");
+    for &b in data {
+        match b {
+            b'"' | b'\\' => {
+                out.push('\\');
+                out.push(b as char);
+            }
+            0x20..=0x7E => out.push(b as char),
+            _ => {
+                let _ = write!(out, "\\x{b:02x}");
+            }
+        }
+    }
+    out.push('"');
+    out
+}
+
+/// Render `config.rs` for `cfg`. The output matches `guest.py::render_config`.
+pub fn render_config(cfg: &GuestConfig) -> Result<String, Box<dyn std::error::Error>> {
+    let layout = compute_layout(cfg)?;
+    let abi = &cfg.abi;
+    let mut out = String::new();
+    out.push_str("//! Auto-generated config constants (patched by Cauldron).\n\n");
+    let _ = writeln!(
+        out,
+        "pub const CONTROL_OFFSET: usize = 0x{:04X};",
+        abi.control_offset
+    );
+    let _ = writeln!(out, "pub const INPUT_MAX: usize = {};", abi.input_max);
+    let _ = writeln!(out, "pub const OUTPUT_MAX: usize = {};", abi.output_max);
+    out.push('\n');
+    let _ = writeln!(out, "pub const SCRATCH_MIN: usize = {};", abi.scratch_min);
+    let _ = writeln!(
+        out,
+        "pub const RESERVED_TAIL: usize = {};",
+        abi.reserved_tail
+    );
+    let _ = writeln!(
+        out,
+        "pub const STACK_GUARD: usize = 0x{:X};",
+        abi.stack_guard
+    );
+    let _ = writeln!(out, "pub const STACK_PTR: usize = {};", layout.stack_ptr);
+
+    match cfg.model {
+        ModelParams::Linear {
+            input_dim,
+            output_dim,
+            w_scale_q16,
+            has_bias,
+        } => {
+            let _ = write!(
+                out,
+                "\npub const INPUT_DIM: usize = {input_dim};\n\
+                 pub const OUTPUT_DIM: usize = {output_dim};\n\n"
+            );
+            push_weights(&mut out, &cfg.weights);
+            let _ = write!(
+                out,
+                "\npub const W_SCALE_Q16: i32 = {w_scale_q16};\n\
+                 pub const HAS_BIAS: bool = {has_bias};\n"
+            );
+        }
+        ModelParams::Softmax {
+            input_dim,
+            output_dim,
+            w_scale_q16,
+            has_bias,
+            apply_softmax,
+        } => {
+            let _ = write!(
+                out,
+                "\npub const INPUT_DIM: usize = {input_dim};\n\
+                 pub const OUTPUT_DIM: usize = {output_dim};\n\n"
+            );
+            push_weights(&mut out, &cfg.weights);
+            let _ = write!(
+                out,
+                "\npub const W_SCALE_Q16: i32 = {w_scale_q16};\n\
+                 pub const HAS_BIAS: bool = {has_bias};\n\
+                 pub const APPLY_SOFTMAX: bool = {apply_softmax};\n"
+            );
+        }
+        ModelParams::Mlp {
+            input_dim,
+            hidden_dim,
+            output_dim,
+            w1_scale_q16,
+            w2_scale_q16,
+            hidden_offset,
+        } => {
+            let _ = write!(
+                out,
+                "\npub const INPUT_DIM: usize = {input_dim};\n\
+                 pub const HIDDEN_DIM: usize = {hidden_dim};\n\
+                 pub const OUTPUT_DIM: usize = {output_dim};\n\n"
+            );
+            push_weights(&mut out, &cfg.weights);
+            out.push('\n');
+            push_scales(&mut out, &[w1_scale_q16, w2_scale_q16]);
+            let _ = writeln!(
+                out,
+                "\npub const HIDDEN_OFFSET: usize = 0x{hidden_offset:X};"
+            );
+        }
+        ModelParams::Mlp2 {
+            input_dim,
+            hidden_dims,
+            output_dim,
+            scales_q16,
+            has_bias,
+            hidden_offsets,
+        } => {
+            let _ = write!(
+                out,
+                "\npub const INPUT_DIM: usize = {input_dim};\n\
+                 pub const OUTPUT_DIM: usize = {output_dim};\n\n"
+            );
+            push_weights(&mut out, &cfg.weights);
+            out.push('\n');
+            let _ = writeln!(out, "pub const HIDDEN_DIM1: usize = {};", hidden_dims[0]);
+            let _ = writeln!(out, "pub const HIDDEN_DIM2: usize = {};", hidden_dims[1]);
+            push_scales(&mut out, &scales_q16);
+            let _ = writeln!(out, "pub const HAS_BIAS: bool = {has_bias};\n");
+            let _ = writeln!(
+                out,
+                "pub const HIDDEN1_OFFSET: usize = 0x{:X};",
+                hidden_offsets[0]
+            );
+            let _ = writeln!(
+                out,
+                "pub const HIDDEN2_OFFSET: usize = 0x{:X};",
+                hidden_offsets[1]
+            );
+        }
+        ModelParams::Mlp3 {
+            input_dim,
+            hidden_dims,
+            output_dim,
+            scales_q16,
+            has_bias,
+            hidden_offsets,
+        } => {
+            let _ = write!(
+                out,
+                "\npub const INPUT_DIM: usize = {input_dim};\n\
+                 pub const OUTPUT_DIM: usize = {output_dim};\n\n"
+            );
+            push_weights(&mut out, &cfg.weights);
+            out.push('\n');
+            for (idx, dim) in hidden_dims.iter().enumerate() {
+                let _ = writeln!(out, "pub const HIDDEN_DIM{}: usize = {};", idx + 1, dim);
+            }
+            push_scales(&mut out, &scales_q16);
+            let _ = writeln!(out, "pub const HAS_BIAS: bool = {has_bias};\n");
+            for (idx, offset) in hidden_offsets.iter().enumerate() {
+                let _ = writeln!(
+                    out,
+                    "pub const HIDDEN{}_OFFSET: usize = 0x{:X};",
+                    idx + 1,
+                    offset
+                );
+            }
+        }
+        ModelParams::Cnn1d {
+            input_len,
+            input_channels,
+            output_dim,
+            kernel_size,
+            stride,
+            out_channels,
+            w1_scale_q16,
+            w2_scale_q16,
+            has_bias,
+            conv_offset,
+        } => {
+            let _ = write!(
+                out,
+                "\npub const INPUT_DIM: usize = {};\n\
+                 pub const OUTPUT_DIM: usize = {output_dim};\n\n",
+                input_len * input_channels
+            );
+            push_weights(&mut out, &cfg.weights);
+            let _ = write!(
+                out,
+                "\npub const INPUT_LEN: usize = {input_len};\n\
+                 pub const INPUT_CHANNELS: usize = {input_channels};\n\
+                 pub const KERNEL_SIZE: usize = {kernel_size};\n\
+                 pub const STRIDE: usize = {stride};\n\
+                 pub const OUT_CHANNELS: usize = {out_channels};\n"
+            );
+            push_scales(&mut out, &[w1_scale_q16, w2_scale_q16]);
+            let _ = writeln!(out, "pub const HAS_BIAS: bool = {has_bias};\n");
+            let _ = writeln!(out, "pub const CONV_OFFSET: usize = 0x{conv_offset:X};");
+        }
+        ModelParams::TinyCnn {
+            input_height,
+            input_width,
+            output_dim,
+            kernel_size,
+            stride,
+            out_channels,
+            w1_scale_q16,
+            w2_scale_q16,
+            has_bias,
+            conv_offset,
+        } => {
+            let _ = write!(
+                out,
+                "\npub const INPUT_DIM: usize = {};\n\
+                 pub const OUTPUT_DIM: usize = {output_dim};\n\n",
+                input_height * input_width
+            );
+            push_weights(&mut out, &cfg.weights);
+            let _ = write!(
+                out,
+                "\npub const INPUT_HEIGHT: usize = {input_height};\n\
+                 pub const INPUT_WIDTH: usize = {input_width};\n\
+                 pub const KERNEL_SIZE: usize = {kernel_size};\n\
+                 pub const STRIDE: usize = {stride};\n\
+                 pub const OUT_CHANNELS: usize = {out_channels};\n"
+            );
+            push_scales(&mut out, &[w1_scale_q16, w2_scale_q16]);
+            let _ = writeln!(out, "pub const HAS_BIAS: bool = {has_bias};\n");
+            let _ = writeln!(out, "pub const CONV_OFFSET: usize = 0x{conv_offset:X};");
+        }
+        ModelParams::TwoTower {
+            input_dim_a,
+            input_dim_b,
+            embed_dim,
+            output_dim,
+            w1_scale_q16,
+            w2_scale_q16,
+            has_bias,
+            dot_shift,
+            embed_a_offset,
+            embed_b_offset,
+        } => {
+            let _ = write!(
+                out,
+                "\npub const INPUT_DIM_A: usize = {input_dim_a};\n\
+                 pub const INPUT_DIM_B: usize = {input_dim_b};\n\
+                 pub const EMBED_DIM: usize = {embed_dim};\n\
+                 pub const OUTPUT_DIM: usize = {output_dim};\n\n"
+            );
+            push_weights(&mut out, &cfg.weights);
+            out.push('\n');
+            push_scales(&mut out, &[w1_scale_q16, w2_scale_q16]);
+            let _ = write!(
+                out,
+                "pub const HAS_BIAS: bool = {has_bias};\n\
+                 pub const DOT_SHIFT: u32 = {dot_shift};\n\n\
+                 pub const EMBED_A_OFFSET: usize = 0x{embed_a_offset:X};\n\
+                 pub const EMBED_B_OFFSET: usize = 0x{embed_b_offset:X};\n"
+            );
+        }
+        ModelParams::Tree {
+            input_dim,
+            output_dim,
+            tree_count,
+            tree_node_count,
+            tree_stride,
+        } => {
+            let _ = write!(
+                out,
+                "\npub const INPUT_DIM: usize = {input_dim};\n\
+                 pub const OUTPUT_DIM: usize = {output_dim};\n\n"
+            );
+            push_weights(&mut out, &cfg.weights);
+            let _ = write!(
+                out,
+                "\npub const TREE_COUNT: usize = {tree_count};\n\
+                 pub const TREE_NODE_COUNT: usize = {tree_node_count};\n\
+                 pub const TREE_STRIDE: usize = {tree_stride};\n"
+            );
+        }
+        ModelParams::Custom {
+            input_blob_size,
+            output_blob_size,
+        } => {
+            let _ = write!(
+                out,
+                "\npub const INPUT_BLOB_SIZE: usize = {input_blob_size};\n\
+                 pub const OUTPUT_BLOB_SIZE: usize = {output_blob_size};\n"
+            );
+        }
+    }
+
+    out.push('\n');
+    match &cfg.schema_canonical {
+        Some(canonical) => {
+            // Derive the hash in the guest so it cannot drift from the SDK's algorithm.
+            let _ = write!(
+                out,
+                "pub const SCHEMA_CANONICAL: &[u8] = {};\n\
+                 pub const EXPECTED_SCHEMA_HASH: u32 = frostbite_sdk::schema_hash(SCHEMA_CANONICAL); // 0x{:08X}\n",
+                rust_byte_string(canonical),
+                cfg.expected_schema_hash
+            );
+        }
+        None => {
+            let _ = writeln!(
+                out,
+                "pub const EXPECTED_SCHEMA_HASH: u32 = 0x{:08X};",
+                cfg.expected_schema_hash
+            );
+        }
+    }
+    let _ = writeln!(
+        out,
+        "pub const EXPECTED_SCHEMA_ID: u32 = {};",
+        cfg.expected_schema_id
+    );
+    Ok(out)
+}
+
+/// Render and write `<guest_dir>/src/config.rs`, returning the written path.
+pub fn write_guest_config(
+    guest_dir: &Path,
+    cfg: &GuestConfig,
+) -> Result<PathBuf, Box<dyn std::error::Error>> {
+    let rendered = render_config(cfg)?;
+    let out_dir = guest_dir.join("src");
+    std::fs::create_dir_all(&out_dir)?;
+    let path = out_dir.join("config.rs");
+    std::fs::write(&path, rendered)?;
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mlp2(hidden_offsets: [usize; 2]) -> GuestConfig {
+        GuestConfig {
+            template: TemplateKind::Mlp2,
+            abi: AbiParams::default(),
+            weights: WeightsRef::default(),
+            model: ModelParams::Mlp2 {
+                input_dim: 64,
+                hidden_dims: [32, 16],
+                output_dim: 1,
+                scales_q16: [DEFAULT_Q16; 3],
+                has_bias: true,
+                hidden_offsets,
+            },
+            expected_schema_id: 0,
+            expected_schema_hash: 0,
+            schema_canonical: None,
+        }
+    }
+
+    #[test]
+    fn chained_offsets_match_guest_py_defaults() {
+        assert_eq!(
+            chained_offsets(DEFAULT_HIDDEN_OFFSET, [32, 16]),
+            [0x3000, 0x3080]
+        );
+    }
+
+    #[test]
+    fn default_layout_has_no_overlap() {
+        let cfg = mlp2(chained_offsets(DEFAULT_HIDDEN_OFFSET, [32, 16]));
+        let layout = compute_layout(&cfg).unwrap();
+        assert_eq!(
+            layout.stack_ptr,
+            DEFAULT_SCRATCH_MIN - DEFAULT_RESERVED_TAIL - DEFAULT_STACK_GUARD
+        );
+        for (idx, a) in layout.regions.iter().enumerate() {
+            assert!(a.end() <= DEFAULT_ENTRY);
+            for b in &layout.regions[idx + 1..] {
+                assert!(!a.overlaps(b), "{a:?} overlaps {b:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn overlapping_hidden_buffers_are_rejected() {
+        let err = compute_layout(&mlp2([0x3000, 0x3040])).unwrap_err();
+        assert!(err.to_string().contains("hidden1"));
+    }
+
+    #[test]
+    fn hidden_buffer_overlapping_output_is_rejected() {
+        let err = compute_layout(&mlp2([0x2000, 0x3000])).unwrap_err();
+        assert!(err.to_string().contains("overlaps"));
+    }
+
+    #[test]
+    fn region_crossing_entry_is_rejected() {
+        let err = compute_layout(&mlp2([0x3000, 0x3FF0])).unwrap_err();
+        assert!(err.to_string().contains("crosses"));
+    }
+
+    #[test]
+    fn template_mismatch_is_rejected() {
+        let mut cfg = mlp2(chained_offsets(DEFAULT_HIDDEN_OFFSET, [32, 16]));
+        cfg.template = TemplateKind::Linear;
+        assert!(compute_layout(&cfg).is_err());
+    }
+
+    #[test]
+    fn renders_mlp2_constants() {
+        let cfg = mlp2(chained_offsets(DEFAULT_HIDDEN_OFFSET, [32, 16]));
+        let text = render_config(&cfg).unwrap();
+        assert!(text.starts_with("//! Auto-generated config constants (patched by Cauldron)."));
+        assert!(text.contains("pub const STACK_PTR: usize = 245728;"));
+        assert!(text.contains("pub const HIDDEN2_OFFSET: usize = 0x3080;"));
+        assert!(text.contains("pub const W3_SCALE_Q16: i32 = 65536;"));
+        assert!(text.contains("pub const EXPECTED_SCHEMA_HASH: u32 = 0x00000000;"));
+    }
+
+    /// `tests/fixtures/guest_config_*.txt` hold `guest.py::render_config`
+    /// output for these configs; `tests/test_guest_config_parity.py` checks
+    /// the Python side against the same files.
+    fn parity_config(template: TemplateKind, model: ModelParams) -> GuestConfig {
+        GuestConfig {
+            template,
+            abi: AbiParams::default(),
+            weights: WeightsRef::default(),
+            model,
+            expected_schema_id: 1,
+            expected_schema_hash: 0x1234_ABCD,
+            schema_canonical: None,
+        }
+    }
+
+    // Includes a quote, a backslash and a non-printable byte to cover escaping.
+    const PARITY_CANONICAL: &[u8] = b"{\"type\":\"vector\",\"note\":\"a\\b\"}\x01";
+
+    fn two_tower(output_dim: usize) -> GuestConfig {
+        let mut cfg = parity_config(
+            TemplateKind::TwoTower,
+            ModelParams::TwoTower {
+                input_dim_a: 8,
+                input_dim_b: 4,
+                embed_dim: 16,
+                output_dim,
+                w1_scale_q16: DEFAULT_Q16,
+                w2_scale_q16: DEFAULT_Q16 / 2,
+                has_bias: true,
+                dot_shift: 16,
+                embed_a_offset: 0x3000,
+                embed_b_offset: 0x3040,
+            },
+        );
+        cfg.expected_schema_hash = frostbite_sdk::schema_hash(PARITY_CANONICAL);
+        cfg.schema_canonical = Some(PARITY_CANONICAL.to_vec());
+        cfg
+    }
+
+    fn tree(output_dim: usize) -> GuestConfig {
+        parity_config(
+            TemplateKind::Tree,
+            ModelParams::Tree {
+                input_dim: 10,
+                output_dim,
+                tree_count: 4,
+                tree_node_count: 15,
+                tree_stride: 16,
+            },
+        )
+    }
+
+    #[test]
+    fn two_tower_matches_guest_py() {
+        assert_eq!(
+            render_config(&two_tower(1)).unwrap(),
+            include_str!("../../../tests/fixtures/guest_config_two_tower.txt")
+        );
+    }
+
+    #[test]
+    fn tree_matches_guest_py() {
+        assert_eq!(
+            render_config(&tree(1)).unwrap(),
+            include_str!("../../../tests/fixtures/guest_config_tree.txt")
+        );
+    }
+
+    #[test]
+    fn canonical_schema_derives_the_hash_in_the_guest() {
+        let text = render_config(&two_tower(1)).unwrap();
+        assert!(text.contains("pub const SCHEMA_CANONICAL: &[u8] = b\""));
+        assert!(text.contains(&format!(
+            "pub const EXPECTED_SCHEMA_HASH: u32 = frostbite_sdk::schema_hash(SCHEMA_CANONICAL); // 0x{:08X}\n",
+            frostbite_sdk::schema_hash(PARITY_CANONICAL)
+        )));
+    }
+
+    #[test]
+    fn scalar_templates_reject_wider_output() {
+        for cfg in [two_tower(2), tree(3)] {
+            let err = compute_layout(&cfg).unwrap_err();
+            assert!(err.to_string().contains("requires output_dim = 1"), "{err}");
+        }
+    }
+
+    #[test]
+    fn template_names_round_trip() {
+        for kind in [
+            TemplateKind::Linear,
+            TemplateKind::Softmax,
+            TemplateKind::NaiveBayes,
+            TemplateKind::Mlp,
+            TemplateKind::Mlp2,
+            TemplateKind::Mlp3,
+            TemplateKind::Cnn1d,
+            TemplateKind::TinyCnn,
+            TemplateKind::TwoTower,
+            TemplateKind::Tree,
+            TemplateKind::Custom,
+        ] {
+            assert_eq!(TemplateKind::from_name(kind.name()), Some(kind));
+        }
+    }
+}
@@ -0,0 +1,10 @@
+//! Shared library code for the Frostbite model-kit tools.
+
+pub mod cli_config;
+pub mod fbh1;
+pub mod guest_config;
+pub mod retry;
+pub mod seeds;
+pub mod segments;
+pub mod upload;
+pub mod vm;
@@ -0,0 +1,402 @@
+//! Shared retry policy for RPC send paths.
+//!
+//! Every tool that submits transactions routes the send through [`RetryPolicy`]
+//! so transient cluster failures (expired blockhash, lagging node, timeouts,
+//! rate limits) are retried with capped exponential backoff and jitter, while
+//! program errors and signing failures surface on the first attempt. Sends
+//! that must not run twice retry only [`is_presubmit_retryable`] failures via
+//! [`RetryPolicy::run_when`].
+//!
+//! The defaults can be tuned per shell with `FROSTBITE_RETRY_MAX_ATTEMPTS`,
+//! `FROSTBITE_RETRY_BASE_MS`, `FROSTBITE_RETRY_MAX_MS` and
+//! `FROSTBITE_RETRY_JITTER` (a fraction in `0.0..=1.0`).
+
+use std::env;
+use std::fmt::Display;
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+pub const DEFAULT_MAX_ATTEMPTS: u32 = 4;
+pub const DEFAULT_BASE_DELAY_MS: u64 = 500;
+pub const DEFAULT_MAX_DELAY_MS: u64 = 8_000;
+pub const DEFAULT_JITTER: f64 = 0.2;
+
+// Checked first: anything the program or signer rejected will fail the same
+// way on every attempt.
+const FATAL_MARKERS: &[&str] = &[
+    "custom program error",
+    "instructionerror",
+    "invalid signature",
+    "signature verification",
+    "signaturefailure",
+    "insufficient funds",
+    "insufficientfunds",
+];
+
+const RETRYABLE_MARKERS: &[&str] = &[
+    "blockhash not found",
+    "blockhashnotfound",
+    "block height exceeded",
+    "node is behind",
+    "nodeunhealthy",
+    "timed out",
+    "timeout",
+    "429",
+    "too many requests",
+    "rate limit",
+    "connection reset",
+    "connection refused",
+    "502 bad gateway",
+    "503 service unavailable",
+];
+
+// Failures reported before the cluster accepted the transaction, so a re-signed
+// resend cannot make it run twice.
+const PRESUBMIT_MARKERS: &[&str] = &[
+    "blockhash not found",
+    "blockhashnotfound",
+    "429",
+    "too many requests",
+    "rate limit",
+    "connection refused",
+];
+
+const TIMEOUT_MARKERS: &[&str] = &["timed out", "timeout"];
+
+fn matches_any(message: &str, markers: &[&str]) -> bool {
+    let lowered = message.to_ascii_lowercase();
+    if FATAL_MARKERS.iter().any(|marker| lowered.contains(marker)) {
+        return false;
+    }
+    markers.iter().any(|marker| lowered.contains(marker))
+}
+
+/// Classify an RPC/send error message as transient.
+pub fn is_retryable(message: &str) -> bool {
+    matches_any(message, RETRYABLE_MARKERS)
+}
+
+/// Transient failures that prove the transaction was never accepted. Use this
+/// instead of [`is_retryable`] for sends that must not run twice.
+pub fn is_presubmit_retryable(message: &str) -> bool {
+    matches_any(message, PRESUBMIT_MARKERS)
+}
+
+/// Timeouts: the transaction may or may not have been processed.
+pub fn is_timeout(message: &str) -> bool {
+    matches_any(message, TIMEOUT_MARKERS)
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RetryPolicy {
+    pub max_attempts: u32,
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+    pub jitter: f64,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: DEFAULT_MAX_ATTEMPTS,
+            base_delay: Duration::from_millis(DEFAULT_BASE_DELAY_MS),
+            max_delay: Duration::from_millis(DEFAULT_MAX_DELAY_MS),
+            jitter: DEFAULT_JITTER,
+        }
+    }
+}
+
+fn env_parse<T: std::str::FromStr>(key: &str) -> Option<T> {
+    env::var(key).ok().and_then(|raw| raw.trim().parse().ok())
+}
+
+fn jitter_unit() -> f64 {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos() as u64)
+        .unwrap_or(0);
+    let mut x = nanos ^ COUNTER.fetch_add(0x9E37_79B9_7F4A_7C15, Ordering::Relaxed);
+    x ^= x << 13;
+    x ^= x >> 7;
+    x ^= x << 17;
+    (x >> 11) as f64 / (1u64 << 53) as f64
+}
+
+impl RetryPolicy {
+    /// Defaults overridden by any valid `FROSTBITE_RETRY_*` variables.
+    pub fn from_env() -> Self {
+        let mut policy = Self::default();
+        if let Some(attempts) = env_parse::<u32>("FROSTBITE_RETRY_MAX_ATTEMPTS") {
+            policy.max_attempts = attempts.max(1);
+        }
+        if let Some(ms) = env_parse::<u64>("FROSTBITE_RETRY_BASE_MS") {
+            policy.base_delay = Duration::from_millis(ms);
+        }
+        if let Some(ms) = env_parse::<u64>("FROSTBITE_RETRY_MAX_MS") {
+            policy.max_delay = Duration::from_millis(ms);
+        }
+        if let Some(jitter) = env_parse::<f64>("FROSTBITE_RETRY_JITTER") {
+            if (0.0..=1.0).contains(&jitter) {
+                policy.jitter = jitter;
+            }
+        }
+        policy
+    }
+
+    /// Delay before retry number `attempt` (1-based), with `unit` in `0.0..1.0`
+    /// spreading the result across `±jitter` of the capped exponential delay.
+    pub fn delay_for(&self, attempt: u32, unit: f64) -> Duration {
+        let shift = attempt.saturating_sub(1).min(31);
+        let exp = self.base_delay.saturating_mul(1u32 << shift);
+        let capped = exp.min(self.max_delay);
+        let scale = 1.0 + self.jitter * (2.0 * unit - 1.0);
+        capped.mul_f64(scale.max(0.0))
+    }
+
+    pub fn run<T, E, F>(&self, op: F) -> Result<T, E>
+    where
+        E: Display,
+        F: FnMut() -> Result<T, E>,
+    {
+        self.run_when(is_retryable, op)
+    }
+
+    /// [`run`](Self::run) with `retryable` deciding which error messages are
+    /// retried.
+    pub fn run_when<T, E, F>(&self, retryable: fn(&str) -> bool, mut op: F) -> Result<T, E>
+    where
+        E: Display,
+        F: FnMut() -> Result<T, E>,
+    {
+        let mut attempt = 1;
+        loop {
+            match op() {
+                Ok(value) => return Ok(value),
+                Err(err) if attempt < self.max_attempts && retryable(&err.to_string()) => {
+                    let delay = self.delay_for(attempt, jitter_unit());
+                    eprintln!(
+                        "retrying in {}ms (attempt {}/{}): {}",
+                        delay.as_millis(),
+                        attempt + 1,
+                        self.max_attempts,
+                        err
+                    );
+                    std::thread::sleep(delay);
+                    attempt += 1;
+                }
+                Err(err) => return Err(err),
+            }
+        }
+    }
+
+    pub async fn run_async<T, E, F, Fut>(&self, mut op: F) -> Result<T, E>
+    where
+        E: Display,
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, E>>,
+    {
+        let mut attempt = 1;
+        loop {
+            match op().await {
+                Ok(value) => return Ok(value),
+                Err(err) if attempt < self.max_attempts && is_retryable(&err.to_string()) => {
+                    let delay = self.delay_for(attempt, jitter_unit());
+                    eprintln!(
+                        "retrying in {}ms (attempt {}/{}): {}",
+                        delay.as_millis(),
+                        attempt + 1,
+                        self.max_attempts,
+                        err
+                    );
+                    tokio::time::sleep(delay).await;
+                    attempt += 1;
+                }
+                Err(err) => return Err(err),
+            }
+        }
+    }
+}
+
+/// Run `op` until it succeeds, fails with a non-retryable error, or
+/// `max_attempts` attempts have been made.
+pub fn with_backoff<T, E, F>(
+    max_attempts: u32,
+    base_delay: Duration,
+    max_delay: Duration,
+    jitter: f64,
+    op: F,
+) -> Result<T, E>
+where
+    E: Display,
+    F: FnMut() -> Result<T, E>,
+{
+    RetryPolicy {
+        max_attempts,
+        base_delay,
+        max_delay,
+        jitter,
+    }
+    .run(op)
+}
+
+/// Async form of [`with_backoff`].
+pub async fn with_backoff_async<T, E, F, Fut>(
+    max_attempts: u32,
+    base_delay: Duration,
+    max_delay: Duration,
+    jitter: f64,
+    op: F,
+) -> Result<T, E>
+where
+    E: Display,
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, E>>,
+{
+    RetryPolicy {
+        max_attempts,
+        base_delay,
+        max_delay,
+        jitter,
+    }
+    .run_async(op)
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn classifies_transient_and_fatal_errors() {
+        assert!(is_retryable("RPC response error: Blockhash not found"));
+        assert!(is_retryable(
+            "HTTP status client error (429 Too Many Requests)"
+        ));
+        assert!(is_retryable("Node is behind by 120 slots"));
+        assert!(is_retryable("operation timed out"));
+        assert!(!is_retryable(
+            "Error processing Instruction 0: custom program error: 0x2000"
+        ));
+        assert!(!is_retryable(
+            "Transaction did not pass signature verification"
+        ));
+        assert!(!is_retryable("account not found"));
+    }
+
+    #[test]
+    fn presubmit_excludes_timeouts() {
+        assert!(is_presubmit_retryable(
+            "RPC response error: Blockhash not found"
+        ));
+        assert!(is_presubmit_retryable("429 Too Many Requests"));
+        assert!(is_presubmit_retryable(
+            "error sending request: Connection refused"
+        ));
+        assert!(!is_presubmit_retryable("operation timed out"));
+        assert!(!is_presubmit_retryable("connection reset by peer"));
+        assert!(!is_presubmit_retryable("custom program error: 0x1"));
+        assert!(is_timeout("request timeout"));
+        assert!(!is_timeout("Blockhash not found"));
+
+        let mut calls = 0;
+        let result: Result<(), String> = RetryPolicy {
+            max_attempts: 5,
+            base_delay: Duration::ZERO,
+            max_delay: Duration::ZERO,
+            jitter: 0.0,
+        }
+        .run_when(is_presubmit_retryable, || {
+            calls += 1;
+            Err("operation timed out".to_string())
+        });
+        assert!(result.is_err());
+        assert_eq!(calls, 1);
+    }
+
+    #[test]
+    fn delay_doubles_and_caps() {
+        let policy = RetryPolicy {
+            max_attempts: 8,
+            base_delay: Duration::from_millis(100),
+            max_delay: Duration::from_millis(500),
+            jitter: 0.0,
+        };
+        assert_eq!(policy.delay_for(1, 0.5), Duration::from_millis(100));
+        assert_eq!(policy.delay_for(2, 0.5), Duration::from_millis(200));
+        assert_eq!(policy.delay_for(3, 0.5), Duration::from_millis(400));
+        assert_eq!(policy.delay_for(4, 0.5), Duration::from_millis(500));
+        assert_eq!(policy.delay_for(40, 0.5), Duration::from_millis(500));
+    }
+
+    #[test]
+    fn jitter_stays_within_bounds() {
+        let policy = RetryPolicy {
+            jitter: 0.5,
+            ..RetryPolicy::default()
+        };
+        let base = policy.base_delay;
+        assert_eq!(policy.delay_for(1, 0.0), base.mul_f64(0.5));
+        assert_eq!(policy.delay_for(1, 1.0), base.mul_f64(1.5));
+    }
+
+    #[test]
+    fn retries_transient_then_succeeds() {
+        let mut calls = 0;
+        let result: Result<u32, String> =
+            with_backoff(3, Duration::ZERO, Duration::ZERO, 0.0, || {
+                calls += 1;
+                if calls < 3 {
+                    Err("Blockhash not found".to_string())
+                } else {
+                    Ok(7)
+                }
+            });
+        assert_eq!(result, Ok(7));
+        assert_eq!(calls, 3);
+    }
+
+    #[test]
+    fn fatal_error_is_not_retried() {
+        let mut calls = 0;
+        let result: Result<(), String> =
+            with_backoff(5, Duration::ZERO, Duration::ZERO, 0.0, || {
+                calls += 1;
+                Err("custom program error: 0x1".to_string())
+            });
+        assert!(result.is_err());
+        assert_eq!(calls, 1);
+    }
+
+    #[test]
+    fn gives_up_after_max_attempts() {
+        let mut calls = 0;
+        let result: Result<(), String> =
+            with_backoff(2, Duration::ZERO, Duration::ZERO, 0.0, || {
+                calls += 1;
+                Err("request timed out".to_string())
+            });
+        assert_eq!(result, Err("request timed out".to_string()));
+        assert_eq!(calls, 2);
+    }
+
+    #[tokio::test]
+    async fn async_variant_retries() {
+        let mut calls = 0;
+        let result: Result<u32, String> =
+            with_backoff_async(3, Duration::ZERO, Duration::ZERO, 0.0, || {
+                calls += 1;
+                let attempt = calls;
+                async move {
+                    if attempt < 2 {
+                        Err("429 Too Many Requests".to_string())
+                    } else {
+                        Ok(attempt)
+                    }
+                }
+            })
+            .await;
+        assert_eq!(result, Ok(2));
+    }
+}
@@ -0,0 +1,202 @@
+//! Seeded (deterministic) account addressing shared by the CLI tools.
+//!
+//! VM and segment accounts are `create_with_seed` addresses under the
+//! authority, with seeds `fbv1:vm:<seed:016x>` and
+//! `fbv1:sg:<seed:016x>:<kind:02x><slot:02x>`.
+
+use solana_sdk::pubkey::{Pubkey, MAX_SEED_LEN};
+
+pub const SEEDED_VM_PREFIX: &str = "fbv1:vm:";
+pub const SEEDED_SEG_PREFIX: &str = "fbv1:sg:";
+
+/// Every seed string has a fixed length: the hex fields are zero-padded, so
+/// these hold for any `vm_seed`, `kind` and `slot`.
+pub const VM_SEED_LEN: usize = SEEDED_VM_PREFIX.len() + 16;
+pub const SEGMENT_SEED_LEN: usize = SEEDED_SEG_PREFIX.len() + 16 + 1 + 4;
+
+const _: () = assert!(
+    VM_SEED_LEN <= MAX_SEED_LEN,
+    "VM seed exceeds create_with_seed limit"
+);
+const _: () = assert!(
+    SEGMENT_SEED_LEN <= MAX_SEED_LEN,
+    "segment seed exceeds create_with_seed limit"
+);
+
+pub const SEGMENT_KIND_WEIGHTS: u8 = 1;
+pub const SEGMENT_KIND_RAM: u8 = 2;
+
+/// Decimal or `0x`-prefixed hex.
+pub fn parse_u64_value(raw: &str) -> Result<u64, Box<dyn std::error::Error>> {
+    let trimmed = raw.trim();
+    if trimmed.is_empty() {
+        return Err("numeric value cannot be empty".into());
+    }
+    if let Some(hex) = trimmed
+        .strip_prefix("0x")
+        .or_else(|| trimmed.strip_prefix("0X"))
+    {
+        return Ok(u64::from_str_radix(hex, 16)?);
+    }
+    Ok(trimmed.parse::<u64>()?)
+}
+
+pub fn parse_segment_kind(raw: &str) -> Result<u8, Box<dyn std::error::Error>> {
+    let lowered = raw.trim().to_ascii_lowercase();
+    match lowered.as_str() {
+        "1" | "weights" => Ok(SEGMENT_KIND_WEIGHTS),
+        "2" | "ram" => Ok(SEGMENT_KIND_RAM),
+        _ => Err(format!(
+            "unsupported segment kind '{}'; expected weights|ram|1|2",
+            raw
+        )
+        .into()),
+    }
+}
+
+pub fn kind_name(kind: u8) -> &'static str {
+    match kind {
+        SEGMENT_KIND_WEIGHTS => "weights",
+        SEGMENT_KIND_RAM => "ram",
+        _ => "unknown",
+    }
+}
+
+pub fn vm_seed_string(vm_seed: u64) -> String {
+    format!("{}{vm_seed:016x}", SEEDED_VM_PREFIX)
+}
+
+pub fn segment_seed_string(vm_seed: u64, kind: u8, slot: u8) -> String {
+    format!("{}{vm_seed:016x}:{kind:02x}{slot:02x}", SEEDED_SEG_PREFIX)
+}
+
+pub fn derive_seeded_address(
+    authority: &Pubkey,
+    seed: &str,
+    program_id: &Pubkey,
+) -> Result<Pubkey, Box<dyn std::error::Error>> {
+    if seed.len() > MAX_SEED_LEN {
+        return Err(format!(
+            "seed '{}' is {} bytes; create_with_seed allows at most {}",
+            seed,
+            seed.len(),
+            MAX_SEED_LEN
+        )
+        .into());
+    }
+    Ok(Pubkey::create_with_seed(authority, seed, program_id)?)
+}
+
+pub fn derive_vm_pda(
+    program_id: &Pubkey,
+    authority: &Pubkey,
+    vm_seed: u64,
+) -> Result<Pubkey, Box<dyn std::error::Error>> {
+    let seed = vm_seed_string(vm_seed);
+    derive_seeded_address(authority, &seed, program_id)
+}
+
+pub fn derive_segment_pda(
+    program_id: &Pubkey,
+    authority: &Pubkey,
+    vm_seed: u64,
+    kind: u8,
+    slot: u8,
+) -> Result<Pubkey, Box<dyn std::error::Error>> {
+    let seed = segment_seed_string(vm_seed, kind, slot);
+    derive_seeded_address(authority, &seed, program_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn seed_strings() {
+        assert_eq!(vm_seed_string(0x2a), "fbv1:vm:000000000000002a");
+        assert_eq!(
+            segment_seed_string(u64::MAX, SEGMENT_KIND_WEIGHTS, 15),
+            "fbv1:sg:ffffffffffffffff:010f"
+        );
+        assert_eq!(segment_seed_string(u64::MAX, 0xff, 0xff).len(), 29);
+    }
+
+    #[test]
+    fn seed_strings_follow_documented_format() {
+        // Lowercase, zero-padded hex: `fbv1:vm:<seed:016x>` and
+        // `fbv1:sg:<seed:016x>:<kind:02x><slot:02x>`.
+        assert_eq!(vm_seed_string(0), "fbv1:vm:0000000000000000");
+        assert_eq!(
+            vm_seed_string(0xDEAD_BEEF_0123_4567),
+            "fbv1:vm:deadbeef01234567"
+        );
+        assert_eq!(
+            segment_seed_string(0, SEGMENT_KIND_RAM, 2),
+            "fbv1:sg:0000000000000000:0202"
+        );
+        assert_eq!(
+            segment_seed_string(0xDEAD_BEEF_0123_4567, SEGMENT_KIND_WEIGHTS, 1),
+            "fbv1:sg:deadbeef01234567:0101"
+        );
+        // The segment seed extends the VM seed's hex, so both parse back to
+        // the same number.
+        let segment = segment_seed_string(12345, SEGMENT_KIND_RAM, 15);
+        let vm = vm_seed_string(12345);
+        assert_eq!(
+            segment[SEEDED_SEG_PREFIX.len()..][..16],
+            vm[SEEDED_VM_PREFIX.len()..]
+        );
+        assert_eq!(
+            u64::from_str_radix(&vm[SEEDED_VM_PREFIX.len()..], 16).unwrap(),
+            12345
+        );
+    }
+
+    #[test]
+    fn seed_lengths_are_fixed_and_within_limit() {
+        for vm_seed in [0, 1, 0x2a, u32::MAX as u64, u64::MAX] {
+            assert_eq!(vm_seed_string(vm_seed).len(), VM_SEED_LEN);
+            for (kind, slot) in [
+                (0, 0),
+                (SEGMENT_KIND_WEIGHTS, 1),
+                (SEGMENT_KIND_RAM, 15),
+                (0xff, 0xff),
+            ] {
+                assert_eq!(
+                    segment_seed_string(vm_seed, kind, slot).len(),
+                    SEGMENT_SEED_LEN
+                );
+            }
+        }
+        assert_eq!((VM_SEED_LEN, SEGMENT_SEED_LEN), (24, 29));
+    }
+
+    #[test]
+    fn parses_numbers_and_kinds() {
+        assert_eq!(parse_u64_value(" 42 ").unwrap(), 42);
+        assert_eq!(parse_u64_value("0X2A").unwrap(), 42);
+        assert!(parse_u64_value("").is_err());
+        assert!(parse_u64_value("0xZZ").is_err());
+        assert_eq!(parse_segment_kind("Weights").unwrap(), SEGMENT_KIND_WEIGHTS);
+        assert_eq!(parse_segment_kind("2").unwrap(), SEGMENT_KIND_RAM);
+        assert!(parse_segment_kind("code").is_err());
+        assert_eq!(kind_name(SEGMENT_KIND_RAM), "ram");
+    }
+
+    #[test]
+    fn derivation_matches_create_with_seed() {
+        let (program, authority) = (Pubkey::new_unique(), Pubkey::new_unique());
+        assert_eq!(
+            derive_segment_pda(&program, &authority, 7, SEGMENT_KIND_WEIGHTS, 1).unwrap(),
+            Pubkey::create_with_seed(&authority, "fbv1:sg:0000000000000007:0101", &program)
+                .unwrap()
+        );
+        assert_ne!(
+            derive_vm_pda(&program, &authority, 7).unwrap(),
+            derive_vm_pda(&program, &authority, 8).unwrap()
+        );
+        let err = derive_seeded_address(&authority, &"x".repeat(33), &program).unwrap_err();
+        assert!(err.to_string().contains("is 33 bytes"), "{err}");
+        assert!(derive_seeded_address(&authority, &"x".repeat(32), &program).is_ok());
+    }
+}
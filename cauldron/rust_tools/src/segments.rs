@@ -0,0 +1,273 @@
+//! `kind:slot:bytes` segment specs for `init_pda_accounts`, and the
+//! read-only segment probe behind `pda_account_ops inspect`.
+
+use crate::seeds::{kind_name, parse_segment_kind, parse_u64_value};
+use crate::upload::{BINARY_HEADER_SIZE, BINARY_MAGIC};
+use solana_sdk::{account::Account, pubkey::Pubkey};
+use std::fmt;
+
+/// Bytes one segment can map: virtual addresses keep a 28-bit offset below
+/// the 4-bit segment index (`MAX_SEGMENT_BYTES` in `cauldron/constants.py`).
+pub const MAX_SEGMENT_BYTES: u64 = 0x1000_0000;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SegmentSpec {
+    pub kind: u8,
+    pub slot: u8,
+    pub payload_len: u32,
+}
+
+pub fn parse_segment_spec(raw: &str) -> Result<SegmentSpec, Box<dyn std::error::Error>> {
+    let parts: Vec<&str> = raw.split(':').collect();
+    if parts.len() != 3 {
+        return Err("segment spec must be kind:slot:bytes".into());
+    }
+    let kind = parse_segment_kind(parts[0])?;
+    let slot = parse_u64_value(parts[1])?;
+    if !(1..=15).contains(&slot) {
+        return Err("segment slot must be in 1..=15".into());
+    }
+    let payload_len = parse_u64_value(parts[2])?;
+    if payload_len > u32::MAX as u64 {
+        return Err("segment payload bytes exceed u32::MAX".into());
+    }
+    Ok(SegmentSpec {
+        kind,
+        slot: slot as u8,
+        payload_len: payload_len as u32,
+    })
+}
+
+/// Reject specs the VM could not map: a repeated `(kind, slot)` pair, or a
+/// segment whose header plus payload runs past its addressable window.
+pub fn validate_segment_specs(specs: &[SegmentSpec]) -> Result<(), String> {
+    for (idx, spec) in specs.iter().enumerate() {
+        if specs[..idx]
+            .iter()
+            .any(|prev| prev.kind == spec.kind && prev.slot == spec.slot)
+        {
+            return Err(format!(
+                "duplicate segment {}:{}",
+                kind_name(spec.kind),
+                spec.slot
+            ));
+        }
+        let mapped = BINARY_HEADER_SIZE as u64 + spec.payload_len as u64;
+        if mapped > MAX_SEGMENT_BYTES {
+            return Err(format!(
+                "segment {}:{} needs {} bytes ({} payload + {} header), over the {}-byte mappable segment window",
+                kind_name(spec.kind),
+                spec.slot,
+                mapped,
+                spec.payload_len,
+                BINARY_HEADER_SIZE,
+                MAX_SEGMENT_BYTES
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Check a `CLEAR_SEGMENT_SEEDED` range against a segment account of
+/// `account_len` bytes. `clear_len == 0` clears from the offset to the end.
+pub fn check_clear_range(
+    account_len: usize,
+    payload_offset: u32,
+    clear_len: u32,
+) -> Result<(), String> {
+    let Some(capacity) = account_len.checked_sub(BINARY_HEADER_SIZE) else {
+        return Err(format!(
+            "segment account is {} bytes, smaller than the {}-byte header",
+            account_len, BINARY_HEADER_SIZE
+        ));
+    };
+    let end = payload_offset as u64 + clear_len as u64;
+    if end > capacity as u64 {
+        return Err(format!(
+            "clear range {}..{} exceeds the segment's {}-byte payload area",
+            payload_offset, end, capacity
+        ));
+    }
+    Ok(())
+}
+
+/// The 12-byte RVCD header at the start of a segment account.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SegmentHeader {
+    pub magic: [u8; 4],
+    pub payload_len: u32,
+    pub reserved: u32,
+}
+
+impl SegmentHeader {
+    /// `None` if the data is shorter than the header; the magic is not checked.
+    pub fn decode(data: &[u8]) -> Option<Self> {
+        let header = data.get(..BINARY_HEADER_SIZE)?;
+        Some(Self {
+            magic: header[..4].try_into().unwrap(),
+            payload_len: u32::from_le_bytes(header[4..8].try_into().unwrap()),
+            reserved: u32::from_le_bytes(header[8..12].try_into().unwrap()),
+        })
+    }
+
+    pub fn has_magic(&self) -> bool {
+        self.magic == BINARY_MAGIC
+    }
+}
+
+/// What an RPC lookup found at a derived address.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SegmentProbe {
+    Missing,
+    Present {
+        size: usize,
+        owner: Pubkey,
+        header: Option<SegmentHeader>,
+    },
+}
+
+pub fn probe_segment(account: Option<&Account>) -> SegmentProbe {
+    match account {
+        None => SegmentProbe::Missing,
+        Some(account) => SegmentProbe::Present {
+            size: account.data.len(),
+            owner: account.owner,
+            header: SegmentHeader::decode(&account.data),
+        },
+    }
+}
+
+impl fmt::Display for SegmentProbe {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (size, owner, header) = match self {
+            SegmentProbe::Missing => return write!(f, "missing"),
+            SegmentProbe::Present {
+                size,
+                owner,
+                header,
+            } => (size, owner, header),
+        };
+        write!(f, "size={} owner={}", size, owner)?;
+        match header {
+            None => write!(f, " header=truncated"),
+            Some(header) if header.has_magic() => {
+                write!(f, " magic=RVCD payload_len={}", header.payload_len)
+            }
+            Some(header) => write!(
+                f,
+                " magic={:02x}{:02x}{:02x}{:02x} (not RVCD)",
+                header.magic[0], header.magic[1], header.magic[2], header.magic[3]
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::seeds::{SEGMENT_KIND_RAM, SEGMENT_KIND_WEIGHTS};
+    use crate::upload::encode_header;
+
+    fn spec(raw: &str) -> SegmentSpec {
+        parse_segment_spec(raw).unwrap()
+    }
+
+    #[test]
+    fn parses_specs() {
+        assert_eq!(
+            spec("weights:1:0x100"),
+            SegmentSpec {
+                kind: SEGMENT_KIND_WEIGHTS,
+                slot: 1,
+                payload_len: 256
+            }
+        );
+        assert_eq!(spec("ram:15:262144").kind, SEGMENT_KIND_RAM);
+        assert!(parse_segment_spec("ram:0:16").is_err());
+        assert!(parse_segment_spec("ram:16:16").is_err());
+        assert!(parse_segment_spec("ram:1").is_err());
+        assert!(parse_segment_spec("ram:1:4294967296").is_err());
+    }
+
+    #[test]
+    fn rejects_duplicate_kind_and_slot() {
+        let specs = [
+            spec("weights:1:1024"),
+            spec("ram:1:262144"),
+            spec("weights:2:1024"),
+        ];
+        assert!(validate_segment_specs(&specs).is_ok());
+
+        let dup = [spec("weights:1:1024"), spec("ram:1:16"), spec("1:1:2048")];
+        assert_eq!(
+            validate_segment_specs(&dup).unwrap_err(),
+            "duplicate segment weights:1"
+        );
+    }
+
+    #[test]
+    fn rejects_segment_past_mappable_window() {
+        let max = (MAX_SEGMENT_BYTES as usize - BINARY_HEADER_SIZE).to_string();
+        assert!(validate_segment_specs(&[spec(&format!("weights:1:{}", max))]).is_ok());
+
+        let over = (MAX_SEGMENT_BYTES as usize - BINARY_HEADER_SIZE + 1).to_string();
+        let specs = [spec("ram:1:16"), spec(&format!("weights:3:{}", over))];
+        let err = validate_segment_specs(&specs).unwrap_err();
+        assert!(err.starts_with("segment weights:3 needs 268435457 bytes"));
+    }
+
+    #[test]
+    fn clear_range_stays_inside_payload() {
+        let account_len = BINARY_HEADER_SIZE + 100;
+        assert!(check_clear_range(account_len, 0, 0).is_ok());
+        assert!(check_clear_range(account_len, 0, 100).is_ok());
+        assert!(check_clear_range(account_len, 60, 40).is_ok());
+        assert!(check_clear_range(account_len, 100, 0).is_ok());
+        assert_eq!(
+            check_clear_range(account_len, 60, 41).unwrap_err(),
+            "clear range 60..101 exceeds the segment's 100-byte payload area"
+        );
+        assert!(check_clear_range(account_len, 101, 0).is_err());
+        assert!(check_clear_range(account_len, u32::MAX, u32::MAX).is_err());
+        assert!(check_clear_range(8, 0, 0).is_err());
+    }
+
+    #[test]
+    fn decodes_segment_header() {
+        let mut data = encode_header(&[7u8; 300]).to_vec();
+        data.extend_from_slice(&[7u8; 300]);
+        let header = SegmentHeader::decode(&data).unwrap();
+        assert!(header.has_magic());
+        assert_eq!(header.payload_len, 300);
+        assert_ne!(header.reserved, 0);
+
+        assert_eq!(SegmentHeader::decode(&data[..11]), None);
+        data[0] = 0;
+        assert!(!SegmentHeader::decode(&data).unwrap().has_magic());
+    }
+
+    #[test]
+    fn probes_accounts() {
+        assert_eq!(probe_segment(None), SegmentProbe::Missing);
+        assert_eq!(probe_segment(None).to_string(), "missing");
+
+        let owner = Pubkey::new_unique();
+        let mut account = Account::new(1, 0, &owner);
+        account.data = encode_header(&[1, 2, 3]).to_vec();
+        account.data.extend_from_slice(&[1, 2, 3, 0]);
+        assert_eq!(
+            probe_segment(Some(&account)).to_string(),
+            format!("size=16 owner={} magic=RVCD payload_len=3", owner)
+        );
+
+        account.data = vec![0xab; 8];
+        assert_eq!(
+            probe_segment(Some(&account)).to_string(),
+            format!("size=8 owner={} header=truncated", owner)
+        );
+        account.data = vec![0xab; 12];
+        assert!(probe_segment(Some(&account))
+            .to_string()
+            .ends_with("magic=abababab (not RVCD)"));
+    }
+}
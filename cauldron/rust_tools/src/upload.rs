@@ -0,0 +1,778 @@
+//! Bookkeeping for `upload_model`.
+//!
+//! A resumable upload records the chunk offsets it has seen confirmed in a
+//! sidecar next to the source file (`<file>.upload.json`). The sidecar is keyed
+//! by target account, payload length, SHA-256 and chunk size, so a changed
+//! source file or a different target starts from scratch instead of trusting
+//! stale offsets.
+//!
+//! Uploaded payloads sit behind the 12-byte RVCD header. Its last word holds a
+//! digest tag (the first four bytes of the payload's SHA-256, little-endian)
+//! when the uploader wrote the header itself; 0 means no tag was recorded.
+
+use serde_json::json;
+use std::collections::BTreeSet;
+use std::path::Path;
+use std::time::Duration;
+
+pub const UPLOAD_STATE_VERSION: u64 = 1;
+
+pub const DEFAULT_CHUNK_SIZE: usize = 900;
+pub const DEFAULT_CONCURRENCY: usize = 100;
+pub const MAX_TRANSACTION_SIZE: usize = solana_sdk::packet::PACKET_DATA_SIZE;
+
+// Instruction data bytes ahead of the chunk: WRITE_ACCOUNT is op + u32 offset,
+// WRITE_SEGMENT_SEEDED is op + u64 seed + kind + slot + u32 offset.
+const LEGACY_WRITE_PREFIX: usize = 1 + 4;
+const SEEDED_WRITE_PREFIX: usize = 1 + 8 + 1 + 1 + 4;
+
+pub const BINARY_HEADER_SIZE: usize = 12;
+pub const BINARY_MAGIC: [u8; 4] = *b"RVCD";
+pub const DIGEST_TAG_OFFSET: usize = 8;
+
+pub fn sha256(data: &[u8]) -> [u8; 32] {
+    solana_sdk::hash::hash(data).to_bytes()
+}
+
+/// Lowercase hex SHA-256 of `data`.
+pub fn sha256_hex(data: &[u8]) -> String {
+    sha256(data).iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+pub fn digest_tag(payload: &[u8]) -> u32 {
+    let digest = sha256(payload);
+    u32::from_le_bytes([digest[0], digest[1], digest[2], digest[3]])
+}
+
+/// RVCD header for `payload`, carrying its digest tag.
+pub fn encode_header(payload: &[u8]) -> [u8; BINARY_HEADER_SIZE] {
+    let mut header = [0u8; BINARY_HEADER_SIZE];
+    header[..4].copy_from_slice(&BINARY_MAGIC);
+    header[4..8].copy_from_slice(&(payload.len() as u32).to_le_bytes());
+    header[DIGEST_TAG_OFFSET..].copy_from_slice(&digest_tag(payload).to_le_bytes());
+    header
+}
+
+/// Whether `account_data` already starts with `encode_header(payload)`. A
+/// reused account whose header carries another file's length or digest tag
+/// has to be rewritten before `--verify-digest` can pass.
+pub fn header_is_current(account_data: &[u8], payload: &[u8]) -> bool {
+    account_data.get(..BINARY_HEADER_SIZE) == Some(&encode_header(payload)[..])
+}
+
+/// Payload of a segment account: validates the RVCD magic and that
+/// `payload_len` fits the account, then strips the header.
+pub fn segment_payload(account_data: &[u8]) -> Result<&[u8], String> {
+    if account_data.len() < BINARY_HEADER_SIZE {
+        return Err(format!(
+            "account is {} bytes, smaller than the {}-byte header",
+            account_data.len(),
+            BINARY_HEADER_SIZE
+        ));
+    }
+    if account_data[..4] != BINARY_MAGIC {
+        return Err("account header magic mismatch (expected RVCD)".to_string());
+    }
+    let payload_len = u32::from_le_bytes(account_data[4..8].try_into().unwrap()) as usize;
+    let available = account_data.len() - BINARY_HEADER_SIZE;
+    if payload_len > available {
+        return Err(format!(
+            "header payload_len {} exceeds the {} bytes after the header",
+            payload_len, available
+        ));
+    }
+    Ok(&account_data[BINARY_HEADER_SIZE..BINARY_HEADER_SIZE + payload_len])
+}
+
+/// Compare the SHA-256 of the payload stored in `account_data` (RVCD header
+/// first) with that of `payload`, and the header's digest tag when one is set.
+/// Returns the hex digest on a match.
+pub fn verify_payload_digest(account_data: &[u8], payload: &[u8]) -> Result<String, String> {
+    if account_data.len() < BINARY_HEADER_SIZE + payload.len() {
+        return Err("account is smaller than header + payload".to_string());
+    }
+    if account_data[..4] != BINARY_MAGIC {
+        return Err("account header magic mismatch".to_string());
+    }
+    let expected = sha256_hex(payload);
+    let stored = &account_data[BINARY_HEADER_SIZE..BINARY_HEADER_SIZE + payload.len()];
+    let actual = sha256_hex(stored);
+    if actual != expected {
+        return Err(format!(
+            "payload digest mismatch: on-chain {} != local {}",
+            actual, expected
+        ));
+    }
+    let tag = u32::from_le_bytes(
+        account_data[DIGEST_TAG_OFFSET..BINARY_HEADER_SIZE]
+            .try_into()
+            .unwrap(),
+    );
+    if tag != 0 && tag != digest_tag(payload) {
+        return Err(format!(
+            "header digest tag 0x{:08x} does not match payload (0x{:08x}); account initialized for a different file?",
+            tag,
+            digest_tag(payload)
+        ));
+    }
+    Ok(expected)
+}
+
+/// Base fee per signature on current clusters (no priority fee).
+pub const LAMPORTS_PER_SIGNATURE: u64 = 5_000;
+
+/// Fee estimate for sending `transactions` transactions, each signed by
+/// `signers` keys. Retries are not included.
+pub fn estimate_fees(transactions: usize, signers: usize) -> u64 {
+    (transactions as u64)
+        .saturating_mul(signers as u64)
+        .saturating_mul(LAMPORTS_PER_SIGNATURE)
+}
+
+/// Pre-flight balance check: rent for accounts still to be created plus
+/// estimated fees must not exceed `balance`.
+pub fn check_balance(balance: u64, rent: u64, fees: u64) -> Result<(), String> {
+    let required = rent.saturating_add(fees);
+    if balance < required {
+        return Err(format!(
+            "payer balance {} lamports is below the estimated {} needed ({} rent + {} fees); fund the payer before uploading",
+            balance, required, rent, fees
+        ));
+    }
+    Ok(())
+}
+
+fn short_vec_len(n: usize) -> usize {
+    match n {
+        0..=0x7f => 1,
+        0x80..=0x3fff => 2,
+        _ => 3,
+    }
+}
+
+/// Wire size of a legacy transaction holding one instruction.
+pub fn single_instruction_tx_size(
+    signers: usize,
+    account_keys: usize,
+    ix_accounts: usize,
+    data_len: usize,
+) -> usize {
+    short_vec_len(signers)
+        + 64 * signers
+        + 3 // message header
+        + short_vec_len(account_keys)
+        + 32 * account_keys
+        + 32 // recent blockhash
+        + short_vec_len(1)
+        + 1 // program id index
+        + short_vec_len(ix_accounts)
+        + ix_accounts
+        + short_vec_len(data_len)
+        + data_len
+}
+
+/// Largest chunk whose write transaction fits in one packet. `seeded` selects
+/// WRITE_SEGMENT_SEEDED over WRITE_ACCOUNT; `separate_authority` adds the
+/// authority as a second signer.
+pub fn max_chunk_size(seeded: bool, separate_authority: bool) -> usize {
+    let signers = if separate_authority { 2 } else { 1 };
+    // Keys: signer(s), target, program, plus the VM account when seeded.
+    let (account_keys, ix_accounts, prefix) = if seeded {
+        (signers + 3, 3, SEEDED_WRITE_PREFIX)
+    } else {
+        (signers + 2, 2, LEGACY_WRITE_PREFIX)
+    };
+    let mut chunk = MAX_TRANSACTION_SIZE;
+    while chunk > 0
+        && single_instruction_tx_size(signers, account_keys, ix_accounts, prefix + chunk)
+            > MAX_TRANSACTION_SIZE
+    {
+        chunk -= 1;
+    }
+    chunk
+}
+
+/// `requested` (from `--chunk-size`) must fit a transaction; without it the
+/// default is capped to what fits.
+pub fn resolve_chunk_size(
+    requested: Option<usize>,
+    seeded: bool,
+    separate_authority: bool,
+) -> Result<usize, String> {
+    let max = max_chunk_size(seeded, separate_authority);
+    match requested {
+        Some(0) => Err("--chunk-size must be positive".to_string()),
+        Some(size) if size > max => Err(format!(
+            "--chunk-size {} does not fit in one transaction; at most {} bytes in this mode",
+            size, max
+        )),
+        Some(size) => Ok(size),
+        None => Ok(DEFAULT_CHUNK_SIZE.min(max)),
+    }
+}
+
+/// `upload_model` command line.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct UploadArgs {
+    pub chunk_path: Option<String>,
+    /// Raw `--split-across-slots <start>:<count>` value.
+    pub split: Option<String>,
+    pub chunk_size: Option<usize>,
+    pub concurrency: usize,
+    pub verify_digest: bool,
+}
+
+impl Default for UploadArgs {
+    fn default() -> Self {
+        Self {
+            chunk_path: None,
+            split: None,
+            chunk_size: None,
+            concurrency: DEFAULT_CONCURRENCY,
+            verify_digest: false,
+        }
+    }
+}
+
+/// `args[0]` is the program name. The first positional argument is the chunk
+/// file; later positionals are ignored.
+pub fn parse_upload_args(args: &[String]) -> Result<UploadArgs, Box<dyn std::error::Error>> {
+    let mut parsed = UploadArgs::default();
+    let mut idx = 1;
+    while idx < args.len() {
+        match args[idx].as_str() {
+            "--split-across-slots" => {
+                let raw = args
+                    .get(idx + 1)
+                    .ok_or("--split-across-slots requires <start>:<count>")?;
+                parsed.split = Some(raw.clone());
+                idx += 2;
+            }
+            "--chunk-size" => {
+                let raw = args.get(idx + 1).ok_or("--chunk-size requires a value")?;
+                parsed.chunk_size = Some(raw.parse()?);
+                idx += 2;
+            }
+            "--concurrency" => {
+                let raw = args.get(idx + 1).ok_or("--concurrency requires a value")?;
+                parsed.concurrency = raw.parse()?;
+                if parsed.concurrency == 0 {
+                    return Err("--concurrency must be positive".into());
+                }
+                idx += 2;
+            }
+            "--verify-digest" => {
+                parsed.verify_digest = true;
+                idx += 1;
+            }
+            other => {
+                if parsed.chunk_path.is_none() {
+                    parsed.chunk_path = Some(other.to_string());
+                }
+                idx += 1;
+            }
+        }
+    }
+    Ok(parsed)
+}
+
+/// Chunk and byte accounting for one payload upload. Elapsed time is passed
+/// in by the caller so the arithmetic does not depend on the clock.
+#[derive(Clone, Debug, PartialEq)]
+pub struct UploadProgress {
+    pub total_chunks: usize,
+    pub total_bytes: usize,
+    /// Chunks known to match on chain: verified at the start of the pass plus
+    /// writes confirmed since.
+    pub confirmed_chunks: usize,
+    /// Bytes sent in confirmed writes during this run.
+    pub bytes_sent: usize,
+    pub failed: usize,
+    report_every: Duration,
+    last_report: Option<Duration>,
+}
+
+impl UploadProgress {
+    pub fn new(total_chunks: usize, total_bytes: usize, report_every: Duration) -> Self {
+        Self {
+            total_chunks,
+            total_bytes,
+            confirmed_chunks: 0,
+            bytes_sent: 0,
+            failed: 0,
+            report_every,
+            last_report: None,
+        }
+    }
+
+    /// Start a verify/send pass in which `pending` chunks still need writes.
+    pub fn start_pass(&mut self, pending: usize) {
+        self.confirmed_chunks = self.total_chunks.saturating_sub(pending);
+        self.failed = 0;
+    }
+
+    pub fn record_sent(&mut self, bytes: usize) {
+        self.confirmed_chunks = (self.confirmed_chunks + 1).min(self.total_chunks);
+        self.bytes_sent += bytes;
+    }
+
+    pub fn record_failed(&mut self) {
+        self.failed += 1;
+    }
+
+    pub fn mb_per_sec(&self, elapsed: Duration) -> f64 {
+        let secs = elapsed.as_secs_f64();
+        if secs <= 0.0 {
+            return 0.0;
+        }
+        self.bytes_sent as f64 / 1_000_000.0 / secs
+    }
+
+    /// Whether a line is due: the first call, every `report_every`, and once
+    /// every chunk is confirmed.
+    pub fn due(&mut self, elapsed: Duration) -> bool {
+        let due = match self.last_report {
+            None => true,
+            Some(last) => {
+                elapsed.saturating_sub(last) >= self.report_every
+                    || self.confirmed_chunks == self.total_chunks
+            }
+        };
+        if due {
+            self.last_report = Some(elapsed);
+        }
+        due
+    }
+
+    pub fn line(&self, elapsed: Duration) -> String {
+        let mut line = format!(
+            "{}/{} chunks, {} bytes sent, {:.2} MB/s",
+            self.confirmed_chunks,
+            self.total_chunks,
+            self.bytes_sent,
+            self.mb_per_sec(elapsed)
+        );
+        if self.failed > 0 {
+            line.push_str(&format!(", {} failed", self.failed));
+        }
+        line
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct UploadState {
+    pub target: String,
+    pub file_len: usize,
+    pub file_sha256: String,
+    pub chunk_size: usize,
+    /// Payload offsets of chunks whose write transaction confirmed.
+    pub confirmed: BTreeSet<usize>,
+}
+
+impl UploadState {
+    pub fn new(target: &str, data: &[u8], chunk_size: usize) -> Self {
+        Self {
+            target: target.to_string(),
+            file_len: data.len(),
+            file_sha256: sha256_hex(data),
+            chunk_size,
+            confirmed: BTreeSet::new(),
+        }
+    }
+
+    /// Same target, payload and chunking; `confirmed` is not compared.
+    pub fn same_upload(&self, other: &UploadState) -> bool {
+        self.target == other.target
+            && self.file_len == other.file_len
+            && self.file_sha256 == other.file_sha256
+            && self.chunk_size == other.chunk_size
+    }
+
+    pub fn is_confirmed(&self, chunk_idx: usize) -> bool {
+        self.confirmed.contains(&(chunk_idx * self.chunk_size))
+    }
+
+    pub fn confirm(&mut self, chunk_idx: usize) {
+        self.confirmed.insert(chunk_idx * self.chunk_size);
+    }
+
+    pub fn forget(&mut self, chunk_idx: usize) {
+        self.confirmed.remove(&(chunk_idx * self.chunk_size));
+    }
+
+    pub fn to_json(&self) -> serde_json::Value {
+        json!({
+            "version": UPLOAD_STATE_VERSION,
+            "target": self.target,
+            "file_len": self.file_len,
+            "file_sha256": self.file_sha256,
+            "chunk_size": self.chunk_size,
+            "confirmed": self.confirmed.iter().collect::<Vec<_>>(),
+        })
+    }
+
+    /// `None` for an unknown version or a malformed document. Offsets that
+    /// are not chunk-aligned or lie past the end of the payload are dropped.
+    pub fn from_json(value: &serde_json::Value) -> Option<Self> {
+        if value.get("version")?.as_u64()? != UPLOAD_STATE_VERSION {
+            return None;
+        }
+        let chunk_size = value.get("chunk_size")?.as_u64()? as usize;
+        let file_len = value.get("file_len")?.as_u64()? as usize;
+        if chunk_size == 0 {
+            return None;
+        }
+        let confirmed = value
+            .get("confirmed")?
+            .as_array()?
+            .iter()
+            .map(|offset| offset.as_u64().map(|v| v as usize))
+            .collect::<Option<Vec<_>>>()?
+            .into_iter()
+            .filter(|offset| offset.is_multiple_of(chunk_size) && *offset < file_len)
+            .collect();
+        Some(Self {
+            target: value.get("target")?.as_str()?.to_string(),
+            file_len,
+            file_sha256: value.get("file_sha256")?.as_str()?.to_string(),
+            chunk_size,
+            confirmed,
+        })
+    }
+
+    /// `fresh` with the confirmed offsets from the sidecar at `path`, when the
+    /// sidecar describes the same upload. Any other sidecar is ignored.
+    pub fn resume(fresh: UploadState, path: &Path) -> UploadState {
+        let Ok(raw) = std::fs::read_to_string(path) else {
+            return fresh;
+        };
+        let stored = serde_json::from_str(&raw)
+            .ok()
+            .and_then(|value| Self::from_json(&value));
+        match stored {
+            Some(stored) if stored.same_upload(&fresh) => stored,
+            _ => fresh,
+        }
+    }
+
+    /// Write through a temporary file so an interrupted save never leaves a
+    /// truncated sidecar behind.
+    pub fn save(&self, path: &Path) -> std::io::Result<()> {
+        let tmp = path.with_extension("json.tmp");
+        std::fs::write(&tmp, serde_json::to_string_pretty(&self.to_json())?)?;
+        std::fs::rename(tmp, path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn state_with(confirmed: &[usize]) -> UploadState {
+        let mut state = UploadState::new("Target1111", &[7u8; 2_000], 900);
+        for &idx in confirmed {
+            state.confirm(idx);
+        }
+        state
+    }
+
+    #[test]
+    fn sha256_matches_known_vector() {
+        assert_eq!(
+            sha256_hex(b"abc"),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+    }
+
+    fn argv(args: &[&str]) -> Vec<String> {
+        std::iter::once("upload_model")
+            .chain(args.iter().copied())
+            .map(String::from)
+            .collect()
+    }
+
+    #[test]
+    fn parse_upload_args_flags() {
+        let parsed = parse_upload_args(&argv(&[
+            "weights.bin",
+            "--chunk-size",
+            "512",
+            "--concurrency",
+            "8",
+            "--verify-digest",
+            "--split-across-slots",
+            "1:2",
+        ]))
+        .unwrap();
+        assert_eq!(
+            parsed,
+            UploadArgs {
+                chunk_path: Some("weights.bin".to_string()),
+                split: Some("1:2".to_string()),
+                chunk_size: Some(512),
+                concurrency: 8,
+                verify_digest: true,
+            }
+        );
+
+        let defaults = parse_upload_args(&argv(&["weights.bin"])).unwrap();
+        assert_eq!(defaults.chunk_size, None);
+        assert_eq!(defaults.concurrency, DEFAULT_CONCURRENCY);
+
+        assert!(parse_upload_args(&argv(&["--chunk-size", "big"])).is_err());
+        assert!(parse_upload_args(&argv(&["--concurrency", "0"])).is_err());
+        assert!(parse_upload_args(&argv(&["--chunk-size"])).is_err());
+    }
+
+    #[test]
+    fn tx_size_matches_serialized_message() {
+        use solana_sdk::{
+            instruction::{AccountMeta, Instruction},
+            message::Message,
+            pubkey::Pubkey,
+        };
+        let (payer, authority) = (Pubkey::new_unique(), Pubkey::new_unique());
+        for chunk in [0usize, 100, 900] {
+            let ix = Instruction {
+                program_id: Pubkey::new_unique(),
+                accounts: vec![
+                    AccountMeta::new_readonly(authority, true),
+                    AccountMeta::new_readonly(Pubkey::new_unique(), false),
+                    AccountMeta::new(Pubkey::new_unique(), false),
+                ],
+                data: vec![0; SEEDED_WRITE_PREFIX + chunk],
+            };
+            let message = Message::new(&[ix], Some(&payer));
+            let wire = 1 + 64 * 2 + message.serialize().len();
+            assert_eq!(
+                single_instruction_tx_size(2, 5, 3, SEEDED_WRITE_PREFIX + chunk),
+                wire
+            );
+        }
+    }
+
+    #[test]
+    fn chunk_size_guard() {
+        assert!(max_chunk_size(false, false) >= DEFAULT_CHUNK_SIZE);
+        assert!(max_chunk_size(true, false) >= DEFAULT_CHUNK_SIZE);
+        // Two signers plus the VM account leave less room than the default.
+        let tight = max_chunk_size(true, true);
+        assert!(tight < DEFAULT_CHUNK_SIZE);
+        assert_eq!(
+            single_instruction_tx_size(2, 5, 3, SEEDED_WRITE_PREFIX + tight),
+            MAX_TRANSACTION_SIZE
+        );
+
+        assert_eq!(
+            resolve_chunk_size(None, false, false),
+            Ok(DEFAULT_CHUNK_SIZE)
+        );
+        assert_eq!(resolve_chunk_size(None, true, true), Ok(tight));
+        assert_eq!(resolve_chunk_size(Some(256), true, true), Ok(256));
+        assert!(resolve_chunk_size(Some(tight + 1), true, true).is_err());
+        assert!(resolve_chunk_size(Some(1_232), false, false).is_err());
+        assert!(resolve_chunk_size(Some(0), false, false).is_err());
+    }
+
+    #[test]
+    fn fee_estimate_scales_with_chunks_and_signers() {
+        assert_eq!(estimate_fees(0, 1), 0);
+        assert_eq!(estimate_fees(1, 1), LAMPORTS_PER_SIGNATURE);
+        // 1 MB at 900 bytes per chunk, authority co-signing.
+        let chunks = 1_000_000usize.div_ceil(900);
+        assert_eq!(chunks, 1_112);
+        assert_eq!(estimate_fees(chunks, 2), 11_120_000);
+        assert_eq!(estimate_fees(usize::MAX, 2), u64::MAX);
+    }
+
+    #[test]
+    fn balance_check() {
+        assert!(check_balance(1_000, 600, 400).is_ok());
+        let err = check_balance(999, 600, 400).unwrap_err();
+        assert!(err.contains("1000 needed"));
+        assert!(check_balance(u64::MAX, u64::MAX, 1).is_ok());
+    }
+
+    #[test]
+    fn progress_accounting() {
+        let mut progress = UploadProgress::new(10, 9_000, Duration::from_secs(1));
+        progress.start_pass(4);
+        assert_eq!(progress.confirmed_chunks, 6);
+        progress.record_sent(900);
+        progress.record_sent(900);
+        progress.record_failed();
+        assert_eq!(
+            progress.line(Duration::from_millis(500)),
+            "8/10 chunks, 1800 bytes sent, 0.00 MB/s, 1 failed"
+        );
+        assert!((progress.mb_per_sec(Duration::from_secs(2)) - 0.0009).abs() < 1e-12);
+        assert_eq!(progress.mb_per_sec(Duration::ZERO), 0.0);
+
+        // The retry pass only resets the failure count.
+        progress.start_pass(2);
+        assert_eq!(progress.failed, 0);
+        assert_eq!(progress.bytes_sent, 1_800);
+        progress.record_sent(900);
+        progress.record_sent(200);
+        progress.record_sent(200);
+        assert_eq!(progress.confirmed_chunks, 10);
+    }
+
+    #[test]
+    fn progress_reports_are_throttled() {
+        let mut progress = UploadProgress::new(3, 300, Duration::from_secs(1));
+        progress.start_pass(3);
+        assert!(progress.due(Duration::from_millis(10)));
+        progress.record_sent(100);
+        assert!(!progress.due(Duration::from_millis(500)));
+        assert!(progress.due(Duration::from_millis(1_100)));
+        progress.record_sent(100);
+        progress.record_sent(100);
+        assert!(progress.due(Duration::from_millis(1_200)));
+    }
+
+    #[test]
+    fn header_carries_digest_tag() {
+        let payload = b"abc";
+        let header = encode_header(payload);
+        assert_eq!(&header[..4], b"RVCD");
+        assert_eq!(&header[4..8], &3u32.to_le_bytes());
+        // SHA-256("abc") starts ba 78 16 bf.
+        assert_eq!(&header[8..], &[0xba, 0x78, 0x16, 0xbf]);
+        assert_eq!(digest_tag(payload), 0xbf16_78ba);
+    }
+
+    #[test]
+    fn stale_header_is_detected() {
+        let payload = b"abc";
+        let mut account = encode_header(payload).to_vec();
+        account.extend_from_slice(payload);
+        assert!(header_is_current(&account, payload));
+
+        // Same length, different content: only the tag is stale.
+        assert!(!header_is_current(&account, b"abd"));
+        // Longer file: payload_len and tag are both stale.
+        assert!(!header_is_current(&account, b"abcd"));
+        // Header written without a tag.
+        account[DIGEST_TAG_OFFSET..BINARY_HEADER_SIZE].fill(0);
+        assert!(!header_is_current(&account, payload));
+        assert!(!header_is_current(&account[..8], payload));
+    }
+
+    #[test]
+    fn segment_payload_strips_header() {
+        let payload = [1u8, 2, 3, 4, 5];
+        let mut account = encode_header(&payload).to_vec();
+        account.extend_from_slice(&payload);
+        account.extend_from_slice(&[0; 16]);
+        assert_eq!(segment_payload(&account), Ok(&payload[..]));
+
+        let mut empty = encode_header(&[]).to_vec();
+        empty.extend_from_slice(&[9; 4]);
+        assert_eq!(segment_payload(&empty), Ok(&[][..]));
+
+        assert!(segment_payload(&account[..11]).is_err());
+        assert!(segment_payload(&account[..BINARY_HEADER_SIZE + 4]).is_err());
+        let mut bad_magic = account;
+        bad_magic[3] = b'X';
+        assert!(segment_payload(&bad_magic).unwrap_err().contains("magic"));
+    }
+
+    #[test]
+    fn verify_payload_digest_checks_body_and_tag() {
+        let payload = vec![5u8; 1_000];
+        let mut account = encode_header(&payload).to_vec();
+        account.extend_from_slice(&payload);
+        account.extend_from_slice(&[0xEE; 8]);
+        assert_eq!(
+            verify_payload_digest(&account, &payload),
+            Ok(sha256_hex(&payload))
+        );
+
+        // Untagged header (program-written, or older uploads).
+        let mut untagged = account.clone();
+        untagged[8..12].fill(0);
+        assert!(verify_payload_digest(&untagged, &payload).is_ok());
+
+        let mut corrupt = account.clone();
+        corrupt[BINARY_HEADER_SIZE + 899] ^= 1;
+        assert!(verify_payload_digest(&corrupt, &payload)
+            .unwrap_err()
+            .contains("payload digest mismatch"));
+
+        let mut wrong_tag = account.clone();
+        wrong_tag[8] ^= 1;
+        assert!(verify_payload_digest(&wrong_tag, &payload)
+            .unwrap_err()
+            .contains("digest tag"));
+
+        assert!(verify_payload_digest(&account[..500], &payload).is_err());
+        let mut bad_magic = account;
+        bad_magic[0] = b'X';
+        assert!(verify_payload_digest(&bad_magic, &payload).is_err());
+    }
+
+    #[test]
+    fn state_json_round_trip() {
+        let state = state_with(&[0, 2]);
+        let value = state.to_json();
+        assert_eq!(value["confirmed"], json!([0, 1_800]));
+        assert_eq!(value["version"], UPLOAD_STATE_VERSION);
+        let back = UploadState::from_json(&value).unwrap();
+        assert_eq!(back, state);
+        assert!(back.is_confirmed(2));
+        assert!(!back.is_confirmed(1));
+    }
+
+    #[test]
+    fn from_json_rejects_bad_documents() {
+        let mut value = state_with(&[1]).to_json();
+        value["version"] = json!(2);
+        assert!(UploadState::from_json(&value).is_none());
+
+        let mut value = state_with(&[1]).to_json();
+        value["chunk_size"] = json!(0);
+        assert!(UploadState::from_json(&value).is_none());
+
+        let mut value = state_with(&[]).to_json();
+        value["confirmed"] = json!([900, 901, 99_000]);
+        let state = UploadState::from_json(&value).unwrap();
+        assert_eq!(state.confirmed, BTreeSet::from([900]));
+
+        assert!(UploadState::from_json(&json!({"version": 1})).is_none());
+    }
+
+    #[test]
+    fn changed_source_invalidates_sidecar() {
+        let stored = state_with(&[0, 1]);
+        let same = UploadState::new("Target1111", &[7u8; 2_000], 900);
+        assert!(stored.same_upload(&same));
+
+        let mut edited = vec![7u8; 2_000];
+        edited[1_999] = 8;
+        assert!(!stored.same_upload(&UploadState::new("Target1111", &edited, 900)));
+        assert!(!stored.same_upload(&UploadState::new("Target1111", &[7u8; 2_001], 900)));
+        assert!(!stored.same_upload(&UploadState::new("Other", &[7u8; 2_000], 900)));
+        assert!(!stored.same_upload(&UploadState::new("Target1111", &[7u8; 2_000], 800)));
+    }
+
+    #[test]
+    fn resume_from_disk() {
+        let dir = std::env::temp_dir().join(format!("fb-upload-state-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("weights.bin.upload.json");
+
+        let fresh = || UploadState::new("Target1111", &[7u8; 2_000], 900);
+        assert!(UploadState::resume(fresh(), &path).confirmed.is_empty());
+
+        state_with(&[0, 2]).save(&path).unwrap();
+        assert_eq!(UploadState::resume(fresh(), &path), state_with(&[0, 2]));
+
+        let other = UploadState::new("Target1111", &[9u8; 2_000], 900);
+        assert!(UploadState::resume(other, &path).confirmed.is_empty());
+
+        std::fs::write(&path, "{ not json").unwrap();
+        assert!(UploadState::resume(fresh(), &path).confirmed.is_empty());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
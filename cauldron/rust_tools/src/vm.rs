@@ -0,0 +1,22 @@
+//! VM account layout shared by the tools. Mirrors the RVVM header in the
+//! Frostbite program (`src/vm/mod.rs`) and `cauldron/constants.py`.
+
+/// Last header field: the guest exit code (i64), ending at the memory base.
+pub const VM_EXIT_CODE_OFFSET: usize = 544;
+/// Start of VM memory, i.e. the header size. The gatekeeper and executor
+/// call the same value `VM_HEADER_SIZE`.
+pub const VM_MEMORY_OFFSET: usize = VM_EXIT_CODE_OFFSET + 8;
+pub const VM_MEMORY_SIZE: usize = 262_144;
+/// Size of an account created by `INIT_VM_PDA`.
+pub const VM_ACCOUNT_SIZE: usize = VM_MEMORY_OFFSET + VM_MEMORY_SIZE;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn vm_account_size_matches_program_layout() {
+        assert_eq!(VM_MEMORY_OFFSET, 552);
+        assert_eq!(VM_ACCOUNT_SIZE, 552 + 262_144);
+    }
+}
@@ -81,10 +81,14 @@ def _canonical_schema(manifest: Dict[str, Any]) -> Dict[str, Any]:
     return out
 
 
-def schema_hash32(manifest: Dict[str, Any]) -> int:
+def schema_canonical_bytes(manifest: Dict[str, Any]) -> bytes:
+    """Bytes hashed by schema_hash32 (and by frostbite_sdk::schema_hash)."""
     canonical = _canonical_schema(manifest)
-    payload = json.dumps(canonical, separators=(",", ":"), sort_keys=True).encode("utf-8")
-    return _fnv1a32(payload)
+    return json.dumps(canonical, separators=(",", ":"), sort_keys=True).encode("utf-8")
+
+
+def schema_hash32(manifest: Dict[str, Any]) -> int:
+    return _fnv1a32(schema_canonical_bytes(manifest))
 
 
 def format_hash32(value: int) -> str:
@@ -0,0 +1,34 @@
+//! Auto-generated config constants (patched by Cauldron).
+
+pub const CONTROL_OFFSET: usize = 0x0000;
+pub const INPUT_MAX: usize = 4096;
+pub const OUTPUT_MAX: usize = 256;
+
+pub const SCRATCH_MIN: usize = 262_144;
+pub const RESERVED_TAIL: usize = 32;
+pub const STACK_GUARD: usize = 0x4000;
+pub const STACK_PTR: usize = SCRATCH_MIN - RESERVED_TAIL - STACK_GUARD;
+
+pub const SEQ_LEN: usize = 8;
+pub const EMBED_DIM: usize = 16;
+pub const HEAD_DIM: usize = 16;
+pub const OUTPUT_DIM: usize = 4;
+
+pub const WEIGHTS_SEG: u32 = 1;
+pub const WEIGHTS_OFFSET: usize = 0;
+pub const WEIGHTS_DATA_OFFSET: usize = 0;
+
+pub const WQ_SCALE_Q16: i32 = 65_536;
+pub const WK_SCALE_Q16: i32 = 65_536;
+pub const WV_SCALE_Q16: i32 = 65_536;
+pub const WO_SCALE_Q16: i32 = 65_536;
+pub const HAS_BIAS: bool = true;
+/// Score scale in Q16, normally 1/sqrt(HEAD_DIM).
+pub const ATTN_SCALE_Q16: i32 = 16_384;
+
+pub const Q_OFFSET: usize = 0x3000;
+pub const K_OFFSET: usize = 0x3200;
+pub const V_OFFSET: usize = 0x3400;
+
+pub const EXPECTED_SCHEMA_HASH: u32 = 0;
+pub const EXPECTED_SCHEMA_ID: u32 = 0;
@@ -0,0 +1,275 @@
+//! Single-head self-attention template (sequence -> pooled logits), integer-only
+#![no_std]
+#![no_main]
+
+use core::panic::PanicInfo;
+use frostbite_sdk::status::{ERR_INPUT_BOUNDS, ERR_INTERNAL, ERR_OK, ERR_OUTPUT_BOUNDS};
+use frostbite_sdk::{
+    dot_i32, exit, matmul_i8_i32, matmul_i8_i8_qkv, parse_fbh1, prequant_len, quantize_i8,
+    region_fits, softmax_i32, weighted_sum_i32, ControlBlock, MatmulQkvConfig, VmAddr,
+    GUEST_TEXT_BASE, SEGMENT_SIZE,
+};
+
+mod config;
+use config::*;
+
+// ============================================================================
+//  Config checks (build-time; a bad config.rs fails to compile)
+// ============================================================================
+
+const QKV_ROWS_BYTES: usize = SEQ_LEN * HEAD_DIM * 4;
+// WQ, WK, WV (i8 HEAD_DIM x EMBED_DIM), WO (i8 OUTPUT_DIM x HEAD_DIM), BO (i32 OUTPUT_DIM).
+const WEIGHTS_BYTES: usize =
+    3 * HEAD_DIM * EMBED_DIM + OUTPUT_DIM * HEAD_DIM + if HAS_BIAS { OUTPUT_DIM * 4 } else { 0 };
+const _: () = assert!(
+    SEQ_LEN * EMBED_DIM * 4 <= INPUT_MAX,
+    "input does not fit INPUT_MAX"
+);
+const _: () = assert!(
+    OUTPUT_DIM * 4 <= OUTPUT_MAX,
+    "output does not fit OUTPUT_MAX"
+);
+const _: () = assert!(
+    region_fits(Q_OFFSET, QKV_ROWS_BYTES, K_OFFSET),
+    "Q_OFFSET overlaps the next scratch buffer"
+);
+const _: () = assert!(
+    region_fits(K_OFFSET, QKV_ROWS_BYTES, V_OFFSET),
+    "K_OFFSET overlaps the next scratch buffer"
+);
+const _: () = assert!(
+    region_fits(V_OFFSET, QKV_ROWS_BYTES, GUEST_TEXT_BASE),
+    "V_OFFSET overlaps the guest code"
+);
+const _: () = assert!(
+    region_fits(
+        WEIGHTS_DATA_OFFSET + WEIGHTS_OFFSET,
+        WEIGHTS_BYTES,
+        SEGMENT_SIZE
+    ),
+    "weights do not fit the segment"
+);
+
+// ============================================================================
+//  Panic / Entry
+// ============================================================================
+
+#[panic_handler]
+fn panic(_info: &PanicInfo) -> ! {
+    unsafe { core::arch::asm!("ebreak") };
+    loop {}
+}
+
+#[unsafe(naked)]
+#[no_mangle]
+pub unsafe extern "C" fn _start() -> ! {
+    // Stack pointer configured via config.rs
+    core::arch::naked_asm!(
+        "li sp, {stack_ptr}",
+        "j {rust_main}",
+        stack_ptr = const STACK_PTR,
+        rust_main = sym rust_main,
+    );
+}
+
+// ============================================================================
+//  Helpers
+// ============================================================================
+
+// Mean pooling over the sequence, as a WEIGHTED_SUM_I32 weight.
+const POOL_WEIGHT_Q16: i32 = (65_536 / SEQ_LEN) as i32;
+const Q16_SHIFT: u32 = 16;
+
+// Control-block words go through the SDK's volatile `ctrl_read_u32` /
+// `ctrl_write_u32`. Input, weights, scratch and output are plain guest memory,
+// so the helpers below use ordinary unaligned loads and stores.
+
+#[inline(always)]
+fn scratch_addr(offset: usize) -> u64 {
+    offset as u64
+}
+
+#[inline(always)]
+fn vaddr(segment: u32, offset: usize) -> u64 {
+    ((segment as u64) << 28) | (offset as u64)
+}
+
+#[inline(always)]
+unsafe fn read_u32(addr: u64) -> u32 {
+    (addr as *const u32).read_unaligned()
+}
+
+#[inline(always)]
+unsafe fn read_i32(addr: u64) -> i32 {
+    read_u32(addr) as i32
+}
+
+#[inline(always)]
+unsafe fn write_u32(addr: u64, value: u32) {
+    (addr as *mut u32).write_unaligned(value);
+}
+
+#[inline(always)]
+unsafe fn write_i32(addr: u64, value: i32) {
+    write_u32(addr, value as u32);
+}
+
+// ============================================================================
+//  Entry
+// ============================================================================
+
+#[inline(always)]
+unsafe fn fail(ctrl: ControlBlock, code: u32) -> ! {
+    ctrl.set_status(code);
+    exit(code as i64);
+}
+
+#[inline(always)]
+unsafe fn scratch_rows(offset: usize) -> &'static mut [i32] {
+    core::slice::from_raw_parts_mut(scratch_addr(offset) as *mut i32, SEQ_LEN * HEAD_DIM)
+}
+
+/// Q/K/V for every token through the fused QKV kernel.
+unsafe fn project_qkv(
+    payload_ptr: u64,
+    q: &mut [i32],
+    k: &mut [i32],
+    v: &mut [i32],
+) -> Result<(), u32> {
+    let wq_base = WEIGHTS_DATA_OFFSET + WEIGHTS_OFFSET;
+    let wk_base = wq_base + HEAD_DIM * EMBED_DIM;
+    let wv_base = wk_base + HEAD_DIM * EMBED_DIM;
+
+    // One token: EMBED_DIM int8 values padded to 4, then the i32 scale.
+    let mut prequant = [0u8; prequant_len(EMBED_DIM)];
+    let mut t = 0usize;
+    while t < SEQ_LEN {
+        let x = core::slice::from_raw_parts(
+            (payload_ptr + (t * EMBED_DIM * 4) as u64) as *const i32,
+            EMBED_DIM,
+        );
+        quantize_i8(&mut prequant, x).map_err(|_| ERR_INTERNAL)?;
+        // Fresh row cursor per token; max_rows = 0 runs every row in one call.
+        let mut state = [0u32; 2];
+        let row = t * HEAD_DIM..(t + 1) * HEAD_DIM;
+        let cfg = MatmulQkvConfig {
+            out_q: VmAddr::from_mut_slice(&mut q[row.clone()]).raw(),
+            out_k: VmAddr::from_mut_slice(&mut k[row.clone()]).raw(),
+            out_v: VmAddr::from_mut_slice(&mut v[row]).raw(),
+            x_ptr: VmAddr::from_slice(&prequant).raw(),
+            wq_ptr: vaddr(WEIGHTS_SEG, wq_base),
+            wk_ptr: vaddr(WEIGHTS_SEG, wk_base),
+            wv_ptr: vaddr(WEIGHTS_SEG, wv_base),
+            wq_scale: WQ_SCALE_Q16 as u32,
+            wk_scale: WK_SCALE_Q16 as u32,
+            wv_scale: WV_SCALE_Q16 as u32,
+            n: EMBED_DIM as u32,
+            d_q: HEAD_DIM as u32,
+            d_k: HEAD_DIM as u32,
+            d_v: HEAD_DIM as u32,
+            _pad0: 0,
+            state_ptr: VmAddr::from_mut(&mut state).raw(),
+        };
+        matmul_i8_i8_qkv(&cfg);
+        t += 1;
+    }
+    Ok(())
+}
+
+/// Attend every query over all keys and mean-pool the context vectors.
+fn attend_and_pool(q: &[i32], k: &[i32], v: &[i32], pooled: &mut [i32]) -> Result<(), u32> {
+    let mut scores = [0i32; SEQ_LEN];
+    let mut ctx = [0i32; HEAD_DIM];
+    pooled.fill(0);
+
+    let mut i = 0usize;
+    while i < SEQ_LEN {
+        let q_row = &q[i * HEAD_DIM..(i + 1) * HEAD_DIM];
+        let mut j = 0usize;
+        while j < SEQ_LEN {
+            let k_row = &k[j * HEAD_DIM..(j + 1) * HEAD_DIM];
+            let dot = dot_i32(q_row, k_row, Q16_SHIFT).map_err(|_| ERR_INTERNAL)?;
+            let scaled = (dot * ATTN_SCALE_Q16 as i64) >> Q16_SHIFT;
+            scores[j] = scaled.clamp(i32::MIN as i64, i32::MAX as i64) as i32;
+            j += 1;
+        }
+
+        softmax_i32(&mut scores);
+
+        ctx.fill(0);
+        let mut j = 0usize;
+        while j < SEQ_LEN {
+            let v_row = &v[j * HEAD_DIM..(j + 1) * HEAD_DIM];
+            weighted_sum_i32(&mut ctx, v_row, scores[j], Q16_SHIFT).map_err(|_| ERR_INTERNAL)?;
+            j += 1;
+        }
+        weighted_sum_i32(pooled, &ctx, POOL_WEIGHT_Q16, Q16_SHIFT).map_err(|_| ERR_INTERNAL)?;
+        i += 1;
+    }
+    Ok(())
+}
+
+#[no_mangle]
+pub extern "C" fn rust_main() -> ! {
+    unsafe {
+        let ctrl = ControlBlock::new(VmAddr(scratch_addr(CONTROL_OFFSET)));
+        if let Err(code) = ctrl.validate() {
+            fail(ctrl, code);
+        }
+
+        let (input, input_len) = ctrl.input();
+        let output_ptr = ctrl.output().0.raw();
+
+        let (payload, payload_len) =
+            match parse_fbh1(input, input_len, EXPECTED_SCHEMA_ID, EXPECTED_SCHEMA_HASH) {
+                Ok(v) => v,
+                Err(code) => fail(ctrl, code),
+            };
+        let payload_ptr = payload.raw();
+
+        let input_bytes = SEQ_LEN * EMBED_DIM * 4;
+        if input_bytes > INPUT_MAX || payload_len < input_bytes {
+            fail(ctrl, ERR_INPUT_BOUNDS);
+        }
+
+        let output_bytes = OUTPUT_DIM * 4;
+        if output_bytes > OUTPUT_MAX {
+            fail(ctrl, ERR_OUTPUT_BOUNDS);
+        }
+
+        let q = scratch_rows(Q_OFFSET);
+        let k = scratch_rows(K_OFFSET);
+        let v = scratch_rows(V_OFFSET);
+        if let Err(code) = project_qkv(payload_ptr, q, k, v) {
+            fail(ctrl, code);
+        }
+
+        let mut pooled = [0i32; HEAD_DIM];
+        if let Err(code) = attend_and_pool(q, k, v, &mut pooled) {
+            fail(ctrl, code);
+        }
+
+        // Output projection: WO (i8 OUTPUT_DIM x HEAD_DIM) then optional BO (i32 OUTPUT_DIM).
+        let wo_base = WEIGHTS_DATA_OFFSET + WEIGHTS_OFFSET + 3 * HEAD_DIM * EMBED_DIM;
+        let bo_base = wo_base + OUTPUT_DIM * HEAD_DIM;
+        let mut logits = [0i32; OUTPUT_DIM];
+        let wo = VmAddr(vaddr(WEIGHTS_SEG, wo_base));
+        if matmul_i8_i32(&mut logits, &pooled, wo, WO_SCALE_Q16).is_err() {
+            fail(ctrl, ERR_INTERNAL);
+        }
+
+        let mut i = 0usize;
+        while i < OUTPUT_DIM {
+            let mut val = logits[i];
+            if HAS_BIAS {
+                val = val.wrapping_add(read_i32(vaddr(WEIGHTS_SEG, bo_base + i * 4)));
+            }
+            write_i32(output_ptr + (i * 4) as u64, val);
+            i += 1;
+        }
+
+        ctrl.set_output_len(output_bytes as u32);
+        ctrl.set_status(ERR_OK);
+        exit(ERR_OK as i64);
+    }
+}
@@ -3,10 +3,36 @@
 #![no_main]
 
 use core::panic::PanicInfo;
+use frostbite_sdk::status::{ERR_INPUT_BOUNDS, ERR_OK, ERR_OUTPUT_BOUNDS};
+use frostbite_sdk::{parse_fbh1, region_fits, ControlBlock, VmAddr, GUEST_TEXT_BASE, SEGMENT_SIZE};
 
 mod config;
 use config::*;
 
+// ============================================================================
+//  Config checks (build-time; a bad config.rs fails to compile)
+// ============================================================================
+
+const WEIGHTS_BYTES: usize =
+    INPUT_DIM * HIDDEN_DIM + HIDDEN_DIM * 4 + HIDDEN_DIM * OUTPUT_DIM + OUTPUT_DIM * 4;
+const _: () = assert!(INPUT_DIM * 4 <= INPUT_MAX, "input does not fit INPUT_MAX");
+const _: () = assert!(
+    OUTPUT_DIM * 4 <= OUTPUT_MAX,
+    "output does not fit OUTPUT_MAX"
+);
+const _: () = assert!(
+    region_fits(HIDDEN_OFFSET, HIDDEN_DIM * 4, GUEST_TEXT_BASE),
+    "HIDDEN_OFFSET overlaps the guest code"
+);
+const _: () = assert!(
+    region_fits(
+        WEIGHTS_DATA_OFFSET + WEIGHTS_OFFSET,
+        WEIGHTS_BYTES,
+        SEGMENT_SIZE
+    ),
+    "weights do not fit the segment"
+);
+
 // ============================================================================
 //  Panic / Entry
 // ============================================================================
@@ -29,52 +55,6 @@ pub unsafe extern "C" fn _start() -> ! {
     );
 }
 
-// ============================================================================
-//  Control block layout
-// ============================================================================
-
-const FBM1_MAGIC: u32 = 0x314D_4246; // "FBM1"
-
-const CTRL_MAGIC: usize = 0;
-const CTRL_ABI_VERSION: usize = 4;
-const CTRL_STATUS: usize = 12;
-const CTRL_INPUT_PTR: usize = 16;
-const CTRL_INPUT_LEN: usize = 20;
-const CTRL_OUTPUT_PTR: usize = 24;
-const CTRL_OUTPUT_LEN: usize = 28;
-
-// ============================================================================
-//  Optional FBH1 input header
-// ============================================================================
-
-const FBH1_MAGIC: u32 = 0x3148_4246; // "FBH1"
-const FBH1_HEADER_LEN: usize = 32;
-
-const FBH_MAGIC: usize = 0;
-const FBH_VERSION: usize = 4;     // u16
-const FBH_FLAGS: usize = 6;       // u16
-const FBH_HEADER_LEN: usize = 8;  // u32
-const FBH_SCHEMA_ID: usize = 12;  // u32
-const FBH_PAYLOAD_LEN: usize = 16; // u32
-const FBH_CRC32: usize = 20;      // u32
-const FBH_SCHEMA_HASH: usize = 24; // u32
-
-const FBH_FLAG_HAS_CRC32: u16 = 1 << 0;
-const FBH_FLAG_HAS_SCHEMA_HASH: u16 = 1 << 1;
-
-// EXPECTED_SCHEMA_ID provided via config
-
-// ============================================================================
-//  Error codes
-// ============================================================================
-
-const ERR_OK: u32 = 0;
-const ERR_CTRL: u32 = 1;
-const ERR_INPUT_HEADER: u32 = 2;
-const ERR_SCHEMA: u32 = 3;
-const ERR_INPUT_BOUNDS: u32 = 4;
-const ERR_OUTPUT_BOUNDS: u32 = 5;
-
 // ============================================================================
 //  Syscalls
 // ============================================================================
@@ -125,6 +105,10 @@ unsafe fn matmul_i8_i32(out: u64, x: u64, w: u64, scale_q16: i32, n: usize, d: u
 //  Helpers
 // ============================================================================
 
+// Control-block words go through the SDK's volatile `ctrl_read_u32` /
+// `ctrl_write_u32`. Input, weights, scratch and output are plain guest memory,
+// so the helpers below use ordinary unaligned loads and stores.
+
 #[inline(always)]
 fn scratch_addr(offset: usize) -> u64 {
     offset as u64
@@ -135,19 +119,9 @@ fn vaddr(segment: u32, offset: usize) -> u64 {
     ((segment as u64) << 28) | (offset as u64)
 }
 
-#[inline(always)]
-unsafe fn read_u8(addr: u64) -> u8 {
-    (addr as *const u8).read_volatile()
-}
-
-#[inline(always)]
-unsafe fn read_u16(addr: u64) -> u16 {
-    (addr as *const u16).read_volatile()
-}
-
 #[inline(always)]
 unsafe fn read_u32(addr: u64) -> u32 {
-    (addr as *const u32).read_volatile()
+    (addr as *const u32).read_unaligned()
 }
 
 #[inline(always)]
@@ -157,7 +131,7 @@ unsafe fn read_i32(addr: u64) -> i32 {
 
 #[inline(always)]
 unsafe fn write_u32(addr: u64, value: u32) {
-    (addr as *mut u32).write_volatile(value);
+    (addr as *mut u32).write_unaligned(value);
 }
 
 #[inline(always)]
@@ -170,76 +144,6 @@ fn relu_i32(x: i32) -> i32 {
     if x > 0 { x } else { 0 }
 }
 
-#[inline(always)]
-fn crc32(payload_ptr: u64, payload_len: usize) -> u32 {
-    let mut crc: u32 = 0xFFFF_FFFF;
-    let mut i = 0usize;
-    while i < payload_len {
-        let byte = unsafe { read_u8(payload_ptr + i as u64) } as u32;
-        crc ^= byte;
-        let mut j = 0u8;
-        while j < 8 {
-            if (crc & 1) != 0 {
-                crc = (crc >> 1) ^ 0xEDB8_8320;
-            } else {
-                crc >>= 1;
-            }
-            j += 1;
-        }
-        i += 1;
-    }
-    !crc
-}
-
-#[inline(always)]
-unsafe fn parse_input_header(input_ptr: u64, input_len: usize) -> Result<(u64, usize), u32> {
-    if input_len < FBH1_HEADER_LEN {
-        return Ok((input_ptr, input_len));
-    }
-
-    let magic = read_u32(input_ptr + FBH_MAGIC as u64);
-    if magic != FBH1_MAGIC {
-        return Ok((input_ptr, input_len));
-    }
-
-    let version = read_u16(input_ptr + FBH_VERSION as u64);
-    let flags = read_u16(input_ptr + FBH_FLAGS as u64);
-    let header_len = read_u32(input_ptr + FBH_HEADER_LEN as u64) as usize;
-    let schema_id = read_u32(input_ptr + FBH_SCHEMA_ID as u64);
-    let payload_len = read_u32(input_ptr + FBH_PAYLOAD_LEN as u64) as usize;
-    let crc_expected = read_u32(input_ptr + FBH_CRC32 as u64);
-    let schema_hash = read_u32(input_ptr + FBH_SCHEMA_HASH as u64);
-
-    if version != 1 || header_len != FBH1_HEADER_LEN {
-        return Err(ERR_INPUT_HEADER);
-    }
-
-    if schema_id != EXPECTED_SCHEMA_ID {
-        return Err(ERR_SCHEMA);
-    }
-
-    if payload_len != input_len - header_len {
-        return Err(ERR_INPUT_HEADER);
-    }
-
-    let payload_ptr = input_ptr + header_len as u64;
-
-    if (flags & FBH_FLAG_HAS_SCHEMA_HASH) != 0 {
-        if EXPECTED_SCHEMA_HASH == 0 || schema_hash != EXPECTED_SCHEMA_HASH {
-            return Err(ERR_SCHEMA);
-        }
-    }
-
-    if (flags & FBH_FLAG_HAS_CRC32) != 0 {
-        let crc = crc32(payload_ptr, payload_len);
-        if crc != crc_expected {
-            return Err(ERR_INPUT_HEADER);
-        }
-    }
-
-    Ok((payload_ptr, payload_len))
-}
-
 // ============================================================================
 //  Entry
 // ============================================================================
@@ -247,35 +151,34 @@ unsafe fn parse_input_header(input_ptr: u64, input_len: usize) -> Result<(u64, u
 #[no_mangle]
 pub extern "C" fn rust_main() -> ! {
     unsafe {
-        let ctrl_base = scratch_addr(CONTROL_OFFSET);
-        let magic = read_u32(ctrl_base + CTRL_MAGIC as u64);
-        let abi_version = read_u32(ctrl_base + CTRL_ABI_VERSION as u64);
-        if magic != FBM1_MAGIC || abi_version != 1 {
-            write_u32(ctrl_base + CTRL_STATUS as u64, ERR_CTRL);
-            sys_exit(ERR_CTRL);
+        let ctrl = ControlBlock::new(VmAddr(scratch_addr(CONTROL_OFFSET)));
+        if let Err(code) = ctrl.validate() {
+            ctrl.set_status(code);
+            sys_exit(code);
         }
 
-        let input_ptr = read_u32(ctrl_base + CTRL_INPUT_PTR as u64) as u64;
-        let input_len = read_u32(ctrl_base + CTRL_INPUT_LEN as u64) as usize;
-        let output_ptr = read_u32(ctrl_base + CTRL_OUTPUT_PTR as u64) as u64;
+        let (input, input_len) = ctrl.input();
+        let output_ptr = ctrl.output().0.raw();
 
-        let (payload_ptr, payload_len) = match parse_input_header(input_ptr, input_len) {
-            Ok(v) => v,
-            Err(code) => {
-                write_u32(ctrl_base + CTRL_STATUS as u64, code);
-                sys_exit(code);
-            }
-        };
+        let (payload, payload_len) =
+            match parse_fbh1(input, input_len, EXPECTED_SCHEMA_ID, EXPECTED_SCHEMA_HASH) {
+                Ok(v) => v,
+                Err(code) => {
+                    ctrl.set_status(code);
+                    sys_exit(code);
+                }
+            };
+        let payload_ptr = payload.raw();
 
         let input_bytes = INPUT_DIM * 4;
         if input_bytes > INPUT_MAX || payload_len < input_bytes {
-            write_u32(ctrl_base + CTRL_STATUS as u64, ERR_INPUT_BOUNDS);
+            ctrl.set_status(ERR_INPUT_BOUNDS);
             sys_exit(ERR_INPUT_BOUNDS);
         }
 
         let output_bytes = OUTPUT_DIM * 4;
         if output_bytes > OUTPUT_MAX {
-            write_u32(ctrl_base + CTRL_STATUS as u64, ERR_OUTPUT_BOUNDS);
+            ctrl.set_status(ERR_OUTPUT_BOUNDS);
             sys_exit(ERR_OUTPUT_BOUNDS);
         }
 
@@ -328,8 +231,8 @@ pub extern "C" fn rust_main() -> ! {
             o += 1;
         }
 
-        write_u32(ctrl_base + CTRL_OUTPUT_LEN as u64, output_bytes as u32);
-        write_u32(ctrl_base + CTRL_STATUS as u64, ERR_OK);
+        ctrl.set_output_len(output_bytes as u32);
+        ctrl.set_status(ERR_OK);
         sys_exit(ERR_OK);
     }
 }
@@ -0,0 +1,234 @@
+//! Streaming template: input split into chunks across execute calls, with a
+//! running sum kept in a RAM segment (mean embedding on the last chunk)
+#![no_std]
+#![no_main]
+
+use core::panic::PanicInfo;
+use frostbite_sdk::status::{ERR_INPUT_BOUNDS, ERR_OK, ERR_SEQUENCE};
+use frostbite_sdk::{exit, parse_fbh1, region_fits, ControlBlock, VmAddr, SEGMENT_SIZE};
+
+mod config;
+use config::*;
+
+// ============================================================================
+//  Chunk and state layout
+// ============================================================================
+
+// Every execute call carries one chunk (after the FBH1 header):
+//
+//   0   u32  seq      chunk index, 0-based; must match the state's cursor
+//   4   u32  flags    CHUNK_FIRST resets the state, CHUNK_LAST emits the mean
+//   8   u32  rows     rows in this chunk
+//   12  i32  rows * EMBED_DIM Q16 values, row-major
+//
+// The state lives in a RAM segment, which survives between calls (scratch
+// does not under EXECUTE_RESTART). Like `RowState`, it carries a cursor so an
+// interrupted sequence resumes at the right chunk and a replayed or skipped
+// chunk is rejected:
+//
+//   0   u32  magic    STATE_MAGIC once a CHUNK_FIRST call has run
+//   4   u32  next_seq
+//   8   u32  rows     rows accumulated so far
+//   12  u32  reserved
+//   16  i64  sums[EMBED_DIM]
+
+const CHUNK_HEADER_LEN: usize = 12;
+const CHUNK_FIRST: u32 = 1 << 0;
+const CHUNK_LAST: u32 = 1 << 1;
+
+const STATE_MAGIC: u32 = 0x3153_4246; // "FBS1"
+const STATE_HEADER_LEN: usize = 16;
+const STATE_BYTES: usize = STATE_HEADER_LEN + EMBED_DIM * 8;
+
+// Output: next_seq and rows after this chunk, then on the last chunk the
+// mean embedding (EMBED_DIM Q16 i32).
+const PROGRESS_BYTES: usize = 8;
+const OUTPUT_BYTES: usize = PROGRESS_BYTES + EMBED_DIM * 4;
+
+// ============================================================================
+//  Config checks (build-time; a bad config.rs fails to compile)
+// ============================================================================
+
+const _: () = assert!(EMBED_DIM > 0, "EMBED_DIM must be positive");
+const _: () = assert!(
+    CHUNK_HEADER_LEN + EMBED_DIM * 4 <= INPUT_MAX,
+    "one row does not fit INPUT_MAX"
+);
+const _: () = assert!(OUTPUT_BYTES <= OUTPUT_MAX, "output does not fit OUTPUT_MAX");
+const _: () = assert!(
+    region_fits(STATE_OFFSET, STATE_BYTES, SEGMENT_SIZE),
+    "state does not fit its segment"
+);
+
+// ============================================================================
+//  Panic / Entry
+// ============================================================================
+
+#[panic_handler]
+fn panic(_info: &PanicInfo) -> ! {
+    unsafe { core::arch::asm!("ebreak") };
+    loop {}
+}
+
+#[unsafe(naked)]
+#[no_mangle]
+pub unsafe extern "C" fn _start() -> ! {
+    // Stack pointer configured via config.rs
+    core::arch::naked_asm!(
+        "li sp, {stack_ptr}",
+        "j {rust_main}",
+        stack_ptr = const STACK_PTR,
+        rust_main = sym rust_main,
+    );
+}
+
+// ============================================================================
+//  Helpers
+// ============================================================================
+
+// Control-block words go through the SDK's volatile `ctrl_read_u32` /
+// `ctrl_write_u32`. Input, state and output are plain guest memory, so the
+// helpers below use ordinary unaligned loads and stores.
+
+#[inline(always)]
+fn scratch_addr(offset: usize) -> u64 {
+    offset as u64
+}
+
+#[inline(always)]
+fn vaddr(segment: u32, offset: usize) -> u64 {
+    ((segment as u64) << 28) | (offset as u64)
+}
+
+#[inline(always)]
+unsafe fn read_u32(addr: u64) -> u32 {
+    (addr as *const u32).read_unaligned()
+}
+
+#[inline(always)]
+unsafe fn read_i32(addr: u64) -> i32 {
+    read_u32(addr) as i32
+}
+
+#[inline(always)]
+unsafe fn write_u32(addr: u64, value: u32) {
+    (addr as *mut u32).write_unaligned(value);
+}
+
+#[inline(always)]
+unsafe fn read_i64(addr: u64) -> i64 {
+    (addr as *const i64).read_unaligned()
+}
+
+#[inline(always)]
+unsafe fn write_i64(addr: u64, value: i64) {
+    (addr as *mut i64).write_unaligned(value);
+}
+
+#[inline(always)]
+unsafe fn fail(ctrl: ControlBlock, code: u32) -> ! {
+    ctrl.set_status(code);
+    exit(code as i64);
+}
+
+// ============================================================================
+//  Entry
+// ============================================================================
+
+#[no_mangle]
+pub extern "C" fn rust_main() -> ! {
+    unsafe {
+        let ctrl = ControlBlock::new(VmAddr(scratch_addr(CONTROL_OFFSET)));
+        if let Err(code) = ctrl.validate() {
+            fail(ctrl, code);
+        }
+
+        let (input, input_len) = ctrl.input();
+        let output_ptr = ctrl.output().0.raw();
+
+        let (payload, payload_len) =
+            match parse_fbh1(input, input_len, EXPECTED_SCHEMA_ID, EXPECTED_SCHEMA_HASH) {
+                Ok(v) => v,
+                Err(code) => fail(ctrl, code),
+            };
+        let payload_ptr = payload.raw();
+
+        if payload_len < CHUNK_HEADER_LEN {
+            fail(ctrl, ERR_INPUT_BOUNDS);
+        }
+        let seq = read_u32(payload_ptr);
+        let flags = read_u32(payload_ptr + 4);
+        let rows = read_u32(payload_ptr + 8) as usize;
+        let row_bytes = EMBED_DIM * 4;
+        if rows > (payload_len - CHUNK_HEADER_LEN) / row_bytes {
+            fail(ctrl, ERR_INPUT_BOUNDS);
+        }
+
+        let state = vaddr(STATE_SEG, STATE_OFFSET);
+        let sums = state + STATE_HEADER_LEN as u64;
+        if flags & CHUNK_FIRST != 0 {
+            if seq != 0 {
+                fail(ctrl, ERR_SEQUENCE);
+            }
+            write_u32(state, STATE_MAGIC);
+            write_u32(state + 4, 0);
+            write_u32(state + 8, 0);
+            write_u32(state + 12, 0);
+            let mut j = 0usize;
+            while j < EMBED_DIM {
+                write_i64(sums + (j * 8) as u64, 0);
+                j += 1;
+            }
+        }
+
+        // A chunk out of order (or before any CHUNK_FIRST) leaves the state
+        // untouched, so the executor can resend the expected one.
+        if read_u32(state) != STATE_MAGIC || read_u32(state + 4) != seq {
+            fail(ctrl, ERR_SEQUENCE);
+        }
+
+        let mut r = 0usize;
+        while r < rows {
+            let row_ptr = payload_ptr + (CHUNK_HEADER_LEN + r * row_bytes) as u64;
+            let mut j = 0usize;
+            while j < EMBED_DIM {
+                let sum_addr = sums + (j * 8) as u64;
+                let v = read_i32(row_ptr + (j * 4) as u64) as i64;
+                write_i64(sum_addr, read_i64(sum_addr).wrapping_add(v));
+                j += 1;
+            }
+            r += 1;
+        }
+
+        let next_seq = seq.wrapping_add(1);
+        let total_rows = read_u32(state + 8).wrapping_add(rows as u32);
+        write_u32(state + 4, next_seq);
+        write_u32(state + 8, total_rows);
+
+        write_u32(output_ptr, next_seq);
+        write_u32(output_ptr + 4, total_rows);
+        let mut output_len = PROGRESS_BYTES;
+
+        if flags & CHUNK_LAST != 0 {
+            let mut j = 0usize;
+            while j < EMBED_DIM {
+                let sum = read_i64(sums + (j * 8) as u64);
+                let mean = if total_rows == 0 {
+                    0
+                } else {
+                    (sum / total_rows as i64).clamp(i32::MIN as i64, i32::MAX as i64)
+                };
+                write_u32(
+                    output_ptr + (PROGRESS_BYTES + j * 4) as u64,
+                    mean as i32 as u32,
+                );
+                j += 1;
+            }
+            output_len = OUTPUT_BYTES;
+        }
+
+        ctrl.set_output_len(output_len as u32);
+        ctrl.set_status(ERR_OK);
+        exit(ERR_OK as i64);
+    }
+}
@@ -0,0 +1,86 @@
+#![no_std]
+#![no_main]
+
+use core::hint::black_box;
+use core::panic::PanicInfo;
+use frostbite_sdk as fb;
+use frostbite_sdk::fb_log;
+
+const TAG_WIDE8: u64 = fb::log_tag(b"wide8");
+const TAG_WIDE9: u64 = fb::log_tag(b"wide9");
+const TAG_FAIL: u64 = fb::log_tag(b"fail");
+
+// Distinct per-slot patterns so a swapped or dropped argument is obvious in
+// the host trace.
+const ARGS: [u64; 9] = [
+    0, // replaced by the tag
+    0x1111_0001,
+    0x2222_0002,
+    0x3333_0003,
+    0x4444_0004,
+    0x5555_0005,
+    0x6666_0006,
+    0x7777_0007,
+    0x8888_0008,
+];
+
+#[no_mangle]
+pub extern "C" fn main() -> i32 {
+    // A live stack frame around the calls: the spill must not clobber it and
+    // `sp` must come back where it was.
+    let sentinel = black_box([0xA5A5_A5A5_A5A5_A5A5u64; 4]);
+
+    // No syscall echoes its stack slots back, so on the VM this only checks
+    // that the register half arrives intact and the spill is balanced:
+    // DEBUG_LOG reads a0-a4 and ignores the slots. The spilled words
+    // themselves are checked against the host mock in the SDK's
+    // `wide_ecalls_pass_spilled_arguments_after_the_registers` test.
+    let ret8 = unsafe {
+        fb::raw::ecall8(
+            fb::SYS_DEBUG_LOG,
+            TAG_WIDE8,
+            ARGS[1],
+            ARGS[2],
+            ARGS[3],
+            ARGS[4],
+            ARGS[5],
+            ARGS[6],
+            ARGS[7],
+        )
+    };
+    let ret9 = unsafe {
+        fb::raw::ecall9(
+            fb::SYS_DEBUG_LOG,
+            TAG_WIDE9,
+            ARGS[1],
+            ARGS[2],
+            ARGS[3],
+            ARGS[4],
+            ARGS[5],
+            ARGS[6],
+            ARGS[7],
+            ARGS[8],
+        )
+    };
+
+    if ret8 != 0 || ret9 != 0 {
+        fb_log!(TAG_FAIL, ret8, ret9);
+        return 1;
+    }
+    if black_box(sentinel) != [0xA5A5_A5A5_A5A5_A5A5u64; 4] {
+        fb_log!(TAG_FAIL, 2);
+        return 2;
+    }
+
+    // Log the values that were spilled, for comparison with a VM-side trace of
+    // the stack slots.
+    fb_log!(TAG_WIDE8, ARGS[7]);
+    fb_log!(TAG_WIDE9, ARGS[7], ARGS[8]);
+    0
+}
+
+#[panic_handler]
+fn panic(_info: &PanicInfo) -> ! {
+    fb::print("panic\n");
+    fb::exit(1);
+}
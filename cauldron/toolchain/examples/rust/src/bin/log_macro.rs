@@ -0,0 +1,32 @@
+#![no_std]
+#![no_main]
+
+use core::panic::PanicInfo;
+use frostbite_sdk as fb;
+use frostbite_sdk::fb_log;
+
+const TAG_START: u64 = fb::log_tag(b"start");
+const TAG_DOT: u64 = fb::log_tag(b"dot");
+const TAG_DONE: u64 = fb::log_tag(b"done");
+
+#[no_mangle]
+pub extern "C" fn main() -> i32 {
+    fb_log!(TAG_START);
+
+    let a: [i8; 4] = [1, 2, 3, 4];
+    let b: [i8; 4] = [4, 3, 2, 1];
+    let dot = fb::dot_i8(&a, &b).unwrap_or(0);
+    fb_log!(TAG_DOT, dot, a.len());
+
+    let negative: i32 = -dot;
+    fb_log!(TAG_DOT, negative, dot == 20, a[0], b[0]);
+
+    fb_log!(TAG_DONE, 0);
+    0
+}
+
+#[panic_handler]
+fn panic(_info: &PanicInfo) -> ! {
+    fb::print("panic\n");
+    fb::exit(1);
+}
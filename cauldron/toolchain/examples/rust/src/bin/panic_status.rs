@@ -0,0 +1,17 @@
+#![no_std]
+#![no_main]
+
+use core::hint::black_box;
+use frostbite_sdk as fb;
+
+// A panic writes STATUS_PANIC (8) to the control block and exits with it.
+// This example keeps its control block at the start of scratch, like the
+// guest templates.
+fb::default_panic_handler!(fb::VmAddr(0));
+
+#[no_mangle]
+pub extern "C" fn main() -> i32 {
+    let divisor: i32 = black_box(0);
+    // Panics (divide by zero), so the run ends with exit code 8.
+    black_box(20) / divisor
+}
@@ -1,7 +1,6 @@
 #![no_std]
 #![no_main]
 
-use core::mem;
 use core::panic::PanicInfo;
 use frostbite_sdk as fb;
 
@@ -30,43 +29,42 @@ const ARB_SEGMENT: u8 = 0;
 const HEAP_OFFSET: usize = 128;
 const RAM_BYTES: usize = 4 * 1024 * 1024;
 
-static mut HEAP_PTR: usize = 0;
-static mut HEAP_END: usize = 0;
+static mut HEAP: Option<fb::BumpAllocator> = None;
 
-unsafe fn heap_init() {
+unsafe fn heap_init() -> fb::BumpAllocator {
     if HEAP_SEGMENT == 0 {
-        HEAP_PTR = &__heap_start as *const u8 as usize;
-        HEAP_END = &__stack_top as *const u8 as usize;
+        let start = &__heap_start as *const u8 as usize;
+        let end = &__stack_top as *const u8 as usize;
+        fb::BumpAllocator::new(fb::VmAddr::from_ptr(&__heap_start), end - start)
     } else {
         let base = fb::VmAddr::new(HEAP_SEGMENT, HEAP_OFFSET as u32)
             .unwrap_or(fb::VmAddr::null());
-        HEAP_PTR = base.raw() as usize;
-        HEAP_END = HEAP_PTR + (RAM_BYTES.saturating_sub(HEAP_OFFSET));
+        fb::BumpAllocator::new(base, RAM_BYTES.saturating_sub(HEAP_OFFSET))
     }
 }
 
 unsafe fn alloc_bytes(size: usize) -> *mut u8 {
-    if size == 0 {
-        return core::ptr::null_mut();
-    }
-
-    if HEAP_PTR == 0 || HEAP_END == 0 {
-        heap_init();
-    }
-
-    let size = (size + 7) & !7;
-    if HEAP_PTR + size > HEAP_END {
-        return core::ptr::null_mut();
-    }
-
-    let ptr = HEAP_PTR as *mut u8;
-    HEAP_PTR += size;
+    let mut heap = match HEAP {
+        Some(heap) => heap,
+        None => heap_init(),
+    };
+    let ptr = heap
+        .alloc_bytes(size)
+        .map_or(core::ptr::null_mut(), |addr| addr.raw() as *mut u8);
+    HEAP = Some(heap);
     ptr
 }
 
 unsafe fn alloc_slice<T>(len: usize) -> *mut T {
-    let bytes = len.saturating_mul(mem::size_of::<T>());
-    alloc_bytes(bytes) as *mut T
+    let mut heap = match HEAP {
+        Some(heap) => heap,
+        None => heap_init(),
+    };
+    let ptr = heap
+        .alloc_slice::<T>(len)
+        .map_or(core::ptr::null_mut(), |addr| addr.raw() as *mut T);
+    HEAP = Some(heap);
+    ptr
 }
 
 fn check(cond: bool, msg: &'static str, failures: &mut i32) {
@@ -80,8 +78,7 @@ fn check(cond: bool, msg: &'static str, failures: &mut i32) {
 
 fn test_system(failures: &mut i32) {
     let msg = "syscall smoke: system\n";
-    let written = fb::write(msg.as_bytes());
-    check(written == msg.len(), "write length", failures);
+    check(fb::write_all(msg.as_bytes()).is_ok(), "write_all", failures);
     fb::putchar(b'O');
     fb::putchar(b'K');
     fb::putchar(b'\n');
@@ -111,7 +108,8 @@ fn test_memory(failures: &mut i32) {
         let f = alloc_slice::<f32>(1);
         check(!f.is_null(), "alloc f32", failures);
         if !f.is_null() {
-            fb::write_f32(fb::VmAddr::from_mut_ptr(f), 3.5);
+            let status = fb::write_f32_checked(fb::VmAddr::from_mut_ptr(f), 3.5);
+            check(status.is_ok(), "write_f32 status", failures);
             let got = fb::read_f32(fb::VmAddr::from_ptr(f));
             check(got.to_bits() == 3.5f32.to_bits(), "read/write f32", failures);
         }
@@ -124,11 +122,12 @@ fn test_memory(failures: &mut i32) {
             core::ptr::write(src.add(1), 2.0);
             core::ptr::write(src.add(2), 3.0);
 
-            fb::memcpy_f32(
+            let status = fb::memcpy_f32_checked(
                 fb::VmAddr::from_mut_ptr(dst),
                 fb::VmAddr::from_ptr(src),
                 3,
             );
+            check(status.is_ok(), "memcpy_f32 status", failures);
 
             let d0 = core::ptr::read(dst.add(0));
             let d1 = core::ptr::read(dst.add(1));
@@ -151,7 +150,8 @@ fn test_memory(failures: &mut i32) {
 
             let out = core::slice::from_raw_parts_mut(accum, 3);
             let input = core::slice::from_raw_parts(inc, 3);
-            let _ = fb::accum(out, input);
+            let status = fb::accum_checked(out, input);
+            check(status.is_ok(), "accum status", failures);
 
             check(out[0].to_bits() == 2.0f32.to_bits(), "accum[0]", failures);
             check(out[1].to_bits() == 3.0f32.to_bits(), "accum[1]", failures);
@@ -174,7 +174,8 @@ fn test_ai(failures: &mut i32) {
     check(dst[3] == 2, "vec_add_i8[3]", failures);
 
     let mut act: [i8; 4] = [-1, 2, -3, 4];
-    let _ = fb::activation(&mut act, fb::ACT_RELU);
+    let status = fb::activation_checked(&mut act, fb::ACT_RELU);
+    check(status.is_ok(), "activation status", failures);
     check(act[0] == 0, "activation[0]", failures);
     check(act[1] == 2, "activation[1]", failures);
     check(act[2] == 0, "activation[2]", failures);
@@ -187,16 +188,16 @@ fn test_llm(failures: &mut i32) {
     let mut empty_i32: [i32; 0] = [];
 
     let _ = fb::matmul(&mut empty_f32, &[], fb::VmAddr::null());
-    let _ = fb::rmsnorm(&mut empty_f32, &[], &[]);
-    let _ = fb::softmax(&mut empty_f32);
-    let _ = fb::silu(&mut empty_f32);
-    let _ = fb::rope(&mut empty_f32, &mut empty_f32_b, 0, 0, 1);
+    let _ = fb::rmsnorm_checked(&mut empty_f32, &[], &[]);
+    let _ = fb::softmax_checked(&mut empty_f32);
+    let _ = fb::silu_checked(&mut empty_f32);
+    let _ = fb::rope_checked(&mut empty_f32, &mut empty_f32_b, 0, 0, 1);
 
-    let _ = fb::matmul_q8(&mut empty_f32, fb::VmAddr::null(), fb::VmAddr::null(), fb::VmAddr::null(), 0, 0);
-    let mut row_state = fb::RowState { cursor: 0, max_rows: 0 };
-    let _ = fb::matmul_q8_partial(&mut empty_f32, fb::VmAddr::null(), fb::VmAddr::null(), fb::VmAddr::null(), 0, 0, &mut row_state);
+    let _ = fb::matmul_q8(&mut empty_f32, fb::VmAddr::null(), fb::VmAddr::null(), fb::VmAddr::null(), 0, fb::Q8Flags::NONE);
+    let mut row_state = fb::RowState::new(0);
+    let _ = fb::matmul_q8_partial(&mut empty_f32, fb::VmAddr::null(), fb::VmAddr::null(), fb::VmAddr::null(), 0, fb::Q8Flags::NONE, &mut row_state);
 
-    let mut argmax_state = fb::ArgmaxState { cursor: 0, max_idx: 0, max_bits: 0, max_per_call: 0 };
+    let mut argmax_state = fb::ArgmaxState::new(0);
     let _ = fb::argmax_partial(&empty_f32, &mut argmax_state);
 
     fb::debug_log(0x1234, 1, 2, 3, 4);
@@ -214,14 +215,14 @@ fn test_llm(failures: &mut i32) {
     check(out[2] == 3, "weighted_sum[2]", failures);
 
     let _ = fb::softmax_i32(&mut empty_i32);
-    let _ = fb::softmax_i32_f32(&mut empty_i32);
+    let _ = fb::softmax_i32_f32_checked(&mut empty_i32);
 
     let _ = fb::matmul_i8_i32(&mut empty_i32, &[], fb::VmAddr::null(), 1 << 16);
-    let mut argmax_i32_state = fb::ArgmaxI32State { cursor: 0, max_idx: 0, max_val: 0, max_per_call: 0 };
+    let mut argmax_i32_state = fb::ArgmaxI32State::new(0);
     let _ = fb::argmax_i32_partial(&empty_i32, &mut argmax_i32_state);
 
-    let _ = fb::silu_mul_i32(&mut empty_i32, &[]);
-    let _ = fb::rmsnorm_i32(&mut empty_i32, &[], fb::VmAddr::null());
+    let _ = fb::silu_mul_i32_checked(&mut empty_i32, &[]);
+    let _ = fb::rmsnorm_i32_checked(&mut empty_i32, &[], fb::VmAddr::null());
 
     let prequant = [0u8; 4];
     let _ = fb::matmul_i8_i8(&mut empty_i32, &prequant, 0, fb::VmAddr::null(), 1 << 16);
@@ -230,7 +231,7 @@ fn test_llm(failures: &mut i32) {
     let mut state_words = [0u32; fb::I8_I8_ARGMAX_HEADER_WORDS];
     let _ = fb::matmul_i8_i8_argmax_partial(&prequant, 0, fb::VmAddr::null(), 1 << 16, 0, &mut state_words);
 
-    let mut qkv_state = fb::RowState { cursor: 0, max_rows: 0 };
+    let mut qkv_state = fb::RowState::new(0);
     let qkv_cfg = fb::MatmulQkvConfig {
         out_q: 0,
         out_k: 0,
@@ -251,7 +252,7 @@ fn test_llm(failures: &mut i32) {
     };
     fb::matmul_i8_i8_qkv(&qkv_cfg);
 
-    let mut w1w3_state = fb::RowState { cursor: 0, max_rows: 0 };
+    let mut w1w3_state = fb::RowState::new(0);
     let w1w3_cfg = fb::MatmulW1W3Config {
         out_a: 0,
         out_b: 0,
@@ -266,7 +267,7 @@ fn test_llm(failures: &mut i32) {
     };
     fb::matmul_i8_i8_w1w3(&w1w3_cfg);
 
-    let mut w1w3_silu_state = fb::RowState { cursor: 0, max_rows: 0 };
+    let mut w1w3_silu_state = fb::RowState::new(0);
     let w1w3_silu_cfg = fb::MatmulW1W3SiluConfig {
         out_ptr: 0,
         x_ptr: 0,
@@ -288,37 +289,12 @@ fn test_quantum(failures: &mut i32) {
     check(meas == 0 || meas == 1, "quantum measure", failures);
 }
 
-#[cfg(feature = "onchain")]
-#[repr(C)]
-struct GraphHeader {
-    magic: u32,
-    num_edges: u32,
-    dim: u32,
-    _pad: u32,
-}
-
 #[cfg(feature = "onchain")]
 unsafe fn init_graph_segment() {
     let base = fb::VmAddr::new(GRAPH_SEGMENT, 0).unwrap();
-    let header_ptr = base.raw() as *mut GraphHeader;
-    core::ptr::write(
-        header_ptr,
-        GraphHeader {
-            magic: 0x48505247,
-            num_edges: 1,
-            dim: 4,
-            _pad: 0,
-        },
-    );
-
-    let edge_base = base.raw() as usize + mem::size_of::<GraphHeader>();
-    let target_ptr = edge_base as *mut u32;
-    core::ptr::write(target_ptr, 7);
-    let weights_ptr = (edge_base + 4) as *mut i8;
-    core::ptr::write(weights_ptr.add(0), 1);
-    core::ptr::write(weights_ptr.add(1), 1);
-    core::ptr::write(weights_ptr.add(2), 1);
-    core::ptr::write(weights_ptr.add(3), 1);
+    let mut graph = fb::GraphBuilder::new(base, 4, 1);
+    let _ = graph.push_edge(7, &[1, 1, 1, 1]);
+    graph.finish();
 }
 
 #[cfg(feature = "onchain")]
@@ -345,15 +321,10 @@ fn test_graph(failures: &mut i32) {
     let mut output: [u32; 2] = [0, 0];
     let graph_idx = (GRAPH_SEGMENT - 1) as u64;
 
-    let hits = fb::graph_search(
-        fb::VmAddr::from_slice(&input),
-        graph_idx,
-        fb::VmAddr::from_mut_slice(&mut output),
-        0,
-        false,
-    );
+    let mut rows = [fb::GraphHit::default(); 1];
+    let hits = fb::graph_search_into(&input, graph_idx, &mut rows, 0, false).unwrap_or(0);
     check(hits == 1, "graph_search hits", failures);
-    check(output[0] == 7, "graph_search node", failures);
+    check(rows[0].node == 7, "graph_search node", failures);
 
     let hits_alt = fb::graph_search(
         fb::VmAddr::from_slice(&input),
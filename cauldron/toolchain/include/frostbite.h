@@ -54,6 +54,7 @@ extern "C" {
 #define FB_SYS_MATMUL_I8_I8_W1W3       142
 #define FB_SYS_MATMUL_I8_I8_ARGMAX     143
 #define FB_SYS_MATMUL_I8_I8_W1W3_SILU  144
+#define FB_SYS_RMSNORM_MATMUL_I8_I8_QKV 145
 
 #define FB_SYS_DOT_I8                  7001
 #define FB_SYS_VEC_ADD_I8              7003
@@ -81,6 +82,8 @@ extern "C" {
 /* Activation types */
 #define FB_ACT_RELU    0
 #define FB_ACT_SIGMOID 1
+#define FB_ACT_GELU    2  /* tanh approximation */
+#define FB_ACT_TANH    3
 
 /* Virtual address helpers */
 #define FB_SCRATCH_ADDR(offset) ((uint64_t)(offset))
@@ -567,7 +570,7 @@ void *memcpy(void *dst, const void *src, size_t n);
 void *memset(void *dst, int c, size_t n);
 
 /* ============================================================================
- * LLM syscalls (110-144)
+ * LLM syscalls (110-145)
  * ============================================================================ */
 
 /**
@@ -819,6 +822,15 @@ static inline void fb_matmul_i8_i8_w1w3_silu(const fb_matmul_w1w3_silu_cfg_t *cf
     fb_syscall1(FB_SYS_MATMUL_I8_I8_W1W3_SILU, (long)cfg);
 }
 
+/**
+ * RMSNORM_MATMUL_I8_I8_QKV: RMSNorm x with norm_weight, then fused Q/K/V matmul.
+ * cfg->x_ptr is ignored; x must hold cfg->n Q16 values.
+ */
+static inline void fb_rmsnorm_matmul_qkv_i8(uint64_t norm_weight, const fb_matmul_qkv_cfg_t *cfg,
+                                            const int32_t *x) {
+    fb_syscall3(FB_SYS_RMSNORM_MATMUL_I8_I8_QKV, (long)cfg, (long)x, (long)norm_weight);
+}
+
 /* ============================================================================
  * AI/ML accelerator syscalls (7000-7019)
  * ============================================================================ */
@@ -1,4 +1,12 @@
-#![no_std]
+#![cfg_attr(not(feature = "host"), no_std)]
+
+#[cfg(feature = "host")]
+pub mod mock;
+pub mod q16;
+pub mod status;
+
+pub use q16::Q16;
+pub use status::status_name;
 
 // ============================================================================
 // Constants and types
@@ -22,6 +30,8 @@ pub const SYS_MEMCPY_F32: u64 = 119;
 pub const SYS_MATMUL_Q8_PARTIAL: u64 = 120;
 pub const SYS_ARGMAX_PARTIAL: u64 = 121;
 pub const SYS_DEBUG_LOG: u64 = 122;
+/// Not implemented by current VMs; see [`memset_bytes`].
+pub const SYS_MEMSET: u64 = 124;
 
 pub const SYS_MATMUL_I8_I32: u64 = 130;
 pub const SYS_SOFTMAX_I32: u64 = 131;
@@ -38,6 +48,10 @@ pub const SYS_MATMUL_I8_I8_QKV: u64 = 141;
 pub const SYS_MATMUL_I8_I8_W1W3: u64 = 142;
 pub const SYS_MATMUL_I8_I8_ARGMAX: u64 = 143;
 pub const SYS_MATMUL_I8_I8_W1W3_SILU: u64 = 144;
+pub const SYS_RMSNORM_MATMUL_I8_I8_QKV: u64 = 145;
+/// Not implemented by current VMs; see [`layernorm_i32`].
+#[cfg(feature = "layernorm")]
+pub const SYS_LAYERNORM_I32: u64 = 146;
 
 pub const SYS_DOT_I8: u64 = 7001;
 pub const SYS_VEC_ADD_I8: u64 = 7003;
@@ -57,6 +71,9 @@ pub const Q8_FLAG_MASK: u64 = Q8_FLAG_PREQUANT | Q8_FLAG_TENSOR_SCALE;
 
 pub const ACT_RELU: i32 = 0;
 pub const ACT_SIGMOID: i32 = 1;
+/// GELU, tanh approximation: `0.5x(1 + tanh(sqrt(2/pi)(x + 0.044715x^3)))`.
+pub const ACT_GELU: i32 = 2;
+pub const ACT_TANH: i32 = 3;
 
 pub const QOP_INIT: u32 = 0;
 pub const QOP_H: u32 = 1;
@@ -74,6 +91,29 @@ pub const fn align4(n: usize) -> usize {
     (n + 3) & !3
 }
 
+/// Bytes addressable within one segment (the 28-bit offset of a `VmAddr`).
+pub const SEGMENT_SIZE: usize = 1 << 28;
+
+/// Load address of guest code in scratch (`riscv.ld` in the templates).
+/// Scratch buffers placed below it must end at or before it.
+pub const GUEST_TEXT_BASE: usize = 0x4000;
+
+/// Whether `len` bytes at `offset` end at or before `end`, without
+/// overflowing. `const` so guests can check their config layout at build time:
+///
+/// ```compile_fail
+/// use frostbite_sdk::{region_fits, GUEST_TEXT_BASE};
+/// const HIDDEN_OFFSET: usize = 0x3F00;
+/// const HIDDEN_DIM: usize = 128;
+/// const _: () = assert!(region_fits(HIDDEN_OFFSET, HIDDEN_DIM * 4, GUEST_TEXT_BASE));
+/// ```
+pub const fn region_fits(offset: usize, len: usize, end: usize) -> bool {
+    match offset.checked_add(len) {
+        Some(stop) => stop <= end,
+        None => false,
+    }
+}
+
 #[repr(transparent)]
 #[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct VmAddr(pub u64);
@@ -87,6 +127,13 @@ impl VmAddr {
         }
     }
 
+    /// [`VmAddr::new`] without the range check, for inputs known to be valid.
+    /// A segment above 15 or an offset of `1 << 28` or more yields a wrong
+    /// address rather than an error; prefer `vmaddr!` for constants.
+    pub const fn new_unchecked(segment: u8, offset: u32) -> Self {
+        VmAddr(((segment as u64) << 28) | offset as u64)
+    }
+
     pub const fn raw(self) -> u64 {
         self.0
     }
@@ -95,31 +142,180 @@ impl VmAddr {
         VmAddr(0)
     }
 
+    /// Segment index (upper 4 bits of the 32-bit address).
+    pub const fn segment(self) -> u8 {
+        ((self.0 >> 28) & 0xF) as u8
+    }
+
+    /// Byte offset within the segment (low 28 bits).
+    pub const fn offset(self) -> u32 {
+        (self.0 & ((1u64 << 28) - 1)) as u32
+    }
+
+    /// `(segment, offset)`; the inverse of [`VmAddr::new`].
+    pub const fn split(self) -> (u8, u32) {
+        (self.segment(), self.offset())
+    }
+
+    /// `Some` if `ptr` fits the 4-bit segment + 28-bit offset encoding
+    /// (i.e. is below `1 << 32`).
+    pub fn try_from_ptr<T>(ptr: *const T) -> Option<Self> {
+        let raw = ptr as u64;
+        if raw < (1u64 << 32) {
+            Some(VmAddr(raw))
+        } else {
+            None
+        }
+    }
+
+    pub fn try_from_slice<T>(s: &[T]) -> Option<Self> {
+        Self::try_from_ptr(s.as_ptr())
+    }
+
+    pub fn try_from_mut_slice<T>(s: &mut [T]) -> Option<Self> {
+        Self::try_from_ptr(s.as_mut_ptr())
+    }
+
+    // The infallible constructors below cast the pointer as-is. Inside the VM
+    // every guest pointer already is a VmAddr; a host pointer (or anything
+    // above `1 << 32`) produces an address the VM will reject. Use the `try_`
+    // forms where the pointer's origin is not known.
+
     pub fn from_ptr<T>(ptr: *const T) -> Self {
+        debug_assert_vm_ptr(ptr as u64);
         VmAddr(ptr as u64)
     }
 
     pub fn from_mut_ptr<T>(ptr: *mut T) -> Self {
+        debug_assert_vm_ptr(ptr as u64);
         VmAddr(ptr as u64)
     }
 
     pub fn from_ref<T>(r: &T) -> Self {
+        debug_assert_vm_ptr(r as *const T as u64);
         VmAddr(r as *const T as u64)
     }
 
     pub fn from_mut<T>(r: &mut T) -> Self {
+        debug_assert_vm_ptr(r as *mut T as u64);
         VmAddr(r as *mut T as u64)
     }
 
     pub fn from_slice<T>(s: &[T]) -> Self {
+        debug_assert_vm_ptr(s.as_ptr() as u64);
         VmAddr(s.as_ptr() as u64)
     }
 
     pub fn from_mut_slice<T>(s: &mut [T]) -> Self {
+        debug_assert_vm_ptr(s.as_mut_ptr() as u64);
         VmAddr(s.as_mut_ptr() as u64)
     }
 }
 
+/// `segment:0xoffset`, e.g. `1:0x3000`. Values that do not fit the 32-bit
+/// encoding (host pointers) print as the raw hex value instead.
+impl core::fmt::Display for VmAddr {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{:#x}", self)
+    }
+}
+
+/// `segment:offset` in hex; `{:#x}` prefixes the offset with `0x`.
+impl core::fmt::LowerHex for VmAddr {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        if self.0 >= 1u64 << 32 {
+            return core::fmt::LowerHex::fmt(&self.0, f);
+        }
+        write!(f, "{}:", self.segment())?;
+        core::fmt::LowerHex::fmt(&self.offset(), f)
+    }
+}
+
+// Only meaningful inside the VM; host-side tests legitimately pass host pointers.
+#[inline(always)]
+fn debug_assert_vm_ptr(_raw: u64) {
+    #[cfg(target_arch = "riscv64")]
+    debug_assert!(_raw < (1u64 << 32), "pointer outside VmAddr range");
+}
+
+/// RVCD header at the start of every uploaded segment payload.
+pub const SEGMENT_HEADER_SIZE: u32 = 12;
+/// Payload bytes per slot for files uploaded with `upload_model --split-across-slots`.
+/// Every piece but the last is this long; the uploader's `.split.json` piece
+/// table is host-only, so this stride is all a guest has to go on.
+pub const SPLIT_PIECE_SIZE: u32 = 256 * 1024;
+
+/// Payload base of `piece` for a file split across slots starting at `slot_base`.
+/// Returns the null address if the slot would exceed 15.
+pub const fn multi_segment_base(slot_base: u8, piece: u8) -> VmAddr {
+    let slot = slot_base as u16 + piece as u16;
+    if slot > 15 {
+        return VmAddr::null();
+    }
+    match VmAddr::new(slot as u8, SEGMENT_HEADER_SIZE) {
+        Some(addr) => addr,
+        None => VmAddr::null(),
+    }
+}
+
+/// Address of `logical_offset` within a file split across slots starting at `slot_base`.
+/// Returns the null address if the offset falls past slot 15.
+pub const fn multi_segment_addr(slot_base: u8, logical_offset: u32) -> VmAddr {
+    let piece = logical_offset / SPLIT_PIECE_SIZE;
+    if piece > 15 {
+        return VmAddr::null();
+    }
+    let base = multi_segment_base(slot_base, piece as u8);
+    if base.raw() == 0 {
+        return base;
+    }
+    VmAddr(base.raw() + (logical_offset % SPLIT_PIECE_SIZE) as u64)
+}
+
+/// Bump allocator over a fixed VM address range, for carving syscall buffers
+/// out of guest scratch or a RAM segment. Sizes are rounded up to 8 bytes, so
+/// an 8-aligned `base` keeps every allocation 8-aligned. Nothing is freed;
+/// drop the allocator (or build a new one) to start over.
+#[derive(Copy, Clone, Debug)]
+pub struct BumpAllocator {
+    next: u64,
+    end: u64,
+}
+
+impl BumpAllocator {
+    pub const fn new(base: VmAddr, len: usize) -> Self {
+        BumpAllocator {
+            next: base.raw(),
+            end: base.raw().saturating_add(len as u64),
+        }
+    }
+
+    /// Reserve `size` bytes (rounded up to 8). `None` for a zero size or when
+    /// the arena cannot hold the request.
+    pub fn alloc_bytes(&mut self, size: usize) -> Option<VmAddr> {
+        if size == 0 {
+            return None;
+        }
+        let size = (size as u64).checked_add(7)? & !7;
+        let next = self.next.checked_add(size)?;
+        if next > self.end {
+            return None;
+        }
+        let addr = VmAddr(self.next);
+        self.next = next;
+        Some(addr)
+    }
+
+    /// Reserve room for `len` values of `T`.
+    pub fn alloc_slice<T>(&mut self, len: usize) -> Option<VmAddr> {
+        self.alloc_bytes(len.checked_mul(core::mem::size_of::<T>())?)
+    }
+
+    pub const fn remaining(&self) -> usize {
+        (self.end - self.next) as usize
+    }
+}
+
 #[repr(C)]
 #[derive(Copy, Clone, Debug)]
 pub struct Q16Complex {
@@ -158,6 +354,105 @@ pub struct ArgmaxI32State {
     pub max_per_call: u32,
 }
 
+// `is_done` treats the per-call limit as the pass length, which holds only when
+// it is set to the full row/element count (as the benchmarks do). With a
+// smaller chunk, or 0 ("everything in one call"), compare the cursor against
+// the total instead, as `run_partial` does.
+
+impl RowState {
+    pub const fn new(max_rows: u32) -> Self {
+        RowState {
+            cursor: 0,
+            max_rows,
+        }
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.cursor >= self.max_rows
+    }
+
+    /// Rewind to row 0, keeping `max_rows`.
+    pub fn reset(&mut self) {
+        self.cursor = 0;
+    }
+}
+
+impl ArgmaxState {
+    pub const fn new(max_per_call: u32) -> Self {
+        ArgmaxState {
+            cursor: 0,
+            max_idx: 0,
+            max_bits: 0,
+            max_per_call,
+        }
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.cursor >= self.max_per_call
+    }
+
+    /// Rewind and clear the running max, keeping `max_per_call`.
+    pub fn reset(&mut self) {
+        *self = ArgmaxState::new(self.max_per_call);
+    }
+}
+
+impl ArgmaxI32State {
+    pub const fn new(max_per_call: u32) -> Self {
+        ArgmaxI32State {
+            cursor: 0,
+            max_idx: 0,
+            max_val: 0,
+            max_per_call,
+        }
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.cursor >= self.max_per_call
+    }
+
+    /// Rewind and clear the running max, keeping `max_per_call`.
+    pub fn reset(&mut self) {
+        *self = ArgmaxI32State::new(self.max_per_call);
+    }
+}
+
+/// One GRAPH_SEARCH output row: 8 bytes, `node` then `score`.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct GraphHit {
+    pub node: u32,
+    pub score: i32,
+}
+
+/// `"GRPH"`: magic at the start of a GRAPH_SEARCH graph segment.
+pub const GRAPH_MAGIC: u32 = 0x4850_5247;
+
+/// 16-byte header at the start of a graph segment; the edges (a `u32`
+/// target node followed by `dim` `i8` weights) start right after it.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct GraphHeader {
+    pub magic: u32,
+    pub num_edges: u32,
+    pub dim: u32,
+    pub reserved: u32,
+}
+
+impl GraphHeader {
+    pub const fn new(num_edges: u32, dim: u32) -> Self {
+        GraphHeader {
+            magic: GRAPH_MAGIC,
+            num_edges,
+            dim,
+            reserved: 0,
+        }
+    }
+}
+
+/// Rows staged on the stack by `graph_search_into` (512 bytes).
+pub const GRAPH_SEARCH_MAX_HITS: usize = 64;
+
 pub const I8_I8_ARGMAX_CURSOR_WORD: usize = 0;
 pub const I8_I8_ARGMAX_MAX_IDX_WORD: usize = 1;
 pub const I8_I8_ARGMAX_MAX_VAL_WORD: usize = 2;
@@ -178,6 +473,128 @@ pub const I8_I8_ARGMAX_STAGE2_MAX_WORD: usize = 16;
 pub const I8_I8_ARGMAX_FULL_MAX_WORD: usize = 17;
 pub const I8_I8_ARGMAX_HEADER_WORDS: usize = 18;
 
+/// Typed view of the MATMUL_I8_I8_ARGMAX_PARTIAL state header; field `n`
+/// is word `I8_I8_ARGMAX_*_WORD` n. With both shortlists disabled
+/// (`topk1 = topk2 = 0`) the header is the whole state and
+/// `as_words_mut()` can be passed straight to `matmul_i8_i8_argmax_partial`;
+/// otherwise the shortlist arrays follow it in a larger word buffer.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct I8I8ArgmaxState {
+    pub cursor: u32,
+    pub max_idx: u32,
+    pub max_val: i32,
+    pub max_rows_per_call: u32,
+    pub topk2: u32,
+    pub filled2: u32,
+    pub min_val2: i32,
+    pub min_pos2: u32,
+    pub short_n2: u32,
+    pub topk1: u32,
+    pub filled1: u32,
+    pub min_val1: i32,
+    pub min_pos1: u32,
+    pub short_n1: u32,
+    pub stage2_cursor: u32,
+    pub full_cursor: u32,
+    pub stage2_max: u32,
+    pub full_max: u32,
+}
+
+const _: () = assert!(core::mem::size_of::<I8I8ArgmaxState>() == I8_I8_ARGMAX_HEADER_WORDS * 4);
+const _: () = assert!(
+    core::mem::offset_of!(I8I8ArgmaxState, max_val) == I8_I8_ARGMAX_MAX_VAL_WORD * 4
+        && core::mem::offset_of!(I8I8ArgmaxState, topk2) == I8_I8_ARGMAX_TOPK2_WORD * 4
+        && core::mem::offset_of!(I8I8ArgmaxState, topk1) == I8_I8_ARGMAX_TOPK1_WORD * 4
+        && core::mem::offset_of!(I8I8ArgmaxState, full_max) == I8_I8_ARGMAX_FULL_MAX_WORD * 4
+);
+
+impl I8I8ArgmaxState {
+    /// All-zero state: no shortlists, `max_rows_per_call = 0` (all rows).
+    pub const fn new() -> Self {
+        I8I8ArgmaxState {
+            cursor: 0,
+            max_idx: 0,
+            max_val: 0,
+            max_rows_per_call: 0,
+            topk2: 0,
+            filled2: 0,
+            min_val2: 0,
+            min_pos2: 0,
+            short_n2: 0,
+            topk1: 0,
+            filled1: 0,
+            min_val1: 0,
+            min_pos1: 0,
+            short_n1: 0,
+            stage2_cursor: 0,
+            full_cursor: 0,
+            stage2_max: 0,
+            full_max: 0,
+        }
+    }
+
+    /// `(max_idx, max_val)`: the best row seen so far and its logit. The
+    /// runner-up lives in the shortlist arrays after the header; see
+    /// [`i8_i8_argmax_top2`].
+    pub const fn best(&self) -> (u32, i32) {
+        (self.max_idx, self.max_val)
+    }
+
+    /// The header as the raw words the syscall reads and writes.
+    pub fn as_words_mut(&mut self) -> &mut [u32; I8_I8_ARGMAX_HEADER_WORDS] {
+        // SAFETY: repr(C), 18 four-byte fields (size asserted above), and
+        // every bit pattern is valid for both u32 and i32.
+        unsafe { &mut *(self as *mut Self as *mut [u32; I8_I8_ARGMAX_HEADER_WORDS]) }
+    }
+}
+
+/// Words a MATMUL_I8_I8_ARGMAX_PARTIAL state needs: the header, then
+/// `topk2_idx`, `topk2_score`, `topk1_idx`, `topk1_score`.
+pub const fn i8_i8_argmax_state_words(topk2: usize, topk1: usize) -> usize {
+    I8_I8_ARGMAX_HEADER_WORDS + 2 * topk2 + 2 * topk1
+}
+
+/// Best and runner-up rows of a MATMUL_I8_I8_ARGMAX_PARTIAL state, for
+/// confidence-margin gating.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct I8I8Top2 {
+    /// `(max_idx, max_val)`.
+    pub top1: (u32, i32),
+    /// Highest-scoring other row in shortlist 1, or `None` when shortlist 1
+    /// is smaller than two entries or holds no other row yet.
+    pub top2: Option<(u32, i32)>,
+}
+
+/// Decode [`I8I8Top2`] from a full state buffer (header plus shortlist
+/// arrays). The runner-up is read from the first `filled1` entries of
+/// shortlist 1 when `topk1 >= 2`; its score is the value the VM stored there.
+pub fn i8_i8_argmax_top2(state_words: &[u32]) -> SdkResult<I8I8Top2> {
+    check_len(state_words.len(), I8_I8_ARGMAX_HEADER_WORDS)?;
+    let top1 = (
+        state_words[I8_I8_ARGMAX_MAX_IDX_WORD],
+        state_words[I8_I8_ARGMAX_MAX_VAL_WORD] as i32,
+    );
+    let topk1 = state_words[I8_I8_ARGMAX_TOPK1_WORD] as usize;
+    if topk1 < 2 {
+        return Ok(I8I8Top2 { top1, top2: None });
+    }
+    let topk2 = state_words[I8_I8_ARGMAX_TOPK2_WORD] as usize;
+    check_len(state_words.len(), i8_i8_argmax_state_words(topk2, topk1))?;
+    let filled = (state_words[I8_I8_ARGMAX_FILLED1_WORD] as usize).min(topk1);
+    let idx_base = i8_i8_argmax_state_words(topk2, 0);
+    let idx = &state_words[idx_base..idx_base + filled];
+    let score = &state_words[idx_base + topk1..idx_base + topk1 + filled];
+    let mut top2: Option<(u32, i32)> = None;
+    for (&row, &raw) in idx.iter().zip(score) {
+        let value = raw as i32;
+        if row != top1.0 && top2.is_none_or(|(_, best)| value > best) {
+            top2 = Some((row, value));
+        }
+    }
+    Ok(I8I8Top2 { top1, top2 })
+}
+
 #[repr(C)]
 #[derive(Copy, Clone, Debug)]
 pub struct MatmulQkvConfig {
@@ -232,14 +649,48 @@ pub struct MatmulW1W3SiluConfig {
 pub enum SdkError {
     BufferTooSmall,
     LengthMismatch,
+    /// A status-only syscall returned a nonzero `a0`.
+    Syscall(u64),
+    /// SOFTMAX_I32 saturated an intermediate exponential; the output
+    /// distribution is unreliable.
+    Saturated,
+    /// A qubit index was `>= QUANTUM_NUM_QUBITS`.
+    InvalidQubit,
+    /// `graph_idx` does not name a segment (graph `i` lives in segment `i + 1`).
+    InvalidGraph,
+    /// A length does not fit the bits the syscall packs it into.
+    LengthTooLarge,
+    /// WRITE accepted no bytes (or reported more than it was given), so
+    /// `write_all` stopped.
+    WriteStalled,
 }
 
 pub type SdkResult<T> = core::result::Result<T, SdkError>;
 
+#[cfg(feature = "host")]
+impl core::fmt::Display for SdkError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            SdkError::BufferTooSmall => f.write_str("buffer too small for the requested length"),
+            SdkError::LengthMismatch => f.write_str("buffer lengths do not match"),
+            SdkError::Syscall(status) => write!(f, "syscall returned status {status}"),
+            SdkError::Saturated => f.write_str("softmax saturated an intermediate exponential"),
+            SdkError::InvalidQubit => f.write_str("qubit index out of range"),
+            SdkError::InvalidGraph => f.write_str("graph index does not name a segment"),
+            SdkError::LengthTooLarge => f.write_str("length overlaps the syscall's flag bits"),
+            SdkError::WriteStalled => f.write_str("VM log write made no progress"),
+        }
+    }
+}
+
+#[cfg(feature = "host")]
+impl std::error::Error for SdkError {}
+
 // ============================================================================
 // Raw syscalls (unsafe)
 // ============================================================================
 
+#[cfg(not(feature = "host"))]
 pub mod raw {
     use core::arch::asm;
 
@@ -367,12 +818,167 @@ pub mod raw {
         a0
     }
 
+    /// Eight-argument syscall. `a0`-`a6` travel in registers as usual; the
+    /// eighth argument is spilled to `0(sp)` for the duration of the `ecall`
+    /// (16 bytes are reserved to keep `sp` aligned).
+    #[inline(always)]
+    pub unsafe fn ecall8(
+        id: u64,
+        a0_in: u64,
+        a1: u64,
+        a2: u64,
+        a3: u64,
+        a4: u64,
+        a5: u64,
+        a6: u64,
+        s0: u64,
+    ) -> u64 {
+        let mut a0 = a0_in;
+        asm!(
+            "addi sp, sp, -16",
+            "sd {s0}, 0(sp)",
+            "ecall",
+            "addi sp, sp, 16",
+            s0 = in(reg) s0,
+            inlateout("a0") a0,
+            in("a1") a1,
+            in("a2") a2,
+            in("a3") a3,
+            in("a4") a4,
+            in("a5") a5,
+            in("a6") a6,
+            in("a7") id,
+        );
+        a0
+    }
+
+    /// Nine-argument syscall: as [`ecall8`], with the ninth argument at
+    /// `8(sp)`.
+    #[inline(always)]
+    pub unsafe fn ecall9(
+        id: u64,
+        a0_in: u64,
+        a1: u64,
+        a2: u64,
+        a3: u64,
+        a4: u64,
+        a5: u64,
+        a6: u64,
+        s0: u64,
+        s1: u64,
+    ) -> u64 {
+        let mut a0 = a0_in;
+        asm!(
+            "addi sp, sp, -16",
+            "sd {s0}, 0(sp)",
+            "sd {s1}, 8(sp)",
+            "ecall",
+            "addi sp, sp, 16",
+            s0 = in(reg) s0,
+            s1 = in(reg) s1,
+            inlateout("a0") a0,
+            in("a1") a1,
+            in("a2") a2,
+            in("a3") a3,
+            in("a4") a4,
+            in("a5") a5,
+            in("a6") a6,
+            in("a7") id,
+        );
+        a0
+    }
+
     #[inline(always)]
     pub unsafe fn exit(code: i64, syscall_id: u64) -> ! {
         asm!("ecall", in("a0") code, in("a7") syscall_id, options(noreturn));
     }
 }
 
+/// Host build of [`raw`]: every `ecall` goes to the thread's
+/// [`mock`] handler instead of the VM.
+#[cfg(feature = "host")]
+#[allow(clippy::missing_safety_doc, clippy::too_many_arguments)]
+pub mod raw {
+    use crate::mock::ecall;
+
+    pub unsafe fn ecall0(id: u64) -> u64 {
+        ecall(id, [0; 9])
+    }
+
+    pub unsafe fn ecall1(id: u64, a0: u64) -> u64 {
+        ecall(id, [a0, 0, 0, 0, 0, 0, 0, 0, 0])
+    }
+
+    pub unsafe fn ecall2(id: u64, a0: u64, a1: u64) -> u64 {
+        ecall(id, [a0, a1, 0, 0, 0, 0, 0, 0, 0])
+    }
+
+    pub unsafe fn ecall3(id: u64, a0: u64, a1: u64, a2: u64) -> u64 {
+        ecall(id, [a0, a1, a2, 0, 0, 0, 0, 0, 0])
+    }
+
+    pub unsafe fn ecall4(id: u64, a0: u64, a1: u64, a2: u64, a3: u64) -> u64 {
+        ecall(id, [a0, a1, a2, a3, 0, 0, 0, 0, 0])
+    }
+
+    pub unsafe fn ecall5(id: u64, a0: u64, a1: u64, a2: u64, a3: u64, a4: u64) -> u64 {
+        ecall(id, [a0, a1, a2, a3, a4, 0, 0, 0, 0])
+    }
+
+    pub unsafe fn ecall6(id: u64, a0: u64, a1: u64, a2: u64, a3: u64, a4: u64, a5: u64) -> u64 {
+        ecall(id, [a0, a1, a2, a3, a4, a5, 0, 0, 0])
+    }
+
+    pub unsafe fn ecall7(
+        id: u64,
+        a0: u64,
+        a1: u64,
+        a2: u64,
+        a3: u64,
+        a4: u64,
+        a5: u64,
+        a6: u64,
+    ) -> u64 {
+        ecall(id, [a0, a1, a2, a3, a4, a5, a6, 0, 0])
+    }
+
+    pub unsafe fn ecall8(
+        id: u64,
+        a0: u64,
+        a1: u64,
+        a2: u64,
+        a3: u64,
+        a4: u64,
+        a5: u64,
+        a6: u64,
+        s0: u64,
+    ) -> u64 {
+        ecall(id, [a0, a1, a2, a3, a4, a5, a6, s0, 0])
+    }
+
+    pub unsafe fn ecall9(
+        id: u64,
+        a0: u64,
+        a1: u64,
+        a2: u64,
+        a3: u64,
+        a4: u64,
+        a5: u64,
+        a6: u64,
+        s0: u64,
+        s1: u64,
+    ) -> u64 {
+        ecall(id, [a0, a1, a2, a3, a4, a5, a6, s0, s1])
+    }
+
+    /// Records the exit like any other call, then panics with
+    /// [`crate::mock::GuestExit`] so tests can catch it.
+    pub unsafe fn exit(code: i64, syscall_id: u64) -> ! {
+        ecall(syscall_id, [code as u64, 0, 0, 0, 0, 0, 0, 0, 0]);
+        std::panic::panic_any(crate::mock::GuestExit(code))
+    }
+}
+
 // ============================================================================
 // Safe wrappers
 // ============================================================================
@@ -395,16 +1001,50 @@ fn check_equal(a: usize, b: usize) -> SdkResult<()> {
     }
 }
 
+/// Map the `a0` of a status-only syscall (documented as `a0=0`) to a result.
+#[inline(always)]
+fn check_status(ret: u64) -> SdkResult<u64> {
+    if ret == 0 {
+        Ok(ret)
+    } else {
+        Err(SdkError::Syscall(ret))
+    }
+}
+
+/// Keeps the pre-`_checked` behaviour of the deprecated shims: argument
+/// errors propagate, the syscall status is dropped.
+#[inline(always)]
+fn ignore_status(res: SdkResult<u64>) -> SdkResult<()> {
+    match res {
+        Ok(_) | Err(SdkError::Syscall(_)) => Ok(()),
+        Err(err) => Err(err),
+    }
+}
+
 /// Exit the VM with the given code.
 pub fn exit(code: i64) -> ! {
     unsafe { raw::exit(code, SYS_EXIT) }
 }
 
-/// Write bytes to the VM log.
+/// Write bytes to the VM log and return how many were written. This may be
+/// fewer than `buf.len()` when the log buffer is full; see [`write_all`].
 pub fn write(buf: &[u8]) -> usize {
     unsafe { raw::ecall3(SYS_WRITE, 1, buf.as_ptr() as u64, buf.len() as u64) as usize }
 }
 
+/// Write all of `buf` to the VM log, retrying after partial writes. Returns
+/// `WriteStalled` if a write makes no progress.
+pub fn write_all(mut buf: &[u8]) -> SdkResult<()> {
+    while !buf.is_empty() {
+        let n = write(buf);
+        if n == 0 || n > buf.len() {
+            return Err(SdkError::WriteStalled);
+        }
+        buf = &buf[n..];
+    }
+    Ok(())
+}
+
 /// Write a single byte to the VM log.
 pub fn putchar(c: u8) {
     unsafe {
@@ -419,9 +1059,39 @@ pub fn yield_now(state: &mut YieldState) {
     }
 }
 
-/// Print a UTF-8 string.
+/// Yield until `done` returns true, checking it before each yield.
+///
+/// Scheduling is cooperative: the VM only hands out a fresh instruction
+/// budget when the guest yields, so a loop that drives `*_partial` syscalls
+/// should advance them in `done` and yield between calls rather than spin.
+pub fn yield_until<F: FnMut() -> bool>(state: &mut YieldState, mut done: F) {
+    while !done() {
+        yield_now(state);
+    }
+}
+
+/// Drive a resumable kernel over `total_rows` rows: call `step` (one
+/// `*_partial` syscall on `state`), then yield and repeat until the cursor
+/// reaches `total_rows`. `state.max_rows` stays the per-call chunk (0 for all
+/// remaining rows), so `state.is_done()` is not consulted. `step` runs at
+/// least once; its first error is returned.
+pub fn run_partial<F>(state: &mut RowState, total_rows: u32, mut step: F) -> SdkResult<()>
+where
+    F: FnMut(&mut RowState) -> SdkResult<()>,
+{
+    let mut ys = YieldState { flag: 0 };
+    loop {
+        step(state)?;
+        if state.cursor >= total_rows {
+            return Ok(());
+        }
+        yield_now(&mut ys);
+    }
+}
+
+/// Print a UTF-8 string. Output the VM log cannot take is dropped.
 pub fn print(s: &str) {
-    write(s.as_bytes());
+    let _ = write_all(s.as_bytes());
 }
 
 /// MATMUL (deprecated): out = W @ x (f32).
@@ -442,48 +1112,74 @@ pub fn matmul(out: &mut [f32], x: &[f32], w: VmAddr) -> SdkResult<()> {
 }
 
 /// RMSNORM: out = (x / rms) * weight.
-pub fn rmsnorm(out: &mut [f32], x: &[f32], weight: &[f32]) -> SdkResult<()> {
+pub fn rmsnorm_checked(out: &mut [f32], x: &[f32], weight: &[f32]) -> SdkResult<u64> {
     check_equal(out.len(), x.len())?;
     check_equal(out.len(), weight.len())?;
-    unsafe {
+    let ret = unsafe {
         raw::ecall4(
             SYS_RMSNORM,
             VmAddr::from_mut_slice(out).raw(),
             VmAddr::from_slice(x).raw(),
             VmAddr::from_slice(weight).raw(),
             out.len() as u64,
-        );
-    }
-    Ok(())
+        )
+    };
+    check_status(ret)
+}
+
+/// RMSNORM: out = (x / rms) * weight.
+#[deprecated(note = "use `rmsnorm_checked` to observe the syscall status")]
+pub fn rmsnorm(out: &mut [f32], x: &[f32], weight: &[f32]) -> SdkResult<()> {
+    ignore_status(rmsnorm_checked(out, x, weight))
 }
 
 /// SOFTMAX: in-place softmax on f32.
-pub fn softmax(data: &mut [f32]) {
-    unsafe {
+pub fn softmax_checked(data: &mut [f32]) -> SdkResult<u64> {
+    let ret = unsafe {
         raw::ecall2(
             SYS_SOFTMAX,
             VmAddr::from_mut_slice(data).raw(),
             data.len() as u64,
-        );
-    }
+        )
+    };
+    check_status(ret)
+}
+
+/// SOFTMAX: in-place softmax on f32.
+#[deprecated(note = "use `softmax_checked` to observe the syscall status")]
+pub fn softmax(data: &mut [f32]) {
+    let _ = softmax_checked(data);
 }
 
 /// SILU: in-place SiLU on f32.
-pub fn silu(data: &mut [f32]) {
-    unsafe {
+pub fn silu_checked(data: &mut [f32]) -> SdkResult<u64> {
+    let ret = unsafe {
         raw::ecall2(
             SYS_SILU,
             VmAddr::from_mut_slice(data).raw(),
             data.len() as u64,
-        );
-    }
+        )
+    };
+    check_status(ret)
+}
+
+/// SILU: in-place SiLU on f32.
+#[deprecated(note = "use `silu_checked` to observe the syscall status")]
+pub fn silu(data: &mut [f32]) {
+    let _ = silu_checked(data);
 }
 
 /// ROPE: rotary embeddings on q/k vectors.
-pub fn rope(q: &mut [f32], k: &mut [f32], pos: u64, dim: usize, head_size: usize) -> SdkResult<()> {
+pub fn rope_checked(
+    q: &mut [f32],
+    k: &mut [f32],
+    pos: u64,
+    dim: usize,
+    head_size: usize,
+) -> SdkResult<u64> {
     check_len(q.len(), dim)?;
     check_len(k.len(), dim)?;
-    unsafe {
+    let ret = unsafe {
         raw::ecall5(
             SYS_ROPE,
             VmAddr::from_mut_slice(q).raw(),
@@ -491,13 +1187,69 @@ pub fn rope(q: &mut [f32], k: &mut [f32], pos: u64, dim: usize, head_size: usize
             pos,
             dim as u64,
             head_size as u64,
-        );
+        )
+    };
+    check_status(ret)
+}
+
+/// ROPE: rotary embeddings on q/k vectors.
+#[deprecated(note = "use `rope_checked` to observe the syscall status")]
+pub fn rope(q: &mut [f32], k: &mut [f32], pos: u64, dim: usize, head_size: usize) -> SdkResult<()> {
+    ignore_status(rope_checked(q, k, pos, dim, head_size))
+}
+
+/// Flag bits for MATMUL_Q8 / MATMUL_Q8_PARTIAL, always within
+/// `Q8_FLAG_MASK`: `Q8Flags::NONE.prequant().tensor_scale()`.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct Q8Flags(u64);
+
+impl Q8Flags {
+    /// f32 input, per-row scales.
+    pub const NONE: Q8Flags = Q8Flags(0);
+
+    /// `x_ptr` is a prequant buffer instead of f32.
+    pub const fn prequant(self) -> Self {
+        Q8Flags(self.0 | Q8_FLAG_PREQUANT)
     }
-    Ok(())
+
+    /// `scale_ptr` is a single f32 for the whole tensor.
+    pub const fn tensor_scale(self) -> Self {
+        Q8Flags(self.0 | Q8_FLAG_TENSOR_SCALE)
+    }
+
+    /// Keeps only the bits in `Q8_FLAG_MASK`.
+    pub const fn from_bits_truncate(bits: u64) -> Self {
+        Q8Flags(bits & Q8_FLAG_MASK)
+    }
+
+    pub const fn bits(self) -> u64 {
+        self.0
+    }
+}
+
+/// Packs `n` and the flag bits into MATMUL_Q8's `a4`; `n` must stay below
+/// the flag bits.
+fn q8_n_flags(n: usize, flags: u64) -> SdkResult<u64> {
+    if n as u64 & Q8_FLAG_MASK != 0 {
+        return Err(SdkError::LengthTooLarge);
+    }
+    Ok(n as u64 | (flags & Q8_FLAG_MASK))
 }
 
 /// MATMUL_Q8: quantized int8 matmul.
 pub fn matmul_q8(
+    out: &mut [f32],
+    x_ptr: VmAddr,
+    w_ptr: VmAddr,
+    scale_ptr: VmAddr,
+    n: usize,
+    flags: Q8Flags,
+) -> SdkResult<()> {
+    matmul_q8_raw(out, x_ptr, w_ptr, scale_ptr, n, flags.bits())
+}
+
+/// MATMUL_Q8 with raw flag bits; bits outside `Q8_FLAG_MASK` are dropped.
+pub fn matmul_q8_raw(
     out: &mut [f32],
     x_ptr: VmAddr,
     w_ptr: VmAddr,
@@ -505,7 +1257,7 @@ pub fn matmul_q8(
     n: usize,
     flags: u64,
 ) -> SdkResult<()> {
-    let n_flags = (n as u64) | (flags & Q8_FLAG_MASK);
+    let n_flags = q8_n_flags(n, flags)?;
     unsafe {
         raw::ecall6(
             SYS_MATMUL_Q8,
@@ -522,6 +1274,20 @@ pub fn matmul_q8(
 
 /// MATMUL_Q8_PARTIAL: resumable rows.
 pub fn matmul_q8_partial(
+    out: &mut [f32],
+    x_ptr: VmAddr,
+    w_ptr: VmAddr,
+    scale_ptr: VmAddr,
+    n: usize,
+    flags: Q8Flags,
+    state: &mut RowState,
+) -> SdkResult<()> {
+    matmul_q8_partial_raw(out, x_ptr, w_ptr, scale_ptr, n, flags.bits(), state)
+}
+
+/// MATMUL_Q8_PARTIAL with raw flag bits; bits outside `Q8_FLAG_MASK` are
+/// dropped.
+pub fn matmul_q8_partial_raw(
     out: &mut [f32],
     x_ptr: VmAddr,
     w_ptr: VmAddr,
@@ -530,7 +1296,7 @@ pub fn matmul_q8_partial(
     flags: u64,
     state: &mut RowState,
 ) -> SdkResult<()> {
-    let n_flags = (n as u64) | (flags & Q8_FLAG_MASK);
+    let n_flags = q8_n_flags(n, flags)?;
     unsafe {
         raw::ecall7(
             SYS_MATMUL_Q8_PARTIAL,
@@ -546,18 +1312,27 @@ pub fn matmul_q8_partial(
     Ok(())
 }
 
-/// ACCUM: out += x (f32).
-pub fn accum(out: &mut [f32], x: &[f32]) -> SdkResult<()> {
+/// ACCUM: out += x (f32). Empty slices return `Ok(0)` without a syscall.
+pub fn accum_checked(out: &mut [f32], x: &[f32]) -> SdkResult<u64> {
     check_equal(out.len(), x.len())?;
-    unsafe {
+    if out.is_empty() {
+        return Ok(0);
+    }
+    let ret = unsafe {
         raw::ecall3(
             SYS_ACCUM,
             VmAddr::from_mut_slice(out).raw(),
             VmAddr::from_slice(x).raw(),
             out.len() as u64,
-        );
-    }
-    Ok(())
+        )
+    };
+    check_status(ret)
+}
+
+/// ACCUM: out += x (f32).
+#[deprecated(note = "use `accum_checked` to observe the syscall status")]
+pub fn accum(out: &mut [f32], x: &[f32]) -> SdkResult<()> {
+    ignore_status(accum_checked(out, x))
 }
 
 /// READ_F32: read a float from any VM address.
@@ -567,17 +1342,67 @@ pub fn read_f32(addr: VmAddr) -> f32 {
 }
 
 /// WRITE_F32: write a float to any VM address.
+pub fn write_f32_checked(addr: VmAddr, value: f32) -> SdkResult<u64> {
+    let ret = unsafe { raw::ecall2(SYS_WRITE_F32, addr.raw(), value.to_bits() as u64) };
+    check_status(ret)
+}
+
+/// WRITE_F32: write a float to any VM address.
+#[deprecated(note = "use `write_f32_checked` to observe the syscall status")]
 pub fn write_f32(addr: VmAddr, value: f32) {
-    unsafe {
-        raw::ecall2(SYS_WRITE_F32, addr.raw(), value.to_bits() as u64);
-    }
+    let _ = write_f32_checked(addr, value);
+}
+
+/// MEMCPY_F32: copy f32 array between VM addresses.
+pub fn memcpy_f32_checked(dst: VmAddr, src: VmAddr, count: usize) -> SdkResult<u64> {
+    let ret = unsafe { raw::ecall3(SYS_MEMCPY_F32, dst.raw(), src.raw(), count as u64) };
+    check_status(ret)
 }
 
 /// MEMCPY_F32: copy f32 array between VM addresses.
+#[deprecated(note = "use `memcpy_f32_checked` to observe the syscall status")]
 pub fn memcpy_f32(dst: VmAddr, src: VmAddr, count: usize) {
-    unsafe {
-        raw::ecall3(SYS_MEMCPY_F32, dst.raw(), src.raw(), count as u64);
+    let _ = memcpy_f32_checked(dst, src, count);
+}
+
+/// MEMSET: fill `dst` with `value`. Empty slices return `Ok(())` without a
+/// syscall.
+///
+/// With the `memset-fallback` feature, for VMs without MEMSET, this is a
+/// guest-side loop instead and always succeeds.
+pub fn memset_bytes(dst: &mut [u8], value: u8) -> SdkResult<()> {
+    #[cfg(not(feature = "memset-fallback"))]
+    {
+        memset_raw(VmAddr::from_mut_slice(dst), value, dst.len())
+    }
+    #[cfg(feature = "memset-fallback")]
+    {
+        dst.fill(value);
+        Ok(())
+    }
+}
+
+/// Zero `dst` (all-zero bits are `0.0`), through MEMSET like
+/// [`memset_bytes`].
+pub fn clear_f32(dst: &mut [f32]) -> SdkResult<()> {
+    #[cfg(not(feature = "memset-fallback"))]
+    {
+        memset_raw(VmAddr::from_mut_slice(dst), 0, core::mem::size_of_val(dst))
     }
+    #[cfg(feature = "memset-fallback")]
+    {
+        dst.fill(0.0);
+        Ok(())
+    }
+}
+
+#[cfg(not(feature = "memset-fallback"))]
+fn memset_raw(dst: VmAddr, value: u8, count: usize) -> SdkResult<()> {
+    if count == 0 {
+        return Ok(());
+    }
+    let ret = unsafe { raw::ecall3(SYS_MEMSET, dst.raw(), value as u64, count as u64) };
+    check_status(ret).map(|_| ())
 }
 
 /// ARGMAX_PARTIAL: resumable argmax over f32.
@@ -599,6 +1424,48 @@ pub fn debug_log(tag: u64, a: u64, b: u64, c: u64, d: u64) {
     }
 }
 
+/// Pack up to 8 ASCII bytes little-endian into a DEBUG_LOG tag, so the logged
+/// tag reads back as the name. Longer names are truncated.
+pub const fn log_tag(name: &[u8]) -> u64 {
+    let mut tag = 0u64;
+    let mut i = 0;
+    while i < name.len() && i < 8 {
+        tag |= (name[i] as u64) << (8 * i);
+        i += 1;
+    }
+    tag
+}
+
+/// `fb_log!(tag, a, b, c, d)`: DEBUG_LOG with up to four values, each cast with
+/// `as u64`; missing slots are zero. No allocation or formatting.
+///
+/// Tags are `log_tag(b"name")` by convention; short numeric tags (e.g. the
+/// benchmarks' `0xB0xx`) are fine for throwaway callsites.
+#[macro_export]
+macro_rules! fb_log {
+    ($tag:expr) => {
+        $crate::fb_log!($tag, 0, 0, 0, 0)
+    };
+    ($tag:expr, $a:expr) => {
+        $crate::fb_log!($tag, $a, 0, 0, 0)
+    };
+    ($tag:expr, $a:expr, $b:expr) => {
+        $crate::fb_log!($tag, $a, $b, 0, 0)
+    };
+    ($tag:expr, $a:expr, $b:expr, $c:expr) => {
+        $crate::fb_log!($tag, $a, $b, $c, 0)
+    };
+    ($tag:expr, $a:expr, $b:expr, $c:expr, $d:expr) => {
+        $crate::debug_log(
+            ($tag) as u64,
+            ($a) as u64,
+            ($b) as u64,
+            ($c) as u64,
+            ($d) as u64,
+        )
+    };
+}
+
 /// MATMUL_I8_I32: int8 weights, i32 activations.
 pub fn matmul_i8_i32(out: &mut [i32], x: &[i32], w: VmAddr, scale_q16: i32) -> SdkResult<()> {
     let n = x.len();
@@ -653,9 +1520,35 @@ pub fn softmax_i32(data: &mut [i32]) {
     }
 }
 
-/// DOT_I32: dot(a, b) >> shift.
+/// SOFTMAX_I32 that reports saturation: a nonzero `a0` becomes
+/// `SdkError::Saturated`. The data is still written in place.
+pub fn softmax_i32_checked(data: &mut [i32]) -> SdkResult<()> {
+    let ret = unsafe {
+        raw::ecall2(
+            SYS_SOFTMAX_I32,
+            VmAddr::from_mut_slice(data).raw(),
+            data.len() as u64,
+        )
+    };
+    check_saturation(ret)
+}
+
+#[inline(always)]
+fn check_saturation(ret: u64) -> SdkResult<()> {
+    if ret == 0 {
+        Ok(())
+    } else {
+        Err(SdkError::Saturated)
+    }
+}
+
+/// DOT_I32: dot(a, b) >> shift. Empty slices return `Ok(0)` without a
+/// syscall.
 pub fn dot_i32(a: &[i32], b: &[i32], shift: u32) -> SdkResult<i64> {
     check_equal(a.len(), b.len())?;
+    if a.is_empty() {
+        return Ok(0);
+    }
     let res = unsafe {
         raw::ecall4(
             SYS_DOT_I32,
@@ -668,9 +1561,13 @@ pub fn dot_i32(a: &[i32], b: &[i32], shift: u32) -> SdkResult<i64> {
     Ok(res as i64)
 }
 
-/// WEIGHTED_SUM_I32: out[i] += (weight * src[i]) >> shift.
+/// WEIGHTED_SUM_I32: out[i] += (weight * src[i]) >> shift. Empty slices are
+/// a no-op without a syscall.
 pub fn weighted_sum_i32(out: &mut [i32], src: &[i32], weight: i32, shift: u32) -> SdkResult<()> {
     check_equal(out.len(), src.len())?;
+    if out.is_empty() {
+        return Ok(());
+    }
     unsafe {
         raw::ecall5(
             SYS_WEIGHTED_SUM_I32,
@@ -684,6 +1581,16 @@ pub fn weighted_sum_i32(out: &mut [i32], src: &[i32], weight: i32, shift: u32) -
     Ok(())
 }
 
+/// dst[i] += src[i], as WEIGHTED_SUM_I32 with weight 1 and shift 0.
+pub fn vec_add_i32(dst: &mut [i32], src: &[i32]) -> SdkResult<()> {
+    weighted_sum_i32(dst, src, 1, 0)
+}
+
+/// dst[i] -= src[i], as WEIGHTED_SUM_I32 with weight -1 and shift 0.
+pub fn vec_sub_i32(dst: &mut [i32], src: &[i32]) -> SdkResult<()> {
+    weighted_sum_i32(dst, src, -1, 0)
+}
+
 /// ARGMAX_I32_PARTIAL: resumable argmax over i32.
 pub fn argmax_i32_partial(data: &[i32], state: &mut ArgmaxI32State) -> u32 {
     unsafe {
@@ -696,46 +1603,262 @@ pub fn argmax_i32_partial(data: &[i32], state: &mut ArgmaxI32State) -> u32 {
     }
 }
 
+/// Index and value of the largest element, in one ARGMAX_I32_PARTIAL call
+/// (`max_per_call = 0`, so the whole slice is scanned at once). Ties go to
+/// the lowest index. Empty input is `BufferTooSmall`.
+pub fn argmax_i32_full(data: &[i32]) -> SdkResult<(u32, i32)> {
+    check_len(data.len(), 1)?;
+    let mut state = ArgmaxI32State::new(0);
+    argmax_i32_partial(data, &mut state);
+    Ok((state.max_idx, state.max_val))
+}
+
+/// The `out.len()` largest elements of `data` as `(index, value)`, largest
+/// first; equal values keep ascending index order. Returns how many entries
+/// were written (`min(out.len(), data.len())`); the rest of `out` is left
+/// untouched.
+///
+/// This is a guest-side selection with no syscall: the `I8_I8_ARGMAX_TOPK*`
+/// shortlists are internal to MATMUL_I8_I8_ARGMAX_PARTIAL and only rank the
+/// rows of its own matmul, so they cannot be pointed at an existing i32 array.
+pub fn argmax_i32_topk(data: &[i32], out: &mut [(u32, i32)]) -> usize {
+    let k = out.len();
+    let mut filled = 0usize;
+    for (i, &value) in data.iter().enumerate() {
+        if filled == k && (k == 0 || value <= out[k - 1].1) {
+            continue;
+        }
+        let mut pos = filled.min(k - 1);
+        while pos > 0 && out[pos - 1].1 < value {
+            out[pos] = out[pos - 1];
+            pos -= 1;
+        }
+        out[pos] = (i as u32, value);
+        if filled < k {
+            filled += 1;
+        }
+    }
+    filled
+}
+
+/// Pick a token from Q16 `logits`. A `temperature_q16` of zero or less is
+/// plain argmax (lowest index on ties, no syscall). Otherwise the logits are
+/// divided by the temperature, turned into Q16 probabilities with
+/// SOFTMAX_I32 (in place), and one index is drawn from their cumulative sum
+/// with a xorshift64 generator advanced from `*rng_state` (a zero state is
+/// replaced by a fixed nonzero seed).
+///
+/// Deterministic: the same logits, temperature and `*rng_state` always give
+/// the same token and leave the same `*rng_state`. Returns 0 for empty
+/// `logits`.
+pub fn sample_i32(logits: &mut [i32], temperature_q16: i32, rng_state: &mut u64) -> u32 {
+    let mut best = [(0u32, 0i32)];
+    if temperature_q16 <= 0 || logits.is_empty() {
+        argmax_i32_topk(logits, &mut best);
+        return best[0].0;
+    }
+    for v in logits.iter_mut() {
+        let scaled = ((*v as i64) << 16) / temperature_q16 as i64;
+        *v = scaled.clamp(i32::MIN as i64, i32::MAX as i64) as i32;
+    }
+    argmax_i32_topk(logits, &mut best);
+    softmax_i32(logits);
+
+    let total: u64 = logits.iter().map(|&p| p.max(0) as u64).sum();
+    if total == 0 {
+        return best[0].0;
+    }
+    let mut target = xorshift64(rng_state) % total;
+    for (i, &p) in logits.iter().enumerate() {
+        let p = p.max(0) as u64;
+        if target < p {
+            return i as u32;
+        }
+        target -= p;
+    }
+    best[0].0
+}
+
+fn xorshift64(state: &mut u64) -> u64 {
+    let mut x = if *state == 0 {
+        0x9E37_79B9_7F4A_7C15
+    } else {
+        *state
+    };
+    x ^= x << 13;
+    x ^= x >> 7;
+    x ^= x << 17;
+    *state = x;
+    x
+}
+
 /// SOFTMAX_I32_F32: i32 softmax using f32 math.
-pub fn softmax_i32_f32(data: &mut [i32]) {
-    unsafe {
+pub fn softmax_i32_f32_checked(data: &mut [i32]) -> SdkResult<u64> {
+    let ret = unsafe {
         raw::ecall2(
             SYS_SOFTMAX_I32_F32,
             VmAddr::from_mut_slice(data).raw(),
             data.len() as u64,
-        );
-    }
+        )
+    };
+    check_status(ret)
 }
 
-/// SILU_MUL_I32: gate SiLU multiply (Q16).
-pub fn silu_mul_i32(hb: &mut [i32], hb2: &[i32]) -> SdkResult<()> {
+/// SOFTMAX_I32_F32: i32 softmax using f32 math.
+#[deprecated(note = "use `softmax_i32_f32_checked` to observe the syscall status")]
+pub fn softmax_i32_f32(data: &mut [i32]) {
+    let _ = softmax_i32_f32_checked(data);
+}
+
+/// SILU_MUL_I32: gate SiLU multiply (Q16). Empty slices return `Ok(0)`
+/// without a syscall.
+pub fn silu_mul_i32_checked(hb: &mut [i32], hb2: &[i32]) -> SdkResult<u64> {
     check_equal(hb.len(), hb2.len())?;
-    unsafe {
+    if hb.is_empty() {
+        return Ok(0);
+    }
+    let ret = unsafe {
         raw::ecall3(
             SYS_SILU_MUL_I32,
             VmAddr::from_mut_slice(hb).raw(),
             VmAddr::from_slice(hb2).raw(),
             hb.len() as u64,
-        );
-    }
-    Ok(())
+        )
+    };
+    check_status(ret)
+}
+
+/// SILU_MUL_I32: gate SiLU multiply (Q16).
+#[deprecated(note = "use `silu_mul_i32_checked` to observe the syscall status")]
+pub fn silu_mul_i32(hb: &mut [i32], hb2: &[i32]) -> SdkResult<()> {
+    ignore_status(silu_mul_i32_checked(hb, hb2))
 }
 
 /// RMSNORM_I32: RMSNorm for Q16 i32.
-pub fn rmsnorm_i32(out: &mut [i32], x: &[i32], weight_addr: VmAddr) -> SdkResult<()> {
+pub fn rmsnorm_i32_checked(out: &mut [i32], x: &[i32], weight_addr: VmAddr) -> SdkResult<u64> {
     check_equal(out.len(), x.len())?;
-    unsafe {
+    let ret = unsafe {
         raw::ecall4(
             SYS_RMSNORM_I32,
             VmAddr::from_mut_slice(out).raw(),
             VmAddr::from_slice(x).raw(),
             weight_addr.raw(),
             out.len() as u64,
-        );
+        )
+    };
+    check_status(ret)
+}
+
+/// Transformer residual step in the canonical order: `x += residual`, then
+/// RMSNORM_I32 on `x` in place (out and x both point at `x`, so no
+/// temporary is needed).
+pub fn residual_rmsnorm_i32(x: &mut [i32], residual: &[i32], weight_addr: VmAddr) -> SdkResult<()> {
+    vec_add_i32(x, residual)?;
+    let x = VmAddr::from_mut_slice(x);
+    let ret = unsafe {
+        raw::ecall4(
+            SYS_RMSNORM_I32,
+            x.raw(),
+            x.raw(),
+            weight_addr.raw(),
+            residual.len() as u64,
+        )
+    };
+    check_status(ret).map(|_| ())
+}
+
+/// RMSNORM_I32: RMSNorm for Q16 i32.
+#[deprecated(note = "use `rmsnorm_i32_checked` to observe the syscall status")]
+pub fn rmsnorm_i32(out: &mut [i32], x: &[i32], weight_addr: VmAddr) -> SdkResult<()> {
+    ignore_status(rmsnorm_i32_checked(out, x, weight_addr))
+}
+
+/// LAYERNORM_I32: LayerNorm for Q16 i32 with Q16 i32 `weight` and `bias`
+/// tables of `x.len()` entries each. Semantics are those of
+/// [`layernorm_i32_ref`].
+///
+/// Behind the `layernorm` feature until the VM ships the syscall; older VMs
+/// reject the id.
+#[cfg(feature = "layernorm")]
+pub fn layernorm_i32(
+    out: &mut [i32],
+    x: &[i32],
+    weight_addr: VmAddr,
+    bias_addr: VmAddr,
+) -> SdkResult<()> {
+    check_equal(out.len(), x.len())?;
+    let ret = unsafe {
+        raw::ecall5(
+            SYS_LAYERNORM_I32,
+            VmAddr::from_mut_slice(out).raw(),
+            VmAddr::from_slice(x).raw(),
+            weight_addr.raw(),
+            bias_addr.raw(),
+            out.len() as u64,
+        )
+    };
+    check_status(ret).map(|_| ())
+}
+
+/// Variance epsilon for LAYERNORM_I32, in Q32 (i.e. `1 / 65536`).
+#[cfg(feature = "layernorm")]
+pub const LAYERNORM_EPS_Q32: u64 = 1 << 16;
+
+/// Guest-side LAYERNORM_I32. Integer-only: the mean truncates toward zero,
+/// the variance is kept in Q32 and its square root taken by integer sqrt,
+/// and outputs saturate to `i32`.
+#[cfg(feature = "layernorm")]
+pub fn layernorm_i32_ref(
+    out: &mut [i32],
+    x: &[i32],
+    weight: &[i32],
+    bias: &[i32],
+) -> SdkResult<()> {
+    check_equal(out.len(), x.len())?;
+    check_equal(weight.len(), x.len())?;
+    check_equal(bias.len(), x.len())?;
+    if x.is_empty() {
+        return Ok(());
+    }
+    let n = x.len() as i64;
+    let mean = x.iter().map(|&v| v as i64).sum::<i64>() / n;
+    let var_q32 = x
+        .iter()
+        .map(|&v| {
+            let d = (v as i64 - mean) as i128;
+            (d * d) as u128
+        })
+        .sum::<u128>()
+        / n as u128;
+    let std_q16 = isqrt_u128(var_q32 + LAYERNORM_EPS_Q32 as u128) as i128;
+    for i in 0..x.len() {
+        let norm_q16 = (((x[i] as i64 - mean) as i128) << 16) / std_q16;
+        let v = ((norm_q16 * weight[i] as i128) >> 16) + bias[i] as i128;
+        out[i] = v.clamp(i32::MIN as i128, i32::MAX as i128) as i32;
     }
     Ok(())
 }
 
+#[cfg(feature = "layernorm")]
+fn isqrt_u128(v: u128) -> u128 {
+    let mut rem = v;
+    let mut root = 0u128;
+    let mut bit = 1u128 << 126;
+    while bit > rem {
+        bit >>= 2;
+    }
+    while bit != 0 {
+        if rem >= root + bit {
+            rem -= root + bit;
+            root = (root >> 1) + bit;
+        } else {
+            root >>= 1;
+        }
+        bit >>= 2;
+    }
+    root
+}
+
 /// MATMUL_I8_I8: int8 weights and prequant buffer.
 pub fn matmul_i8_i8(
     out: &mut [i32],
@@ -759,6 +1882,31 @@ pub fn matmul_i8_i8(
     Ok(())
 }
 
+/// MATMUL_I8_I8 over a batch of prequant activation rows sharing one weight
+/// matrix. Row `b` writes `out[b * d..(b + 1) * d]`, so `out.len()` must equal
+/// `prequants.len() * d`. Every buffer is validated before the first syscall;
+/// rows are still issued one syscall each.
+pub fn matmul_i8_i8_batch(
+    out: &mut [i32],
+    prequants: &[&[u8]],
+    n: usize,
+    w: VmAddr,
+    w_scale_q16: i32,
+    d: usize,
+) -> SdkResult<()> {
+    check_equal(out.len(), prequants.len() * d)?;
+    for prequant in prequants {
+        check_len(prequant.len(), align4(n) + 4)?;
+    }
+    if d == 0 {
+        return Ok(());
+    }
+    for (row, prequant) in out.chunks_exact_mut(d).zip(prequants) {
+        matmul_i8_i8(row, prequant, n, w, w_scale_q16)?;
+    }
+    Ok(())
+}
+
 /// MATMUL_I8_I8_PARTIAL: resumable rows.
 pub fn matmul_i8_i8_partial(
     out: &mut [i32],
@@ -809,6 +1957,21 @@ pub fn matmul_i8_i8_argmax_partial(
     Ok(res as u32)
 }
 
+/// [`matmul_i8_i8_argmax_partial`], then [`i8_i8_argmax_top2`] on the
+/// updated state. `state_words` must hold the shortlist arrays when
+/// `topk1 >= 2` (see [`i8_i8_argmax_state_words`]).
+pub fn matmul_i8_i8_argmax_top2_partial(
+    prequant: &[u8],
+    n: usize,
+    w: VmAddr,
+    w_scale_q16: i32,
+    d: usize,
+    state_words: &mut [u32],
+) -> SdkResult<I8I8Top2> {
+    matmul_i8_i8_argmax_partial(prequant, n, w, w_scale_q16, d, state_words)?;
+    i8_i8_argmax_top2(state_words)
+}
+
 /// MATMUL_I8_I8_QKV: fused Q/K/V matmul.
 pub fn matmul_i8_i8_qkv(cfg: &MatmulQkvConfig) {
     unsafe {
@@ -816,6 +1979,26 @@ pub fn matmul_i8_i8_qkv(cfg: &MatmulQkvConfig) {
     }
 }
 
+/// RMSNORM_MATMUL_I8_I8_QKV: RMSNorm `x` with `norm_weight`, then fused Q/K/V matmul.
+///
+/// The normalized vector is quantized inside the VM, so `cfg.x_ptr` is ignored.
+pub fn rmsnorm_matmul_qkv_i8(
+    norm_weight: VmAddr,
+    cfg: &MatmulQkvConfig,
+    x: &[i32],
+) -> SdkResult<()> {
+    check_equal(cfg.n as usize, x.len())?;
+    unsafe {
+        raw::ecall3(
+            SYS_RMSNORM_MATMUL_I8_I8_QKV,
+            VmAddr::from_ref(cfg).raw(),
+            VmAddr::from_slice(x).raw(),
+            norm_weight.raw(),
+        );
+    }
+    Ok(())
+}
+
 /// MATMUL_I8_I8_W1W3: fused W1/W3 matmul.
 pub fn matmul_i8_i8_w1w3(cfg: &MatmulW1W3Config) {
     unsafe {
@@ -844,9 +2027,13 @@ pub fn dot_i8(a: &[i8], b: &[i8]) -> SdkResult<i32> {
     Ok(res as i32)
 }
 
-/// VEC_ADD_I8: dst[i] += src[i].
+/// VEC_ADD_I8: dst[i] += src[i]. Empty slices are a no-op without a
+/// syscall.
 pub fn vec_add_i8(dst: &mut [i8], src: &[i8]) -> SdkResult<()> {
     check_equal(dst.len(), src.len())?;
+    if dst.is_empty() {
+        return Ok(());
+    }
     unsafe {
         raw::ecall3(
             SYS_VEC_ADD_I8,
@@ -859,24 +2046,226 @@ pub fn vec_add_i8(dst: &mut [i8], src: &[i8]) -> SdkResult<()> {
 }
 
 /// ACTIVATION: apply activation in-place.
-pub fn activation(data: &mut [i8], act_type: i32) {
-    unsafe {
+pub fn activation_checked(data: &mut [i8], act_type: i32) -> SdkResult<u64> {
+    let ret = unsafe {
         raw::ecall3(
             SYS_ACTIVATION,
             VmAddr::from_mut_slice(data).raw(),
             data.len() as u64,
             act_type as u64,
-        );
-    }
+        )
+    };
+    check_status(ret)
 }
 
-/// GRAPH_SEARCH (8001/8002): graph edge search.
+/// ACTIVATION: apply activation in-place.
+#[deprecated(note = "use `activation_checked` to observe the syscall status")]
+pub fn activation(data: &mut [i8], act_type: i32) {
+    let _ = activation_checked(data, act_type);
+}
+
+/// ACTIVATION with [`ACT_GELU`] (tanh approximation).
+pub fn gelu_i8(data: &mut [i8]) -> SdkResult<u64> {
+    activation_checked(data, ACT_GELU)
+}
+
+/// ACTIVATION with [`ACT_TANH`].
+pub fn tanh_i8(data: &mut [i8]) -> SdkResult<u64> {
+    activation_checked(data, ACT_TANH)
+}
+
+/// GRAPH_SEARCH (8001/8002): graph edge search.
 pub fn graph_search(input: VmAddr, graph_idx: u64, output: VmAddr, min_score: i32, alt: bool) -> u32 {
     let id = if alt { SYS_GRAPH_SEARCH_ALT } else { SYS_GRAPH_SEARCH };
     unsafe { raw::ecall4(id, input.raw(), graph_idx, output.raw(), min_score as u64) as u32 }
 }
 
-/// ARB_SEARCH: arbitrage search in graph.
+/// GRAPH_SEARCH into a typed slice. The syscall writes one 8-byte `GraphHit`
+/// row per matching edge into a stack buffer of `GRAPH_SEARCH_MAX_HITS` rows
+/// and is not told its size, so the graph header's `num_edges` is read first.
+/// A graph with more edges returns `BufferTooSmall` and a segment without a
+/// graph header returns `InvalidGraph`, both without a syscall. At most
+/// `out.len()` rows are copied out. Returns the number of rows written to
+/// `out`.
+pub fn graph_search_into(
+    input: &[i8],
+    graph_idx: u64,
+    out: &mut [GraphHit],
+    min_score: i32,
+    alt: bool,
+) -> SdkResult<usize> {
+    let base = graph_segment_base(graph_idx)?;
+    // SAFETY: segment `graph_idx + 1` is mapped for the whole run; the VM
+    // faults the read if it is shorter than the header.
+    let header = unsafe { read_graph_header(base) }.ok_or(SdkError::InvalidGraph)?;
+    graph_search_staged(
+        input,
+        graph_idx,
+        header.num_edges as usize,
+        out,
+        min_score,
+        alt,
+    )
+}
+
+fn graph_search_staged(
+    input: &[i8],
+    graph_idx: u64,
+    num_edges: usize,
+    out: &mut [GraphHit],
+    min_score: i32,
+    alt: bool,
+) -> SdkResult<usize> {
+    check_len(GRAPH_SEARCH_MAX_HITS, num_edges)?;
+    let mut staged = [GraphHit::default(); GRAPH_SEARCH_MAX_HITS];
+    let hits = graph_search(
+        VmAddr::from_slice(input),
+        graph_idx,
+        VmAddr::from_mut_slice(&mut staged),
+        min_score,
+        alt,
+    );
+    Ok(copy_hits(&staged, hits, out))
+}
+
+fn copy_hits(staged: &[GraphHit], hits: u32, out: &mut [GraphHit]) -> usize {
+    let count = (hits as usize).min(staged.len()).min(out.len());
+    out[..count].copy_from_slice(&staged[..count]);
+    count
+}
+
+/// Reads the graph header at `base`; `None` if the magic is not `GRAPH_MAGIC`.
+///
+/// # Safety
+///
+/// `base` must point to 16 readable bytes (no alignment needed), e.g. the
+/// start of a mapped graph segment.
+pub unsafe fn read_graph_header(base: VmAddr) -> Option<GraphHeader> {
+    let header = (base.raw() as *const GraphHeader).read_unaligned();
+    (header.magic == GRAPH_MAGIC).then_some(header)
+}
+
+/// Writes `header` at `base`.
+///
+/// # Safety
+///
+/// `base` must point to 16 writable bytes (no alignment needed) that nothing
+/// else is borrowing.
+pub unsafe fn write_graph_header(base: VmAddr, header: &GraphHeader) {
+    (base.raw() as *mut GraphHeader).write_unaligned(*header);
+}
+
+/// Bytes per packed edge: a `u32` target followed by `dim` `i8` weights.
+pub const fn graph_edge_stride(dim: u32) -> usize {
+    4 + dim as usize
+}
+
+/// Iterates the `header.num_edges` edges after the header at `base` as
+/// `(target, weights)`.
+pub struct GraphEdges<'a> {
+    next: u64,
+    remaining: u32,
+    dim: usize,
+    _weights: core::marker::PhantomData<&'a [i8]>,
+}
+
+impl<'a> GraphEdges<'a> {
+    /// # Safety
+    ///
+    /// The `header.num_edges * graph_edge_stride(header.dim)` bytes after the
+    /// 16-byte header at `base` must be readable and must not be written for
+    /// as long as `'a` (the lifetime of the yielded weight slices) lasts.
+    pub unsafe fn new(base: VmAddr, header: &GraphHeader) -> Self {
+        GraphEdges {
+            next: base.raw() + core::mem::size_of::<GraphHeader>() as u64,
+            remaining: header.num_edges,
+            dim: header.dim as usize,
+            _weights: core::marker::PhantomData,
+        }
+    }
+}
+
+impl<'a> Iterator for GraphEdges<'a> {
+    type Item = (u32, &'a [i8]);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+        // SAFETY: `remaining > 0`, so this edge is within the `num_edges`
+        // edges `new`'s contract covers, readable for `'a`.
+        let target = unsafe { (self.next as *const u32).read_unaligned() };
+        let weights =
+            unsafe { core::slice::from_raw_parts((self.next + 4) as *const i8, self.dim) };
+        self.next += graph_edge_stride(self.dim as u32) as u64;
+        self.remaining -= 1;
+        Some((target, weights))
+    }
+}
+
+/// Writes packed edges after the header at `base`, up to `capacity` edges;
+/// `finish` writes the header.
+pub struct GraphBuilder {
+    base: VmAddr,
+    cursor: u64,
+    dim: u32,
+    capacity: u32,
+    num_edges: u32,
+}
+
+impl GraphBuilder {
+    /// # Safety
+    ///
+    /// `16 + capacity * graph_edge_stride(dim)` bytes from `base` must be
+    /// writable, and nothing else may access them until `finish` returns.
+    pub unsafe fn new(base: VmAddr, dim: u32, capacity: u32) -> Self {
+        GraphBuilder {
+            base,
+            cursor: base.raw() + core::mem::size_of::<GraphHeader>() as u64,
+            dim,
+            capacity,
+            num_edges: 0,
+        }
+    }
+
+    /// `weights` must hold exactly `dim` values.
+    pub fn push_edge(&mut self, target: u32, weights: &[i8]) -> SdkResult<()> {
+        check_equal(weights.len(), self.dim as usize)?;
+        if self.num_edges >= self.capacity {
+            return Err(SdkError::BufferTooSmall);
+        }
+        // SAFETY: `num_edges < capacity`, so the edge lies inside the buffer
+        // `new`'s contract covers.
+        unsafe {
+            (self.cursor as *mut u32).write_unaligned(target);
+            let dst = (self.cursor + 4) as *mut i8;
+            core::ptr::copy_nonoverlapping(weights.as_ptr(), dst, weights.len());
+        }
+        self.cursor += graph_edge_stride(self.dim) as u64;
+        self.num_edges += 1;
+        Ok(())
+    }
+
+    pub fn len(&self) -> u32 {
+        self.num_edges
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.num_edges == 0
+    }
+
+    /// Writes and returns the header for the edges pushed so far.
+    pub fn finish(self) -> GraphHeader {
+        let header = GraphHeader::new(self.num_edges, self.dim);
+        // SAFETY: `new`'s contract covers the header bytes at `base`.
+        unsafe { write_graph_header(self.base, &header) };
+        header
+    }
+}
+
+/// ARB_SEARCH: arbitrage search in graph. `output` receives the VM's match
+/// records (the smoke tests reserve 72 bytes); their layout is defined by the
+/// VM and is not specified by this toolchain, so the SDK does not decode it.
 pub fn arb_search(
     input_mint: VmAddr,
     graph_idx: u64,
@@ -909,6 +2298,42 @@ pub fn arb_score(graph_idx: u64, weights: VmAddr, threshold: u64, mask_ptr: VmAd
     }
 }
 
+/// Mask bytes ARB_SCORE writes for `num_edges` edges: one bit per edge.
+pub const fn arb_mask_len(num_edges: usize) -> usize {
+    num_edges.div_ceil(8)
+}
+
+/// ARB_SCORE over typed buffers. `num_edges` is the edge count of ARB graph
+/// `graph_idx` as the caller knows it (the VM does not publish the ARB
+/// segment layout); `mask` must hold at least `arb_mask_len(num_edges)`
+/// bytes (`ceil(num_edges / 8)`), otherwise no syscall is issued. Empty
+/// `weights` passes a null pointer. Returns the passing edge count.
+pub fn arb_score_into(
+    graph_idx: u64,
+    num_edges: usize,
+    weights: &[i32],
+    threshold: u64,
+    mask: &mut [u8],
+) -> SdkResult<u32> {
+    check_len(mask.len(), arb_mask_len(num_edges))?;
+    let weights = if weights.is_empty() {
+        VmAddr::null()
+    } else {
+        VmAddr::from_slice(weights)
+    };
+    let mask = VmAddr::from_mut_slice(mask);
+    Ok(arb_score(graph_idx, weights, threshold, mask))
+}
+
+/// Base of graph `graph_idx`, which lives in segment `graph_idx + 1`.
+fn graph_segment_base(graph_idx: u64) -> SdkResult<VmAddr> {
+    u8::try_from(graph_idx)
+        .ok()
+        .and_then(|idx| idx.checked_add(1))
+        .and_then(|segment| VmAddr::new(segment, 0))
+        .ok_or(SdkError::InvalidGraph)
+}
+
 /// AGGREGATE: GNN message passing.
 pub fn aggregate(graph_idx: u64, table_ptr: VmAddr, features_ptr: VmAddr, max_nodes: u64) -> u32 {
     unsafe {
@@ -922,6 +2347,31 @@ pub fn aggregate(graph_idx: u64, table_ptr: VmAddr, features_ptr: VmAddr, max_no
     }
 }
 
+/// AGGREGATE over typed buffers: message passing over graph `graph_idx` for
+/// at most `max_nodes` nodes, with `table` as the node table and `features`
+/// holding one `feature_dim`-wide `i8` row per node. `features` must hold
+/// `max_nodes * feature_dim` values; otherwise no syscall is issued.
+/// Returns the number of unique nodes.
+pub fn aggregate_into(
+    graph_idx: u64,
+    table: &mut [i8],
+    features: &mut [i8],
+    max_nodes: u64,
+    feature_dim: usize,
+) -> SdkResult<u32> {
+    let required = usize::try_from(max_nodes)
+        .ok()
+        .and_then(|n| n.checked_mul(feature_dim))
+        .ok_or(SdkError::BufferTooSmall)?;
+    check_len(features.len(), required)?;
+    Ok(aggregate(
+        graph_idx,
+        VmAddr::from_mut_slice(table),
+        VmAddr::from_mut_slice(features),
+        max_nodes,
+    ))
+}
+
 /// QUANTUM_OP: 7-qubit state ops (Q16.16 complex).
 pub fn quantum_op(op: u32, target: u32, control: u32, state: &mut [Q16Complex]) -> SdkResult<u32> {
     check_len(state.len(), QUANTUM_STATE_LEN)?;
@@ -936,3 +2386,2793 @@ pub fn quantum_op(op: u32, target: u32, control: u32, state: &mut [Q16Complex])
     };
     Ok(res as u32)
 }
+
+/// Encodes an angle for the control slot of `QOP_RX`, `QOP_RZ` and
+/// `QOP_PHASE`: Q16.16 radians, two's complement, reinterpreted as `u32`
+/// (`pi` is `205_887`, `-pi/2` is `0xFFFE_6DE0`). Saturates like
+/// `Q16::from_f32`.
+#[cfg(feature = "float")]
+pub fn angle_to_control(radians: f32) -> u32 {
+    Q16::from_f32(radians).raw() as u32
+}
+
+/// Inverse of `angle_to_control`.
+#[cfg(feature = "float")]
+pub fn control_to_angle(control: u32) -> f32 {
+    Q16::from_raw(control as i32).to_f32()
+}
+
+/// Gate-by-gate builder over QUANTUM_OP. `new` resets the state to
+/// `ket(0...0)`; each method issues one syscall and returns `self` so gates
+/// chain with `?`:
+///
+/// ```
+/// # use frostbite_sdk::{Q16Complex, QuantumCircuit, SdkResult, QUANTUM_STATE_LEN};
+/// # fn main() -> SdkResult<()> {
+/// let mut state = [Q16Complex { re: 0, im: 0 }; QUANTUM_STATE_LEN];
+/// let mut circuit = QuantumCircuit::new(&mut state)?;
+/// circuit.h(0)?.cnot(0, 1)?;
+/// let bit = circuit.measure(0)?;
+/// # let _ = bit;
+/// # Ok(())
+/// # }
+/// ```
+pub struct QuantumCircuit<'a> {
+    state: &'a mut [Q16Complex],
+    seed: u32,
+}
+
+impl<'a> QuantumCircuit<'a> {
+    /// Borrows `state` (at least `QUANTUM_STATE_LEN` amplitudes) and
+    /// issues `QOP_INIT`.
+    pub fn new(state: &'a mut [Q16Complex]) -> SdkResult<Self> {
+        quantum_op(QOP_INIT, 0, 0, state)?;
+        Ok(QuantumCircuit { state, seed: 0 })
+    }
+
+    /// RNG seed passed in the control slot of later `measure` calls.
+    pub fn seed(&mut self, seed: u32) -> &mut Self {
+        self.seed = seed;
+        self
+    }
+
+    pub fn reset(&mut self) -> SdkResult<&mut Self> {
+        self.op(QOP_INIT, 0, 0)?;
+        Ok(self)
+    }
+
+    pub fn h(&mut self, q: u32) -> SdkResult<&mut Self> {
+        self.op(QOP_H, check_qubit(q)?, 0)?;
+        Ok(self)
+    }
+
+    pub fn cnot(&mut self, control: u32, target: u32) -> SdkResult<&mut Self> {
+        if control == target {
+            return Err(SdkError::InvalidQubit);
+        }
+        self.op(QOP_CNOT, check_qubit(target)?, check_qubit(control)?)?;
+        Ok(self)
+    }
+
+    /// Rotation gates take radians, encoded with `angle_to_control`.
+    #[cfg(feature = "float")]
+    pub fn rx(&mut self, q: u32, radians: f32) -> SdkResult<&mut Self> {
+        self.op(QOP_RX, check_qubit(q)?, angle_to_control(radians))?;
+        Ok(self)
+    }
+
+    #[cfg(feature = "float")]
+    pub fn rz(&mut self, q: u32, radians: f32) -> SdkResult<&mut Self> {
+        self.op(QOP_RZ, check_qubit(q)?, angle_to_control(radians))?;
+        Ok(self)
+    }
+
+    #[cfg(feature = "float")]
+    pub fn phase(&mut self, q: u32, radians: f32) -> SdkResult<&mut Self> {
+        self.op(QOP_PHASE, check_qubit(q)?, angle_to_control(radians))?;
+        Ok(self)
+    }
+
+    /// Measures qubit `q` (collapsing the state) and returns 0 or 1.
+    pub fn measure(&mut self, q: u32) -> SdkResult<u32> {
+        let seed = self.seed;
+        self.op(QOP_MEASURE, check_qubit(q)?, seed)
+    }
+
+    /// Measures every qubit; see `measure_all`.
+    pub fn measure_all(&mut self) -> SdkResult<u8> {
+        measure_all_seeded(self.state, self.seed)
+    }
+
+    /// Non-destructive snapshot; see `state_probabilities`.
+    pub fn probabilities(&self, out: &mut [u32]) -> SdkResult<()> {
+        state_probabilities(self.state, out)
+    }
+
+    /// The current amplitudes.
+    pub fn state(&self) -> &[Q16Complex] {
+        self.state
+    }
+
+    #[inline(always)]
+    fn op(&mut self, op: u32, target: u32, control: u32) -> SdkResult<u32> {
+        quantum_op(op, target, control, self.state)
+    }
+}
+
+/// Measures qubits `0..QUANTUM_NUM_QUBITS` in order (collapsing the state)
+/// and packs the outcomes as bit `i` = qubit `i`. Uses seed 0 for every
+/// MEASURE; `QuantumCircuit::measure_all` uses the circuit's seed.
+pub fn measure_all(state: &mut [Q16Complex]) -> SdkResult<u8> {
+    measure_all_seeded(state, 0)
+}
+
+/// Writes `|amp|^2 = re*re + im*im` for each basis state into `out`, in
+/// Q16 (`1 << 16` is probability 1). Pure guest-side math: no syscall, and
+/// the state is not collapsed. `out` must hold exactly `QUANTUM_STATE_LEN`
+/// entries.
+pub fn state_probabilities(state: &[Q16Complex], out: &mut [u32]) -> SdkResult<()> {
+    check_len(state.len(), QUANTUM_STATE_LEN)?;
+    check_equal(out.len(), QUANTUM_STATE_LEN)?;
+    for (p, a) in out.iter_mut().zip(state) {
+        let (re, im) = (a.re as i64, a.im as i64);
+        *p = ((re * re + im * im) >> 16).min(u32::MAX as i64) as u32;
+    }
+    Ok(())
+}
+
+fn measure_all_seeded(state: &mut [Q16Complex], seed: u32) -> SdkResult<u8> {
+    check_len(state.len(), QUANTUM_STATE_LEN)?;
+    let mut bits = 0u8;
+    for q in 0..QUANTUM_NUM_QUBITS as u32 {
+        bits |= ((quantum_op(QOP_MEASURE, q, seed, state)? & 1) as u8) << q;
+    }
+    Ok(bits)
+}
+
+#[inline(always)]
+fn check_qubit(q: u32) -> SdkResult<u32> {
+    if (q as usize) < QUANTUM_NUM_QUBITS {
+        Ok(q)
+    } else {
+        Err(SdkError::InvalidQubit)
+    }
+}
+
+// ============================================================================
+// Control block and FBH1 headers
+// ============================================================================
+
+/// `"FBM1"`: magic at the start of the control block in scratch.
+pub const FBM1_MAGIC: u32 = 0x314D_4246;
+/// The only control-block `abi_version` current runners write.
+pub const FBM1_ABI_VERSION: u32 = 1;
+
+pub const CTRL_MAGIC: usize = 0;
+pub const CTRL_ABI_VERSION: usize = 4;
+pub const CTRL_FLAGS: usize = 8;
+pub const CTRL_STATUS: usize = 12;
+pub const CTRL_INPUT_PTR: usize = 16;
+pub const CTRL_INPUT_LEN: usize = 20;
+pub const CTRL_OUTPUT_PTR: usize = 24;
+pub const CTRL_OUTPUT_LEN: usize = 28;
+/// `reserved0` (u64); by convention the run nonce checked by the gatekeeper.
+pub const CTRL_RESERVED0: usize = 48;
+/// `vm_seed` (u64): the VM account's seed, written by the runner (0 if unset).
+pub const CTRL_VM_SEED: usize = 56;
+
+// The control block is the guest's channel to the host runner: the runner
+// fills it before the run and reads status / output length back afterwards.
+// Every access goes through `ctrl_read_u32` / `ctrl_write_u32`, which are
+// volatile so the compiler never caches, merges or drops them. Input, output
+// and model scratch are plain guest memory for the duration of a run;
+// templates access those with ordinary unaligned loads and stores.
+
+/// Volatile read of the control-block word at `ctrl_base + field`, where
+/// `field` is one of the `CTRL_*` offsets.
+///
+/// # Safety
+///
+/// `ctrl_base + field` must be a readable, 4-byte aligned address, normally
+/// a field of the mapped control block.
+#[inline(always)]
+pub unsafe fn ctrl_read_u32(ctrl_base: VmAddr, field: usize) -> u32 {
+    peek_u32(ctrl_base.raw() + field as u64)
+}
+
+/// Volatile write of the control-block word at `ctrl_base + field`.
+///
+/// # Safety
+///
+/// `ctrl_base + field` must be a writable, 4-byte aligned address, normally
+/// a field of the mapped control block.
+#[inline(always)]
+pub unsafe fn ctrl_write_u32(ctrl_base: VmAddr, field: usize, value: u32) {
+    poke_u32(ctrl_base.raw() + field as u64, value)
+}
+
+/// A `len`-byte window of guest memory at `base` (typically scratch, up to
+/// the template's `SCRATCH_MIN`) whose accessors refuse out-of-range
+/// offsets instead of touching memory past the end. Accesses are unaligned.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct ScratchView {
+    base: VmAddr,
+    len: usize,
+}
+
+impl ScratchView {
+    /// # Safety
+    ///
+    /// `base..base + len` must be valid, writable guest memory for as long
+    /// as the view is used; the accessors only check offsets against `len`.
+    pub const unsafe fn new(base: VmAddr, len: usize) -> Self {
+        ScratchView { base, len }
+    }
+
+    pub const fn base(&self) -> VmAddr {
+        self.base
+    }
+
+    pub const fn len(&self) -> usize {
+        self.len
+    }
+
+    pub const fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// The `u32` at `offset`, or `None` unless `offset + 4 <= len`.
+    pub fn read_u32(&self, offset: usize) -> Option<u32> {
+        if !region_fits(offset, 4, self.len) {
+            return None;
+        }
+        Some(unsafe { ((self.base.raw() + offset as u64) as *const u32).read_unaligned() })
+    }
+
+    /// Store `value` at `offset`; `BufferTooSmall` unless `offset + 4 <= len`.
+    pub fn write_u32(&self, offset: usize, value: u32) -> SdkResult<()> {
+        if !region_fits(offset, 4, self.len) {
+            return Err(SdkError::BufferTooSmall);
+        }
+        unsafe { ((self.base.raw() + offset as u64) as *mut u32).write_unaligned(value) };
+        Ok(())
+    }
+}
+
+/// Bytes covered by the control block, `magic` through `vm_seed`.
+pub const CONTROL_BLOCK_LEN: usize = 64;
+
+/// Typed access to the control block at `base`, going through
+/// `ctrl_read_u32` / `ctrl_write_u32`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct ControlBlock {
+    base: VmAddr,
+}
+
+impl ControlBlock {
+    /// # Safety
+    ///
+    /// `base..base + CONTROL_BLOCK_LEN` must be the mapped control block
+    /// (readable, writable and 4-byte aligned) for as long as the returned
+    /// value is used; every accessor reads or writes it.
+    pub const unsafe fn new(base: VmAddr) -> Self {
+        ControlBlock { base }
+    }
+
+    pub const fn base(&self) -> VmAddr {
+        self.base
+    }
+
+    pub fn magic(&self) -> u32 {
+        unsafe { ctrl_read_u32(self.base, CTRL_MAGIC) }
+    }
+
+    pub fn abi_version(&self) -> u32 {
+        unsafe { ctrl_read_u32(self.base, CTRL_ABI_VERSION) }
+    }
+
+    pub fn flags(&self) -> u32 {
+        unsafe { ctrl_read_u32(self.base, CTRL_FLAGS) }
+    }
+
+    pub fn status(&self) -> u32 {
+        unsafe { ctrl_read_u32(self.base, CTRL_STATUS) }
+    }
+
+    pub fn set_status(&self, code: u32) {
+        unsafe { ctrl_write_u32(self.base, CTRL_STATUS, code) };
+    }
+
+    /// `(input_ptr, input_len)` as filled in by the runner.
+    pub fn input(&self) -> (VmAddr, usize) {
+        let (ptr, len) = unsafe {
+            (
+                ctrl_read_u32(self.base, CTRL_INPUT_PTR),
+                ctrl_read_u32(self.base, CTRL_INPUT_LEN),
+            )
+        };
+        (VmAddr(ptr as u64), len as usize)
+    }
+
+    /// `(output_ptr, output_len)`; `output_len` is whatever was last stored,
+    /// normally by [`ControlBlock::set_output_len`].
+    pub fn output(&self) -> (VmAddr, usize) {
+        let (ptr, len) = unsafe {
+            (
+                ctrl_read_u32(self.base, CTRL_OUTPUT_PTR),
+                ctrl_read_u32(self.base, CTRL_OUTPUT_LEN),
+            )
+        };
+        (VmAddr(ptr as u64), len as usize)
+    }
+
+    pub fn set_output_len(&self, len: u32) {
+        unsafe { ctrl_write_u32(self.base, CTRL_OUTPUT_LEN, len) };
+    }
+
+    /// The runner-provided `vm_seed`. The same VM always reports the same
+    /// seed, so it makes a reproducible `rng_state` for [`sample_i32`].
+    pub fn seed(&self) -> u64 {
+        let (lo, hi) = unsafe {
+            (
+                ctrl_read_u32(self.base, CTRL_VM_SEED) as u64,
+                ctrl_read_u32(self.base, CTRL_VM_SEED + 4) as u64,
+            )
+        };
+        (hi << 32) | lo
+    }
+
+    /// The magic / ABI version check every guest starts with; `Err` carries
+    /// the status to report (`ERR_CTRL`).
+    pub fn validate(&self) -> Result<(), u32> {
+        if self.magic() != FBM1_MAGIC || self.abi_version() != FBM1_ABI_VERSION {
+            return Err(status::ERR_CTRL);
+        }
+        Ok(())
+    }
+}
+
+/// Status (and exit code) reserved for guest panics; see [`status`].
+pub const STATUS_PANIC: u32 = status::ERR_PANIC;
+
+/// Write `code` to the control block's `status` and exit with it, so the
+/// runner can tell a panic from a normal error exit. Meant for
+/// `#[panic_handler]`s; see `default_panic_handler!`.
+///
+/// # Safety
+///
+/// `ctrl_base` must be the address of the mapped control block.
+pub unsafe fn report_panic(ctrl_base: VmAddr, code: u32) -> ! {
+    ctrl_write_u32(ctrl_base, CTRL_STATUS, code);
+    exit(code as i64)
+}
+
+/// `default_panic_handler!(ctrl_base)`: defines the guest's `#[panic_handler]`
+/// as `report_panic(ctrl_base, STATUS_PANIC)`. `ctrl_base` is a `VmAddr`
+/// expression for the control block, usually the scratch address of
+/// `CONTROL_OFFSET`; the handler relies on it being mapped there.
+#[macro_export]
+macro_rules! default_panic_handler {
+    ($ctrl_base:expr) => {
+        #[panic_handler]
+        fn panic(_info: &core::panic::PanicInfo) -> ! {
+            unsafe { $crate::report_panic($ctrl_base, $crate::STATUS_PANIC) }
+        }
+    };
+}
+
+/// `vmaddr!(segment, offset)`: a `VmAddr` checked at compile time. Both
+/// arguments must be constant; out-of-range values fail const evaluation:
+///
+/// ```
+/// use frostbite_sdk::{vmaddr, VmAddr};
+/// const WEIGHTS: VmAddr = vmaddr!(1, 0x40);
+/// assert_eq!(WEIGHTS.split(), (1, 0x40));
+/// ```
+///
+/// ```compile_fail
+/// use frostbite_sdk::{vmaddr, VmAddr};
+/// const WEIGHTS: VmAddr = vmaddr!(1, 1 << 28);
+/// ```
+#[macro_export]
+macro_rules! vmaddr {
+    ($segment:expr, $offset:expr) => {{
+        const ADDR: $crate::VmAddr = match $crate::VmAddr::new($segment, $offset) {
+            Some(addr) => addr,
+            None => panic!("vmaddr!: segment must be <= 15 and offset < 1 << 28"),
+        };
+        ADDR
+    }};
+}
+
+/// `"FBH1"`: optional self-describing header in front of an input or output
+/// payload.
+pub const FBH1_MAGIC: u32 = 0x3148_4246;
+pub const FBH1_VERSION: u16 = 1;
+pub const FBH1_HEADER_LEN: usize = 32;
+
+pub const FBH_MAGIC: usize = 0;
+pub const FBH_VERSION: usize = 4; // u16
+pub const FBH_FLAGS: usize = 6; // u16
+pub const FBH_HEADER_LEN: usize = 8; // u32
+pub const FBH_SCHEMA_ID: usize = 12; // u32
+pub const FBH_PAYLOAD_LEN: usize = 16; // u32
+pub const FBH_CRC32: usize = 20; // u32
+pub const FBH_SCHEMA_HASH: usize = 24; // u32
+
+pub const FBH_FLAG_HAS_CRC32: u16 = 1 << 0;
+pub const FBH_FLAG_HAS_SCHEMA_HASH: u16 = 1 << 1;
+
+#[inline(always)]
+unsafe fn peek_u16(addr: u64) -> u16 {
+    (addr as *const u16).read_volatile()
+}
+
+#[inline(always)]
+unsafe fn peek_u32(addr: u64) -> u32 {
+    (addr as *const u32).read_volatile()
+}
+
+#[inline(always)]
+unsafe fn poke_u16(addr: u64, value: u16) {
+    (addr as *mut u16).write_volatile(value);
+}
+
+#[inline(always)]
+unsafe fn poke_u32(addr: u64, value: u32) {
+    (addr as *mut u32).write_volatile(value);
+}
+
+/// CRC-32 (IEEE, reflected polynomial `0xEDB8_8320`, `0xFFFF_FFFF` init and
+/// final xor) as used by the FBH1 `crc32` field.
+pub fn crc32(data: &[u8]) -> u32 {
+    let mut crc: u32 = 0xFFFF_FFFF;
+    for &byte in data {
+        crc = crc32_step(crc, byte);
+    }
+    !crc
+}
+
+/// [`crc32`] over `len` bytes at a raw VM address (volatile byte reads).
+///
+/// # Safety
+///
+/// `ptr..ptr + len` must be readable guest memory.
+pub unsafe fn crc32_vmaddr(ptr: VmAddr, len: usize) -> u32 {
+    let mut crc: u32 = 0xFFFF_FFFF;
+    for i in 0..len {
+        let byte = ((ptr.raw() + i as u64) as *const u8).read_volatile();
+        crc = crc32_step(crc, byte);
+    }
+    !crc
+}
+
+#[inline(always)]
+fn crc32_step(mut crc: u32, byte: u8) -> u32 {
+    crc ^= byte as u32;
+    for _ in 0..8 {
+        if (crc & 1) != 0 {
+            crc = (crc >> 1) ^ 0xEDB8_8320;
+        } else {
+            crc >>= 1;
+        }
+    }
+    crc
+}
+
+/// FBH1 `schema_hash`: 32-bit FNV-1a (offset basis `0x811C_9DC5`, prime
+/// `0x0100_0193`) over the canonical schema bytes. The canonical form is the
+/// manifest's `[schema]` table reduced to its type-specific keys and
+/// serialized as compact JSON with sorted keys, exactly as
+/// `cauldron schema-hash` hashes it.
+pub const fn schema_hash(schema_bytes: &[u8]) -> u32 {
+    let mut h: u32 = 0x811C_9DC5;
+    let mut i = 0;
+    while i < schema_bytes.len() {
+        h ^= schema_bytes[i] as u32;
+        h = h.wrapping_mul(0x0100_0193);
+        i += 1;
+    }
+    h
+}
+
+/// Frame an output that was written `FBH1_HEADER_LEN` bytes past
+/// `output_ptr`: emit the 32-byte FBH1 header at `output_ptr` and set the
+/// control block's output length to header + payload (returned).
+///
+/// With `FBH_FLAG_HAS_CRC32` in `flags` the CRC32 of the payload is computed
+/// and stored; otherwise the field is zero. The schema-hash field is always
+/// written as zero.
+///
+/// # Safety
+///
+/// `output_ptr` must be 4-byte aligned, with `FBH1_HEADER_LEN + payload_len`
+/// writable bytes behind it, and `ctrl_base` must be the mapped control
+/// block.
+pub unsafe fn write_output_header(
+    ctrl_base: VmAddr,
+    output_ptr: VmAddr,
+    schema_id: u32,
+    payload_len: u32,
+    flags: u16,
+) -> u32 {
+    let base = output_ptr.raw();
+    let crc = if (flags & FBH_FLAG_HAS_CRC32) != 0 {
+        crc32_vmaddr(VmAddr(base + FBH1_HEADER_LEN as u64), payload_len as usize)
+    } else {
+        0
+    };
+    let total = payload_len.saturating_add(FBH1_HEADER_LEN as u32);
+    poke_u32(base + FBH_MAGIC as u64, FBH1_MAGIC);
+    poke_u16(base + FBH_VERSION as u64, FBH1_VERSION);
+    poke_u16(base + FBH_FLAGS as u64, flags);
+    poke_u32(base + FBH_HEADER_LEN as u64, FBH1_HEADER_LEN as u32);
+    poke_u32(base + FBH_SCHEMA_ID as u64, schema_id);
+    poke_u32(base + FBH_PAYLOAD_LEN as u64, payload_len);
+    poke_u32(base + FBH_CRC32 as u64, crc);
+    poke_u32(base + FBH_SCHEMA_HASH as u64, 0);
+    poke_u32(base + 28, 0);
+    ctrl_write_u32(ctrl_base, CTRL_OUTPUT_LEN, total);
+    total
+}
+
+/// Why [`parse_input_header`] rejected an FBH1 input header. Templates report
+/// these as `ERR_INPUT_HEADER` and `ERR_SCHEMA`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum InputHeaderError {
+    /// Bad version, header length, payload length or CRC32.
+    Header,
+    /// Schema id mismatch, or a schema hash that does not match
+    /// `expected_schema_hash` (zero rejects any hash).
+    Schema,
+}
+
+impl InputHeaderError {
+    /// The guest status for this error: `ERR_INPUT_HEADER` or `ERR_SCHEMA`.
+    pub const fn status(self) -> u32 {
+        match self {
+            InputHeaderError::Header => status::ERR_INPUT_HEADER,
+            InputHeaderError::Schema => status::ERR_SCHEMA,
+        }
+    }
+}
+
+/// How the FBH1 parsers load the header and the payload bytes for the CRC.
+///
+/// Under the Frostbite MMU the input segment is ordinary guest memory that
+/// nothing else writes during a run, so `Plain` is correct for inputs and is
+/// what [`parse_fbh1`] uses. `Volatile` is for buffers the runtime may change
+/// between reads, such as an account-backed control region; it also requires
+/// the header words to be aligned.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HeaderAccess {
+    /// Volatile aligned loads, as `ctrl_read_u32` does.
+    Volatile,
+    /// Ordinary unaligned loads, as the templates use for scratch and input.
+    Plain,
+}
+
+impl HeaderAccess {
+    unsafe fn u16_at(self, addr: u64) -> u16 {
+        match self {
+            HeaderAccess::Volatile => peek_u16(addr),
+            HeaderAccess::Plain => (addr as *const u16).read_unaligned(),
+        }
+    }
+
+    unsafe fn u32_at(self, addr: u64) -> u32 {
+        match self {
+            HeaderAccess::Volatile => peek_u32(addr),
+            HeaderAccess::Plain => (addr as *const u32).read_unaligned(),
+        }
+    }
+
+    unsafe fn crc32(self, ptr: VmAddr, len: usize) -> u32 {
+        match self {
+            HeaderAccess::Volatile => crc32_vmaddr(ptr, len),
+            HeaderAccess::Plain => crc32(core::slice::from_raw_parts(ptr.raw() as *const u8, len)),
+        }
+    }
+}
+
+/// Strip an optional FBH1 header from the input at `input`.
+///
+/// Inputs shorter than a header or without the `FBH1` magic are returned
+/// unchanged as raw payloads. Otherwise the header is validated against
+/// `expected_schema_id` / `expected_schema_hash` (and the payload CRC32 when
+/// flagged) and the payload address and length are returned.
+///
+/// Reads are volatile; see [`parse_input_header_with`].
+///
+/// # Safety
+///
+/// `input..input + input_len` must be readable guest memory, and the header
+/// words must be 4-byte aligned.
+pub unsafe fn parse_input_header(
+    input: VmAddr,
+    input_len: usize,
+    expected_schema_id: u32,
+    expected_schema_hash: u32,
+) -> Result<(VmAddr, usize), InputHeaderError> {
+    parse_input_header_with(
+        HeaderAccess::Volatile,
+        input,
+        input_len,
+        expected_schema_id,
+        expected_schema_hash,
+    )
+}
+
+/// [`parse_input_header`] with the loads chosen by `access`.
+///
+/// # Safety
+///
+/// `input..input + input_len` must be readable guest memory; with
+/// `HeaderAccess::Volatile` the header words must also be 4-byte aligned.
+pub unsafe fn parse_input_header_with(
+    access: HeaderAccess,
+    input: VmAddr,
+    input_len: usize,
+    expected_schema_id: u32,
+    expected_schema_hash: u32,
+) -> Result<(VmAddr, usize), InputHeaderError> {
+    if input_len < FBH1_HEADER_LEN {
+        return Ok((input, input_len));
+    }
+
+    let base = input.raw();
+    if access.u32_at(base + FBH_MAGIC as u64) != FBH1_MAGIC {
+        return Ok((input, input_len));
+    }
+
+    let (version, flags, header_len, schema_id, payload_len, crc_expected, schema_hash) = (
+        access.u16_at(base + FBH_VERSION as u64),
+        access.u16_at(base + FBH_FLAGS as u64),
+        access.u32_at(base + FBH_HEADER_LEN as u64) as usize,
+        access.u32_at(base + FBH_SCHEMA_ID as u64),
+        access.u32_at(base + FBH_PAYLOAD_LEN as u64) as usize,
+        access.u32_at(base + FBH_CRC32 as u64),
+        access.u32_at(base + FBH_SCHEMA_HASH as u64),
+    );
+    if version != FBH1_VERSION || header_len != FBH1_HEADER_LEN {
+        return Err(InputHeaderError::Header);
+    }
+    if schema_id != expected_schema_id {
+        return Err(InputHeaderError::Schema);
+    }
+    if payload_len != input_len - header_len {
+        return Err(InputHeaderError::Header);
+    }
+
+    let payload = VmAddr(base + header_len as u64);
+    if (flags & FBH_FLAG_HAS_SCHEMA_HASH) != 0
+        && (expected_schema_hash == 0 || schema_hash != expected_schema_hash)
+    {
+        return Err(InputHeaderError::Schema);
+    }
+    if (flags & FBH_FLAG_HAS_CRC32) != 0 && access.crc32(payload, payload_len) != crc_expected {
+        return Err(InputHeaderError::Header);
+    }
+
+    Ok((payload, payload_len))
+}
+
+/// [`parse_input_header_with`] using `HeaderAccess::Plain`, with the error
+/// already mapped to the guest status to report, which is what templates
+/// want.
+///
+/// # Safety
+///
+/// `input..input + input_len` must be readable guest memory, normally the
+/// input region from [`ControlBlock::input`].
+pub unsafe fn parse_fbh1(
+    input: VmAddr,
+    input_len: usize,
+    expected_schema_id: u32,
+    expected_schema_hash: u32,
+) -> Result<(VmAddr, usize), u32> {
+    parse_input_header_with(
+        HeaderAccess::Plain,
+        input,
+        input_len,
+        expected_schema_id,
+        expected_schema_hash,
+    )
+    .map_err(InputHeaderError::status)
+}
+
+// ============================================================================
+// Composite helpers
+// ============================================================================
+
+/// Longest key sequence `attention_i32` accepts; scores live on the stack.
+pub const ATTENTION_MAX_SEQ: usize = 256;
+
+/// Logit written for masked keys before SOFTMAX_I32. Half of `i32::MIN` so
+/// max-subtraction inside the softmax cannot overflow.
+pub const ATTENTION_MASKED_LOGIT: i32 = i32::MIN / 2;
+
+/// Which keys an attention query may see.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum AttentionMask<'a> {
+    /// Every key is visible.
+    None,
+    /// Causal: the query at position `pos` sees keys `0..=pos`.
+    Causal { pos: usize },
+    /// Bit `j` (LSB-first within byte `j / 8`) set means key `j` is visible.
+    /// Must be exactly `seq_len.div_ceil(8)` bytes.
+    Bits(&'a [u8]),
+}
+
+impl AttentionMask<'_> {
+    fn validate(&self, seq_len: usize) -> SdkResult<()> {
+        match self {
+            AttentionMask::Bits(bits) => check_equal(bits.len(), seq_len.div_ceil(8)),
+            _ => Ok(()),
+        }
+    }
+
+    fn visible(&self, key: usize) -> bool {
+        match *self {
+            AttentionMask::None => true,
+            AttentionMask::Causal { pos } => key <= pos,
+            AttentionMask::Bits(bits) => bits[key / 8] & (1 << (key % 8)) != 0,
+        }
+    }
+}
+
+/// Single-query scaled dot-product attention over `seq_len` keys, composed
+/// from DOT_I32, SOFTMAX_I32 and WEIGHTED_SUM_I32. All values are Q16.
+///
+/// `k_rows` and `v_rows` point at `seq_len` contiguous rows of `head_dim`
+/// i32s. Scores are `(q . k_j) >> 16`, multiplied by `scale_q16` (usually
+/// `1/sqrt(head_dim)` in Q16); `out` is overwritten with `sum_j p_j * v_j`.
+/// Every key is attended to; see `attention_i32_masked` for causal decoding.
+pub fn attention_i32(
+    q: &[i32],
+    k_rows: VmAddr,
+    v_rows: VmAddr,
+    out: &mut [i32],
+    seq_len: usize,
+    head_dim: usize,
+    scale_q16: i32,
+) -> SdkResult<()> {
+    attention_i32_masked(
+        q,
+        k_rows,
+        v_rows,
+        out,
+        seq_len,
+        head_dim,
+        scale_q16,
+        AttentionMask::None,
+    )
+}
+
+/// `attention_i32` with masked keys set to `ATTENTION_MASKED_LOGIT` before
+/// the softmax. Masked keys skip their DOT_I32 and WEIGHTED_SUM_I32 calls.
+#[allow(clippy::too_many_arguments)]
+pub fn attention_i32_masked(
+    q: &[i32],
+    k_rows: VmAddr,
+    v_rows: VmAddr,
+    out: &mut [i32],
+    seq_len: usize,
+    head_dim: usize,
+    scale_q16: i32,
+    mask: AttentionMask,
+) -> SdkResult<()> {
+    check_equal(q.len(), head_dim)?;
+    check_equal(out.len(), head_dim)?;
+    mask.validate(seq_len)?;
+    if seq_len > ATTENTION_MAX_SEQ {
+        return Err(SdkError::BufferTooSmall);
+    }
+    out.fill(0);
+    if seq_len == 0 {
+        return Ok(());
+    }
+
+    let row_bytes = (head_dim * core::mem::size_of::<i32>()) as u64;
+    let mut scores = [0i32; ATTENTION_MAX_SEQ];
+    let scores = &mut scores[..seq_len];
+    let scale = Q16::from_raw(scale_q16);
+    for (j, score) in scores.iter_mut().enumerate() {
+        if !mask.visible(j) {
+            continue;
+        }
+        let dot = unsafe {
+            raw::ecall4(
+                SYS_DOT_I32,
+                VmAddr::from_slice(q).raw(),
+                k_rows.raw() + j as u64 * row_bytes,
+                head_dim as u64,
+                q16::FRAC_BITS as u64,
+            )
+        };
+        *score = Q16::from_raw(dot as i32).saturating_mul(scale).raw();
+    }
+    apply_mask(scores, mask);
+
+    softmax_i32(scores);
+
+    for (j, &weight) in scores.iter().enumerate() {
+        if !mask.visible(j) {
+            continue;
+        }
+        unsafe {
+            raw::ecall5(
+                SYS_WEIGHTED_SUM_I32,
+                VmAddr::from_mut_slice(out).raw(),
+                v_rows.raw() + j as u64 * row_bytes,
+                weight as u64,
+                head_dim as u64,
+                q16::FRAC_BITS as u64,
+            );
+        }
+    }
+    Ok(())
+}
+
+fn apply_mask(scores: &mut [i32], mask: AttentionMask) {
+    for (j, score) in scores.iter_mut().enumerate() {
+        if !mask.visible(j) {
+            *score = ATTENTION_MASKED_LOGIT;
+        }
+    }
+}
+
+/// ROPE from precomputed tables instead of a position. Runs on the guest
+/// (no syscall), so one table pair can be reused across every layer at the
+/// same position.
+///
+/// Pair `(x[2i], x[2i + 1])` of `q` and `k` is rotated by `cos[i]`/`sin[i]`
+/// for `i in 0..dim / 2`. With the usual per-head frequencies the tables hold
+/// `cos(pos * theta_j)` / `sin(pos * theta_j)` for `j = (2i % head_size) / 2`,
+/// i.e. the `head_size / 2` per-head entries repeated for each head.
+pub fn rope_cached(
+    q: &mut [f32],
+    k: &mut [f32],
+    cos: &[f32],
+    sin: &[f32],
+    dim: usize,
+    head_size: usize,
+) -> SdkResult<()> {
+    check_len(q.len(), dim)?;
+    check_len(k.len(), dim)?;
+    check_equal(cos.len(), dim / 2)?;
+    check_equal(sin.len(), dim / 2)?;
+    if head_size == 0 || !head_size.is_multiple_of(2) || !dim.is_multiple_of(head_size) {
+        return Err(SdkError::LengthMismatch);
+    }
+    for (i, (&c, &s)) in cos.iter().zip(sin).enumerate() {
+        for v in [&mut *q, &mut *k] {
+            let (x0, x1) = (v[2 * i], v[2 * i + 1]);
+            v[2 * i] = x0 * c - x1 * s;
+            v[2 * i + 1] = x0 * s + x1 * c;
+        }
+    }
+    Ok(())
+}
+
+/// Quantize Q16 activations into the prequant buffer consumed by
+/// MATMUL_I8_I8 (see "Prequant Buffer" in SYSCALLS.md): `src.len()` int8
+/// values zero-padded to `align4(n)`, then the little-endian i32 Q16 scale.
+/// Symmetric absmax quantization, so `src[i] ~= q[i] * scale`. Returns the
+/// scale (0 for an all-zero input).
+pub fn quantize_i8(dst: &mut [u8], src: &[i32]) -> SdkResult<i32> {
+    let n = src.len();
+    check_len(dst.len(), align4(n) + 4)?;
+    let absmax = src.iter().map(|v| v.unsigned_abs()).max().unwrap_or(0) as i64;
+    let scale = ((absmax + 63) / 127) as i32;
+    for (byte, &v) in dst[..n].iter_mut().zip(src) {
+        let q = if absmax == 0 {
+            0
+        } else {
+            let num = v as i64 * 127;
+            let half = absmax / 2;
+            let rounded = if num >= 0 { num + half } else { num - half } / absmax;
+            rounded.clamp(-127, 127)
+        };
+        *byte = q as i8 as u8;
+    }
+    dst[n..align4(n)].fill(0);
+    dst[align4(n)..align4(n) + 4].copy_from_slice(&scale.to_le_bytes());
+    Ok(scale)
+}
+
+/// Requantize the Q16 output of [`matmul_i8_i32`] into the prequant input
+/// of a following [`matmul_i8_i8`]. Same layout and per-tensor absmax scale
+/// as [`quantize_i8`]; the returned Q16 scale is the one now stored in
+/// `dst`. The largest magnitude maps to +-127, so nothing clips and each
+/// value comes back within about `scale / 2`.
+pub fn requantize_i32_to_prequant(dst: &mut [u8], src: &[i32]) -> SdkResult<i32> {
+    quantize_i8(dst, src)
+}
+
+/// Bytes in a prequant buffer for `n` values: `align4(n) + 4`.
+pub const fn prequant_len(n: usize) -> usize {
+    align4(n) + 4
+}
+
+/// Fills a prequant buffer value by value, for guests that quantize
+/// themselves instead of calling [`quantize_i8`]. `finalize` writes the
+/// scale word and returns the buffer trimmed to `prequant_len(n)`.
+pub struct Prequant<'a> {
+    buf: &'a mut [u8],
+    n: usize,
+}
+
+impl<'a> Prequant<'a> {
+    /// `buf` must hold at least `prequant_len(n)` bytes. Values and padding
+    /// start zeroed.
+    pub fn new(buf: &'a mut [u8], n: usize) -> SdkResult<Self> {
+        check_len(buf.len(), prequant_len(n))?;
+        buf[..align4(n)].fill(0);
+        Ok(Prequant { buf, n })
+    }
+
+    pub fn len(&self) -> usize {
+        self.n
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.n == 0
+    }
+
+    /// Panics if `index >= len()`.
+    pub fn set(&mut self, index: usize, value: i8) {
+        assert!(index < self.n, "prequant index out of range");
+        self.buf[index] = value as u8;
+    }
+
+    pub fn finalize(self, scale_q16: i32) -> &'a [u8] {
+        let at = align4(self.n);
+        self.buf[at..at + 4].copy_from_slice(&scale_q16.to_le_bytes());
+        &self.buf[..at + 4]
+    }
+}
+
+/// Convert raw i32 accumulators (e.g. MATMUL_I8_I32 output) to floats:
+/// `out[i] = acc[i] * scale_q16 / 65536`. Pass the Q16 scale that maps one
+/// accumulator unit to a real value (`Q16::ONE` for already-Q16 outputs).
+#[cfg(feature = "float")]
+pub fn dequantize_to_f32(out: &mut [f32], acc: &[i32], scale_q16: i32) -> SdkResult<()> {
+    check_equal(out.len(), acc.len())?;
+    let scale = Q16::from_raw(scale_q16).to_f32();
+    for (dst, &v) in out.iter_mut().zip(acc) {
+        *dst = v as f32 * scale;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A template config that must be rejected by its `const _: () = assert!(..)`
+    // checks; evaluated here at runtime since a failing const item would stop
+    // the build.
+    mod broken_config {
+        pub const INPUT_MAX: usize = 256;
+        pub const INPUT_DIM: usize = 128;
+        pub const HIDDEN_OFFSET: usize = 0x3F00;
+        pub const HIDDEN_DIM: usize = 128;
+        pub const WEIGHTS_OFFSET: usize = super::SEGMENT_SIZE - 16;
+    }
+
+    #[test]
+    fn region_fits_rejects_broken_config() {
+        use broken_config::*;
+        assert!(!region_fits(0, INPUT_DIM * 4, INPUT_MAX));
+        assert!(!region_fits(HIDDEN_OFFSET, HIDDEN_DIM * 4, GUEST_TEXT_BASE));
+        assert!(!region_fits(WEIGHTS_OFFSET, 64, SEGMENT_SIZE));
+
+        assert!(region_fits(0x3000, 0x1000, GUEST_TEXT_BASE));
+        assert!(region_fits(SEGMENT_SIZE - 16, 16, SEGMENT_SIZE));
+        assert!(!region_fits(usize::MAX, 1, usize::MAX));
+    }
+
+    #[test]
+    fn vmaddr_formats_as_segment_and_offset() {
+        let weights = VmAddr::new(1, 0x3000).unwrap();
+        assert_eq!(format!("{}", weights), "1:0x3000");
+        assert_eq!(format!("{:x}", weights), "1:3000");
+        assert_eq!(format!("{:#x}", weights), "1:0x3000");
+        assert_eq!(format!("{}", VmAddr::null()), "0:0x0");
+        assert_eq!(
+            format!("{}", VmAddr::new(15, (1 << 28) - 1).unwrap()),
+            "15:0xfffffff"
+        );
+        assert_eq!(format!("{}", VmAddr(1 << 40)), "0x10000000000");
+    }
+
+    #[test]
+    fn vmaddr_split_inverts_new() {
+        let scratch = VmAddr::new(0, 0x1000).unwrap();
+        assert_eq!(scratch.segment(), 0);
+        assert_eq!(scratch.offset(), 0x1000);
+
+        let last = VmAddr::new(15, 12).unwrap();
+        assert_eq!(last.split(), (15, 12));
+
+        let max = VmAddr::new(3, (1 << 28) - 1).unwrap();
+        assert_eq!(max.split(), (3, (1 << 28) - 1));
+        assert_eq!(VmAddr::new(max.segment(), max.offset()), Some(max));
+
+        assert_eq!(VmAddr::null().split(), (0, 0));
+    }
+
+    #[test]
+    fn nonzero_status_maps_to_err() {
+        assert_eq!(check_status(0), Ok(0));
+        assert_eq!(check_status(3), Err(SdkError::Syscall(3)));
+        assert_eq!(check_status(u64::MAX), Err(SdkError::Syscall(u64::MAX)));
+    }
+
+    #[test]
+    fn deprecated_shims_drop_only_the_status() {
+        assert_eq!(ignore_status(Err(SdkError::Syscall(7))), Ok(()));
+        assert_eq!(
+            ignore_status(Err(SdkError::LengthMismatch)),
+            Err(SdkError::LengthMismatch)
+        );
+    }
+
+    #[test]
+    fn graph_hits_are_capped_to_output() {
+        assert_eq!(core::mem::size_of::<GraphHit>(), 8);
+        let staged = [
+            GraphHit { node: 7, score: 4 },
+            GraphHit { node: 9, score: 2 },
+            GraphHit { node: 3, score: 1 },
+        ];
+        let mut short = [GraphHit::default(); 2];
+        assert_eq!(copy_hits(&staged, 3, &mut short), 2);
+        assert_eq!(short, [staged[0], staged[1]]);
+
+        let mut wide = [GraphHit::default(); 4];
+        assert_eq!(copy_hits(&staged, 1, &mut wide), 1);
+        assert_eq!(wide[0], staged[0]);
+        assert_eq!(wide[1], GraphHit::default());
+
+        let mut none: [GraphHit; 0] = [];
+        assert_eq!(copy_hits(&staged, 3, &mut none), 0);
+    }
+
+    #[test]
+    fn graph_search_rejects_graphs_larger_than_the_staging_buffer() {
+        // Every edge matches: the mock fills one row per edge, as the VM would.
+        fn all_match(_: u64, args: &[u64; 9]) -> u64 {
+            let rows = args[2] as *mut GraphHit;
+            for i in 0..GRAPH_SEARCH_MAX_HITS {
+                unsafe {
+                    *rows.add(i) = GraphHit {
+                        node: i as u32,
+                        score: 1,
+                    }
+                };
+            }
+            GRAPH_SEARCH_MAX_HITS as u64
+        }
+        let input = [1i8; 4];
+        let mut out = [GraphHit::default(); 2];
+        let previous = mock::set_handler(all_match);
+        mock::take_calls();
+        assert_eq!(
+            graph_search_staged(&input, 0, GRAPH_SEARCH_MAX_HITS + 1, &mut out, 0, false),
+            Err(SdkError::BufferTooSmall)
+        );
+        assert!(mock::take_calls().is_empty());
+
+        assert_eq!(
+            graph_search_staged(&input, 0, GRAPH_SEARCH_MAX_HITS, &mut out, 0, false),
+            Ok(2)
+        );
+        mock::set_handler(previous);
+        assert_eq!(out[1], GraphHit { node: 1, score: 1 });
+        assert_eq!(mock::take_calls()[0].id, SYS_GRAPH_SEARCH);
+
+        assert_eq!(
+            graph_search_into(&input, 15, &mut out, 0, false),
+            Err(SdkError::InvalidGraph)
+        );
+    }
+
+    #[test]
+    fn matmul_batch_validates_lengths() {
+        let row = [0u8; 8];
+        let short = [0u8; 7];
+        let mut out = [0i32; 6];
+
+        assert_eq!(
+            matmul_i8_i8_batch(&mut out, &[&row, &row], 4, VmAddr::null(), 1 << 16, 2),
+            Err(SdkError::LengthMismatch)
+        );
+        assert_eq!(
+            matmul_i8_i8_batch(
+                &mut out,
+                &[&row, &short, &row],
+                4,
+                VmAddr::null(),
+                1 << 16,
+                2
+            ),
+            Err(SdkError::BufferTooSmall)
+        );
+        assert_eq!(
+            matmul_i8_i8_batch(&mut out[..0], &[&row, &row], 4, VmAddr::null(), 1 << 16, 0),
+            Ok(())
+        );
+        assert_eq!(
+            matmul_i8_i8_batch(&mut [], &[], 4, VmAddr::null(), 1 << 16, 3),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn row_state_transitions() {
+        let mut state = RowState::new(4);
+        assert!(!state.is_done());
+        state.cursor = 3;
+        assert!(!state.is_done());
+        state.cursor = 4;
+        assert!(state.is_done());
+        state.reset();
+        assert_eq!((state.cursor, state.max_rows), (0, 4));
+        assert!(!state.is_done());
+    }
+
+    #[test]
+    fn control_block_reads_and_validates_the_fields() {
+        let mut words = [0u32; CONTROL_BLOCK_LEN / 4];
+        words[0] = FBM1_MAGIC;
+        words[1] = FBM1_ABI_VERSION;
+        words[2] = 0x5;
+        words[4] = 0x1000;
+        words[5] = 64;
+        words[6] = 0x2000;
+        words[7] = 16;
+        let base = VmAddr(words.as_mut_ptr() as u64);
+        let ctrl = unsafe { ControlBlock::new(base) };
+        assert_eq!(ctrl.base(), base);
+        assert_eq!(ctrl.validate(), Ok(()));
+        assert_eq!(ctrl.flags(), 0x5);
+        assert_eq!(ctrl.input(), (VmAddr(0x1000), 64));
+        assert_eq!(ctrl.output(), (VmAddr(0x2000), 16));
+
+        ctrl.set_status(status::ERR_SCHEMA);
+        ctrl.set_output_len(12);
+        assert_eq!(ctrl.status(), status::ERR_SCHEMA);
+        assert_eq!(ctrl.output(), (VmAddr(0x2000), 12));
+
+        unsafe { ctrl_write_u32(base, CTRL_ABI_VERSION, 2) };
+        assert_eq!(ctrl.validate(), Err(status::ERR_CTRL));
+        unsafe {
+            ctrl_write_u32(base, CTRL_ABI_VERSION, FBM1_ABI_VERSION);
+            ctrl_write_u32(base, CTRL_MAGIC, FBH1_MAGIC);
+        }
+        assert_eq!(ctrl.validate(), Err(status::ERR_CTRL));
+        assert_eq!(words[3], status::ERR_SCHEMA);
+    }
+
+    #[test]
+    fn control_block_seed_reads_the_vm_seed_word() {
+        let mut words = [0u32; 16];
+        let seed: u64 = 0x0123_4567_89AB_CDEF;
+        words[CTRL_VM_SEED / 4] = seed as u32;
+        words[CTRL_VM_SEED / 4 + 1] = (seed >> 32) as u32;
+        words[CTRL_RESERVED0 / 4] = 41;
+        let ctrl = unsafe { ControlBlock::new(VmAddr::from_mut_slice(&mut words)) };
+        assert_eq!(ctrl.seed(), seed);
+
+        let mut rng = ctrl.seed();
+        let mut again = ctrl.seed();
+        let token = sample_i32(&mut [1 << 16, 1 << 16], Q16::ONE.raw(), &mut rng);
+        let again_token = sample_i32(&mut [1 << 16, 1 << 16], Q16::ONE.raw(), &mut again);
+        assert_eq!((again_token, again), (token, rng));
+    }
+
+    #[test]
+    fn scratch_view_checks_the_last_word() {
+        let mut buf = [0u8; 12];
+        let view = unsafe { ScratchView::new(VmAddr(buf.as_mut_ptr() as u64), 10) };
+        assert_eq!(view.write_u32(6, 0xA1B2_C3D4), Ok(()));
+        assert_eq!(view.write_u32(7, 1), Err(SdkError::BufferTooSmall));
+        assert_eq!(
+            view.write_u32(usize::MAX - 1, 1),
+            Err(SdkError::BufferTooSmall)
+        );
+        assert_eq!(view.read_u32(6), Some(0xA1B2_C3D4));
+        assert_eq!(view.read_u32(0), Some(0));
+        assert_eq!(view.read_u32(7), None);
+        assert_eq!(view.read_u32(usize::MAX), None);
+        let short = unsafe { ScratchView::new(view.base(), 3) };
+        assert_eq!((short.len(), short.read_u32(0)), (3, None));
+        assert_eq!(&buf[6..], &[0xD4, 0xC3, 0xB2, 0xA1, 0, 0]);
+    }
+
+    #[test]
+    fn vmaddr_constructors_agree_with_new() {
+        const W: VmAddr = vmaddr!(2, 0x1234);
+        assert_eq!(Some(W), VmAddr::new(2, 0x1234));
+        assert_eq!(VmAddr::new_unchecked(2, 0x1234), W);
+        assert_eq!(VmAddr::new_unchecked(15, (1 << 28) - 1).raw(), 0xFFFF_FFFF);
+    }
+
+    #[test]
+    fn run_partial_steps_in_chunks_until_the_cursor_reaches_the_total() {
+        // 64 rows, 16 per call: the pass must not stop at the first chunk.
+        let chunk = |s: &mut RowState| {
+            let limit = if s.max_rows == 0 { 64 } else { s.max_rows };
+            s.cursor = (s.cursor + limit).min(64);
+            Ok(())
+        };
+        mock::take_calls();
+        let mut state = RowState::new(16);
+        let mut steps = 0;
+        let result = run_partial(&mut state, 64, |s| {
+            steps += 1;
+            chunk(s)
+        });
+        assert_eq!(result, Ok(()));
+        assert_eq!((steps, state.cursor), (4, 64));
+        let ids: Vec<u64> = mock::take_calls().iter().map(|c| c.id).collect();
+        assert_eq!(ids, [SYS_YIELD, SYS_YIELD, SYS_YIELD]);
+
+        // A limit of 0 processes everything in one call.
+        let mut state = RowState::new(0);
+        assert_eq!(run_partial(&mut state, 64, chunk), Ok(()));
+        assert_eq!(state.cursor, 64);
+        assert!(mock::take_calls().is_empty());
+
+        state.reset();
+        let result = run_partial(&mut state, 64, |_| Err(SdkError::Syscall(2)));
+        assert_eq!(result, Err(SdkError::Syscall(2)));
+        assert!(mock::take_calls().is_empty());
+    }
+
+    #[test]
+    fn argmax_state_transitions() {
+        let mut state = ArgmaxState::new(8);
+        assert!(!state.is_done());
+        state.cursor = 8;
+        state.max_idx = 5;
+        state.max_bits = 2.5f32.to_bits();
+        assert!(state.is_done());
+        state.reset();
+        assert_eq!((state.cursor, state.max_idx, state.max_bits), (0, 0, 0));
+        assert_eq!(state.max_per_call, 8);
+
+        let mut state = ArgmaxI32State::new(2);
+        state.cursor = 3;
+        state.max_idx = 1;
+        state.max_val = -7;
+        assert!(state.is_done());
+        state.reset();
+        assert_eq!((state.cursor, state.max_idx, state.max_val), (0, 0, 0));
+        assert_eq!(state.max_per_call, 2);
+        assert!(!state.is_done());
+    }
+
+    #[test]
+    fn log_tag_packs_ascii_little_endian() {
+        assert_eq!(log_tag(b""), 0);
+        assert_eq!(log_tag(b"A"), 0x41);
+        assert_eq!(log_tag(b"attn"), u32::from_le_bytes(*b"attn") as u64);
+        assert_eq!(log_tag(b"layer-0123"), u64::from_le_bytes(*b"layer-01"));
+    }
+
+    #[test]
+    fn fb_log_accepts_zero_to_four_values() {
+        const TAG: u64 = log_tag(b"test");
+        fb_log!(TAG);
+        fb_log!(TAG, 1u8);
+        fb_log!(TAG, -2i32, true);
+        fb_log!(0xB01B, 3usize, 4u32, 5i64);
+        fb_log!(TAG, 1, 2, 3, 4);
+    }
+
+    #[test]
+    fn wide_ecalls_pass_spilled_arguments_after_the_registers() {
+        // Echoes the ninth argument back so the return path is covered too.
+        fn echo_last(_: u64, args: &[u64; 9]) -> u64 {
+            args[8]
+        }
+        let previous = mock::set_handler(echo_last);
+        mock::take_calls();
+        let ret8 = unsafe { raw::ecall8(7000, 1, 2, 3, 4, 5, 6, 7, 0x8888) };
+        let ret9 = unsafe { raw::ecall9(7001, 1, 2, 3, 4, 5, 6, 7, 0x8888, 0x9999) };
+        mock::set_handler(previous);
+
+        assert_eq!((ret8, ret9), (0, 0x9999));
+        let calls = mock::take_calls();
+        assert_eq!(calls[0].id, 7000);
+        assert_eq!(calls[0].args, [1, 2, 3, 4, 5, 6, 7, 0x8888, 0]);
+        assert_eq!(calls[1].id, 7001);
+        assert_eq!(calls[1].args, [1, 2, 3, 4, 5, 6, 7, 0x8888, 0x9999]);
+    }
+
+    #[test]
+    fn try_from_rejects_pointers_above_4g() {
+        let high: &[u8] = unsafe { core::slice::from_raw_parts(0x1_0000_0000 as *const u8, 0) };
+        assert_eq!(VmAddr::try_from_slice(high), None);
+        assert_eq!(VmAddr::try_from_ptr(0x1_0000_0000usize as *const u8), None);
+
+        let top = 0xFFFF_FFF0usize as *const u32;
+        assert_eq!(
+            VmAddr::try_from_ptr(top).map(VmAddr::split),
+            Some((15, 0x0FFF_FFF0))
+        );
+        let low: &[u8] = unsafe { core::slice::from_raw_parts(0x2000 as *const u8, 0) };
+        assert_eq!(VmAddr::try_from_slice(low), VmAddr::new(0, 0x2000));
+    }
+
+    #[test]
+    fn softmax_saturation_is_propagated() {
+        assert_eq!(check_saturation(0), Ok(()));
+        assert_eq!(check_saturation(1), Err(SdkError::Saturated));
+        assert_eq!(check_saturation(u64::MAX), Err(SdkError::Saturated));
+    }
+
+    #[test]
+    fn attention_validates_lengths() {
+        let q = [0i32; 4];
+        let mut out = [0i32; 4];
+        let null = VmAddr::null();
+
+        assert_eq!(
+            attention_i32(&q[..3], null, null, &mut out, 2, 4, 1 << 16),
+            Err(SdkError::LengthMismatch)
+        );
+        assert_eq!(
+            attention_i32(&q, null, null, &mut out[..2], 2, 4, 1 << 16),
+            Err(SdkError::LengthMismatch)
+        );
+        assert_eq!(
+            attention_i32(&q, null, null, &mut out, ATTENTION_MAX_SEQ + 1, 4, 1 << 16),
+            Err(SdkError::BufferTooSmall)
+        );
+
+        out = [7; 4];
+        assert_eq!(
+            attention_i32(&q, null, null, &mut out, 0, 4, 1 << 16),
+            Ok(())
+        );
+        assert_eq!(out, [0; 4]);
+    }
+
+    #[test]
+    fn causal_mask_hides_later_keys() {
+        let logits = [3 << 16, -(1 << 16), 5 << 16, 0];
+
+        let mut last = logits;
+        apply_mask(&mut last, AttentionMask::Causal { pos: 3 });
+        let mut unmasked = logits;
+        apply_mask(&mut unmasked, AttentionMask::None);
+        assert_eq!(last, unmasked);
+        assert_eq!(last, logits);
+
+        let mut first = logits;
+        apply_mask(&mut first, AttentionMask::Causal { pos: 0 });
+        assert_eq!(first[0], logits[0]);
+        assert!(first[1..].iter().all(|&v| v == ATTENTION_MASKED_LOGIT));
+
+        let mut bits = logits;
+        apply_mask(&mut bits, AttentionMask::Bits(&[0b0101]));
+        const M: i32 = ATTENTION_MASKED_LOGIT;
+        assert_eq!(bits, [logits[0], M, logits[2], M]);
+    }
+
+    #[test]
+    fn attention_bitmask_length_must_cover_seq_len() {
+        let q = [0i32; 2];
+        let mut out = [0i32; 2];
+        let null = VmAddr::null();
+        let mask = AttentionMask::Bits(&[0xFF]);
+        assert_eq!(
+            attention_i32_masked(&q, null, null, &mut out, 9, 2, 1 << 16, mask),
+            Err(SdkError::LengthMismatch)
+        );
+        let mask = AttentionMask::Bits(&[0xFF, 0x01]);
+        assert_eq!(
+            attention_i32_masked(&q, null, null, &mut out, 0, 2, 1 << 16, mask),
+            Err(SdkError::LengthMismatch)
+        );
+        let mask = AttentionMask::Bits(&[]);
+        assert_eq!(
+            attention_i32_masked(&q, null, null, &mut out, 0, 2, 1 << 16, mask),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn rope_cached_validates_tables() {
+        let mut q = [0.0f32; 8];
+        let mut k = [0.0f32; 8];
+        let table = [1.0f32; 4];
+        assert_eq!(
+            rope_cached(&mut q, &mut k, &table[..3], &table, 8, 4),
+            Err(SdkError::LengthMismatch)
+        );
+        assert_eq!(
+            rope_cached(&mut q, &mut k, &table, &table[..3], 8, 4),
+            Err(SdkError::LengthMismatch)
+        );
+        assert_eq!(
+            rope_cached(&mut q[..6], &mut k, &table, &table, 8, 4),
+            Err(SdkError::BufferTooSmall)
+        );
+        assert_eq!(
+            rope_cached(&mut q, &mut k, &table, &table, 8, 3),
+            Err(SdkError::LengthMismatch)
+        );
+    }
+
+    #[test]
+    fn rope_cached_rotates_pairs() {
+        let mut q = [1.0f32, 0.0, 2.0, 3.0];
+        let mut k = [0.0f32, 1.0, 1.0, 1.0];
+        // First pair by 90 degrees, second pair untouched.
+        let cos = [0.0f32, 1.0];
+        let sin = [1.0f32, 0.0];
+        rope_cached(&mut q, &mut k, &cos, &sin, 4, 2).unwrap();
+        assert_eq!(q, [0.0, 1.0, 2.0, 3.0]);
+        assert_eq!(k, [-1.0, 0.0, 1.0, 1.0]);
+    }
+
+    #[test]
+    fn quantize_i8_round_trips_known_vector() {
+        let src = [65_536, -32_768, 0];
+        let mut dst = [0xAAu8; 8];
+        let scale = quantize_i8(&mut dst, &src).unwrap();
+        assert_eq!(scale, 516);
+        assert_eq!(&dst[..3], &[127u8, (-64i8) as u8, 0]);
+        // Padding up to align4(3) = 4, then the scale word.
+        assert_eq!(dst[3], 0);
+        assert_eq!(&dst[4..8], &516i32.to_le_bytes());
+        assert_eq!(dst.len(), align4(src.len()) + 4);
+
+        for (&q, &orig) in dst[..3].iter().zip(&src) {
+            let back = q as i8 as i32 * scale;
+            assert!((back - orig).abs() <= scale, "{} vs {}", back, orig);
+        }
+    }
+
+    #[test]
+    fn prequant_builder_matches_quantize_i8() {
+        let src = [65_536, -32_768, 127 << 10, 0, 3];
+        let mut expected = [0u8; 12];
+        let scale = quantize_i8(&mut expected, &src).unwrap();
+
+        let mut buf = [0xAAu8; 16];
+        let mut pq = Prequant::new(&mut buf, src.len()).unwrap();
+        assert_eq!(pq.len(), 5);
+        for (i, &q) in expected[..5].iter().enumerate() {
+            pq.set(i, q as i8);
+        }
+        let built = pq.finalize(scale);
+        assert_eq!(built, &expected[..]);
+        assert_eq!(built.len(), prequant_len(5));
+
+        mock::take_calls();
+        let mut out = [0i32; 2];
+        assert_eq!(
+            matmul_i8_i8(&mut out, built, 5, VmAddr(0x1000_0000), 1 << 16),
+            Ok(())
+        );
+        let calls = mock::take_calls();
+        assert_eq!(calls[0].args[1], built.as_ptr() as u64);
+        assert_eq!(&calls[0].args[4..6], &[5, 2]);
+        assert_eq!(&buf[12..], &[0xAA; 4]);
+    }
+
+    #[test]
+    fn requantize_i32_to_prequant_stays_close_to_reference() {
+        let src: [i32; 7] = [3 << 16, -(5 << 16) / 2, 1, -1, 0, i32::MAX / 4, -(7 << 14)];
+        let mut dst = [0u8; 12];
+        let scale = requantize_i32_to_prequant(&mut dst, &src).unwrap();
+        assert_eq!(&dst[8..], &scale.to_le_bytes());
+
+        // Reference: real-valued x / scale, rounded to nearest.
+        for (i, &x) in src.iter().enumerate() {
+            let q = dst[i] as i8;
+            let want = (x as f64 / scale as f64).round().clamp(-127.0, 127.0);
+            assert!((q as f64 - want).abs() <= 1.0, "{i}: {q} vs {want}");
+            let err = (q as i64 * scale as i64 - x as i64).abs();
+            assert!(
+                err <= scale as i64 / 2 + 1,
+                "{i}: error {err} > scale {scale} / 2"
+            );
+        }
+
+        // The returned scale is what the next layer sees as its input scale.
+        mock::take_calls();
+        let mut out = [0i32; 3];
+        matmul_i8_i8(&mut out, &dst, src.len(), VmAddr(0x1000_0000), 1 << 16).unwrap();
+        let calls = mock::take_calls();
+        assert_eq!(calls[0].args[1], dst.as_ptr() as u64);
+        assert_eq!(&calls[0].args[4..6], &[7, 3]);
+    }
+
+    #[test]
+    fn prequant_builder_rejects_short_buffers() {
+        let mut buf = [0u8; 7];
+        assert!(matches!(
+            Prequant::new(&mut buf, 4),
+            Err(SdkError::BufferTooSmall)
+        ));
+        let mut buf = [0u8; 8];
+        let pq = Prequant::new(&mut buf, 4).unwrap();
+        assert_eq!(pq.finalize(-2), &[0, 0, 0, 0, 0xFE, 0xFF, 0xFF, 0xFF]);
+        assert!(std::panic::catch_unwind(|| {
+            let mut buf = [0u8; 4];
+            Prequant::new(&mut buf, 0).unwrap().set(0, 1);
+        })
+        .is_err());
+    }
+
+    #[test]
+    fn quantize_i8_checks_buffer_and_zero_input() {
+        let mut short = [0u8; 7];
+        assert_eq!(
+            quantize_i8(&mut short, &[1, 2, 3, 4]),
+            Err(SdkError::BufferTooSmall)
+        );
+        let mut dst = [0xFFu8; 8];
+        assert_eq!(quantize_i8(&mut dst, &[0, 0, 0, 0]), Ok(0));
+        assert_eq!(dst, [0u8; 8]);
+        let mut empty = [0xFFu8; 4];
+        assert_eq!(quantize_i8(&mut empty, &[]), Ok(0));
+        assert_eq!(empty, [0u8; 4]);
+    }
+
+    #[test]
+    fn dequantize_matches_scalar_reference() {
+        let acc = [0, 1, -1, 65_536, -131_072, 12_345, i32::MAX, i32::MIN];
+        for scale_q16 in [1 << 16, 1 << 15, 3 << 16, 77, -(1 << 16)] {
+            let mut out = [0.0f32; 8];
+            dequantize_to_f32(&mut out, &acc, scale_q16).unwrap();
+            for (&got, &v) in out.iter().zip(&acc) {
+                let reference = (v as f64 * scale_q16 as f64 / 65_536.0) as f32;
+                let tolerance = if reference == 0.0 {
+                    0.0
+                } else {
+                    (reference * 1e-6).max(-reference * 1e-6)
+                };
+                let diff = if got > reference {
+                    got - reference
+                } else {
+                    reference - got
+                };
+                assert!(
+                    diff <= tolerance,
+                    "{} * {}: {} vs {}",
+                    v,
+                    scale_q16,
+                    got,
+                    reference
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn dequantize_checks_lengths() {
+        let mut out = [0.0f32; 2];
+        assert_eq!(
+            dequantize_to_f32(&mut out, &[1, 2, 3], 1 << 16),
+            Err(SdkError::LengthMismatch)
+        );
+        assert_eq!(dequantize_to_f32(&mut [], &[], 1 << 16), Ok(()));
+    }
+
+    #[test]
+    fn bump_allocator_rounds_to_eight_bytes() {
+        let base = VmAddr::new(2, 128).unwrap();
+        let mut heap = BumpAllocator::new(base, 64);
+        let a = heap.alloc_bytes(3).unwrap();
+        let b = heap.alloc_slice::<u32>(3).unwrap();
+        let c = heap.alloc_bytes(8).unwrap();
+        assert_eq!(a.raw(), base.raw());
+        assert_eq!(b.raw(), base.raw() + 8);
+        assert_eq!(c.raw(), base.raw() + 24);
+        assert_eq!(heap.remaining(), 32);
+        assert_eq!(heap.alloc_bytes(0), None);
+    }
+
+    #[test]
+    fn bump_allocator_exhausts_without_overflow() {
+        let base = VmAddr::new(0, 0x1000).unwrap();
+        let mut heap = BumpAllocator::new(base, 24);
+        assert!(heap.alloc_bytes(16).is_some());
+        assert_eq!(heap.alloc_bytes(9), None);
+        assert_eq!(heap.alloc_slice::<u64>(2), None);
+        assert!(heap.alloc_bytes(8).is_some());
+        assert_eq!(heap.alloc_bytes(1), None);
+        assert_eq!(heap.remaining(), 0);
+
+        assert_eq!(heap.alloc_slice::<u64>(usize::MAX), None);
+        let mut top = BumpAllocator::new(VmAddr(u64::MAX - 8), usize::MAX);
+        assert!(top.alloc_bytes(8).is_some());
+        assert_eq!(top.alloc_bytes(usize::MAX), None);
+        assert_eq!(top.alloc_bytes(8), None);
+    }
+
+    #[test]
+    fn crc32_check_value() {
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+        assert_eq!(crc32(b""), 0);
+        let data = *b"123456789";
+        assert_eq!(
+            unsafe { crc32_vmaddr(VmAddr::from_slice(&data), data.len()) },
+            0xCBF4_3926
+        );
+    }
+
+    #[test]
+    fn schema_hash_golden() {
+        const VECTOR: &[u8] = b"{\"input_dtype\":\"i32\",\"input_shape\":[4],\
+            \"output_dtype\":\"i32\",\"output_shape\":[1],\"type\":\"vector\"}";
+        const HASH: u32 = schema_hash(VECTOR);
+        assert_eq!(HASH, 0xCA4E_3EBF);
+        assert_eq!(schema_hash(b""), 0x811C_9DC5);
+        assert_eq!(schema_hash(b"a"), 0xE40C_292C);
+    }
+
+    fn header_word(buf: &[u32], offset: usize) -> u32 {
+        buf[offset / 4]
+    }
+
+    #[test]
+    fn output_header_matches_fbh1_layout() {
+        let mut ctrl = [0u32; 16];
+        let mut out = [0xFFFF_FFFFu32; 10];
+        out[8] = u32::from_le_bytes(*b"1234");
+        out[9] = u32::from_le_bytes(*b"5678");
+        let total = unsafe {
+            write_output_header(
+                VmAddr::from_mut_slice(&mut ctrl),
+                VmAddr::from_mut_slice(&mut out),
+                0xBEEF,
+                8,
+                FBH_FLAG_HAS_CRC32,
+            )
+        };
+        assert_eq!(total, 40);
+        assert_eq!(ctrl[CTRL_OUTPUT_LEN / 4], 40);
+        assert_eq!(header_word(&out, FBH_MAGIC), FBH1_MAGIC);
+        assert_eq!(
+            header_word(&out, FBH_VERSION),
+            (FBH1_VERSION as u32) | ((FBH_FLAG_HAS_CRC32 as u32) << 16)
+        );
+        assert_eq!(header_word(&out, FBH_HEADER_LEN), FBH1_HEADER_LEN as u32);
+        assert_eq!(header_word(&out, FBH_SCHEMA_ID), 0xBEEF);
+        assert_eq!(header_word(&out, FBH_PAYLOAD_LEN), 8);
+        // CRC-32/ISO-HDLC of "12345678".
+        assert_eq!(header_word(&out, FBH_CRC32), 0x9AE0_DAAF);
+        assert_eq!(header_word(&out, FBH_SCHEMA_HASH), 0);
+        assert_eq!(out[7], 0);
+        assert_eq!(out[8], u32::from_le_bytes(*b"1234"));
+    }
+
+    #[test]
+    fn output_header_without_crc_zeroes_the_field() {
+        let mut ctrl = [0u32; 16];
+        let mut out = [0xFFFF_FFFFu32; 8];
+        let total = unsafe {
+            write_output_header(
+                VmAddr::from_mut_slice(&mut ctrl),
+                VmAddr::from_mut_slice(&mut out),
+                1,
+                0,
+                0,
+            )
+        };
+        assert_eq!(total, FBH1_HEADER_LEN as u32);
+        assert_eq!(header_word(&out, FBH_VERSION), FBH1_VERSION as u32);
+        assert_eq!(header_word(&out, FBH_CRC32), 0);
+    }
+
+    #[test]
+    fn dot_i32_passes_lengths_and_returns_a0() {
+        mock::set_handler(|id, _| if id == SYS_DOT_I32 { (-7i64) as u64 } else { 0 });
+        let a = [1i32, 2, 3];
+        let b = [4i32, 5, 6];
+        assert_eq!(dot_i32(&a, &b, 16), Ok(-7));
+        assert_eq!(dot_i32(&a, &b[..2], 16), Err(SdkError::LengthMismatch));
+
+        let calls = mock::take_calls();
+        assert_eq!(calls.len(), 1);
+        assert_eq!(calls[0].id, SYS_DOT_I32);
+        assert_eq!(calls[0].args[0], VmAddr::from_slice(&a).raw());
+        assert_eq!(calls[0].args[1], VmAddr::from_slice(&b).raw());
+        assert_eq!(&calls[0].args[2..4], &[3, 16]);
+    }
+
+    #[test]
+    fn wrappers_issue_their_syscall_ids() {
+        mock::take_calls();
+        let mut data = [0i32; 5];
+        softmax_i32(&mut data);
+        let src = [1i32; 5];
+        weighted_sum_i32(&mut data, &src, 3, 16).unwrap();
+        matmul_i8_i32(&mut data[..2], &src, VmAddr(0x1000_0040), 65_536).unwrap();
+        print("hi");
+
+        let calls = mock::take_calls();
+        let ids: Vec<u64> = calls.iter().map(|c| c.id).collect();
+        assert_eq!(
+            ids,
+            [
+                SYS_SOFTMAX_I32,
+                SYS_WEIGHTED_SUM_I32,
+                SYS_MATMUL_I8_I32,
+                SYS_WRITE
+            ]
+        );
+        // SOFTMAX_I32: (ptr, len).
+        assert_eq!(calls[0].args[0], VmAddr::from_slice(&data).raw());
+        assert_eq!(calls[0].args[1], 5);
+        // WEIGHTED_SUM_I32: (out, src, weight, len, shift).
+        assert_eq!(&calls[1].args[2..5], &[3, 5, 16]);
+        // MATMUL_I8_I32: (out, x, w, scale, n, d).
+        assert_eq!(&calls[2].args[2..6], &[0x1000_0040, 65_536, 5, 2]);
+        assert_eq!(&calls[3].args[..3], &[1, "hi".as_ptr() as u64, 2]);
+    }
+
+    #[test]
+    fn matmul_qkv_passes_the_config_pointer() {
+        mock::take_calls();
+        let cfg = MatmulQkvConfig {
+            out_q: 0,
+            out_k: 0,
+            out_v: 0,
+            x_ptr: 0,
+            wq_ptr: 0,
+            wk_ptr: 0,
+            wv_ptr: 0,
+            wq_scale: 0,
+            wk_scale: 0,
+            wv_scale: 0,
+            n: 16,
+            d_q: 8,
+            d_k: 8,
+            d_v: 8,
+            _pad0: 0,
+            state_ptr: 0,
+        };
+        matmul_i8_i8_qkv(&cfg);
+        let calls = mock::take_calls();
+        assert_eq!(calls.len(), 1);
+        assert_eq!(calls[0].id, SYS_MATMUL_I8_I8_QKV);
+        assert_eq!(calls[0].args[0], VmAddr::from_ref(&cfg).raw());
+        assert_eq!(&calls[0].args[1..], &[0; 8]);
+    }
+
+    #[test]
+    fn checked_wrappers_skip_the_syscall_on_bad_lengths() {
+        mock::take_calls();
+        let mut out = [0i32; 3];
+        assert_eq!(
+            weighted_sum_i32(&mut out, &[1, 2], 1, 0),
+            Err(SdkError::LengthMismatch)
+        );
+        let mut state = [Q16Complex { re: 0, im: 0 }; 4];
+        assert_eq!(
+            quantum_op(QOP_H, 0, 0, &mut state),
+            Err(SdkError::BufferTooSmall)
+        );
+        assert!(mock::take_calls().is_empty());
+    }
+
+    #[test]
+    fn quantum_circuit_bell_pair_issues_init_h_cnot_measure() {
+        mock::take_calls();
+        mock::set_handler(|_, args| if args[0] == QOP_MEASURE as u64 { 1 } else { 0 });
+        let mut state = [Q16Complex { re: 0, im: 0 }; QUANTUM_STATE_LEN];
+        let state_ptr = state.as_ptr() as u64;
+        let mut circuit = QuantumCircuit::new(&mut state).unwrap();
+        circuit.seed(77).h(0).unwrap().cnot(0, 1).unwrap();
+        assert_eq!(circuit.measure(1), Ok(1));
+        mock::set_handler(|_, _| 0);
+
+        let ops: Vec<[u64; 4]> = mock::take_calls()
+            .iter()
+            .map(|c| {
+                assert_eq!(c.id, SYS_QUANTUM_OP);
+                [c.args[0], c.args[1], c.args[2], c.args[3]]
+            })
+            .collect();
+        let qop = |op: u32, target: u64, control: u64| [op as u64, target, control, state_ptr];
+        assert_eq!(
+            ops,
+            vec![
+                qop(QOP_INIT, 0, 0),
+                qop(QOP_H, 0, 0),
+                qop(QOP_CNOT, 1, 0),
+                qop(QOP_MEASURE, 1, 77),
+            ]
+        );
+    }
+
+    #[test]
+    fn quantum_circuit_rejects_bad_qubits_without_a_syscall() {
+        let mut state = [Q16Complex { re: 0, im: 0 }; QUANTUM_STATE_LEN];
+        let mut circuit = QuantumCircuit::new(&mut state).unwrap();
+        mock::take_calls();
+        let q = QUANTUM_NUM_QUBITS as u32;
+        assert_eq!(circuit.h(q).err(), Some(SdkError::InvalidQubit));
+        assert_eq!(circuit.cnot(0, q).err(), Some(SdkError::InvalidQubit));
+        assert_eq!(circuit.cnot(2, 2).err(), Some(SdkError::InvalidQubit));
+        assert_eq!(circuit.rx(q, 1.0).err(), Some(SdkError::InvalidQubit));
+        assert_eq!(circuit.measure(q), Err(SdkError::InvalidQubit));
+        assert!(mock::take_calls().is_empty());
+        assert_eq!(circuit.rz(6, 0.5).map(|_| ()), Ok(()));
+        assert_eq!(mock::take_calls()[0].args[..3], [QOP_RZ as u64, 6, 1 << 15]);
+    }
+
+    // Host stand-in for QUANTUM_OP covering INIT and a deterministic MEASURE
+    // (reports the more likely outcome instead of sampling).
+    fn fake_quantum(id: u64, args: &[u64; 9]) -> u64 {
+        assert_eq!(id, SYS_QUANTUM_OP);
+        let state = unsafe {
+            core::slice::from_raw_parts_mut(args[3] as *mut Q16Complex, QUANTUM_STATE_LEN)
+        };
+        match args[0] as u32 {
+            QOP_INIT => {
+                state.fill(Q16Complex { re: 0, im: 0 });
+                state[0].re = 1 << 16;
+                0
+            }
+            QOP_MEASURE => {
+                let (mut p0, mut p1) = (0i64, 0i64);
+                for (i, a) in state.iter().enumerate() {
+                    let p = a.re as i64 * a.re as i64 + a.im as i64 * a.im as i64;
+                    if i >> args[1] & 1 == 1 {
+                        p1 += p;
+                    } else {
+                        p0 += p;
+                    }
+                }
+                (p1 > p0) as u64
+            }
+            op => panic!("unexpected quantum op {op}"),
+        }
+    }
+
+    #[test]
+    fn measure_all_packs_one_bit_per_qubit() {
+        mock::set_handler(fake_quantum);
+        let mut state = [Q16Complex { re: 0, im: 0 }; QUANTUM_STATE_LEN];
+        quantum_op(QOP_INIT, 0, 0, &mut state).unwrap();
+        mock::take_calls();
+        assert_eq!(measure_all(&mut state), Ok(0));
+        let targets: Vec<u64> = mock::take_calls().iter().map(|c| c.args[1]).collect();
+        assert_eq!(targets, (0..QUANTUM_NUM_QUBITS as u64).collect::<Vec<_>>());
+
+        state[0].re = 0;
+        state[0b100_0101].re = 1 << 16;
+        assert_eq!(measure_all(&mut state), Ok(0b100_0101));
+        mock::set_handler(|_, _| 0);
+
+        assert_eq!(measure_all(&mut state[..4]), Err(SdkError::BufferTooSmall));
+    }
+
+    #[test]
+    fn state_probabilities_reads_a_superposition_without_a_syscall() {
+        // (|0000000> - i|0000011>) / sqrt(2), amplitudes in Q16.
+        const HALF_SQRT2_Q16: i32 = 46_341;
+        let mut state = [Q16Complex { re: 0, im: 0 }; QUANTUM_STATE_LEN];
+        state[0].re = HALF_SQRT2_Q16;
+        state[0b11].im = -HALF_SQRT2_Q16;
+        let mut probs = [u32::MAX; QUANTUM_STATE_LEN];
+        mock::take_calls();
+        assert_eq!(state_probabilities(&state, &mut probs), Ok(()));
+        assert!(mock::take_calls().is_empty());
+
+        for (i, &p) in probs.iter().enumerate() {
+            match i {
+                0 | 0b11 => assert!(p.abs_diff(1 << 15) <= 1, "p[{i}] = {p}"),
+                _ => assert_eq!(p, 0, "p[{i}]"),
+            }
+        }
+        let total: u32 = probs.iter().sum();
+        assert!(total.abs_diff(1 << 16) <= 2);
+
+        let mut short = [0u32; QUANTUM_STATE_LEN - 1];
+        assert_eq!(
+            state_probabilities(&state, &mut short),
+            Err(SdkError::LengthMismatch)
+        );
+        assert_eq!(
+            state_probabilities(&state[..4], &mut probs),
+            Err(SdkError::BufferTooSmall)
+        );
+    }
+
+    #[test]
+    fn arb_score_mask_must_cover_every_edge() {
+        assert_eq!(arb_mask_len(0), 0);
+        assert_eq!(arb_mask_len(1), 1);
+        assert_eq!(arb_mask_len(8), 1);
+        assert_eq!(arb_mask_len(9), 2);
+
+        mock::set_handler(|_, _| 3);
+        mock::take_calls();
+        let weights = [1i32, -2, 3];
+        let mut mask = [0u8; 2];
+        assert_eq!(
+            arb_score_into(4, 17, &weights, 10, &mut mask),
+            Err(SdkError::BufferTooSmall)
+        );
+        assert!(mock::take_calls().is_empty());
+
+        assert_eq!(arb_score_into(4, 16, &weights, 10, &mut mask), Ok(3));
+        assert_eq!(arb_score_into(4, 9, &[], 10, &mut mask), Ok(3));
+        mock::set_handler(|_, _| 0);
+        let calls = mock::take_calls();
+        assert_eq!(calls[0].id, SYS_ARB_SCORE);
+        assert_eq!(
+            calls[0].args[..4],
+            [4, weights.as_ptr() as u64, 10, mask.as_ptr() as u64]
+        );
+        assert_eq!(calls[1].args[1], 0);
+    }
+
+    #[test]
+    fn graph_header_round_trips_and_rejects_bad_magic() {
+        assert_eq!(core::mem::size_of::<GraphHeader>(), 16);
+        let mut buf = [0u8; 20];
+        let base = VmAddr::from_mut_slice(&mut buf[1..]);
+        unsafe {
+            assert_eq!(read_graph_header(base), None);
+
+            let header = GraphHeader::new(3, 4);
+            write_graph_header(base, &header);
+            assert_eq!(read_graph_header(base), Some(header));
+            let magic = core::slice::from_raw_parts(base.raw() as *const u8, 4);
+            assert_eq!(magic, b"GRPH");
+
+            let bad = GraphHeader {
+                magic: GRAPH_MAGIC ^ 1,
+                ..header
+            };
+            write_graph_header(base, &bad);
+            assert_eq!(read_graph_header(base), None);
+        }
+    }
+
+    #[test]
+    fn graph_builder_edges_iterate_back() {
+        const DIM: u32 = 3;
+        let mut buf = [0u8; 16 + 2 * graph_edge_stride(DIM)];
+        let base = VmAddr::from_mut_slice(&mut buf);
+
+        let mut builder = unsafe { GraphBuilder::new(base, DIM, 2) };
+        assert_eq!(
+            builder.push_edge(7, &[1, -2]),
+            Err(SdkError::LengthMismatch)
+        );
+        builder.push_edge(7, &[1, -2, 3]).unwrap();
+        builder.push_edge(9, &[-128, 0, 127]).unwrap();
+        assert_eq!(
+            builder.push_edge(11, &[0, 0, 0]),
+            Err(SdkError::BufferTooSmall)
+        );
+        assert_eq!(builder.len(), 2);
+        let header = builder.finish();
+        assert_eq!(header, GraphHeader::new(2, DIM));
+
+        let header = unsafe { read_graph_header(base) }.unwrap();
+        let edges: Vec<(u32, Vec<i8>)> = unsafe { GraphEdges::new(base, &header) }
+            .map(|(target, weights)| (target, weights.to_vec()))
+            .collect();
+        assert_eq!(edges, vec![(7, vec![1, -2, 3]), (9, vec![-128, 0, 127])]);
+    }
+
+    #[test]
+    fn aggregate_into_requires_a_feature_row_per_node() {
+        mock::set_handler(|_, _| 2);
+        mock::take_calls();
+        let mut table = [0i8; 32];
+        let mut features = [0i8; 4 * 8];
+        assert_eq!(
+            aggregate_into(1, &mut table, &mut features, 5, 8),
+            Err(SdkError::BufferTooSmall)
+        );
+        assert_eq!(
+            aggregate_into(1, &mut table, &mut features, u64::MAX, 8),
+            Err(SdkError::BufferTooSmall)
+        );
+        assert!(mock::take_calls().is_empty());
+
+        assert_eq!(aggregate_into(1, &mut table, &mut features, 4, 8), Ok(2));
+        mock::set_handler(|_, _| 0);
+        let calls = mock::take_calls();
+        assert_eq!(calls[0].id, SYS_AGGREGATE);
+        assert_eq!(
+            calls[0].args[..4],
+            [1, table.as_ptr() as u64, features.as_ptr() as u64, 4]
+        );
+    }
+
+    #[cfg(not(feature = "memset-fallback"))]
+    #[test]
+    fn memset_forwards_id_and_args() {
+        mock::take_calls();
+        let mut bytes = [1u8; 5];
+        let mut floats = [1.5f32; 6];
+        assert_eq!(memset_bytes(&mut bytes, 0xAB), Ok(()));
+        assert_eq!(clear_f32(&mut floats), Ok(()));
+        assert_eq!(memset_bytes(&mut [], 0), Ok(()));
+        let calls = mock::take_calls();
+        assert_eq!(calls.len(), 2);
+        assert_eq!(calls[0].id, SYS_MEMSET);
+        assert_eq!(
+            calls[0].args[..3],
+            [VmAddr::from_slice(&bytes).raw(), 0xAB, 5]
+        );
+        assert_eq!(
+            calls[1].args[..3],
+            [VmAddr::from_slice(&floats).raw(), 0, 24]
+        );
+
+        mock::set_handler(|_, _| 1);
+        assert_eq!(memset_bytes(&mut bytes, 0), Err(SdkError::Syscall(1)));
+        mock::set_handler(|_, _| 0);
+    }
+
+    #[cfg(feature = "memset-fallback")]
+    #[test]
+    fn memset_fallback_fills_without_a_syscall() {
+        mock::take_calls();
+        let mut floats = [1.5f32; 4];
+        let mut bytes = [0u8; 3];
+        assert_eq!(clear_f32(&mut floats[..3]), Ok(()));
+        assert_eq!(memset_bytes(&mut bytes[1..], 0xFF), Ok(()));
+        assert!(mock::take_calls().is_empty());
+        assert_eq!(floats, [0.0, 0.0, 0.0, 1.5]);
+        assert_eq!(bytes, [0, 0xFF, 0xFF]);
+    }
+
+    #[test]
+    fn vec_add_sub_i32_are_unit_weighted_sums() {
+        mock::take_calls();
+        let mut dst = [1i32, 2, 3];
+        let mismatch = Err(SdkError::LengthMismatch);
+        assert_eq!(vec_add_i32(&mut dst, &[1, 2]), mismatch);
+        assert_eq!(vec_sub_i32(&mut dst, &[1; 4]), mismatch);
+        assert!(mock::take_calls().is_empty());
+
+        let src = [4i32, 5, 6];
+        vec_add_i32(&mut dst, &src).unwrap();
+        vec_sub_i32(&mut dst, &src).unwrap();
+        let calls = mock::take_calls();
+        assert!(calls.iter().all(|c| c.id == SYS_WEIGHTED_SUM_I32));
+        let (dst_ptr, src_ptr) = (dst.as_ptr() as u64, src.as_ptr() as u64);
+        assert_eq!(calls[0].args[..5], [dst_ptr, src_ptr, 1, 3, 0]);
+        assert_eq!(calls[1].args[..5], [dst_ptr, src_ptr, -1i64 as u64, 3, 0]);
+    }
+
+    // Host stand-ins: WEIGHTED_SUM_I32 as documented, and an RMSNORM_I32
+    // substitute (divide by the max magnitude, in Q16) that reads all of
+    // `x` before writing `out`, as the in-place call requires.
+    fn fake_residual_ops(id: u64, args: &[u64; 9]) -> u64 {
+        match id {
+            SYS_WEIGHTED_SUM_I32 => {
+                let len = args[3] as usize;
+                let out = unsafe { core::slice::from_raw_parts_mut(args[0] as *mut i32, len) };
+                let src = unsafe { core::slice::from_raw_parts(args[1] as *const i32, len) };
+                for (o, &v) in out.iter_mut().zip(src) {
+                    *o += ((args[2] as i32 as i64 * v as i64) >> args[4]) as i32;
+                }
+            }
+            SYS_RMSNORM_I32 => {
+                let len = args[3] as usize;
+                let x = unsafe { core::slice::from_raw_parts(args[1] as *const i32, len) }.to_vec();
+                let scale = x
+                    .iter()
+                    .map(|v| v.unsigned_abs() as i64)
+                    .max()
+                    .unwrap_or(0)
+                    .max(1);
+                let out = unsafe { core::slice::from_raw_parts_mut(args[0] as *mut i32, len) };
+                for (o, &v) in out.iter_mut().zip(&x) {
+                    *o = ((v as i64) << 16).wrapping_div(scale) as i32;
+                }
+            }
+            _ => panic!("unexpected syscall {id}"),
+        }
+        0
+    }
+
+    #[test]
+    fn residual_rmsnorm_matches_add_then_norm() {
+        mock::take_calls();
+        let mut x = [3i32, -8, 5];
+        let mismatch = Err(SdkError::LengthMismatch);
+        assert_eq!(
+            residual_rmsnorm_i32(&mut x, &[1, 2], VmAddr::null()),
+            mismatch
+        );
+        assert!(mock::take_calls().is_empty());
+
+        mock::set_handler(fake_residual_ops);
+        let residual = [1i32, 4, -5];
+        let weight = VmAddr(0x1000);
+        let mut summed = x;
+        vec_add_i32(&mut summed, &residual).unwrap();
+        let mut expected = [0i32; 3];
+        rmsnorm_i32_checked(&mut expected, &summed, weight).unwrap();
+
+        residual_rmsnorm_i32(&mut x, &residual, weight).unwrap();
+        mock::set_handler(|_, _| 0);
+        assert_eq!(x, expected);
+        let calls = mock::take_calls();
+        let norm = calls.last().unwrap();
+        assert_eq!(norm.id, SYS_RMSNORM_I32);
+        assert_eq!(
+            norm.args[..4],
+            [x.as_ptr() as u64, x.as_ptr() as u64, 0x1000, 3]
+        );
+    }
+
+    #[test]
+    fn q8_flags_builder_sets_only_masked_bits() {
+        assert_eq!(Q8Flags::NONE.bits(), 0);
+        assert_eq!(Q8Flags::NONE.prequant().bits(), 1 << 63);
+        assert_eq!(Q8Flags::NONE.tensor_scale().bits(), 1 << 62);
+        let both = Q8Flags::NONE.prequant().tensor_scale();
+        assert_eq!(both.bits(), Q8_FLAG_MASK);
+        assert_eq!(both, Q8Flags::NONE.tensor_scale().prequant());
+        assert_eq!(Q8Flags::from_bits_truncate(u64::MAX), both);
+        assert_eq!(Q8Flags::from_bits_truncate(0xFF), Q8Flags::NONE);
+
+        mock::take_calls();
+        let mut out = [0f32; 2];
+        let null = VmAddr::null();
+        matmul_q8(&mut out, null, null, null, 8, both).unwrap();
+        matmul_q8_raw(&mut out, null, null, null, 8, u64::MAX).unwrap();
+        let mut state = RowState::new(0);
+        let flags = Q8Flags::NONE.prequant();
+        matmul_q8_partial(&mut out, null, null, null, 8, flags, &mut state).unwrap();
+        let n_flags: Vec<u64> = mock::take_calls().iter().map(|c| c.args[4]).collect();
+        assert_eq!(n_flags, [8 | Q8_FLAG_MASK, 8 | Q8_FLAG_MASK, 8 | (1 << 63)]);
+    }
+
+    #[test]
+    fn yield_until_yields_between_predicate_checks() {
+        mock::take_calls();
+        let mut state = YieldState { flag: 0 };
+        let state_ptr = VmAddr::from_mut(&mut state).raw();
+        let mut checks = 0;
+        yield_until(&mut state, || {
+            checks += 1;
+            checks == 4
+        });
+        assert_eq!(checks, 4);
+        yield_until(&mut state, || true);
+
+        let calls = mock::take_calls();
+        assert_eq!(calls.len(), 3);
+        assert!(calls
+            .iter()
+            .all(|c| c.id == SYS_YIELD && c.args[0] == state_ptr));
+    }
+
+    #[test]
+    fn write_all_retries_short_writes() {
+        mock::take_calls();
+        mock::set_handler(|id, args| if id == SYS_WRITE { args[2].min(3) } else { 0 });
+        let msg = b"partial";
+        assert_eq!(write(msg), 3);
+        assert_eq!(write_all(msg), Ok(()));
+        mock::set_handler(|_, _| 0);
+        assert_eq!(write_all(msg), Err(SdkError::WriteStalled));
+        assert_eq!(write_all(b""), Ok(()));
+
+        let calls = mock::take_calls();
+        let chunks: Vec<[u64; 3]> = calls
+            .iter()
+            .map(|c| [c.args[0], c.args[1], c.args[2]])
+            .collect();
+        let base = msg.as_ptr() as u64;
+        assert_eq!(
+            chunks,
+            [
+                [1, base, 7],
+                [1, base, 7],
+                [1, base + 3, 4],
+                [1, base + 6, 1],
+                [1, base, 7],
+            ]
+        );
+    }
+
+    #[test]
+    fn empty_vector_ops_skip_the_syscall() {
+        mock::take_calls();
+        assert_eq!(dot_i32(&[], &[], 16), Ok(0));
+        assert_eq!(accum_checked(&mut [], &[]), Ok(0));
+        assert_eq!(weighted_sum_i32(&mut [], &[], 3, 16), Ok(()));
+        assert_eq!(vec_sub_i32(&mut [], &[]), Ok(()));
+        assert_eq!(silu_mul_i32_checked(&mut [], &[]), Ok(0));
+        assert_eq!(vec_add_i8(&mut [], &[]), Ok(()));
+        assert!(mock::take_calls().is_empty());
+        // Lengths are still checked first.
+        assert_eq!(dot_i32(&[], &[1], 0), Err(SdkError::LengthMismatch));
+    }
+
+    #[test]
+    fn sdk_error_displays_a_message() {
+        assert_eq!(
+            SdkError::BufferTooSmall.to_string(),
+            "buffer too small for the requested length"
+        );
+        assert_eq!(
+            SdkError::LengthMismatch.to_string(),
+            "buffer lengths do not match"
+        );
+        assert_eq!(
+            SdkError::Syscall(3).to_string(),
+            "syscall returned status 3"
+        );
+        let boxed: Box<dyn std::error::Error> = Box::new(SdkError::LengthTooLarge);
+        assert_eq!(boxed.to_string(), "length overlaps the syscall's flag bits");
+    }
+
+    #[test]
+    fn matmul_q8_rejects_n_reaching_the_flag_bits() {
+        let max_n = (1usize << 62) - 1;
+        assert_eq!(q8_n_flags(max_n, Q8_FLAG_MASK), Ok(u64::MAX));
+        assert_eq!(q8_n_flags(1 << 62, 0), Err(SdkError::LengthTooLarge));
+        assert_eq!(q8_n_flags(1 << 63, 0), Err(SdkError::LengthTooLarge));
+
+        mock::take_calls();
+        let mut out = [0f32; 1];
+        let null = VmAddr::null();
+        let too_large = Err(SdkError::LengthTooLarge);
+        assert_eq!(
+            matmul_q8(&mut out, null, null, null, 1 << 62, Q8Flags::NONE),
+            too_large
+        );
+        let mut state = RowState::new(0);
+        assert_eq!(
+            matmul_q8_partial_raw(&mut out, null, null, null, 1 << 62, 0, &mut state),
+            too_large
+        );
+        assert!(mock::take_calls().is_empty());
+    }
+
+    #[test]
+    fn angle_control_encoding_round_trips() {
+        use core::f32::consts::{FRAC_PI_2, PI, TAU};
+        const Q16_ANGLE_STEP: f32 = 1.0 / 65_536.0;
+        for radians in [PI, FRAC_PI_2, TAU, -FRAC_PI_2, 0.0] {
+            let back = control_to_angle(angle_to_control(radians));
+            let err = (back - radians).abs();
+            assert!(err <= Q16_ANGLE_STEP, "{radians} -> {back}");
+        }
+        assert_eq!(angle_to_control(PI), 205_887);
+        assert_eq!(angle_to_control(-FRAC_PI_2), 0xFFFE_6DE0);
+        assert_eq!(angle_to_control(1.0), 1 << 16);
+    }
+
+    // Host stand-in for ARGMAX_I32_PARTIAL: scans the whole slice in one call.
+    fn fake_argmax_i32(id: u64, args: &[u64; 9]) -> u64 {
+        assert_eq!(id, SYS_ARGMAX_I32_PARTIAL);
+        let data = unsafe { core::slice::from_raw_parts(args[0] as *const i32, args[1] as usize) };
+        let state = unsafe { &mut *(args[2] as *mut ArgmaxI32State) };
+        for (i, &v) in data.iter().enumerate() {
+            if i == 0 || v > state.max_val {
+                state.max_idx = i as u32;
+                state.max_val = v;
+            }
+        }
+        state.cursor = data.len() as u32;
+        state.max_idx as u64
+    }
+
+    #[test]
+    fn argmax_i32_full_returns_index_and_value() {
+        let prev = mock::set_handler(fake_argmax_i32);
+        assert_eq!(argmax_i32_full(&[-5, 9, 3, 9]), Ok((1, 9)));
+        assert_eq!(argmax_i32_full(&[-5, -2]), Ok((1, -2)));
+        assert_eq!(argmax_i32_full(&[]), Err(SdkError::BufferTooSmall));
+        mock::set_handler(prev);
+
+        let calls = mock::take_calls();
+        assert_eq!(calls.len(), 2);
+        // max_per_call = 0: one call covers the whole slice.
+        assert_eq!(calls[0].args[1], 4);
+    }
+
+    #[test]
+    fn argmax_i32_topk_orders_by_value_then_index() {
+        let data = [3, 7, -1, 7, 5, 0];
+        let mut top = [(u32::MAX, 0i32); 3];
+        assert_eq!(argmax_i32_topk(&data, &mut top), 3);
+        assert_eq!(top, [(1, 7), (3, 7), (4, 5)]);
+
+        let mut all = [(u32::MAX, 0i32); 8];
+        assert_eq!(argmax_i32_topk(&data, &mut all), data.len());
+        assert_eq!(
+            &all[..6],
+            &[(1, 7), (3, 7), (4, 5), (0, 3), (5, 0), (2, -1)]
+        );
+        assert_eq!(all[6], (u32::MAX, 0));
+
+        let mut one = [(0u32, 0i32); 1];
+        assert_eq!(argmax_i32_topk(&[i32::MIN, i32::MIN], &mut one), 1);
+        assert_eq!(one, [(0, i32::MIN)]);
+        assert_eq!(argmax_i32_topk(&data, &mut []), 0);
+        assert_eq!(argmax_i32_topk(&[], &mut top), 0);
+    }
+
+    #[test]
+    fn sample_i32_is_argmax_at_zero_temperature() {
+        mock::take_calls();
+        let mut rng = 7;
+        let mut logits = [3 << 16, -1 << 16, 5 << 16, 5 << 16];
+        assert_eq!(sample_i32(&mut logits, 0, &mut rng), 2);
+        assert_eq!(sample_i32(&mut logits, -Q16::ONE.raw(), &mut rng), 2);
+        assert_eq!(sample_i32(&mut [], Q16::ONE.raw(), &mut rng), 0);
+        assert_eq!(rng, 7);
+        assert!(mock::take_calls().is_empty());
+    }
+
+    #[test]
+    fn sample_i32_is_reproducible_for_a_seed() {
+        // The mock SOFTMAX_I32 leaves the data alone, so at temperature 1.0
+        // these values are the Q16 probabilities drawn from.
+        let probs = [10_000, 30_000, 0, 25_536];
+        let draw = |seed: u64| {
+            let mut rng = seed;
+            let mut logits = probs;
+            let token = sample_i32(&mut logits, Q16::ONE.raw(), &mut rng);
+            (token, rng)
+        };
+        for seed in [0, 1, 42, u64::MAX] {
+            assert_eq!(draw(seed), draw(seed));
+            assert_ne!(draw(seed).1, seed);
+        }
+        let tokens: Vec<u32> = (1..64).map(|seed| draw(seed).0).collect();
+        assert!(tokens.iter().all(|&t| t != 2));
+        for t in [0, 1, 3] {
+            assert!(tokens.contains(&t), "token {t} never drawn");
+        }
+        let ids: Vec<u64> = mock::take_calls().iter().map(|c| c.id).collect();
+        assert!(ids.iter().all(|&id| id == SYS_SOFTMAX_I32));
+    }
+
+    #[test]
+    fn i8_i8_argmax_state_fields_match_word_offsets() {
+        let mut state = I8I8ArgmaxState::new();
+        assert_eq!(state, I8I8ArgmaxState::default());
+        {
+            let words = state.as_words_mut();
+            assert!(words.iter().all(|&w| w == 0));
+            words[I8_I8_ARGMAX_MAX_IDX_WORD] = 42;
+            words[I8_I8_ARGMAX_MAX_VAL_WORD] = (-9i32) as u32;
+            words[I8_I8_ARGMAX_TOPK1_WORD] = 4;
+            words[I8_I8_ARGMAX_FILLED1_WORD] = 3;
+            words[I8_I8_ARGMAX_MIN_VAL1_WORD] = (-100i32) as u32;
+            words[I8_I8_ARGMAX_TOPK2_WORD] = 16;
+            words[I8_I8_ARGMAX_MIN_POS2_WORD] = 7;
+            words[I8_I8_ARGMAX_FULL_MAX_WORD] = 64;
+        }
+        assert_eq!((state.max_idx, state.max_val), (42, -9));
+        assert_eq!(state.best(), (42, -9));
+        assert_eq!((state.topk1, state.filled1, state.min_val1), (4, 3, -100));
+        assert_eq!((state.topk2, state.min_pos2), (16, 7));
+        assert_eq!(state.full_max, 64);
+
+        state.max_rows_per_call = 8;
+        assert_eq!(state.as_words_mut()[I8_I8_ARGMAX_MAX_ROWS_WORD], 8);
+    }
+
+    #[test]
+    fn i8_i8_argmax_best_decodes_max_words() {
+        let state = I8I8ArgmaxState {
+            max_idx: 17,
+            max_val: -3,
+            topk1: 4,
+            ..I8I8ArgmaxState::new()
+        };
+        assert_eq!(state.best(), (17, -3));
+        assert_eq!(I8I8ArgmaxState::new().best(), (0, 0));
+    }
+
+    /// Header with `topk2 = 2`, `topk1 = 3`, followed by the four arrays.
+    fn top2_state(filled1: u32, rows: [u32; 3], scores: [i32; 3]) -> Vec<u32> {
+        let mut words = vec![0u32; i8_i8_argmax_state_words(2, 3)];
+        words[I8_I8_ARGMAX_MAX_IDX_WORD] = 42;
+        words[I8_I8_ARGMAX_MAX_VAL_WORD] = 30;
+        words[I8_I8_ARGMAX_TOPK2_WORD] = 2;
+        words[I8_I8_ARGMAX_TOPK1_WORD] = 3;
+        words[I8_I8_ARGMAX_FILLED1_WORD] = filled1;
+        // Shortlist 2 must not be mistaken for shortlist 1.
+        words[18..22].copy_from_slice(&[99, 98, 1000, 1000]);
+        words[22..25].copy_from_slice(&rows);
+        for (slot, score) in words[25..28].iter_mut().zip(scores) {
+            *slot = score as u32;
+        }
+        words
+    }
+
+    #[test]
+    fn i8_i8_argmax_top2_reads_shortlist_1() {
+        assert_eq!(i8_i8_argmax_state_words(2, 3), 28);
+        let words = top2_state(3, [5, 42, 7], [-12, 30, -4]);
+        assert_eq!(
+            i8_i8_argmax_top2(&words),
+            Ok(I8I8Top2 {
+                top1: (42, 30),
+                top2: Some((7, -4)),
+            })
+        );
+
+        // Only filled entries count.
+        let words = top2_state(2, [5, 42, 7], [-12, 30, 25]);
+        assert_eq!(i8_i8_argmax_top2(&words).unwrap().top2, Some((5, -12)));
+        let words = top2_state(1, [42, 5, 7], [30, 25, 20]);
+        assert_eq!(i8_i8_argmax_top2(&words).unwrap().top2, None);
+
+        // topk1 < 2: header only, no runner-up.
+        let mut header = [0u32; I8_I8_ARGMAX_HEADER_WORDS];
+        header[I8_I8_ARGMAX_MAX_IDX_WORD] = 3;
+        header[I8_I8_ARGMAX_TOPK1_WORD] = 1;
+        assert_eq!(
+            i8_i8_argmax_top2(&header),
+            Ok(I8I8Top2 {
+                top1: (3, 0),
+                top2: None,
+            })
+        );
+
+        let words = top2_state(3, [5, 42, 7], [1, 2, 3]);
+        assert_eq!(
+            i8_i8_argmax_top2(&words[..27]),
+            Err(SdkError::BufferTooSmall)
+        );
+        assert_eq!(
+            i8_i8_argmax_top2(&words[..17]),
+            Err(SdkError::BufferTooSmall)
+        );
+    }
+
+    #[test]
+    fn matmul_i8_i8_argmax_top2_partial_decodes_after_the_call() {
+        fn finish_scan(_: u64, args: &[u64; 9]) -> u64 {
+            let state = args[5] as *mut u32;
+            unsafe {
+                *state.add(I8_I8_ARGMAX_MAX_IDX_WORD) = 42;
+                *state.add(I8_I8_ARGMAX_MAX_VAL_WORD) = 30;
+                *state.add(I8_I8_ARGMAX_FILLED1_WORD) = 3;
+            }
+            42
+        }
+        let mut words = top2_state(0, [5, 42, 7], [-12, 30, -4]);
+        words[I8_I8_ARGMAX_MAX_IDX_WORD] = 0;
+        words[I8_I8_ARGMAX_MAX_VAL_WORD] = 0;
+        let prequant = [0u8; 8];
+        let previous = mock::set_handler(finish_scan);
+        let got = matmul_i8_i8_argmax_top2_partial(
+            &prequant,
+            4,
+            VmAddr(0x1000_0000),
+            1 << 16,
+            3,
+            &mut words,
+        );
+        mock::set_handler(previous);
+        assert_eq!(
+            got,
+            Ok(I8I8Top2 {
+                top1: (42, 30),
+                top2: Some((7, -4)),
+            })
+        );
+        assert_eq!(mock::take_calls()[0].id, SYS_MATMUL_I8_I8_ARGMAX);
+    }
+
+    #[test]
+    fn exit_records_the_code_and_unwinds() {
+        let err = std::panic::catch_unwind(|| exit(5)).unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<mock::GuestExit>(),
+            Some(&mock::GuestExit(5))
+        );
+        assert_eq!(mock::take_calls()[0].args[0], 5);
+    }
+
+    #[test]
+    fn report_panic_sets_status_before_exiting() {
+        let mut ctrl = [0u32; 8];
+        let base = VmAddr::from_mut_slice(&mut ctrl);
+        mock::take_calls();
+        let err =
+            std::panic::catch_unwind(|| unsafe { report_panic(base, STATUS_PANIC) }).unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<mock::GuestExit>(),
+            Some(&mock::GuestExit(STATUS_PANIC as i64))
+        );
+        assert_eq!(unsafe { ctrl_read_u32(base, CTRL_STATUS) }, STATUS_PANIC);
+        let calls = mock::take_calls();
+        assert_eq!((calls[0].id, calls[0].args[0]), (SYS_EXIT, 8));
+    }
+
+    #[test]
+    fn ctrl_accessors_use_ctrl_offsets() {
+        let mut ctrl = [0u32; 8];
+        ctrl[CTRL_MAGIC / 4] = FBM1_MAGIC;
+        ctrl[CTRL_INPUT_LEN / 4] = 64;
+        let base = VmAddr::from_mut_slice(&mut ctrl);
+        unsafe {
+            assert_eq!(ctrl_read_u32(base, CTRL_MAGIC), FBM1_MAGIC);
+            assert_eq!(ctrl_read_u32(base, CTRL_INPUT_LEN), 64);
+            ctrl_write_u32(base, CTRL_STATUS, 5);
+            ctrl_write_u32(base, CTRL_OUTPUT_LEN, 16);
+        }
+        assert_eq!(ctrl[CTRL_STATUS / 4], 5);
+        assert_eq!(ctrl[CTRL_OUTPUT_LEN / 4], 16);
+    }
+
+    #[test]
+    fn input_header_round_trip_and_rejections() {
+        let mut ctrl = [0u32; 16];
+        // One spare word so the 44-byte length below stays in bounds.
+        let mut buf = [0u32; 11];
+        buf[8] = u32::from_le_bytes(*b"1234");
+        buf[9] = u32::from_le_bytes(*b"5678");
+        let input = VmAddr::from_mut_slice(&mut buf);
+        unsafe {
+            write_output_header(
+                VmAddr::from_mut_slice(&mut ctrl),
+                input,
+                7,
+                8,
+                FBH_FLAG_HAS_CRC32,
+            )
+        };
+
+        let payload = VmAddr(input.raw() + FBH1_HEADER_LEN as u64);
+        assert_eq!(
+            unsafe { parse_input_header(input, 40, 7, 0) },
+            Ok((payload, 8))
+        );
+        assert_eq!(
+            unsafe { parse_input_header(input, 40, 8, 0) },
+            Err(InputHeaderError::Schema)
+        );
+        assert_eq!(
+            unsafe { parse_input_header(input, 44, 7, 0) },
+            Err(InputHeaderError::Header)
+        );
+        // Too short for a header: passed through as a raw payload.
+        assert_eq!(
+            unsafe { parse_input_header(input, 16, 7, 0) },
+            Ok((input, 16))
+        );
+
+        buf[9] ^= 1;
+        let input = VmAddr::from_slice(&buf);
+        assert_eq!(
+            unsafe { parse_input_header(input, 40, 7, 0) },
+            Err(InputHeaderError::Header)
+        );
+
+        buf[FBH_MAGIC / 4] = 0;
+        let input = VmAddr::from_slice(&buf);
+        assert_eq!(
+            unsafe { parse_input_header(input, 40, 7, 0) },
+            Ok((input, 40))
+        );
+    }
+
+    #[test]
+    fn schema_hash_check_follows_the_header_flag() {
+        let mut ctrl = [0u32; 16];
+        let mut buf = [0u32; 10];
+        let input = VmAddr::from_mut_slice(&mut buf);
+        unsafe { write_output_header(VmAddr::from_mut_slice(&mut ctrl), input, 7, 8, 0) };
+        let payload = VmAddr(input.raw() + FBH1_HEADER_LEN as u64);
+
+        // No hash in the header: only the schema id is checked, whatever the
+        // guest expects.
+        let input = VmAddr::from_slice(&buf);
+        for expected in [0, 0xA66A_3CC0] {
+            assert_eq!(
+                unsafe { parse_input_header(input, 40, 7, expected) },
+                Ok((payload, 8))
+            );
+        }
+
+        buf[FBH_FLAGS / 4] |= (FBH_FLAG_HAS_SCHEMA_HASH as u32) << 16;
+        buf[FBH_SCHEMA_HASH / 4] = 0xA66A_3CC0;
+        let input = VmAddr::from_slice(&buf);
+        assert_eq!(
+            unsafe { parse_input_header(input, 40, 7, 0xA66A_3CC0) },
+            Ok((payload, 8))
+        );
+        assert_eq!(
+            unsafe { parse_input_header(input, 40, 7, 0xA66A_3CC1) },
+            Err(InputHeaderError::Schema)
+        );
+        // A guest built with EXPECTED_SCHEMA_HASH = 0 rejects hashed input.
+        assert_eq!(
+            unsafe { parse_input_header(input, 40, 7, 0) },
+            Err(InputHeaderError::Schema)
+        );
+    }
+
+    #[test]
+    fn header_access_modes_agree() {
+        let mut ctrl = [0u32; 16];
+        let mut buf = [0u32; 10];
+        buf[8] = 0x0403_0201;
+        buf[9] = 0x0807_0605;
+        let input = VmAddr::from_mut_slice(&mut buf);
+        unsafe {
+            write_output_header(
+                VmAddr::from_mut_slice(&mut ctrl),
+                input,
+                9,
+                8,
+                FBH_FLAG_HAS_CRC32,
+            )
+        };
+        let payload = VmAddr(input.raw() + FBH1_HEADER_LEN as u64);
+        for access in [HeaderAccess::Volatile, HeaderAccess::Plain] {
+            assert_eq!(
+                unsafe { parse_input_header_with(access, input, 40, 9, 0) },
+                Ok((payload, 8))
+            );
+            assert_eq!(
+                unsafe { parse_input_header_with(access, input, 40, 1, 0) },
+                Err(InputHeaderError::Schema)
+            );
+        }
+
+        // Plain loads also accept a header that is not word aligned.
+        let mut bytes = [0u8; 41];
+        for (i, word) in buf.iter().enumerate() {
+            bytes[1 + i * 4..5 + i * 4].copy_from_slice(&word.to_le_bytes());
+        }
+        let unaligned = VmAddr(bytes.as_ptr() as u64 + 1);
+        let payload = VmAddr(unaligned.raw() + FBH1_HEADER_LEN as u64);
+        assert_eq!(
+            unsafe { parse_input_header_with(HeaderAccess::Plain, unaligned, 40, 9, 0) },
+            Ok((payload, 8))
+        );
+        bytes[40] ^= 1;
+        let unaligned = VmAddr(bytes.as_ptr() as u64 + 1);
+        assert_eq!(
+            unsafe { parse_input_header_with(HeaderAccess::Plain, unaligned, 40, 9, 0) },
+            Err(InputHeaderError::Header)
+        );
+    }
+
+    #[test]
+    fn parse_fbh1_reports_guest_statuses() {
+        let mut ctrl = [0u32; 16];
+        let mut buf = [0u32; 10];
+        buf[8] = 0x0403_0201;
+        buf[9] = 0x0807_0605;
+        let input = VmAddr::from_mut_slice(&mut buf);
+        unsafe {
+            write_output_header(
+                VmAddr::from_mut_slice(&mut ctrl),
+                input,
+                3,
+                8,
+                FBH_FLAG_HAS_CRC32,
+            )
+        };
+        let payload = VmAddr(input.raw() + FBH1_HEADER_LEN as u64);
+        assert_eq!(unsafe { parse_fbh1(input, 40, 3, 0) }, Ok((payload, 8)));
+        // Schema mismatch, by id and by an unexpected hash.
+        assert_eq!(
+            unsafe { parse_fbh1(input, 40, 4, 0) },
+            Err(status::ERR_SCHEMA)
+        );
+        buf[FBH_FLAGS / 4] |= (FBH_FLAG_HAS_SCHEMA_HASH as u32) << 16;
+        let input = VmAddr::from_mut_slice(&mut buf);
+        assert_eq!(
+            unsafe { parse_fbh1(input, 40, 3, 0) },
+            Err(status::ERR_SCHEMA)
+        );
+        buf[FBH_FLAGS / 4] &= !((FBH_FLAG_HAS_SCHEMA_HASH as u32) << 16);
+
+        // CRC mismatch.
+        buf[8] ^= 0x100;
+        let input = VmAddr::from_mut_slice(&mut buf);
+        assert_eq!(
+            unsafe { parse_fbh1(input, 40, 3, 0) },
+            Err(status::ERR_INPUT_HEADER)
+        );
+
+        // No header: the input is the payload.
+        buf[FBH_MAGIC / 4] = 0;
+        let input = VmAddr::from_slice(&buf);
+        assert_eq!(unsafe { parse_fbh1(input, 40, 3, 0) }, Ok((input, 40)));
+        assert_eq!(unsafe { parse_fbh1(input, 8, 3, 0) }, Ok((input, 8)));
+    }
+
+    #[cfg(feature = "layernorm")]
+    #[test]
+    fn layernorm_checks_lengths() {
+        let x = [1 << 16; 4];
+        let mut out = [0i32; 3];
+        assert_eq!(
+            layernorm_i32(&mut out, &x, VmAddr::null(), VmAddr::null()),
+            Err(SdkError::LengthMismatch)
+        );
+        let mut out = [0i32; 4];
+        assert_eq!(
+            layernorm_i32_ref(&mut out, &x, &[1 << 16; 3], &[0; 4]),
+            Err(SdkError::LengthMismatch)
+        );
+        assert_eq!(
+            layernorm_i32_ref(&mut out, &x, &[1 << 16; 4], &[0; 5]),
+            Err(SdkError::LengthMismatch)
+        );
+    }
+
+    #[cfg(feature = "layernorm")]
+    #[test]
+    fn layernorm_ref_matches_float_math() {
+        let x = [-3 << 16, 1 << 15, 2 << 16, 7 << 16, -(1 << 14), 5 << 16];
+        let weight = [1 << 16, 1 << 15, 2 << 16, 3 << 16, -(1 << 16), 1 << 16];
+        let bias = [0, 1 << 16, -(1 << 16), 0, 1 << 14, 3 << 16];
+        let mut out = [0i32; 6];
+        layernorm_i32_ref(&mut out, &x, &weight, &bias).unwrap();
+
+        let q = 65_536.0f64;
+        let n = x.len() as f64;
+        let mean = x.iter().map(|&v| v as f64 / q).sum::<f64>() / n;
+        let var = x
+            .iter()
+            .map(|&v| (v as f64 / q - mean) * (v as f64 / q - mean))
+            .sum::<f64>()
+            / n;
+        let denom = var + 1.0 / q;
+        let mut std = denom;
+        for _ in 0..64 {
+            std = 0.5 * (std + denom / std);
+        }
+        for i in 0..x.len() {
+            let norm = (x[i] as f64 / q - mean) / std;
+            let expected = (norm * (weight[i] as f64 / q) + bias[i] as f64 / q) * q;
+            let diff = out[i] as f64 - expected;
+            assert!(
+                diff < 64.0 && diff > -64.0,
+                "{}: {} vs {}",
+                i,
+                out[i],
+                expected
+            );
+        }
+
+        layernorm_i32_ref(&mut out, &[9 << 16; 6], &weight, &bias).unwrap();
+        assert_eq!(out, bias);
+    }
+
+    #[test]
+    fn activation_types_are_distinct() {
+        let acts = [ACT_RELU, ACT_SIGMOID, ACT_GELU, ACT_TANH];
+        for (i, a) in acts.iter().enumerate() {
+            assert_eq!(*a, i as i32);
+            assert!(acts[i + 1..].iter().all(|b| b != a));
+        }
+    }
+
+    #[test]
+    fn vmaddr_accessors_are_const() {
+        const ADDR: VmAddr = multi_segment_base(2, 1);
+        const SEGMENT: u8 = ADDR.segment();
+        const OFFSET: u32 = ADDR.offset();
+        assert_eq!((SEGMENT, OFFSET), (3, SEGMENT_HEADER_SIZE));
+    }
+}
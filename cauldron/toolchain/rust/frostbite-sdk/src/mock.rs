@@ -0,0 +1,61 @@
+//! Syscall mock and recorder for host builds (feature `host`).
+//!
+//! With `host` enabled the crate builds for the host target: [`crate::raw`]
+//! forwards every syscall to a per-thread handler here instead of executing
+//! `ecall`, and each call is recorded so tests can assert on the exact
+//! arguments a wrapper passes to the VM.
+//!
+//! ```
+//! use frostbite_sdk::{mock, VmAddr, SYS_MATMUL_I8_I32};
+//!
+//! mock::set_handler(|_, _| 0);
+//! let x = [1i32; 4];
+//! let mut out = [0i32; 2];
+//! frostbite_sdk::matmul_i8_i32(&mut out, &x, VmAddr(0x1000_0000), 65_536).unwrap();
+//! let calls = mock::take_calls();
+//! assert_eq!(calls[0].id, SYS_MATMUL_I8_I32);
+//! assert_eq!(&calls[0].args[3..6], &[65_536, 4, 2]);
+//! ```
+
+use std::cell::{Cell, RefCell};
+use std::vec::Vec;
+
+/// Handles one syscall: `(id, a0..a6 then the two stack-spilled arguments)`
+/// to the value returned in `a0`.
+pub type EcallHandler = fn(u64, &[u64; 9]) -> u64;
+
+/// One recorded syscall. Unused argument slots are zero.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RecordedCall {
+    pub id: u64,
+    pub args: [u64; 9],
+}
+
+/// Panic payload raised by [`crate::exit`] on the host.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct GuestExit(pub i64);
+
+fn return_zero(_: u64, _: &[u64; 9]) -> u64 {
+    0
+}
+
+std::thread_local! {
+    static HANDLER: Cell<EcallHandler> = Cell::new(return_zero);
+    static CALLS: RefCell<Vec<RecordedCall>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Install `handler` for syscalls made on this thread and return the previous
+/// one. The default handler returns 0 for everything.
+pub fn set_handler(handler: EcallHandler) -> EcallHandler {
+    HANDLER.with(|cell| cell.replace(handler))
+}
+
+/// Drain the syscalls recorded on this thread, oldest first.
+pub fn take_calls() -> Vec<RecordedCall> {
+    CALLS.with(|calls| calls.take())
+}
+
+pub(crate) fn ecall(id: u64, args: [u64; 9]) -> u64 {
+    CALLS.with(|calls| calls.borrow_mut().push(RecordedCall { id, args }));
+    HANDLER.with(|cell| cell.get()(id, &args))
+}
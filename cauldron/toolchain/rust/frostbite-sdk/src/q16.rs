@@ -0,0 +1,158 @@
+//! Q16.16 fixed-point helpers.
+//!
+//! `Q16` wraps the raw `i32` used by the `scale_q16` arguments of
+//! `matmul_i8_i32` / `matmul_i8_i8` and by `Q16Complex`, so scales can be
+//! computed in code instead of hardcoding `65_536`-based constants.
+
+pub const FRAC_BITS: u32 = 16;
+
+#[repr(transparent)]
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Q16(pub i32);
+
+impl Q16 {
+    pub const ZERO: Q16 = Q16(0);
+    pub const ONE: Q16 = Q16(1 << FRAC_BITS);
+    pub const MIN: Q16 = Q16(i32::MIN);
+    pub const MAX: Q16 = Q16(i32::MAX);
+
+    /// Wrap a raw Q16.16 value (e.g. a `scale_q16` constant).
+    pub const fn from_raw(raw: i32) -> Self {
+        Q16(raw)
+    }
+
+    /// Raw Q16.16 value, as passed to `scale_q16` syscall arguments.
+    pub const fn raw(self) -> i32 {
+        self.0
+    }
+
+    /// Integer to Q16.16, saturating outside `-32768..=32767`.
+    pub const fn from_int(value: i32) -> Self {
+        let wide = (value as i64) << FRAC_BITS;
+        Q16(clamp_i64(wide))
+    }
+
+    /// Round to nearest; saturates out-of-range values and maps NaN to zero.
+    /// On the guest this goes through soft-float, so prefer it for constants.
+    #[cfg(feature = "float")]
+    pub fn from_f32(value: f32) -> Self {
+        let scaled = value * (1u32 << FRAC_BITS) as f32;
+        let rounded = if scaled >= 0.0 {
+            scaled + 0.5
+        } else {
+            scaled - 0.5
+        };
+        Q16(rounded as i32)
+    }
+
+    #[cfg(feature = "float")]
+    pub fn to_f32(self) -> f32 {
+        self.0 as f32 / (1u32 << FRAC_BITS) as f32
+    }
+
+    pub const fn saturating_add(self, rhs: Q16) -> Q16 {
+        Q16(self.0.saturating_add(rhs.0))
+    }
+
+    pub const fn saturating_sub(self, rhs: Q16) -> Q16 {
+        Q16(self.0.saturating_sub(rhs.0))
+    }
+
+    /// `(self * rhs) >> 16` in 64-bit, saturated back to `i32`.
+    pub const fn saturating_mul(self, rhs: Q16) -> Q16 {
+        let wide = (self.0 as i64 * rhs.0 as i64) >> FRAC_BITS;
+        Q16(clamp_i64(wide))
+    }
+}
+
+impl From<Q16> for i32 {
+    fn from(value: Q16) -> i32 {
+        value.0
+    }
+}
+
+const fn clamp_i64(value: i64) -> i32 {
+    if value > i32::MAX as i64 {
+        i32::MAX
+    } else if value < i32::MIN as i64 {
+        i32::MIN
+    } else {
+        value as i32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TOLERANCE: f32 = 1.0 / 65_536.0;
+
+    fn close_within(a: f32, b: f32, tolerance: f32) -> bool {
+        let diff = if a > b { a - b } else { b - a };
+        diff <= tolerance
+    }
+
+    fn close(a: f32, b: f32) -> bool {
+        close_within(a, b, TOLERANCE)
+    }
+
+    #[test]
+    fn f32_round_trip_within_one_ulp() {
+        for value in [0.0f32, 1.0, -1.0, 0.5, 3.25, -2.875, 1234.5678, -0.0001] {
+            let q = Q16::from_f32(value);
+            assert!(close(q.to_f32(), value), "{} -> {}", value, q.to_f32());
+        }
+        assert_eq!(Q16::from_f32(1.0), Q16::ONE);
+        assert_eq!(Q16::from_f32(1.0).raw(), 65_536);
+    }
+
+    #[test]
+    fn from_f32_saturates_and_handles_nan() {
+        assert_eq!(Q16::from_f32(1.0e9), Q16::MAX);
+        assert_eq!(Q16::from_f32(-1.0e9), Q16::MIN);
+        assert_eq!(Q16::from_f32(f32::NAN), Q16::ZERO);
+    }
+
+    #[test]
+    fn arithmetic_matches_float_reference() {
+        let pairs = [
+            (1.5f32, 2.25f32),
+            (-3.0, 0.125),
+            (100.0, -0.75),
+            (0.001, 0.002),
+        ];
+        for (a, b) in pairs {
+            let (qa, qb) = (Q16::from_f32(a), Q16::from_f32(b));
+            assert!(close(qa.saturating_add(qb).to_f32(), a + b));
+            assert!(close(qa.saturating_sub(qb).to_f32(), a - b));
+            assert!(close_within(
+                qa.saturating_mul(qb).to_f32(),
+                a * b,
+                4.0 * TOLERANCE
+            ));
+        }
+    }
+
+    #[test]
+    fn saturating_ops_clamp() {
+        assert_eq!(Q16::MAX.saturating_add(Q16::ONE), Q16::MAX);
+        assert_eq!(Q16::MIN.saturating_sub(Q16::ONE), Q16::MIN);
+        assert_eq!(
+            Q16::from_int(30_000).saturating_mul(Q16::from_int(30_000)),
+            Q16::MAX
+        );
+        assert_eq!(
+            Q16::from_int(-30_000).saturating_mul(Q16::from_int(30_000)),
+            Q16::MIN
+        );
+        assert_eq!(Q16::from_int(40_000), Q16::MAX);
+    }
+
+    #[test]
+    fn raw_scale_conversion() {
+        const HALF: Q16 = Q16::from_raw(32_768);
+        let scale: i32 = HALF.into();
+        assert_eq!(scale, 32_768);
+        assert_eq!(Q16::from_int(2).raw(), 2 * 65_536);
+    }
+}
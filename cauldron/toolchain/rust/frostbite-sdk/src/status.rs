@@ -0,0 +1,103 @@
+//! Status codes shared by guests and the gatekeeper program.
+//!
+//! Guests write one of the `ERR_*` codes below to the control block's
+//! `status` word and exit with it (`0..GUEST_RANGE_END`). The gatekeeper
+//! reports its own failures as `ProgramError::Custom` with the codes in
+//! [`gatekeeper`] (`GATEKEEPER_RANGE_START..GATEKEEPER_RANGE_END`), so the
+//! two can be told apart on-chain. This file has no dependencies so the
+//! gatekeeper can include it directly.
+
+pub const ERR_OK: u32 = 0;
+pub const ERR_CTRL: u32 = 1;
+pub const ERR_INPUT_HEADER: u32 = 2;
+pub const ERR_SCHEMA: u32 = 3;
+pub const ERR_INPUT_BOUNDS: u32 = 4;
+pub const ERR_OUTPUT_BOUNDS: u32 = 5;
+pub const ERR_MISALIGNED: u32 = 6;
+/// A syscall or other internal step failed.
+pub const ERR_INTERNAL: u32 = 7;
+/// Reserved for panic handlers; see `report_panic`.
+pub const ERR_PANIC: u32 = 8;
+/// A chunk of a multi-call input arrived out of order or was replayed.
+pub const ERR_SEQUENCE: u32 = 9;
+
+/// Guest statuses are below this.
+pub const GUEST_RANGE_END: u32 = 0x100;
+
+pub const GATEKEEPER_RANGE_START: u32 = 0x2000;
+pub const GATEKEEPER_RANGE_END: u32 = 0x2100;
+
+/// Gatekeeper `ProgramError::Custom` codes.
+pub mod gatekeeper {
+    pub const ERR_INVALID_INPUT: u32 = 0x2000;
+    pub const ERR_INVALID_CONTROL: u32 = 0x2001;
+    pub const ERR_OUTPUT_BOUNDS: u32 = 0x2002;
+    pub const ERR_BELOW_THRESHOLD: u32 = 0x2003;
+    pub const ERR_OUTPUT_HEADER: u32 = 0x2004;
+    pub const ERR_STALE_OUTPUT: u32 = 0x2005;
+}
+
+const _: () = assert!(GUEST_RANGE_END <= GATEKEEPER_RANGE_START);
+
+use gatekeeper as gk;
+
+const NAMES: [(u32, &str); 16] = [
+    (ERR_OK, "ERR_OK"),
+    (ERR_CTRL, "ERR_CTRL"),
+    (ERR_INPUT_HEADER, "ERR_INPUT_HEADER"),
+    (ERR_SCHEMA, "ERR_SCHEMA"),
+    (ERR_INPUT_BOUNDS, "ERR_INPUT_BOUNDS"),
+    (ERR_OUTPUT_BOUNDS, "ERR_OUTPUT_BOUNDS"),
+    (ERR_MISALIGNED, "ERR_MISALIGNED"),
+    (ERR_INTERNAL, "ERR_INTERNAL"),
+    (ERR_PANIC, "ERR_PANIC"),
+    (ERR_SEQUENCE, "ERR_SEQUENCE"),
+    (gk::ERR_INVALID_INPUT, "gatekeeper::ERR_INVALID_INPUT"),
+    (gk::ERR_INVALID_CONTROL, "gatekeeper::ERR_INVALID_CONTROL"),
+    (gk::ERR_OUTPUT_BOUNDS, "gatekeeper::ERR_OUTPUT_BOUNDS"),
+    (gk::ERR_BELOW_THRESHOLD, "gatekeeper::ERR_BELOW_THRESHOLD"),
+    (gk::ERR_OUTPUT_HEADER, "gatekeeper::ERR_OUTPUT_HEADER"),
+    (gk::ERR_STALE_OUTPUT, "gatekeeper::ERR_STALE_OUTPUT"),
+];
+
+/// Constant name for `code`, for logs; `"unknown"` if it is not listed here.
+pub const fn status_name(code: u32) -> &'static str {
+    let mut i = 0;
+    while i < NAMES.len() {
+        if NAMES[i].0 == code {
+            return NAMES[i].1;
+        }
+        i += 1;
+    }
+    "unknown"
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn codes_and_names_are_unique() {
+        for (i, a) in NAMES.iter().enumerate() {
+            for b in &NAMES[i + 1..] {
+                assert_ne!(a.0, b.0, "{} and {} share a code", a.1, b.1);
+                assert_ne!(a.1, b.1);
+            }
+            assert_eq!(status_name(a.0), a.1);
+        }
+        assert_eq!(status_name(0x1FFF), "unknown");
+    }
+
+    #[test]
+    fn guest_and_gatekeeper_ranges_do_not_collide() {
+        for &(code, name) in &NAMES {
+            let guest = code < GUEST_RANGE_END;
+            let gk = (GATEKEEPER_RANGE_START..GATEKEEPER_RANGE_END).contains(&code);
+            assert!(
+                guest != gk,
+                "{name} ({code:#x}) is not in exactly one range"
+            );
+            assert_eq!(gk, name.starts_with("gatekeeper::"), "{name}");
+        }
+    }
+}
@@ -1282,10 +1282,6 @@ def cmd_input_write(
                 message=f"Input {len(payload_bytes)} bytes exceeds abi.input_max {input_max}",
             )
 
-        control_bytes = build_control_block(
-            control_size, input_offset, len(payload_bytes), output_offset, 0,
-        )
-
         info, _ = accounts_segment_metas(
             str(accounts_path), program_id_override=program_id, payer_override=payer,
         )
@@ -1293,6 +1289,12 @@ def cmd_input_write(
         if not vm_pubkey:
             return CommandResult(success=False, message="Accounts file missing vm pubkey")
 
+        vm_seed = info.get("vm_seed")
+        control_bytes = build_control_block(
+            control_size, input_offset, len(payload_bytes), output_offset, 0,
+            int(vm_seed) if isinstance(vm_seed, str) and vm_seed else 0,
+        )
+
         env = os.environ.copy()
         if rpc_url or info.get("rpc_url"):
             env["FROSTBITE_RPC_URL"] = rpc_url or info["rpc_url"]
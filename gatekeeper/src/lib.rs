@@ -1,5 +1,9 @@
 #![no_std]
 
+#[macro_use]
+extern crate alloc;
+
+use core::cmp::Ordering;
 use solana_program::{
     account_info::{next_account_info, AccountInfo},
     entrypoint,
@@ -15,10 +19,87 @@ const VM_ACCOUNT_SIZE_MIN: usize = 262_696;
 const FBM1_MAGIC: u32 = 0x314D_4246;
 const ABI_VERSION: u32 = 1;
 
-const ERR_INVALID_INPUT: u32 = 0x2000;
-const ERR_INVALID_CONTROL: u32 = 0x2001;
-const ERR_OUTPUT_BOUNDS: u32 = 0x2002;
-const ERR_BELOW_THRESHOLD: u32 = 0x2003;
+// Error codes are shared with guests through the SDK's status table, which
+// has no dependencies and is included as-is.
+#[allow(dead_code)]
+#[path = "../../cauldron/toolchain/rust/frostbite-sdk/src/status.rs"]
+mod status;
+
+use status::gatekeeper::{
+    ERR_BELOW_THRESHOLD, ERR_INVALID_CONTROL, ERR_INVALID_INPUT, ERR_OUTPUT_BOUNDS,
+    ERR_OUTPUT_HEADER, ERR_STALE_OUTPUT,
+};
+
+const CONTROL_BLOCK_LEN: usize = 64;
+
+// Extended instructions start with an opcode byte. Legacy instructions
+// (8..=12 bytes, no opcode) are always OP_THRESHOLD.
+const OP_THRESHOLD: u8 = 0;
+const OP_COMPARE: u8 = 1;
+
+const CMP_GT: u8 = 0;
+const CMP_GE: u8 = 1;
+const CMP_EQ: u8 = 2;
+
+// How output words (and the threshold/margin bits) are interpreted.
+const TYPE_I32: u8 = 0;
+const TYPE_U32: u8 = 1;
+const TYPE_F32: u8 = 2;
+
+// Flags byte (after the type tag).
+const FLAG_VERIFY_HEADER: u8 = 1 << 0;
+// Followed by `nonce_offset` u32 (into the control block) and `min_nonce` u64.
+const FLAG_MIN_NONCE: u8 = 1 << 1;
+
+const FBH1_MAGIC: u32 = 0x3148_4246;
+const FBH1_HEADER_LEN: usize = 32;
+const FBH_VERSION: usize = 4; // u16
+const FBH_FLAGS: usize = 6; // u16
+const FBH_HEADER_LEN: usize = 8;
+const FBH_PAYLOAD_LEN: usize = 16;
+const FBH_CRC32: usize = 20;
+const FBH_FLAG_HAS_CRC32: u16 = 1 << 0;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Check {
+    /// `output[index] >= threshold`
+    Threshold { index: usize, threshold: u32 },
+    /// `output[lhs] <op> output[rhs] + margin`, evaluated without overflow.
+    Compare {
+        op: u8,
+        lhs: usize,
+        rhs: usize,
+        margin: u32,
+    },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct GateInstruction {
+    control_offset: usize,
+    value_type: u8,
+    flags: u8,
+    /// `(nonce_offset, min_nonce)`: reject unless the u64 at
+    /// `control_offset + nonce_offset` is at least `min_nonce`.
+    freshness: Option<(usize, u64)>,
+    check: Check,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Value {
+    I32(i32),
+    U32(u32),
+    F32(f32),
+}
+
+impl Value {
+    fn decode(value_type: u8, bits: u32) -> Self {
+        match value_type {
+            TYPE_U32 => Value::U32(bits),
+            TYPE_F32 => Value::F32(f32::from_bits(bits)),
+            _ => Value::I32(bits as i32),
+        }
+    }
+}
 
 entrypoint!(process_instruction);
 
@@ -31,31 +112,224 @@ fn read_u32_le(buf: &[u8], offset: usize) -> Result<u32, ProgramError> {
     ))
 }
 
-fn read_i32_le(buf: &[u8], offset: usize) -> Result<i32, ProgramError> {
-    if offset + 4 > buf.len() {
-        return Err(ProgramError::Custom(ERR_INVALID_CONTROL));
+fn ix_u32(ix_data: &[u8], offset: usize) -> Result<u32, ProgramError> {
+    ix_data
+        .get(offset..offset + 4)
+        .map(|b| u32::from_le_bytes(b.try_into().unwrap()))
+        .ok_or(ProgramError::InvalidInstructionData)
+}
+
+fn parse_value_type(body: &[u8], offset: usize) -> Result<u8, ProgramError> {
+    match body.get(offset) {
+        None => Ok(TYPE_I32),
+        Some(&tag @ (TYPE_I32 | TYPE_U32 | TYPE_F32)) => Ok(tag),
+        Some(_) => Err(ProgramError::Custom(ERR_INVALID_INPUT)),
     }
-    Ok(i32::from_le_bytes(
-        buf[offset..offset + 4].try_into().unwrap(),
-    ))
 }
 
-pub fn process_instruction(
-    _program_id: &Pubkey,
-    accounts: &[AccountInfo],
-    ix_data: &[u8],
-) -> ProgramResult {
-    if ix_data.len() < 8 {
-        return Err(ProgramError::InvalidInstructionData);
+fn parse_flags(body: &[u8], offset: usize) -> Result<u8, ProgramError> {
+    match body.get(offset) {
+        None => Ok(0),
+        Some(&flags) if flags & !(FLAG_VERIFY_HEADER | FLAG_MIN_NONCE) == 0 => Ok(flags),
+        Some(_) => Err(ProgramError::Custom(ERR_INVALID_INPUT)),
     }
+}
 
-    let control_offset = u32::from_le_bytes(ix_data[0..4].try_into().unwrap()) as usize;
-    let threshold = i32::from_le_bytes(ix_data[4..8].try_into().unwrap());
-    let output_index = if ix_data.len() >= 12 {
-        u32::from_le_bytes(ix_data[8..12].try_into().unwrap()) as usize
+fn parse_freshness(
+    body: &[u8],
+    flags: u8,
+    offset: usize,
+) -> Result<Option<(usize, u64)>, ProgramError> {
+    if (flags & FLAG_MIN_NONCE) == 0 {
+        return Ok(None);
+    }
+    let nonce_offset = ix_u32(body, offset)? as usize;
+    let min_nonce = body
+        .get(offset + 4..offset + 12)
+        .map(|b| u64::from_le_bytes(b.try_into().unwrap()))
+        .ok_or(ProgramError::InvalidInstructionData)?;
+    if nonce_offset.saturating_add(8) > CONTROL_BLOCK_LEN {
+        return Err(ProgramError::Custom(ERR_INVALID_INPUT));
+    }
+    Ok(Some((nonce_offset, min_nonce)))
+}
+
+fn parse_threshold(body: &[u8]) -> Result<GateInstruction, ProgramError> {
+    let control_offset = ix_u32(body, 0)? as usize;
+    let threshold = ix_u32(body, 4)?;
+    let index = if body.len() >= 12 {
+        ix_u32(body, 8)? as usize
     } else {
         0
     };
+    Ok(GateInstruction {
+        control_offset,
+        value_type: TYPE_I32,
+        flags: 0,
+        freshness: None,
+        check: Check::Threshold { index, threshold },
+    })
+}
+
+fn parse_instruction(ix_data: &[u8]) -> Result<GateInstruction, ProgramError> {
+    if (8..=12).contains(&ix_data.len()) {
+        return parse_threshold(ix_data);
+    }
+    let (&opcode, body) = ix_data
+        .split_first()
+        .ok_or(ProgramError::InvalidInstructionData)?;
+    match opcode {
+        // control_offset u32 | threshold | output_index u32 | [type u8 | [flags u8 | ..]]
+        OP_THRESHOLD => {
+            if body.len() < 12 {
+                return Err(ProgramError::InvalidInstructionData);
+            }
+            let flags = parse_flags(body, 13)?;
+            Ok(GateInstruction {
+                value_type: parse_value_type(body, 12)?,
+                flags,
+                freshness: parse_freshness(body, flags, 14)?,
+                ..parse_threshold(body)?
+            })
+        }
+        // control_offset u32 | cmp u8 | lhs u32 | rhs u32 | [margin | [type u8 | [flags u8 | ..]]]
+        OP_COMPARE => {
+            let control_offset = ix_u32(body, 0)? as usize;
+            let op = *body.get(4).ok_or(ProgramError::InvalidInstructionData)?;
+            if !matches!(op, CMP_GT | CMP_GE | CMP_EQ) {
+                return Err(ProgramError::Custom(ERR_INVALID_INPUT));
+            }
+            let lhs = ix_u32(body, 5)? as usize;
+            let rhs = ix_u32(body, 9)? as usize;
+            // A partial margin must not quietly become 0, which would loosen
+            // the check.
+            let margin = match body.len() {
+                13 => 0,
+                14..=16 => return Err(ProgramError::InvalidInstructionData),
+                _ => ix_u32(body, 13)?,
+            };
+            let flags = parse_flags(body, 18)?;
+            Ok(GateInstruction {
+                control_offset,
+                value_type: parse_value_type(body, 17)?,
+                flags,
+                freshness: parse_freshness(body, flags, 19)?,
+                check: Check::Compare {
+                    op,
+                    lhs,
+                    rhs,
+                    margin,
+                },
+            })
+        }
+        _ => Err(ProgramError::Custom(ERR_INVALID_INPUT)),
+    }
+}
+
+/// Order of `lhs` against `rhs + margin` in the instruction's type; `None`
+/// when an f32 operand (or the sum) is NaN, which fails every check.
+fn relation(value_type: u8, lhs: u32, rhs: u32, margin: u32) -> Option<Ordering> {
+    match (
+        Value::decode(value_type, lhs),
+        Value::decode(value_type, rhs),
+        Value::decode(value_type, margin),
+    ) {
+        (Value::I32(l), Value::I32(r), Value::I32(m)) => {
+            Some((l as i64).cmp(&(r as i64 + m as i64)))
+        }
+        (Value::U32(l), Value::U32(r), Value::U32(m)) => {
+            Some((l as u64).cmp(&(r as u64 + m as u64)))
+        }
+        (Value::F32(l), Value::F32(r), Value::F32(m)) => l.partial_cmp(&(r + m)),
+        _ => None,
+    }
+}
+
+fn compare(op: u8, ordering: Option<Ordering>) -> bool {
+    matches!(
+        (op, ordering),
+        (CMP_GT, Some(Ordering::Greater))
+            | (CMP_GE, Some(Ordering::Greater | Ordering::Equal))
+            | (CMP_EQ, Some(Ordering::Equal))
+    )
+}
+
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc: u32 = 0xFFFF_FFFF;
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            if (crc & 1) != 0 {
+                crc = (crc >> 1) ^ 0xEDB8_8320;
+            } else {
+                crc >>= 1;
+            }
+        }
+    }
+    !crc
+}
+
+/// Check the FBH1 header at the start of the output region and return the
+/// payload as `(ptr, len)`.
+fn verify_output_header(
+    scratch: &[u8],
+    output_ptr: usize,
+    output_len: usize,
+) -> Result<(usize, usize), ProgramError> {
+    let header_err = ProgramError::Custom(ERR_OUTPUT_HEADER);
+    let output_end = output_ptr
+        .checked_add(output_len)
+        .filter(|&end| end <= scratch.len())
+        .ok_or(ProgramError::Custom(ERR_OUTPUT_BOUNDS))?;
+    if output_len < FBH1_HEADER_LEN {
+        return Err(header_err);
+    }
+    let header = &scratch[output_ptr..output_ptr + FBH1_HEADER_LEN];
+    let u16_at = |off: usize| u16::from_le_bytes(header[off..off + 2].try_into().unwrap());
+    let u32_at = |off: usize| u32::from_le_bytes(header[off..off + 4].try_into().unwrap());
+    if u32_at(0) != FBH1_MAGIC
+        || u16_at(FBH_VERSION) != 1
+        || u32_at(FBH_HEADER_LEN) as usize != FBH1_HEADER_LEN
+    {
+        return Err(header_err);
+    }
+    let payload_ptr = output_ptr + FBH1_HEADER_LEN;
+    let payload_len = u32_at(FBH_PAYLOAD_LEN) as usize;
+    if payload_len != output_len - FBH1_HEADER_LEN {
+        return Err(header_err);
+    }
+    if (u16_at(FBH_FLAGS) & FBH_FLAG_HAS_CRC32) != 0
+        && crc32(&scratch[payload_ptr..output_end]) != u32_at(FBH_CRC32)
+    {
+        return Err(header_err);
+    }
+    Ok((payload_ptr, payload_len))
+}
+
+fn read_output(
+    scratch: &[u8],
+    output_ptr: usize,
+    output_len: usize,
+    index: usize,
+) -> Result<u32, ProgramError> {
+    let output_offset = index
+        .checked_mul(4)
+        .and_then(|off| off.checked_add(output_ptr))
+        .ok_or(ProgramError::Custom(ERR_OUTPUT_BOUNDS))?;
+    let output_end = output_ptr.saturating_add(output_len);
+    if output_offset + 4 > scratch.len() || output_offset + 4 > output_end {
+        return Err(ProgramError::Custom(ERR_OUTPUT_BOUNDS));
+    }
+    read_u32_le(scratch, output_offset)
+}
+
+pub fn process_instruction(
+    _program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    ix_data: &[u8],
+) -> ProgramResult {
+    let ix = parse_instruction(ix_data)?;
+    let control_offset = ix.control_offset;
 
     let mut account_iter = accounts.iter();
     let authority = next_account_info(&mut account_iter)?;
@@ -71,7 +345,7 @@ pub fn process_instruction(
     }
     let scratch = &data[MMU_VM_HEADER_SIZE..];
 
-    if control_offset + 64 > scratch.len() {
+    if control_offset + CONTROL_BLOCK_LEN > scratch.len() {
         return Err(ProgramError::Custom(ERR_INVALID_CONTROL));
     }
 
@@ -88,26 +362,439 @@ pub fn process_instruction(
         return Err(ProgramError::Custom(status));
     }
 
-    if output_len < 4 {
-        return Err(ProgramError::Custom(ERR_OUTPUT_BOUNDS));
+    if let Some((nonce_offset, min_nonce)) = ix.freshness {
+        let at = control_offset + nonce_offset;
+        let nonce = u64::from_le_bytes(scratch[at..at + 8].try_into().unwrap());
+        if nonce < min_nonce {
+            msg!("gatekeeper: run nonce {} < required {}", nonce, min_nonce);
+            return Err(ProgramError::Custom(ERR_STALE_OUTPUT));
+        }
     }
 
-    let output_offset = output_ptr + output_index * 4;
-    let output_end = output_ptr.saturating_add(output_len);
-    if output_offset + 4 > scratch.len() || output_offset + 4 > output_end {
+    // With a verified header, output indices address the payload after it.
+    let (output_ptr, output_len) = if (ix.flags & FLAG_VERIFY_HEADER) != 0 {
+        verify_output_header(scratch, output_ptr, output_len)?
+    } else {
+        (output_ptr, output_len)
+    };
+
+    if output_len < 4 {
         return Err(ProgramError::Custom(ERR_OUTPUT_BOUNDS));
     }
 
-    let value = read_i32_le(scratch, output_offset)?;
-    msg!(
-        "gatekeeper: output[{}]={} threshold={}",
-        output_index,
-        value,
-        threshold
-    );
-    if value < threshold {
-        return Err(ProgramError::Custom(ERR_BELOW_THRESHOLD));
+    match ix.check {
+        Check::Threshold { index, threshold } => {
+            let value = read_output(scratch, output_ptr, output_len, index)?;
+            msg!(
+                "gatekeeper: output[{}]={:?} threshold={:?}",
+                index,
+                Value::decode(ix.value_type, value),
+                Value::decode(ix.value_type, threshold)
+            );
+            if !compare(CMP_GE, relation(ix.value_type, value, threshold, 0)) {
+                return Err(ProgramError::Custom(ERR_BELOW_THRESHOLD));
+            }
+        }
+        Check::Compare {
+            op,
+            lhs,
+            rhs,
+            margin,
+        } => {
+            let lhs_value = read_output(scratch, output_ptr, output_len, lhs)?;
+            let rhs_value = read_output(scratch, output_ptr, output_len, rhs)?;
+            msg!(
+                "gatekeeper: output[{}]={:?} op={} output[{}]={:?} margin={:?}",
+                lhs,
+                Value::decode(ix.value_type, lhs_value),
+                op,
+                rhs,
+                Value::decode(ix.value_type, rhs_value),
+                Value::decode(ix.value_type, margin)
+            );
+            if !compare(op, relation(ix.value_type, lhs_value, rhs_value, margin)) {
+                return Err(ProgramError::Custom(ERR_BELOW_THRESHOLD));
+            }
+        }
     }
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec::Vec;
+
+    const OUTPUT_PTR: usize = 256;
+    // The control block's `reserved0` (u64), where the README suggests
+    // recording the run nonce.
+    const CTRL_RUN_NONCE: usize = 48;
+
+    fn vm_data(outputs: &[i32]) -> Vec<u8> {
+        let mut data = vec![0u8; VM_ACCOUNT_SIZE_MIN];
+        let ctrl = MMU_VM_HEADER_SIZE;
+        let words = [
+            (0, FBM1_MAGIC),
+            (4, ABI_VERSION),
+            (24, OUTPUT_PTR as u32),
+            (28, (outputs.len() * 4) as u32),
+        ];
+        for (off, value) in words {
+            data[ctrl + off..ctrl + off + 4].copy_from_slice(&value.to_le_bytes());
+        }
+        for (i, value) in outputs.iter().enumerate() {
+            let at = ctrl + OUTPUT_PTR + i * 4;
+            data[at..at + 4].copy_from_slice(&value.to_le_bytes());
+        }
+        data
+    }
+
+    fn run(data: &mut [u8], ix_data: &[u8]) -> ProgramResult {
+        let program_id = Pubkey::new_unique();
+        let authority_key = Pubkey::new_unique();
+        let vm_key = Pubkey::new_unique();
+        let mut authority_lamports = 0;
+        let mut vm_lamports = 0;
+        let mut no_data = [];
+        let accounts = [
+            AccountInfo::new(
+                &authority_key,
+                true,
+                false,
+                &mut authority_lamports,
+                &mut no_data,
+                &program_id,
+                false,
+                0,
+            ),
+            AccountInfo::new(
+                &vm_key,
+                false,
+                false,
+                &mut vm_lamports,
+                data,
+                &program_id,
+                false,
+                0,
+            ),
+        ];
+        process_instruction(&program_id, &accounts, ix_data)
+    }
+
+    fn threshold_ix(threshold: i32, index: u32) -> Vec<u8> {
+        let mut ix = Vec::new();
+        ix.extend_from_slice(&0u32.to_le_bytes());
+        ix.extend_from_slice(&threshold.to_le_bytes());
+        ix.extend_from_slice(&index.to_le_bytes());
+        ix
+    }
+
+    fn compare_ix(op: u8, lhs: u32, rhs: u32, margin: Option<i32>) -> Vec<u8> {
+        let mut ix = vec![OP_COMPARE];
+        ix.extend_from_slice(&0u32.to_le_bytes());
+        ix.push(op);
+        ix.extend_from_slice(&lhs.to_le_bytes());
+        ix.extend_from_slice(&rhs.to_le_bytes());
+        if let Some(margin) = margin {
+            ix.extend_from_slice(&margin.to_le_bytes());
+        }
+        ix
+    }
+
+    #[test]
+    fn legacy_threshold_layout_still_parses() {
+        let mut data = vm_data(&[5, 9]);
+        assert_eq!(run(&mut data, &threshold_ix(9, 1)), Ok(()));
+        assert_eq!(
+            run(&mut data, &threshold_ix(6, 0)),
+            Err(ProgramError::Custom(ERR_BELOW_THRESHOLD))
+        );
+        // 8-byte form defaults to output[0].
+        assert_eq!(run(&mut data, &threshold_ix(5, 0)[..8]), Ok(()));
+        assert_eq!(
+            run(&mut data, &threshold_ix(0, 2)),
+            Err(ProgramError::Custom(ERR_OUTPUT_BOUNDS))
+        );
+    }
+
+    #[test]
+    fn opcode_threshold_matches_legacy() {
+        let mut data = vm_data(&[5, 9]);
+        let mut ix = vec![OP_THRESHOLD];
+        ix.extend_from_slice(&threshold_ix(9, 1));
+        assert_eq!(run(&mut data, &ix), Ok(()));
+        assert_eq!(
+            parse_instruction(&ix),
+            parse_instruction(&threshold_ix(9, 1))
+        );
+    }
+
+    #[test]
+    fn compare_ops_across_outputs() {
+        let mut data = vm_data(&[7, 3, 7]);
+        let below = Err(ProgramError::Custom(ERR_BELOW_THRESHOLD));
+        assert_eq!(run(&mut data, &compare_ix(CMP_GT, 0, 1, None)), Ok(()));
+        assert_eq!(run(&mut data, &compare_ix(CMP_GT, 1, 0, None)), below);
+        assert_eq!(run(&mut data, &compare_ix(CMP_GT, 0, 2, None)), below);
+        assert_eq!(run(&mut data, &compare_ix(CMP_GE, 0, 2, None)), Ok(()));
+        assert_eq!(run(&mut data, &compare_ix(CMP_EQ, 0, 2, None)), Ok(()));
+        assert_eq!(run(&mut data, &compare_ix(CMP_EQ, 0, 1, None)), below);
+        // output[0] > output[1] + 4 fails, + 3 passes with GE.
+        assert_eq!(run(&mut data, &compare_ix(CMP_GT, 0, 1, Some(4))), below);
+        assert_eq!(run(&mut data, &compare_ix(CMP_GE, 0, 1, Some(4))), Ok(()));
+        assert_eq!(
+            run(&mut data, &compare_ix(CMP_GT, 0, 3, None)),
+            Err(ProgramError::Custom(ERR_OUTPUT_BOUNDS))
+        );
+    }
+
+    #[test]
+    fn truncated_compare_margin_is_rejected() {
+        let full = compare_ix(CMP_GT, 0, 1, Some(4));
+        assert_eq!(full.len(), 18);
+        for len in 15..18 {
+            assert_eq!(
+                parse_instruction(&full[..len]),
+                Err(ProgramError::InvalidInstructionData),
+                "{len}-byte instruction"
+            );
+        }
+        assert!(parse_instruction(&full[..14]).is_ok());
+        assert!(parse_instruction(&full).is_ok());
+    }
+
+    #[test]
+    fn compare_margin_does_not_overflow() {
+        let rel = |l: i32, r: i32, m: i32| relation(TYPE_I32, l as u32, r as u32, m as u32);
+        assert!(compare(CMP_GT, rel(i32::MAX, 0, i32::MIN)));
+        assert!(!compare(CMP_GT, rel(i32::MAX, i32::MAX, 1)));
+        assert!(compare(CMP_GE, rel(i32::MIN, i32::MIN, 0)));
+        assert!(compare(
+            CMP_GT,
+            relation(TYPE_U32, u32::MAX, u32::MAX - 1, 0)
+        ));
+        assert!(!compare(CMP_GE, relation(TYPE_U32, u32::MAX, u32::MAX, 1)));
+    }
+
+    fn typed_threshold_ix(threshold: u32, index: u32, value_type: u8) -> Vec<u8> {
+        let mut ix = vec![OP_THRESHOLD];
+        ix.extend_from_slice(&threshold_ix(threshold as i32, index));
+        ix.push(value_type);
+        ix
+    }
+
+    #[test]
+    fn type_tag_disambiguates_sign() {
+        let below = Err(ProgramError::Custom(ERR_BELOW_THRESHOLD));
+        // 0xFFFF_FFF0 is -16 as i32 but ~4.29e9 as u32.
+        let mut data = vm_data(&[-16, 3]);
+        assert_eq!(run(&mut data, &typed_threshold_ix(5, 0, TYPE_I32)), below);
+        assert_eq!(run(&mut data, &typed_threshold_ix(5, 0, TYPE_U32)), Ok(()));
+        // A threshold of 0xFFFF_FFFF is -1 as i32 and u32::MAX as u32.
+        assert_eq!(
+            run(&mut data, &typed_threshold_ix(u32::MAX, 1, TYPE_I32)),
+            Ok(())
+        );
+        assert_eq!(
+            run(&mut data, &typed_threshold_ix(u32::MAX, 1, TYPE_U32)),
+            below
+        );
+        // Legacy and untagged opcode forms stay signed.
+        assert_eq!(run(&mut data, &threshold_ix(5, 0)), below);
+    }
+
+    #[test]
+    fn f32_outputs_compare_as_floats() {
+        let below = Err(ProgramError::Custom(ERR_BELOW_THRESHOLD));
+        let outputs = [1.5f32, -2.0, f32::NAN].map(|v| v.to_bits() as i32);
+        let mut data = vm_data(&outputs);
+        let f = |v: f32| v.to_bits();
+        assert_eq!(
+            run(&mut data, &typed_threshold_ix(f(1.25), 0, TYPE_F32)),
+            Ok(())
+        );
+        assert_eq!(
+            run(&mut data, &typed_threshold_ix(f(2.0), 0, TYPE_F32)),
+            below
+        );
+        assert_eq!(
+            run(&mut data, &typed_threshold_ix(f(-3.0), 1, TYPE_F32)),
+            Ok(())
+        );
+        assert_eq!(
+            run(&mut data, &typed_threshold_ix(f(0.0), 2, TYPE_F32)),
+            below
+        );
+
+        let mut ix = compare_ix(CMP_GT, 0, 1, Some(f(3.0) as i32));
+        ix.push(TYPE_F32);
+        assert_eq!(run(&mut data, &ix), Ok(()));
+        let mut ix = compare_ix(CMP_GT, 0, 1, Some(f(3.5) as i32));
+        ix.push(TYPE_F32);
+        assert_eq!(run(&mut data, &ix), below);
+    }
+
+    fn framed_vm_data(outputs: &[i32], with_crc: bool) -> Vec<u8> {
+        let payload: Vec<u8> = outputs.iter().flat_map(|v| v.to_le_bytes()).collect();
+        let mut framed = Vec::new();
+        framed.extend_from_slice(&FBH1_MAGIC.to_le_bytes());
+        framed.extend_from_slice(&1u16.to_le_bytes());
+        let flags = if with_crc { FBH_FLAG_HAS_CRC32 } else { 0 };
+        framed.extend_from_slice(&flags.to_le_bytes());
+        framed.extend_from_slice(&(FBH1_HEADER_LEN as u32).to_le_bytes());
+        framed.extend_from_slice(&0u32.to_le_bytes());
+        framed.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+        let crc = if with_crc { crc32(&payload) } else { 0 };
+        framed.extend_from_slice(&crc.to_le_bytes());
+        framed.extend_from_slice(&[0u8; 8]);
+        framed.extend_from_slice(&payload);
+
+        let words: Vec<i32> = framed
+            .chunks(4)
+            .map(|c| i32::from_le_bytes(c.try_into().unwrap()))
+            .collect();
+        vm_data(&words)
+    }
+
+    fn verify_ix(threshold: i32, index: u32) -> Vec<u8> {
+        let mut ix = vec![OP_THRESHOLD];
+        ix.extend_from_slice(&threshold_ix(threshold, index));
+        ix.push(TYPE_I32);
+        ix.push(FLAG_VERIFY_HEADER);
+        ix
+    }
+
+    fn output_byte(data: &mut [u8], offset: usize) -> &mut u8 {
+        &mut data[MMU_VM_HEADER_SIZE + OUTPUT_PTR + offset]
+    }
+
+    #[test]
+    fn verified_header_gates_on_payload() {
+        let mut data = framed_vm_data(&[4, 11], true);
+        assert_eq!(run(&mut data, &verify_ix(11, 1)), Ok(()));
+        assert_eq!(
+            run(&mut data, &verify_ix(5, 0)),
+            Err(ProgramError::Custom(ERR_BELOW_THRESHOLD))
+        );
+        assert_eq!(
+            run(&mut data, &verify_ix(0, 2)),
+            Err(ProgramError::Custom(ERR_OUTPUT_BOUNDS))
+        );
+        let mut data = framed_vm_data(&[4, 11], false);
+        assert_eq!(run(&mut data, &verify_ix(11, 1)), Ok(()));
+    }
+
+    #[test]
+    fn tampered_or_truncated_output_is_rejected() {
+        let header_err = Err(ProgramError::Custom(ERR_OUTPUT_HEADER));
+
+        let mut data = framed_vm_data(&[4, 11], true);
+        *output_byte(&mut data, FBH1_HEADER_LEN + 4) ^= 0x40;
+        assert_eq!(run(&mut data, &verify_ix(0, 0)), header_err);
+
+        let mut data = framed_vm_data(&[4, 11], true);
+        *output_byte(&mut data, 0) = b'X';
+        assert_eq!(run(&mut data, &verify_ix(0, 0)), header_err);
+
+        // Control block claims fewer bytes than the header's payload_len.
+        let mut data = framed_vm_data(&[4, 11], true);
+        let len_at = MMU_VM_HEADER_SIZE + 28;
+        data[len_at..len_at + 4].copy_from_slice(&((FBH1_HEADER_LEN + 4) as u32).to_le_bytes());
+        assert_eq!(run(&mut data, &verify_ix(0, 0)), header_err);
+
+        // Raw (unframed) output fails verification but passes without the flag.
+        let mut data = vm_data(&[4; 10]);
+        assert_eq!(run(&mut data, &verify_ix(0, 0)), header_err);
+        assert_eq!(run(&mut data, &threshold_ix(0, 0)), Ok(()));
+    }
+
+    fn fresh_ix(nonce_offset: u32, min_nonce: u64) -> Vec<u8> {
+        let mut ix = vec![OP_THRESHOLD];
+        ix.extend_from_slice(&threshold_ix(0, 0));
+        ix.push(TYPE_I32);
+        ix.push(FLAG_MIN_NONCE);
+        ix.extend_from_slice(&nonce_offset.to_le_bytes());
+        ix.extend_from_slice(&min_nonce.to_le_bytes());
+        ix
+    }
+
+    fn set_nonce(data: &mut [u8], offset: usize, nonce: u64) {
+        let at = MMU_VM_HEADER_SIZE + offset;
+        data[at..at + 8].copy_from_slice(&nonce.to_le_bytes());
+    }
+
+    #[test]
+    fn stale_run_nonce_is_rejected() {
+        let stale = Err(ProgramError::Custom(ERR_STALE_OUTPUT));
+        let mut data = vm_data(&[1]);
+        set_nonce(&mut data, CTRL_RUN_NONCE, 41);
+        assert_eq!(run(&mut data, &fresh_ix(CTRL_RUN_NONCE as u32, 41)), Ok(()));
+        assert_eq!(run(&mut data, &fresh_ix(CTRL_RUN_NONCE as u32, 42)), stale);
+        set_nonce(&mut data, CTRL_RUN_NONCE, u64::MAX);
+        assert_eq!(
+            run(&mut data, &fresh_ix(CTRL_RUN_NONCE as u32, u64::MAX)),
+            Ok(())
+        );
+        // Any offset inside the control block works.
+        set_nonce(&mut data, 56, 7);
+        assert_eq!(run(&mut data, &fresh_ix(56, 8)), stale);
+
+        let mut ix = compare_ix(CMP_GE, 0, 0, Some(0));
+        ix.extend_from_slice(&[TYPE_I32, FLAG_MIN_NONCE]);
+        ix.extend_from_slice(&56u32.to_le_bytes());
+        ix.extend_from_slice(&7u64.to_le_bytes());
+        assert_eq!(run(&mut data, &ix), Ok(()));
+    }
+
+    #[test]
+    fn nonce_offset_must_stay_in_control_block() {
+        assert!(parse_instruction(&fresh_ix(56, 0)).is_ok());
+        for offset in [57, 64, u32::MAX] {
+            assert_eq!(
+                parse_instruction(&fresh_ix(offset, 0)),
+                Err(ProgramError::Custom(ERR_INVALID_INPUT))
+            );
+        }
+        let truncated = fresh_ix(48, 1);
+        assert_eq!(
+            parse_instruction(&truncated[..truncated.len() - 1]),
+            Err(ProgramError::InvalidInstructionData)
+        );
+    }
+
+    #[test]
+    fn crc32_check_value() {
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+    }
+
+    #[test]
+    fn rejects_unknown_opcode_and_op() {
+        let mut bad_flags = verify_ix(0, 0);
+        *bad_flags.last_mut().unwrap() = 0x80;
+        assert_eq!(
+            parse_instruction(&bad_flags),
+            Err(ProgramError::Custom(ERR_INVALID_INPUT))
+        );
+        assert_eq!(
+            parse_instruction(&typed_threshold_ix(0, 0, 3)),
+            Err(ProgramError::Custom(ERR_INVALID_INPUT))
+        );
+        assert_eq!(
+            parse_instruction(&[9; 16]),
+            Err(ProgramError::Custom(ERR_INVALID_INPUT))
+        );
+        assert_eq!(
+            parse_instruction(&compare_ix(7, 0, 1, None)),
+            Err(ProgramError::Custom(ERR_INVALID_INPUT))
+        );
+        assert_eq!(
+            parse_instruction(&compare_ix(CMP_GT, 0, 1, None)[..13]),
+            Err(ProgramError::InvalidInstructionData)
+        );
+        assert_eq!(
+            parse_instruction(&[0; 4]),
+            Err(ProgramError::InvalidInstructionData)
+        );
+    }
+}
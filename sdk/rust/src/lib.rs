@@ -0,0 +1,915 @@
+//! Execute/read helpers shared by the Frostbite Rust CLI and other tools.
+
+use frostbite_modelkit_tools::retry::{is_presubmit_retryable, is_timeout, RetryPolicy};
+use serde::{Deserialize, Serialize};
+use solana_account_decoder::UiAccountEncoding;
+use solana_client::{
+    rpc_client::RpcClient,
+    rpc_config::{RpcSimulateTransactionAccountsConfig, RpcSimulateTransactionConfig},
+    rpc_response::RpcSimulateTransactionResult,
+};
+use solana_sdk::{
+    hash::Hash,
+    instruction::Instruction,
+    pubkey::Pubkey,
+    signature::{Keypair, Signature, Signer},
+    transaction::Transaction,
+};
+
+pub const VM_HEADER_SIZE: usize = 552;
+pub const MMU_VM_HEADER_SIZE: usize = VM_HEADER_SIZE;
+pub const VM_ACCOUNT_SIZE_MIN: usize = 262_696;
+/// Scratch bytes in a minimum-size VM account.
+pub const SCRATCH_SIZE: usize = VM_ACCOUNT_SIZE_MIN - MMU_VM_HEADER_SIZE;
+/// Bytes the control block occupies in scratch.
+pub const CONTROL_BLOCK_LEN: usize = 64;
+
+pub const CTRL_STATUS: usize = 12;
+pub const CTRL_INPUT_PTR: usize = 16;
+pub const CTRL_INPUT_LEN: usize = 20;
+pub const CTRL_OUTPUT_LEN: usize = 28;
+
+/// Where a model's control block and output live in VM scratch (from the
+/// manifest's `[abi]` table).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct OutputLayout {
+    pub control_offset: usize,
+    pub output_offset: usize,
+    pub output_max: usize,
+    /// Read `output_max` bytes when the guest left `output_len` at zero.
+    pub use_max: bool,
+}
+
+pub fn read_u32_le(buf: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(buf[offset..offset + 4].try_into().unwrap())
+}
+
+pub fn decode_i32(buf: &[u8]) -> Vec<i32> {
+    let mut out = Vec::new();
+    let mut i = 0usize;
+    while i + 4 <= buf.len() {
+        out.push(i32::from_le_bytes(buf[i..i + 4].try_into().unwrap()));
+        i += 4;
+    }
+    out
+}
+
+pub fn decode_u32(buf: &[u8]) -> Vec<u32> {
+    buf.chunks_exact(4)
+        .map(|word| u32::from_le_bytes(word.try_into().unwrap()))
+        .collect()
+}
+
+pub fn decode_f32(buf: &[u8]) -> Vec<f32> {
+    buf.chunks_exact(4)
+        .map(|word| f32::from_le_bytes(word.try_into().unwrap()))
+        .collect()
+}
+
+/// How to interpret the output words (`--output-type`).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OutputType {
+    #[default]
+    I32,
+    U32,
+    F32,
+}
+
+impl std::str::FromStr for OutputType {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "i32" => Ok(Self::I32),
+            "u32" => Ok(Self::U32),
+            "f32" => Ok(Self::F32),
+            other => Err(format!(
+                "unknown output type {:?} (expected i32, u32 or f32)",
+                other
+            )),
+        }
+    }
+}
+
+/// Output words decoded per [`OutputType`]; serializes as a plain array.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum OutputValues {
+    I32(Vec<i32>),
+    U32(Vec<u32>),
+    F32(Vec<f32>),
+}
+
+impl OutputValues {
+    pub fn decode(output_type: OutputType, buf: &[u8]) -> Self {
+        match output_type {
+            OutputType::I32 => Self::I32(decode_i32(buf)),
+            OutputType::U32 => Self::U32(decode_u32(buf)),
+            OutputType::F32 => Self::F32(decode_f32(buf)),
+        }
+    }
+
+    /// Text form for the CLI, e.g. `Output (f32): [1.500000, -2.000000]`.
+    pub fn describe(&self) -> String {
+        match self {
+            Self::I32(values) => format!("Output (i32): {:?}", values),
+            Self::U32(values) => format!("Output (u32): {:?}", values),
+            Self::F32(values) => {
+                let items: Vec<String> = values.iter().map(|v| format!("{:.6}", v)).collect();
+                format!("Output (f32): [{}]", items.join(", "))
+            }
+        }
+    }
+}
+
+impl OutputLayout {
+    /// Layout from a manifest's `[abi]` table. `control_offset`,
+    /// `output_offset` and `output_max` are required, and the control block
+    /// and output region must both fit in scratch.
+    pub fn from_abi(abi: &toml::value::Table, use_max: bool) -> Result<Self, String> {
+        let field = |key: &str| -> Result<usize, String> {
+            let value = abi
+                .get(key)
+                .ok_or_else(|| format!("manifest abi.{} is required", key))?
+                .as_integer()
+                .ok_or_else(|| format!("manifest abi.{} must be an integer", key))?;
+            usize::try_from(value).map_err(|_| format!("manifest abi.{} must be >= 0", key))
+        };
+        let layout = OutputLayout {
+            control_offset: field("control_offset")?,
+            output_offset: field("output_offset")?,
+            output_max: field("output_max")?,
+            use_max,
+        };
+        if layout.control_offset.saturating_add(CONTROL_BLOCK_LEN) > SCRATCH_SIZE {
+            return Err(format!(
+                "abi.control_offset 0x{:X} leaves no room for the control block in {} bytes of scratch",
+                layout.control_offset, SCRATCH_SIZE
+            ));
+        }
+        if layout.output_offset.saturating_add(layout.output_max) > SCRATCH_SIZE {
+            return Err(format!(
+                "abi.output_offset 0x{:X} + abi.output_max {} exceeds {} bytes of scratch",
+                layout.output_offset, layout.output_max, SCRATCH_SIZE
+            ));
+        }
+        Ok(layout)
+    }
+}
+
+/// Status and output bytes from raw VM account data. The output is empty when
+/// it would run past the end of scratch.
+pub fn decode_output(
+    account_data: &[u8],
+    layout: &OutputLayout,
+) -> Result<(u32, Vec<u8>), Box<dyn std::error::Error>> {
+    if account_data.len() < VM_ACCOUNT_SIZE_MIN {
+        return Err(format!(
+            "VM account data too small: {} < {}",
+            account_data.len(),
+            VM_ACCOUNT_SIZE_MIN
+        )
+        .into());
+    }
+    let scratch = &account_data[MMU_VM_HEADER_SIZE..];
+    if layout.control_offset + CTRL_OUTPUT_LEN + 4 > scratch.len() {
+        return Err(format!(
+            "control block at 0x{:X} is outside scratch",
+            layout.control_offset
+        )
+        .into());
+    }
+    let status = read_u32_le(scratch, layout.control_offset + CTRL_STATUS);
+    let mut output_len = read_u32_le(scratch, layout.control_offset + CTRL_OUTPUT_LEN) as usize;
+    if output_len == 0 && layout.use_max {
+        output_len = layout.output_max;
+    }
+    let output_end = layout.output_offset + output_len;
+    let output = if output_end <= scratch.len() {
+        scratch[layout.output_offset..output_end].to_vec()
+    } else {
+        Vec::new()
+    };
+    Ok((status, output))
+}
+
+/// The gatekeeper's single-index threshold check, run locally on an output
+/// read back from the VM: `Ok(value)` when `output[index] >= threshold`
+/// (i32), `Err` describing the failure the gatekeeper would report
+/// otherwise, including a nonzero guest status or a short output.
+pub fn check_threshold(
+    status: u32,
+    output: &[u8],
+    index: usize,
+    threshold: i32,
+) -> Result<i32, String> {
+    if status != 0 {
+        return Err(format!("guest status {} is not 0", status));
+    }
+    let values = decode_i32(output);
+    let value = *values.get(index).ok_or_else(|| {
+        format!(
+            "output[{}] is out of bounds ({} words)",
+            index,
+            values.len()
+        )
+    })?;
+    if value < threshold {
+        return Err(format!(
+            "output[{}]={} is below threshold {}",
+            index, value, threshold
+        ));
+    }
+    Ok(value)
+}
+
+pub fn fetch_output(
+    client: &RpcClient,
+    vm_pubkey: &Pubkey,
+    layout: &OutputLayout,
+) -> Result<(u32, Vec<u8>), Box<dyn std::error::Error>> {
+    let account = client.get_account(vm_pubkey)?;
+    decode_output(&account.data, layout)
+}
+
+/// Send retry policy: `FROSTBITE_RETRY_*` from the environment, with
+/// `max_retries` (retries after the first attempt) overriding the attempt count.
+/// Without either, that is 3 retries.
+pub fn retry_policy(max_retries: Option<u32>) -> RetryPolicy {
+    let mut policy = RetryPolicy::from_env();
+    if let Some(retries) = max_retries {
+        policy.max_attempts = retries.saturating_add(1);
+    }
+    policy
+}
+
+/// Sign with `recent` first. EXECUTE is not idempotent, so only failures that
+/// prove the transaction was never accepted (expired blockhash, rate limit,
+/// refused connection) are re-signed with a fresh blockhash. After a timeout
+/// the first transaction may still land: its signature status is checked and,
+/// if the cluster has not seen it, the same signed bytes are resent so it can
+/// run at most once.
+pub fn send_with_retry(
+    client: &RpcClient,
+    retry: &RetryPolicy,
+    instructions: &[Instruction],
+    payer: &Keypair,
+    signers: &[&dyn Signer],
+    recent: Hash,
+) -> Result<Signature, Box<dyn std::error::Error>> {
+    let mut first = Some(recent);
+    send_at_most_once(
+        retry,
+        || {
+            let blockhash = match first.take() {
+                Some(hash) => hash,
+                None => client.get_latest_blockhash()?,
+            };
+            Ok(Transaction::new_signed_with_payer(
+                instructions,
+                Some(&payer.pubkey()),
+                signers,
+                blockhash,
+            ))
+        },
+        |tx| Ok(client.send_and_confirm_transaction(tx)?),
+        |tx| {
+            let signature = tx.signatures[0];
+            match client.get_signature_status(&signature)? {
+                Some(status) => Ok(Some(status.map(|()| signature)?)),
+                None => Ok(None),
+            }
+        },
+    )
+}
+
+fn is_send_retryable(message: &str) -> bool {
+    is_presubmit_retryable(message) || is_timeout(message)
+}
+
+/// Retry loop of [`send_with_retry`]: `sign` builds a fresh transaction, `send`
+/// submits one, and `landed` reports whether an earlier submission was
+/// processed (`Ok(None)` if the cluster has not seen it).
+fn send_at_most_once<T, S, E: std::fmt::Display>(
+    retry: &RetryPolicy,
+    mut sign: impl FnMut() -> Result<T, E>,
+    mut send: impl FnMut(&T) -> Result<S, E>,
+    mut landed: impl FnMut(&T) -> Result<Option<S>, E>,
+) -> Result<S, E> {
+    let mut in_flight: Option<T> = None;
+    retry.run_when(is_send_retryable, || {
+        let tx = match in_flight.take() {
+            Some(tx) => match landed(&tx) {
+                Ok(Some(done)) => return Ok(done),
+                Ok(None) => tx,
+                Err(err) => {
+                    in_flight = Some(tx);
+                    return Err(err);
+                }
+            },
+            None => sign()?,
+        };
+        send(&tx).inspect_err(|err| {
+            if is_timeout(&err.to_string()) {
+                in_flight = Some(tx);
+            }
+        })
+    })
+}
+
+/// Result of one confirmed execute.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Execution {
+    pub signature: Signature,
+    pub status: u32,
+    pub output: Vec<u8>,
+}
+
+/// Send the execute transaction (`instructions`, typically a compute-budget
+/// instruction plus EXECUTE/EXECUTE_V3), then read the VM account back.
+pub fn execute(
+    client: &RpcClient,
+    retry: &RetryPolicy,
+    instructions: &[Instruction],
+    payer: &Keypair,
+    signers: &[&dyn Signer],
+    vm_pubkey: &Pubkey,
+    layout: &OutputLayout,
+) -> Result<Execution, Box<dyn std::error::Error>> {
+    let recent = client.get_latest_blockhash()?;
+    let signature = send_with_retry(client, retry, instructions, payer, signers, recent)?;
+    let (status, output) = fetch_output(client, vm_pubkey, layout)?;
+    Ok(Execution {
+        signature,
+        status,
+        output,
+    })
+}
+
+/// [`execute`], decoding the control-block status and i32 output.
+pub fn execute_and_read(
+    client: &RpcClient,
+    retry: &RetryPolicy,
+    instructions: &[Instruction],
+    payer: &Keypair,
+    signers: &[&dyn Signer],
+    vm_pubkey: &Pubkey,
+    layout: &OutputLayout,
+) -> Result<(u32, Vec<i32>), Box<dyn std::error::Error>> {
+    let run = execute(
+        client,
+        retry,
+        instructions,
+        payer,
+        signers,
+        vm_pubkey,
+        layout,
+    )?;
+    Ok((run.status, decode_i32(&run.output)))
+}
+
+/// `--json` output of a run.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct RunReport {
+    pub status: u32,
+    pub output: OutputValues,
+    pub vm: String,
+    pub signature: String,
+}
+
+impl RunReport {
+    pub fn new(vm_pubkey: &Pubkey, run: &Execution, output_type: OutputType) -> Self {
+        Self {
+            status: run.status,
+            output: OutputValues::decode(output_type, &run.output),
+            vm: vm_pubkey.to_string(),
+            signature: run.signature.to_string(),
+        }
+    }
+}
+
+/// The parts of a simulation response a dry run reports.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SimulationSummary {
+    pub err: Option<String>,
+    pub logs: Vec<String>,
+    pub units_consumed: Option<u64>,
+    /// Control-block status and output bytes, when the VM account came back
+    /// and decoded.
+    pub output: Option<(u32, Vec<u8>)>,
+}
+
+/// `result` is expected to carry the VM account first in `accounts`, as
+/// requested by [`simulate_execute`].
+pub fn parse_simulation(
+    result: &RpcSimulateTransactionResult,
+    layout: &OutputLayout,
+) -> SimulationSummary {
+    let output = result
+        .accounts
+        .as_ref()
+        .and_then(|accounts| accounts.first())
+        .and_then(|account| account.as_ref())
+        .and_then(|account| account.data.decode())
+        .and_then(|data| decode_output(&data, layout).ok());
+    SimulationSummary {
+        err: result.err.as_ref().map(|err| err.to_string()),
+        logs: result.logs.clone().unwrap_or_default(),
+        units_consumed: result.units_consumed,
+        output,
+    }
+}
+
+/// Simulate the execute transaction instead of sending it. Nothing is
+/// charged and the VM account is left untouched; its post-simulation state is
+/// requested back so the would-be status and output can be reported.
+pub fn simulate_execute(
+    client: &RpcClient,
+    instructions: &[Instruction],
+    payer: &Keypair,
+    signers: &[&dyn Signer],
+    vm_pubkey: &Pubkey,
+    layout: &OutputLayout,
+) -> Result<SimulationSummary, Box<dyn std::error::Error>> {
+    let recent = client.get_latest_blockhash()?;
+    let tx =
+        Transaction::new_signed_with_payer(instructions, Some(&payer.pubkey()), signers, recent);
+    let config = RpcSimulateTransactionConfig {
+        sig_verify: false,
+        accounts: Some(RpcSimulateTransactionAccountsConfig {
+            encoding: Some(UiAccountEncoding::Base64),
+            addresses: vec![vm_pubkey.to_string()],
+        }),
+        ..RpcSimulateTransactionConfig::default()
+    };
+    let response = client.simulate_transaction_with_config(&tx, config)?;
+    Ok(parse_simulation(&response.value, layout))
+}
+
+/// Bytes from a hex string; whitespace and a leading `0x` are ignored.
+pub fn parse_hex_bytes(text: &str) -> Result<Vec<u8>, String> {
+    let trimmed = text.trim();
+    let body = trimmed
+        .strip_prefix("0x")
+        .or_else(|| trimmed.strip_prefix("0X"))
+        .unwrap_or(trimmed);
+    let digits: Vec<u8> = body.bytes().filter(|b| !b.is_ascii_whitespace()).collect();
+    if !digits.len().is_multiple_of(2) {
+        return Err("hex input has an odd number of digits".to_string());
+    }
+    digits
+        .chunks_exact(2)
+        .map(|pair| {
+            let pair =
+                std::str::from_utf8(pair).map_err(|_| "hex input is not ASCII".to_string())?;
+            u8::from_str_radix(pair, 16).map_err(|_| format!("invalid hex byte {:?}", pair))
+        })
+        .collect()
+}
+
+/// Smallest budget in `1..=ceiling` for which `run` succeeds, assuming success
+/// is monotonic in the budget. `None` when even `ceiling` fails. `ceiling` is
+/// tried first so a hopeless search costs one call.
+pub fn search_min_instructions<E>(
+    ceiling: u64,
+    mut run: impl FnMut(u64) -> Result<bool, E>,
+) -> Result<Option<u64>, E> {
+    if ceiling == 0 || !run(ceiling)? {
+        return Ok(None);
+    }
+    // Invariant: `hi` succeeds, everything below `lo` fails.
+    let (mut lo, mut hi) = (1u64, ceiling);
+    while lo < hi {
+        let mid = lo + (hi - lo) / 2;
+        if run(mid)? {
+            hi = mid;
+        } else {
+            lo = mid + 1;
+        }
+    }
+    Ok(Some(hi))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use base64::Engine;
+    use solana_account_decoder::{UiAccount, UiAccountData};
+    use solana_sdk::{instruction::InstructionError, transaction::TransactionError};
+
+    fn account_with(control_offset: usize, status: u32, output_len: u32) -> Vec<u8> {
+        let mut data = vec![0u8; VM_ACCOUNT_SIZE_MIN];
+        let ctrl = MMU_VM_HEADER_SIZE + control_offset;
+        data[ctrl + CTRL_STATUS..ctrl + CTRL_STATUS + 4].copy_from_slice(&status.to_le_bytes());
+        data[ctrl + CTRL_OUTPUT_LEN..ctrl + CTRL_OUTPUT_LEN + 4]
+            .copy_from_slice(&output_len.to_le_bytes());
+        data
+    }
+
+    #[test]
+    fn read_u32_le_at_offset() {
+        let buf = [0xFF, 0x78, 0x56, 0x34, 0x12];
+        assert_eq!(read_u32_le(&buf, 1), 0x1234_5678);
+    }
+
+    #[test]
+    fn decode_i32_drops_trailing_partial_word() {
+        let mut buf = Vec::new();
+        buf.extend_from_slice(&(-2i32).to_le_bytes());
+        buf.extend_from_slice(&i32::MAX.to_le_bytes());
+        buf.extend_from_slice(&[1, 2]);
+        assert_eq!(decode_i32(&buf), vec![-2, i32::MAX]);
+        assert!(decode_i32(&[]).is_empty());
+    }
+
+    #[test]
+    fn decode_output_reads_status_and_len() {
+        let layout = OutputLayout {
+            control_offset: 0x100,
+            output_offset: 0x200,
+            output_max: 16,
+            use_max: false,
+        };
+        let mut data = account_with(0x100, 7, 8);
+        let out = MMU_VM_HEADER_SIZE + 0x200;
+        data[out..out + 4].copy_from_slice(&5i32.to_le_bytes());
+        data[out + 4..out + 8].copy_from_slice(&(-9i32).to_le_bytes());
+
+        let (status, bytes) = decode_output(&data, &layout).unwrap();
+        assert_eq!(status, 7);
+        assert_eq!(decode_i32(&bytes), vec![5, -9]);
+    }
+
+    #[test]
+    fn decode_output_use_max_and_bounds() {
+        let mut layout = OutputLayout {
+            control_offset: 0,
+            output_offset: 0x40,
+            output_max: 12,
+            use_max: true,
+        };
+        let data = account_with(0, 0, 0);
+        assert_eq!(decode_output(&data, &layout).unwrap().1.len(), 12);
+
+        layout.use_max = false;
+        assert!(decode_output(&data, &layout).unwrap().1.is_empty());
+
+        let data = account_with(0, 0, u32::MAX);
+        assert!(decode_output(&data, &layout).unwrap().1.is_empty());
+
+        assert!(decode_output(&data[..VM_ACCOUNT_SIZE_MIN - 1], &layout).is_err());
+        layout.control_offset = VM_ACCOUNT_SIZE_MIN;
+        assert!(decode_output(&data, &layout).is_err());
+    }
+
+    #[test]
+    fn check_threshold_matches_the_gatekeeper() {
+        let mut output = Vec::new();
+        for v in [5i32, -3, 9] {
+            output.extend_from_slice(&v.to_le_bytes());
+        }
+        assert_eq!(check_threshold(0, &output, 0, 5), Ok(5));
+        assert_eq!(check_threshold(0, &output, 2, -100), Ok(9));
+        // Signed comparison, equal passes.
+        assert_eq!(check_threshold(0, &output, 1, -3), Ok(-3));
+        assert!(check_threshold(0, &output, 1, 0)
+            .unwrap_err()
+            .contains("output[1]=-3 is below threshold 0"));
+        assert!(check_threshold(0, &output, 3, 0)
+            .unwrap_err()
+            .contains("out of bounds"));
+        assert!(check_threshold(0, &output[..3], 0, 0).is_err());
+        assert!(check_threshold(7, &output, 0, 0)
+            .unwrap_err()
+            .contains("status 7"));
+    }
+
+    fn abi(text: &str) -> toml::value::Table {
+        text.parse::<toml::Value>()
+            .unwrap()
+            .as_table()
+            .unwrap()
+            .clone()
+    }
+
+    #[test]
+    fn output_layout_from_abi_requires_every_field() {
+        let full = abi("control_offset = 0\noutput_offset = 0x2000\noutput_max = 256");
+        assert_eq!(
+            OutputLayout::from_abi(&full, true),
+            Ok(OutputLayout {
+                control_offset: 0,
+                output_offset: 0x2000,
+                output_max: 256,
+                use_max: true,
+            })
+        );
+
+        for missing in ["control_offset", "output_offset", "output_max"] {
+            let mut partial = full.clone();
+            partial.remove(missing);
+            let err = OutputLayout::from_abi(&partial, false).unwrap_err();
+            assert!(
+                err.contains(&format!("abi.{} is required", missing)),
+                "{err}"
+            );
+        }
+
+        let negative = abi("control_offset = -1\noutput_offset = 0\noutput_max = 4");
+        assert!(OutputLayout::from_abi(&negative, false).is_err());
+        let text = abi("control_offset = \"0\"\noutput_offset = 0\noutput_max = 4");
+        assert!(OutputLayout::from_abi(&text, false)
+            .unwrap_err()
+            .contains("must be an integer"));
+    }
+
+    #[test]
+    fn output_layout_from_abi_checks_scratch_bounds() {
+        let edge = format!(
+            "control_offset = 0\noutput_offset = {}\noutput_max = 16",
+            SCRATCH_SIZE - 16
+        );
+        assert!(OutputLayout::from_abi(&abi(&edge), false).is_ok());
+        let past = format!(
+            "control_offset = 0\noutput_offset = {}\noutput_max = 17",
+            SCRATCH_SIZE - 16
+        );
+        assert!(OutputLayout::from_abi(&abi(&past), false).is_err());
+        let ctrl = format!(
+            "control_offset = {}\noutput_offset = 0\noutput_max = 0",
+            SCRATCH_SIZE - 32
+        );
+        assert!(OutputLayout::from_abi(&abi(&ctrl), false).is_err());
+    }
+
+    fn simulation(data: Option<Vec<u8>>) -> RpcSimulateTransactionResult {
+        RpcSimulateTransactionResult {
+            err: None,
+            logs: Some(vec!["Program log: hello".to_string()]),
+            accounts: data.map(|data| {
+                vec![Some(UiAccount {
+                    lamports: 1,
+                    data: UiAccountData::Binary(
+                        base64::engine::general_purpose::STANDARD.encode(data),
+                        UiAccountEncoding::Base64,
+                    ),
+                    owner: Pubkey::default().to_string(),
+                    executable: false,
+                    rent_epoch: 0,
+                    space: None,
+                })]
+            }),
+            units_consumed: Some(12_345),
+            return_data: None,
+            inner_instructions: None,
+        }
+    }
+
+    #[test]
+    fn parse_hex_bytes_accepts_prefix_and_whitespace() {
+        assert_eq!(
+            parse_hex_bytes("0x01ff 10\n").unwrap(),
+            vec![0x01, 0xFF, 0x10]
+        );
+        assert_eq!(parse_hex_bytes("").unwrap(), Vec::<u8>::new());
+        assert!(parse_hex_bytes("abc").is_err());
+        assert!(parse_hex_bytes("zz").is_err());
+    }
+
+    #[test]
+    fn parse_simulation_reads_account_status() {
+        let layout = OutputLayout {
+            control_offset: 0x80,
+            output_offset: 0x100,
+            output_max: 0,
+            use_max: false,
+        };
+        let mut data = account_with(0x80, 3, 4);
+        let out = MMU_VM_HEADER_SIZE + 0x100;
+        data[out..out + 4].copy_from_slice(&42i32.to_le_bytes());
+
+        let summary = parse_simulation(&simulation(Some(data)), &layout);
+        assert_eq!(summary.err, None);
+        assert_eq!(summary.logs, vec!["Program log: hello".to_string()]);
+        assert_eq!(summary.units_consumed, Some(12_345));
+        assert_eq!(summary.output, Some((3, 42i32.to_le_bytes().to_vec())));
+    }
+
+    #[test]
+    fn parse_simulation_without_account() {
+        let mut result = simulation(None);
+        result.err = Some(TransactionError::InstructionError(
+            1,
+            InstructionError::Custom(7),
+        ));
+        result.logs = None;
+        let summary = parse_simulation(&result, &OutputLayout::default());
+        assert!(summary.err.is_some());
+        assert!(summary.logs.is_empty());
+        assert_eq!(summary.output, None);
+
+        // A truncated account is reported as "no output", not an error.
+        let summary = parse_simulation(&simulation(Some(vec![0; 64])), &OutputLayout::default());
+        assert_eq!(summary.output, None);
+    }
+
+    #[test]
+    fn search_min_instructions_converges() {
+        for needed in [1u64, 2, 3, 999, 1_000, 1_001, 49_999, 50_000] {
+            let mut calls = 0;
+            let found = search_min_instructions(50_000, |budget| {
+                calls += 1;
+                Ok::<_, ()>(budget >= needed)
+            })
+            .unwrap();
+            assert_eq!(found, Some(needed));
+            assert!(calls <= 18, "{} calls for {}", calls, needed);
+        }
+    }
+
+    #[test]
+    fn search_min_instructions_ceiling_and_errors() {
+        assert_eq!(
+            search_min_instructions(100, |budget| Ok::<_, ()>(budget > 100)),
+            Ok(None)
+        );
+        assert_eq!(search_min_instructions(0, |_| Ok::<_, ()>(true)), Ok(None));
+
+        let mut calls = 0;
+        let result = search_min_instructions(1 << 20, |budget| {
+            calls += 1;
+            if calls == 3 {
+                Err("rpc down")
+            } else {
+                Ok(budget >= 10)
+            }
+        });
+        assert_eq!(result, Err("rpc down"));
+        assert_eq!(calls, 3);
+    }
+
+    #[test]
+    fn run_report_json_round_trip() {
+        let vm = Pubkey::new_unique();
+        let mut output = 7i32.to_le_bytes().to_vec();
+        output.extend_from_slice(&(-1i32).to_le_bytes());
+        let run = Execution {
+            signature: Signature::from([3u8; 64]),
+            status: 0,
+            output,
+        };
+        let report = RunReport::new(&vm, &run, OutputType::I32);
+        let json = serde_json::to_string(&report).unwrap();
+
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(value["status"], 0);
+        assert_eq!(value["output"], serde_json::json!([7, -1]));
+        assert_eq!(value["vm"], vm.to_string());
+        assert_eq!(value["signature"], run.signature.to_string());
+        assert_eq!(value.as_object().unwrap().len(), 4);
+
+        let back: RunReport = serde_json::from_str(&json).unwrap();
+        assert_eq!(back, report);
+    }
+
+    #[test]
+    fn decode_f32_and_u32_words() {
+        let mut buf = Vec::new();
+        for v in [1.5f32, -2.0, 0.1] {
+            buf.extend_from_slice(&v.to_le_bytes());
+        }
+        buf.push(0xFF);
+        assert_eq!(decode_f32(&buf), vec![1.5, -2.0, 0.1]);
+        // 1.0f32 == 0x3F80_0000
+        assert_eq!(decode_f32(&[0x00, 0x00, 0x80, 0x3F]), vec![1.0]);
+        assert_eq!(decode_u32(&(-1i32).to_le_bytes()), vec![u32::MAX]);
+
+        let values = OutputValues::decode(OutputType::F32, &buf[..8]);
+        assert_eq!(values.describe(), "Output (f32): [1.500000, -2.000000]");
+        assert_eq!(serde_json::to_string(&values).unwrap(), "[1.5,-2.0]");
+    }
+
+    #[test]
+    fn output_type_from_str() {
+        assert_eq!("f32".parse::<OutputType>(), Ok(OutputType::F32));
+        assert_eq!("u32".parse::<OutputType>(), Ok(OutputType::U32));
+        assert_eq!("i32".parse::<OutputType>(), Ok(OutputType::I32));
+        assert!("f64".parse::<OutputType>().is_err());
+    }
+
+    #[test]
+    fn retry_policy_retries_transient_then_succeeds() {
+        let mut policy = retry_policy(Some(2));
+        assert_eq!(policy.max_attempts, 3);
+        policy.base_delay = std::time::Duration::ZERO;
+        policy.max_delay = std::time::Duration::ZERO;
+
+        let mut calls = 0;
+        let result = policy.run(|| {
+            calls += 1;
+            if calls < 3 {
+                Err("Blockhash not found".to_string())
+            } else {
+                Ok(calls)
+            }
+        });
+        assert_eq!(result, Ok(3));
+
+        // Exhausted: the last transient error surfaces.
+        let mut calls = 0;
+        let result: Result<(), String> = policy.run(|| {
+            calls += 1;
+            Err("429 Too Many Requests".to_string())
+        });
+        assert!(result.is_err());
+        assert_eq!(calls, 3);
+
+        // Program errors are not retried.
+        let mut calls = 0;
+        let result: Result<(), String> = policy.run(|| {
+            calls += 1;
+            Err("custom program error: 0x2003".to_string())
+        });
+        assert!(result.is_err());
+        assert_eq!(calls, 1);
+
+        assert_eq!(retry_policy(Some(0)).max_attempts, 1);
+    }
+
+    #[test]
+    fn send_retries_timeouts_without_resigning() {
+        let mut policy = retry_policy(Some(3));
+        policy.base_delay = std::time::Duration::ZERO;
+        policy.max_delay = std::time::Duration::ZERO;
+
+        // Timed out but landed: the status check returns it, nothing is resent.
+        let (mut signed, mut sent) = (0, 0);
+        let result: Result<u32, String> = send_at_most_once(
+            &policy,
+            || {
+                signed += 1;
+                Ok(signed)
+            },
+            |_| {
+                sent += 1;
+                Err("operation timed out".to_string())
+            },
+            |tx| Ok(Some(*tx)),
+        );
+        assert_eq!((result, signed, sent), (Ok(1), 1, 1));
+
+        // Timed out and unseen: the same transaction goes out again.
+        let (mut signed, mut sent) = (0, Vec::new());
+        let result: Result<u32, String> = send_at_most_once(
+            &policy,
+            || {
+                signed += 1;
+                Ok(signed)
+            },
+            |tx| {
+                sent.push(*tx);
+                if sent.len() < 2 {
+                    Err("request timeout".to_string())
+                } else {
+                    Ok(*tx)
+                }
+            },
+            |_| Ok(None),
+        );
+        assert_eq!((result, signed, sent), (Ok(1), 1, vec![1, 1]));
+
+        // Rejected before submission: re-signed with a fresh blockhash.
+        let (mut signed, mut sent) = (0, Vec::new());
+        let result: Result<u32, String> = send_at_most_once(
+            &policy,
+            || {
+                signed += 1;
+                Ok(signed)
+            },
+            |tx| {
+                sent.push(*tx);
+                if sent.len() < 2 {
+                    Err("Blockhash not found".to_string())
+                } else {
+                    Ok(*tx)
+                }
+            },
+            |_| Ok(None),
+        );
+        assert_eq!((result, signed, sent), (Ok(2), 2, vec![1, 2]));
+
+        // Other transient errors could follow submission: not retried.
+        let mut sent = 0;
+        let result: Result<u32, String> = send_at_most_once(
+            &policy,
+            || Ok(0),
+            |_| {
+                sent += 1;
+                Err("connection reset by peer".to_string())
+            },
+            |_| Ok(None),
+        );
+        assert!(result.is_err());
+        assert_eq!(sent, 1);
+    }
+}
@@ -1,3 +1,12 @@
+use frostbite_modelkit_tools::fbh1::{build_fbh1, schema_id};
+use frostbite_modelkit_tools::retry::RetryPolicy;
+use frostbite_modelkit_tools::seeds::{derive_seeded_address, segment_seed_string, vm_seed_string};
+use frostbite_sdk_rust::{
+    check_threshold, execute, fetch_output, parse_hex_bytes, retry_policy, search_min_instructions,
+    send_with_retry, simulate_execute, Execution, OutputLayout, OutputType, OutputValues,
+    RunReport, SimulationSummary, CTRL_INPUT_LEN, CTRL_INPUT_PTR, MMU_VM_HEADER_SIZE,
+};
+use futures::stream::{FuturesUnordered, StreamExt};
 use solana_client::rpc_client::RpcClient;
 use solana_sdk::{
     compute_budget::ComputeBudgetInstruction,
@@ -10,29 +19,18 @@ use std::env;
 use std::fs;
 use std::path::{Path, PathBuf};
 use std::str::FromStr;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::Semaphore;
 use toml::value::Table;
 
-const VM_HEADER_SIZE: usize = 552;
-const MMU_VM_HEADER_SIZE: usize = VM_HEADER_SIZE;
-const VM_ACCOUNT_SIZE_MIN: usize = 262_696;
 const EXECUTE_OP: u8 = 2;
+const WRITE_ACCOUNT_OP: u8 = 5;
 const EXECUTE_V3_OP: u8 = 43;
 const SEGMENT_KIND_WEIGHTS: u8 = 1;
 const SEGMENT_KIND_RAM: u8 = 2;
-
-fn read_u32_le(buf: &[u8], offset: usize) -> u32 {
-    u32::from_le_bytes(buf[offset..offset + 4].try_into().unwrap())
-}
-
-fn decode_i32(buf: &[u8]) -> Vec<i32> {
-    let mut out = Vec::new();
-    let mut i = 0usize;
-    while i + 4 <= buf.len() {
-        out.push(i32::from_le_bytes(buf[i..i + 4].try_into().unwrap()));
-        i += 4;
-    }
-    out
-}
+const WRITE_CHUNK_SIZE: usize = 900;
+const DEFAULT_CONCURRENCY: usize = 4;
 
 fn parse_u64_value(raw: &str) -> Result<u64, Box<dyn std::error::Error>> {
     let value = if let Some(hex) = raw.strip_prefix("0x").or_else(|| raw.strip_prefix("0X")) {
@@ -99,12 +97,85 @@ fn segment_kind_code(kind: &str) -> Option<u8> {
     }
 }
 
-fn vm_seed_string(vm_seed: u64) -> String {
-    format!("fbv1:vm:{vm_seed:016x}")
+struct BenchStats {
+    min: Duration,
+    median: Duration,
+    max: Duration,
+    total: Duration,
 }
 
-fn segment_seed_string(vm_seed: u64, kind: u8, slot: u8) -> String {
-    format!("fbv1:sg:{vm_seed:016x}:{kind:02x}{slot:02x}")
+fn bench_stats(latencies: &[Duration]) -> Option<BenchStats> {
+    if latencies.is_empty() {
+        return None;
+    }
+    let mut sorted = latencies.to_vec();
+    sorted.sort();
+    let mid = sorted.len() / 2;
+    let median = if sorted.len().is_multiple_of(2) {
+        (sorted[mid - 1] + sorted[mid]) / 2
+    } else {
+        sorted[mid]
+    };
+    Some(BenchStats {
+        min: sorted[0],
+        median,
+        max: sorted[sorted.len() - 1],
+        total: sorted.iter().sum(),
+    })
+}
+
+fn write_account_ix(
+    program_id: Pubkey,
+    signer: Pubkey,
+    target: Pubkey,
+    offset: usize,
+    chunk: &[u8],
+) -> Instruction {
+    let mut data = Vec::with_capacity(1 + 4 + chunk.len());
+    data.push(WRITE_ACCOUNT_OP);
+    data.extend_from_slice(&(offset as u32).to_le_bytes());
+    data.extend_from_slice(chunk);
+    Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new_readonly(signer, true),
+            AccountMeta::new(target, false),
+        ],
+        data,
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn write_vm_bytes(
+    client: &RpcClient,
+    retry: &RetryPolicy,
+    program_id: Pubkey,
+    payer: &Keypair,
+    signers: &[&dyn Signer],
+    signer: Pubkey,
+    vm_pubkey: Pubkey,
+    offset: usize,
+    bytes: &[u8],
+) -> Result<(), Box<dyn std::error::Error>> {
+    for (idx, chunk) in bytes.chunks(WRITE_CHUNK_SIZE).enumerate() {
+        let ix = write_account_ix(
+            program_id,
+            signer,
+            vm_pubkey,
+            offset + idx * WRITE_CHUNK_SIZE,
+            chunk,
+        );
+        retry.run(|| -> Result<_, Box<dyn std::error::Error>> {
+            let tx = Transaction::new_signed_with_payer(
+                std::slice::from_ref(&ix),
+                Some(&payer.pubkey()),
+                signers,
+                client.get_latest_blockhash()?,
+            );
+            Ok(client.send_and_confirm_transaction(&tx)?)
+        })?;
+    }
+    Ok(())
 }
 
 #[derive(Clone)]
@@ -147,15 +218,12 @@ fn parse_pda_segments(
             None
         };
 
-        let kind_str = table
-            .get("kind")
-            .and_then(|v| v.as_str())
-            .ok_or_else(|| {
-                format!(
-                    "segment {} missing kind in deterministic account mode",
-                    idx + 1
-                )
-            })?;
+        let kind_str = table.get("kind").and_then(|v| v.as_str()).ok_or_else(|| {
+            format!(
+                "segment {} missing kind in deterministic account mode",
+                idx + 1
+            )
+        })?;
         let kind = segment_kind_code(kind_str).ok_or_else(|| {
             format!(
                 "segment {} has unsupported kind '{}' (expected weights|ram)",
@@ -225,7 +293,7 @@ fn parse_pda_segments(
             .into());
         }
 
-        let derived_pubkey = Pubkey::create_with_seed(
+        let derived_pubkey = derive_seeded_address(
             authority_pubkey,
             &segment_seed_string(vm_seed, kind, slot),
             program_id,
@@ -254,13 +322,11 @@ fn parse_pda_segments(
     parsed.sort_by_key(|entry| entry.slot);
     for (idx, segment) in parsed.iter().enumerate() {
         if idx > 0 && parsed[idx - 1].slot == segment.slot {
-            return Err(
-                format!(
-                    "duplicate segment slot {} in deterministic account mode",
-                    segment.slot
-                )
-                .into(),
-            );
+            return Err(format!(
+                "duplicate segment slot {} in deterministic account mode",
+                segment.slot
+            )
+            .into());
         }
         let expected_slot = (idx + 1) as u8;
         if segment.slot != expected_slot {
@@ -278,65 +344,449 @@ fn parse_pda_segments(
     Ok(parsed)
 }
 
-fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let args: Vec<String> = env::args().collect();
-    let mut manifest_path: Option<String> = None;
-    let mut accounts_path: Option<String> = None;
-    let mut instructions: u64 = 50_000;
-    let mut rpc_override: Option<String> = None;
-    let mut program_override: Option<String> = None;
-    let mut payer_override: Option<String> = None;
-    let mut authority_override: Option<String> = None;
-    let mut use_max = false;
+/// Copy of an EXECUTE/EXECUTE_V3 instruction with the u64 budget at
+/// `budget_at` in its data replaced.
+fn with_budget(exec_ix: &Instruction, budget_at: usize, budget: u64) -> Instruction {
+    let mut ix = exec_ix.clone();
+    ix.data[budget_at..budget_at + 8].copy_from_slice(&budget.to_le_bytes());
+    ix
+}
+
+#[derive(Debug)]
+struct CliArgs {
+    manifest_path: Option<String>,
+    accounts_path: Option<String>,
+    instructions: u64,
+    rpc_override: Option<String>,
+    program_override: Option<String>,
+    payer_override: Option<String>,
+    authority_override: Option<String>,
+    use_max: bool,
+    bench_runs: usize,
+    bench_verify: bool,
+    input_path: Option<String>,
+    input_hex: Option<String>,
+    fbh1: bool,
+    fbh1_crc: bool,
+    schema_hash: Option<u32>,
+    dry_run: bool,
+    auto_instructions: Option<u64>,
+    json: bool,
+    output_type: OutputType,
+    max_retries: Option<u32>,
+    manifest_list: Option<String>,
+    concurrency: usize,
+    verify_threshold: Option<i32>,
+    verify_index: Option<usize>,
+}
+
+impl Default for CliArgs {
+    fn default() -> Self {
+        Self {
+            manifest_path: None,
+            accounts_path: None,
+            instructions: 50_000,
+            rpc_override: None,
+            program_override: None,
+            payer_override: None,
+            authority_override: None,
+            use_max: false,
+            bench_runs: 0,
+            bench_verify: false,
+            input_path: None,
+            input_hex: None,
+            fbh1: false,
+            fbh1_crc: false,
+            schema_hash: None,
+            dry_run: false,
+            auto_instructions: None,
+            json: false,
+            output_type: OutputType::I32,
+            max_retries: None,
+            manifest_list: None,
+            concurrency: DEFAULT_CONCURRENCY,
+            verify_threshold: None,
+            verify_index: None,
+        }
+    }
+}
+
+/// `args[0]` is the program name; unknown flags are skipped.
+const DRY_RUN_WITH_INPUT: &str =
+    "--input writes to the VM account and cannot be combined with --dry-run";
 
+fn parse_args(args: &[String]) -> Result<CliArgs, Box<dyn std::error::Error>> {
+    let mut cli = CliArgs::default();
     let mut i = 1;
     while i < args.len() {
         match args[i].as_str() {
             "--manifest" => {
-                manifest_path = args.get(i + 1).cloned();
+                cli.manifest_path = args.get(i + 1).cloned();
                 i += 2;
             }
             "--accounts" => {
-                accounts_path = args.get(i + 1).cloned();
+                cli.accounts_path = args.get(i + 1).cloned();
                 i += 2;
             }
             "--instructions" => {
                 if let Some(val) = args.get(i + 1) {
-                    instructions = val.parse()?;
+                    cli.instructions = val.parse()?;
                 }
                 i += 2;
             }
             "--rpc-url" => {
-                rpc_override = args.get(i + 1).cloned();
+                cli.rpc_override = args.get(i + 1).cloned();
                 i += 2;
             }
             "--program-id" => {
-                program_override = args.get(i + 1).cloned();
+                cli.program_override = args.get(i + 1).cloned();
                 i += 2;
             }
             "--payer" => {
-                payer_override = args.get(i + 1).cloned();
+                cli.payer_override = args.get(i + 1).cloned();
                 i += 2;
             }
             "--authority-keypair" => {
-                authority_override = args.get(i + 1).cloned();
+                cli.authority_override = args.get(i + 1).cloned();
                 i += 2;
             }
             "--use-max" => {
-                use_max = true;
+                cli.use_max = true;
                 i += 1;
             }
+            "--bench" => {
+                if let Some(val) = args.get(i + 1) {
+                    cli.bench_runs = val.parse()?;
+                }
+                i += 2;
+            }
+            "--input" => {
+                cli.input_path = args.get(i + 1).cloned();
+                i += 2;
+            }
+            "--input-hex" => {
+                cli.input_hex = args.get(i + 1).cloned();
+                i += 2;
+            }
+            "--fbh1" => {
+                cli.fbh1 = true;
+                i += 1;
+            }
+            "--fbh1-crc" => {
+                cli.fbh1_crc = true;
+                i += 1;
+            }
+            "--schema-hash" => {
+                if let Some(val) = args.get(i + 1) {
+                    let hash = parse_u64_value(val)?;
+                    cli.schema_hash =
+                        Some(u32::try_from(hash).map_err(|_| "--schema-hash must fit in u32")?);
+                }
+                i += 2;
+            }
+            "--bench-verify" => {
+                cli.bench_verify = true;
+                i += 1;
+            }
+            "--dry-run" => {
+                cli.dry_run = true;
+                i += 1;
+            }
+            "--output-type" => {
+                if let Some(val) = args.get(i + 1) {
+                    cli.output_type = val.parse()?;
+                }
+                i += 2;
+            }
+            "--max-retries" => {
+                if let Some(val) = args.get(i + 1) {
+                    cli.max_retries = Some(val.parse()?);
+                }
+                i += 2;
+            }
+            "--json" => {
+                cli.json = true;
+                i += 1;
+            }
+            "--manifest-list" => {
+                cli.manifest_list = args.get(i + 1).cloned();
+                i += 2;
+            }
+            "--concurrency" => {
+                if let Some(val) = args.get(i + 1) {
+                    cli.concurrency = val.parse()?;
+                }
+                i += 2;
+            }
+            "--verify-threshold" => {
+                if let Some(val) = args.get(i + 1) {
+                    cli.verify_threshold = Some(val.parse()?);
+                }
+                i += 2;
+            }
+            "--verify-index" => {
+                if let Some(val) = args.get(i + 1) {
+                    cli.verify_index = Some(val.parse::<u32>()? as usize);
+                }
+                i += 2;
+            }
+            "--auto-instructions" => {
+                if let Some(val) = args.get(i + 1) {
+                    cli.auto_instructions = Some(val.parse()?);
+                }
+                i += 2;
+            }
             _ => {
                 i += 1;
             }
         }
     }
+    if cli.input_path.is_some() && cli.input_hex.is_some() {
+        return Err("--input and --input-hex are mutually exclusive".into());
+    }
+    let has_input = cli.input_path.is_some() || cli.input_hex.is_some();
+    if cli.dry_run && has_input {
+        return Err(DRY_RUN_WITH_INPUT.into());
+    }
+    if (cli.fbh1_crc || cli.schema_hash.is_some()) && !cli.fbh1 {
+        return Err("--fbh1-crc and --schema-hash need --fbh1".into());
+    }
+    if cli.fbh1 && !has_input {
+        return Err("--fbh1 needs --input or --input-hex".into());
+    }
+    if cli.dry_run && cli.bench_runs > 0 {
+        return Err("--bench cannot be combined with --dry-run".into());
+    }
+    if cli.auto_instructions == Some(0) {
+        return Err("--auto-instructions ceiling must be positive".into());
+    }
+    if cli.verify_index.is_some() && cli.verify_threshold.is_none() {
+        return Err("--verify-index needs --verify-threshold".into());
+    }
+    if cli.verify_threshold.is_some() && (cli.bench_runs > 0 || cli.manifest_list.is_some()) {
+        return Err("--verify-threshold checks a single run; drop --bench/--manifest-list".into());
+    }
+    if cli.concurrency == 0 {
+        return Err("--concurrency must be positive".into());
+    }
+    if cli.manifest_list.is_some() {
+        let single_run_only = [
+            (cli.manifest_path.is_some(), "--manifest"),
+            (cli.accounts_path.is_some(), "--accounts"),
+            (has_input, "--input/--input-hex"),
+            (cli.bench_runs > 0, "--bench"),
+            (cli.dry_run, "--dry-run"),
+            (cli.auto_instructions.is_some(), "--auto-instructions"),
+        ];
+        if let Some((_, flag)) = single_run_only.iter().find(|(set, _)| *set) {
+            return Err(format!("{} cannot be combined with --manifest-list", flag).into());
+        }
+    }
+    Ok(cli)
+}
 
-    let manifest_path = manifest_path.ok_or("--manifest required")?;
-    let accounts_path = accounts_path.ok_or("--accounts required")?;
+/// `--rpc-url` / `--program-id` / `--payer` / `--authority-keypair`, which
+/// take precedence over the accounts file.
+#[derive(Clone, Debug, Default)]
+struct Overrides {
+    rpc_url: Option<String>,
+    program_id: Option<String>,
+    payer: Option<String>,
+    authority: Option<String>,
+}
+
+/// Everything needed to execute one VM, resolved from a manifest and an
+/// accounts file.
+struct Target {
+    rpc_url: String,
+    program_id: Pubkey,
+    payer: Keypair,
+    authority_keypair: Option<Keypair>,
+    signer_pubkey: Pubkey,
+    vm_pubkey: Pubkey,
+    exec_ix: Instruction,
+    /// Offset of the instruction budget in `exec_ix.data`.
+    budget_at: usize,
+    manifest: toml::Value,
+    layout: OutputLayout,
+}
+
+impl Target {
+    /// Payer first, then the authority when it is a different key.
+    fn signers(&self) -> Vec<&dyn Signer> {
+        let mut signers: Vec<&dyn Signer> = vec![&self.payer];
+        if let Some(authority) = self.authority_keypair.as_ref() {
+            if authority.pubkey() != self.payer.pubkey() {
+                signers.push(authority);
+            }
+        }
+        signers
+    }
+}
+
+/// `(manifest, accounts)` pairs from a `--manifest-list` file: one pair per
+/// line, separated by whitespace or a comma. Blank lines and `#` comments are
+/// skipped; relative paths are resolved against `base`, the list's directory.
+fn parse_manifest_list(text: &str, base: &Path) -> Result<Vec<(PathBuf, PathBuf)>, String> {
+    let mut pairs = Vec::new();
+    for (lineno, raw) in text.lines().enumerate() {
+        let line = raw.split('#').next().unwrap_or("").trim();
+        if line.is_empty() {
+            continue;
+        }
+        let fields: Vec<&str> = line
+            .split(|c: char| c == ',' || c.is_whitespace())
+            .filter(|field| !field.is_empty())
+            .collect();
+        let [manifest, accounts] = fields[..] else {
+            return Err(format!(
+                "manifest list line {}: expected `<manifest> <accounts>`, got {:?}",
+                lineno + 1,
+                raw.trim()
+            ));
+        };
+        pairs.push((base.join(manifest), base.join(accounts)));
+    }
+    if pairs.is_empty() {
+        return Err("manifest list has no entries".to_string());
+    }
+    Ok(pairs)
+}
+
+/// One confirmed execute of `target`, on its own blocking RPC client.
+fn run_target(target: &Target, max_retries: Option<u32>) -> Result<Execution, String> {
+    let client = RpcClient::new(target.rpc_url.clone());
+    let cu_ix = ComputeBudgetInstruction::set_compute_unit_limit(1_400_000);
+    execute(
+        &client,
+        &retry_policy(max_retries),
+        &[cu_ix, target.exec_ix.clone()],
+        &target.payer,
+        &target.signers(),
+        &target.vm_pubkey,
+        &target.layout,
+    )
+    .map_err(|err| err.to_string())
+}
+
+/// Execute every target with at most `concurrency` transactions in flight.
+/// Results come back in input order.
+async fn run_targets(
+    targets: Vec<Target>,
+    concurrency: usize,
+    max_retries: Option<u32>,
+) -> Result<Vec<(Pubkey, Result<Execution, String>)>, Box<dyn std::error::Error>> {
+    let semaphore = Arc::new(Semaphore::new(concurrency));
+    let mut futures = FuturesUnordered::new();
+    for (index, target) in targets.into_iter().enumerate() {
+        let permit = semaphore.clone().acquire_owned().await?;
+        futures.push(tokio::task::spawn_blocking(move || {
+            let result = run_target(&target, max_retries);
+            drop(permit);
+            (index, target.vm_pubkey, result)
+        }));
+    }
+    let mut results = Vec::with_capacity(futures.len());
+    while let Some(joined) = futures.next().await {
+        results.push(joined?);
+    }
+    results.sort_by_key(|(index, _, _)| *index);
+    Ok(results
+        .into_iter()
+        .map(|(_, vm, result)| (vm, result))
+        .collect())
+}
+
+fn run_manifest_list(
+    list_path: &str,
+    overrides: &Overrides,
+    cli: &CliArgs,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let base = Path::new(list_path)
+        .parent()
+        .unwrap_or_else(|| Path::new("."));
+    let pairs = parse_manifest_list(&fs::read_to_string(list_path)?, base)?;
+    let mut targets = Vec::with_capacity(pairs.len());
+    for (manifest, accounts) in &pairs {
+        let target = load_target(
+            &manifest.to_string_lossy(),
+            &accounts.to_string_lossy(),
+            overrides,
+            cli.instructions,
+            cli.use_max,
+        )
+        .map_err(|err| format!("{}: {}", manifest.display(), err))?;
+        targets.push(target);
+    }
+
+    progress(
+        cli.json,
+        &format!(
+            "Executing {} VMs (concurrency {})",
+            targets.len(),
+            cli.concurrency
+        ),
+    );
+    let runtime = tokio::runtime::Runtime::new()?;
+    let results = runtime.block_on(run_targets(targets, cli.concurrency, cli.max_retries))?;
+
+    let mut failed = 0usize;
+    let mut reports = Vec::new();
+    for ((manifest, _), (vm, result)) in pairs.iter().zip(&results) {
+        match result {
+            Ok(run) if cli.json => reports.push(serde_json::json!({
+                "manifest": manifest.display().to_string(),
+                "report": RunReport::new(vm, run, cli.output_type),
+            })),
+            Ok(run) => {
+                println!("{} (VM {})", manifest.display(), vm);
+                println!("  Status: {}", run.status);
+                if run.output.is_empty() {
+                    println!("  Output: <empty>");
+                } else {
+                    println!(
+                        "  {}",
+                        OutputValues::decode(cli.output_type, &run.output).describe()
+                    );
+                }
+            }
+            Err(err) => {
+                failed += 1;
+                if cli.json {
+                    reports.push(serde_json::json!({
+                        "manifest": manifest.display().to_string(),
+                        "vm": vm.to_string(),
+                        "error": err,
+                    }));
+                } else {
+                    println!("{} (VM {})", manifest.display(), vm);
+                    println!("  Error: {}", err);
+                }
+            }
+        }
+    }
+    if cli.json {
+        println!("{}", serde_json::to_string(&reports)?);
+    }
+    if failed > 0 {
+        return Err(format!("{} of {} executions failed", failed, results.len()).into());
+    }
+    Ok(())
+}
 
-    let accounts_toml: toml::Value = fs::read_to_string(&accounts_path)?.parse()?;
-    let manifest_toml: toml::Value = fs::read_to_string(&manifest_path)?.parse()?;
+fn load_target(
+    manifest_path: &str,
+    accounts_path: &str,
+    overrides: &Overrides,
+    instructions: u64,
+    use_max: bool,
+) -> Result<Target, Box<dyn std::error::Error>> {
+    let rpc_override = overrides.rpc_url.clone();
+    let program_override = overrides.program_id.clone();
+    let payer_override = overrides.payer.clone();
+    let authority_override = overrides.authority.clone();
+    let accounts_toml: toml::Value = fs::read_to_string(accounts_path)?.parse()?;
+    let manifest_toml: toml::Value = fs::read_to_string(manifest_path)?.parse()?;
 
     let cluster = accounts_toml.get("cluster").and_then(|v| v.as_table());
     let rpc_url = rpc_override
@@ -378,7 +828,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
             entry
                 .get("authority_keypair")
                 .and_then(|v| v.as_str())
-                .map(|value| resolve_accounts_path(&accounts_path, value))
+                .map(|value| resolve_accounts_path(accounts_path, value))
         })
     });
     let authority_keypair: Option<Keypair> = match authority_path {
@@ -395,7 +845,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
             .and_then(|v| v.as_str())
         {
             if authority_pubkey.to_string() != expected_authority {
-            return Err(
+                return Err(
                 "authority signer pubkey does not match vm.authority; provide matching --authority-keypair or update accounts file"
                         .into(),
                 );
@@ -411,7 +861,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         authority_pubkey
     };
     let vm_pubkey = if let Some(vm_seed) = vm_seed {
-        let derived_vm = Pubkey::create_with_seed(
+        let derived_vm = derive_seeded_address(
             &authority_derivation_pubkey,
             &vm_seed_string(vm_seed),
             &program_id,
@@ -429,28 +879,22 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         configured_vm_pubkey.ok_or("Missing vm.pubkey in accounts file")?
     };
 
+    let signer_pubkey = if vm_seed.is_some() {
+        authority_pubkey
+    } else {
+        payer.pubkey()
+    };
     let mut metas = Vec::new();
-    metas.push(AccountMeta::new_readonly(
-        if vm_seed.is_some() {
-            authority_pubkey
-        } else {
-            payer.pubkey()
-        },
-        true,
-    ));
+    metas.push(AccountMeta::new_readonly(signer_pubkey, true));
     metas.push(AccountMeta::new(vm_pubkey, false));
 
-    let data = if let Some(vm_seed) = vm_seed {
+    let (data, budget_at) = if let Some(vm_seed) = vm_seed {
         let segments = accounts_toml
             .get("segments")
             .and_then(|v| v.as_array())
             .ok_or("accounts file has no segments in deterministic account mode")?;
-        let pda_segments = parse_pda_segments(
-            segments,
-            vm_seed,
-            &authority_derivation_pubkey,
-            &program_id,
-        )?;
+        let pda_segments =
+            parse_pda_segments(segments, vm_seed, &authority_derivation_pubkey, &program_id)?;
         if pda_segments.len() > 15 {
             return Err("deterministic execute supports at most 15 mapped segments".into());
         }
@@ -465,13 +909,14 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         let mut data = Vec::with_capacity(1 + 8 + 8 + 1 + 1 + pda_segments.len());
         data.push(EXECUTE_V3_OP);
         data.extend_from_slice(&vm_seed.to_le_bytes());
+        let budget_at = data.len();
         data.extend_from_slice(&instructions.to_le_bytes());
         data.push(0); // flags
         data.push(pda_segments.len() as u8);
         for seg in &pda_segments {
             data.push(seg.kind);
         }
-        data
+        (data, budget_at)
     } else {
         let segments = accounts_toml
             .get("segments")
@@ -501,78 +946,614 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         }
         let mut data = Vec::with_capacity(9);
         data.push(EXECUTE_OP);
+        let budget_at = data.len();
         data.extend_from_slice(&instructions.to_le_bytes());
-        data
+        (data, budget_at)
     };
-    let exec_ix = Instruction {
+    let abi = manifest_toml
+        .get("abi")
+        .and_then(|v| v.as_table())
+        .ok_or("Missing abi")?;
+    let layout = OutputLayout::from_abi(abi, use_max)?;
+
+    Ok(Target {
+        rpc_url,
         program_id,
-        accounts: metas,
-        data,
+        payer,
+        authority_keypair,
+        signer_pubkey,
+        vm_pubkey,
+        exec_ix: Instruction {
+            program_id,
+            accounts: metas,
+            data,
+        },
+        budget_at,
+        manifest: manifest_toml,
+        layout,
+    })
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let args: Vec<String> = env::args().collect();
+    let cli = parse_args(&args)?;
+    if let Some(list_path) = cli.manifest_list.as_deref() {
+        let overrides = Overrides {
+            rpc_url: cli.rpc_override.clone(),
+            program_id: cli.program_override.clone(),
+            payer: cli.payer_override.clone(),
+            authority: cli.authority_override.clone(),
+        };
+        return run_manifest_list(list_path, &overrides, &cli);
+    }
+    let CliArgs {
+        manifest_path,
+        accounts_path,
+        instructions,
+        rpc_override,
+        program_override,
+        payer_override,
+        authority_override,
+        use_max,
+        bench_runs,
+        bench_verify,
+        input_path,
+        input_hex,
+        fbh1,
+        fbh1_crc,
+        schema_hash,
+        dry_run,
+        auto_instructions,
+        json,
+        output_type,
+        max_retries,
+        verify_threshold,
+        verify_index,
+        ..
+    } = cli;
+    // The gatekeeper's threshold instruction reads output[0] unless told otherwise.
+    let verify = verify_threshold.map(|threshold| (verify_index.unwrap_or(0), threshold));
+
+    // The input writes below are real transactions; never reach them on a
+    // dry run, however the arguments were assembled.
+    if dry_run && (input_path.is_some() || input_hex.is_some()) {
+        return Err(DRY_RUN_WITH_INPUT.into());
+    }
+    let manifest_path = manifest_path.ok_or("--manifest required")?;
+    let accounts_path = accounts_path.ok_or("--accounts required")?;
+
+    let overrides = Overrides {
+        rpc_url: rpc_override,
+        program_id: program_override,
+        payer: payer_override,
+        authority: authority_override,
     };
+    let Target {
+        rpc_url,
+        program_id,
+        payer,
+        authority_keypair,
+        signer_pubkey,
+        vm_pubkey,
+        mut exec_ix,
+        budget_at,
+        manifest: manifest_toml,
+        layout,
+    } = load_target(
+        &manifest_path,
+        &accounts_path,
+        &overrides,
+        instructions,
+        use_max,
+    )?;
+    let abi = manifest_toml
+        .get("abi")
+        .and_then(|v| v.as_table())
+        .ok_or("Missing abi")?;
 
     let cu_ix = ComputeBudgetInstruction::set_compute_unit_limit(1_400_000);
     let client = RpcClient::new(rpc_url);
-    let recent = client.get_latest_blockhash()?;
+    let retry = retry_policy(max_retries);
+    let mut recent = client.get_latest_blockhash()?;
     let mut signers: Vec<&dyn Signer> = vec![&payer];
     if let Some(authority) = authority_keypair.as_ref() {
         if authority.pubkey() != payer.pubkey() {
             signers.push(authority);
         }
     }
-    let tx = Transaction::new_signed_with_payer(
-        &[cu_ix, exec_ix],
-        Some(&payer.pubkey()),
-        &signers,
-        recent,
-    );
-    client.send_and_confirm_transaction(&tx)?;
 
-    let account = client.get_account(&vm_pubkey)?;
-    if account.data.len() < VM_ACCOUNT_SIZE_MIN {
-        return Err(
+    let raw_input = match (input_path.as_ref(), input_hex.as_ref()) {
+        (Some(path), _) => Some(fs::read(path)?),
+        (None, Some(hex)) => Some(parse_hex_bytes(hex)?),
+        (None, None) => None,
+    };
+    if let Some(raw_input) = raw_input {
+        let input = if fbh1 {
+            let schema = manifest_toml.get("schema").and_then(|v| v.as_table());
+            let schema_type = schema
+                .and_then(|t| t.get("type"))
+                .and_then(|v| v.as_str())
+                .ok_or("schema.type is required with --fbh1")?;
+            let id = schema_id(schema_type)
+                .ok_or_else(|| format!("unknown schema.type {:?}", schema_type))?;
+            // Same default as `cauldron input-write --schema-hash manifest`.
+            let manifest_hash = schema
+                .and_then(|t| t.get("custom"))
+                .and_then(|v| v.get("schema_hash32"))
+                .and_then(|v| v.as_str())
+                .map(|text| -> Result<u32, Box<dyn std::error::Error>> {
+                    Ok(u32::try_from(parse_u64_value(text)?)?)
+                })
+                .transpose()?
+                .filter(|&hash| hash != 0);
+            build_fbh1(&raw_input, id, schema_hash.or(manifest_hash), fbh1_crc)
+        } else {
+            raw_input
+        };
+        let input_offset =
+            abi.get("input_offset")
+                .and_then(|v| v.as_integer())
+                .ok_or("abi.input_offset is required with --input")? as usize;
+        let input_max = abi
+            .get("input_max")
+            .and_then(|v| v.as_integer())
+            .ok_or("abi.input_max is required with --input")? as usize;
+        if input.len() > input_max {
+            return Err(format!(
+                "input is {} bytes, exceeds abi.input_max {}",
+                input.len(),
+                input_max
+            )
+            .into());
+        }
+        progress(
+            json,
+            &format!(
+                "Writing input ({} bytes) @ scratch 0x{:X}",
+                input.len(),
+                input_offset
+            ),
+        );
+        write_vm_bytes(
+            &client,
+            &retry,
+            program_id,
+            &payer,
+            &signers,
+            signer_pubkey,
+            vm_pubkey,
+            MMU_VM_HEADER_SIZE + input_offset,
+            &input,
+        )?;
+
+        // Point the control block at exactly what was written so guests that
+        // honor CTRL_INPUT_PTR/CTRL_INPUT_LEN see the same bytes.
+        let mut ctrl_input = [0u8; CTRL_INPUT_LEN + 4 - CTRL_INPUT_PTR];
+        ctrl_input[..4].copy_from_slice(&(input_offset as u32).to_le_bytes());
+        ctrl_input[CTRL_INPUT_LEN - CTRL_INPUT_PTR..]
+            .copy_from_slice(&(input.len() as u32).to_le_bytes());
+        write_vm_bytes(
+            &client,
+            &retry,
+            program_id,
+            &payer,
+            &signers,
+            signer_pubkey,
+            vm_pubkey,
+            MMU_VM_HEADER_SIZE + layout.control_offset + CTRL_INPUT_PTR,
+            &ctrl_input,
+        )?;
+        recent = client.get_latest_blockhash()?;
+    }
+
+    if let Some(ceiling) = auto_instructions {
+        progress(
+            json,
+            &format!("Searching instruction budget (ceiling {})", ceiling),
+        );
+        let found = search_min_instructions(
+            ceiling,
+            |budget| -> Result<bool, Box<dyn std::error::Error>> {
+                let summary = simulate_execute(
+                    &client,
+                    &[cu_ix.clone(), with_budget(&exec_ix, budget_at, budget)],
+                    &payer,
+                    &signers,
+                    &vm_pubkey,
+                    &layout,
+                )?;
+                Ok(summary.err.is_none() && matches!(summary.output, Some((0, _))))
+            },
+        )?
+        .ok_or_else(|| {
             format!(
-                "VM account data too small: {} < {}",
-                account.data.len(),
-                VM_ACCOUNT_SIZE_MIN
+                "no instruction budget up to {} finished with status 0",
+                ceiling
             )
-            .into(),
+        })?;
+        progress(json, &format!("Auto instruction budget: {}", found));
+        exec_ix = with_budget(&exec_ix, budget_at, found);
+        recent = client.get_latest_blockhash()?;
+    }
+
+    if bench_runs > 0 {
+        let mut latencies = Vec::with_capacity(bench_runs);
+        let mut baseline: Option<(u32, Vec<u8>)> = None;
+        for run in 0..bench_runs {
+            if run > 0 {
+                // Identical instructions under the same blockhash would be rejected
+                // as duplicates, so wait for a fresh one outside the timed window.
+                recent = client.get_new_latest_blockhash(&recent)?;
+            }
+            let instructions = [cu_ix.clone(), exec_ix.clone()];
+            let started = Instant::now();
+            send_with_retry(&client, &retry, &instructions, &payer, &signers, recent)?;
+            latencies.push(started.elapsed());
+
+            if bench_verify {
+                let result = fetch_output(&client, &vm_pubkey, &layout)?;
+                match &baseline {
+                    None => baseline = Some(result),
+                    Some(expected) if *expected != result => {
+                        return Err(format!(
+                            "bench run {} output differs from run 1 (status {} vs {})",
+                            run + 1,
+                            result.0,
+                            expected.0
+                        )
+                        .into());
+                    }
+                    Some(_) => {}
+                }
+            }
+        }
+
+        let stats = bench_stats(&latencies).ok_or("bench produced no samples")?;
+        let throughput = bench_runs as f64 / stats.total.as_secs_f64();
+        if json {
+            let ms = |d: Duration| d.as_secs_f64() * 1000.0;
+            println!(
+                "{}",
+                serde_json::json!({
+                    "vm": vm_pubkey.to_string(),
+                    "runs": bench_runs,
+                    "latency_ms": {
+                        "min": ms(stats.min),
+                        "median": ms(stats.median),
+                        "max": ms(stats.max),
+                    },
+                    "throughput": throughput,
+                    "output_stable": bench_verify.then_some(true),
+                })
+            );
+            return Ok(());
+        }
+        println!("Bench runs: {}", bench_runs);
+        println!("Latency min: {:.1} ms", stats.min.as_secs_f64() * 1000.0);
+        println!(
+            "Latency median: {:.1} ms",
+            stats.median.as_secs_f64() * 1000.0
         );
+        println!("Latency max: {:.1} ms", stats.max.as_secs_f64() * 1000.0);
+        println!("Throughput: {:.2} exec/s", throughput);
+        if bench_verify {
+            println!("Output stable across {} runs", bench_runs);
+        }
+        return Ok(());
     }
-    let scratch = &account.data[MMU_VM_HEADER_SIZE..];
-    let abi = manifest_toml
-        .get("abi")
-        .and_then(|v| v.as_table())
-        .ok_or("Missing abi")?;
-    let control_offset = abi
-        .get("control_offset")
-        .and_then(|v| v.as_integer())
-        .unwrap_or(0) as usize;
-    let output_offset = abi
-        .get("output_offset")
-        .and_then(|v| v.as_integer())
-        .unwrap_or(0) as usize;
-    let output_max = abi
-        .get("output_max")
-        .and_then(|v| v.as_integer())
-        .unwrap_or(0) as usize;
-
-    let status = read_u32_le(scratch, control_offset + 12);
-    let mut output_len = read_u32_le(scratch, control_offset + 28) as usize;
-    if output_len == 0 && use_max {
-        output_len = output_max;
-    }
-    let output_end = output_offset + output_len;
-    let output = if output_end <= scratch.len() {
-        &scratch[output_offset..output_end]
+
+    if dry_run {
+        let summary = simulate_execute(
+            &client,
+            &[cu_ix, exec_ix],
+            &payer,
+            &signers,
+            &vm_pubkey,
+            &layout,
+        )?;
+        if json {
+            let (status, output) = match &summary.output {
+                Some((status, output)) => (
+                    Some(*status),
+                    Some(OutputValues::decode(output_type, output)),
+                ),
+                None => (None, None),
+            };
+            println!(
+                "{}",
+                serde_json::json!({
+                    "vm": vm_pubkey.to_string(),
+                    "dry_run": true,
+                    "status": status,
+                    "output": output,
+                    "units_consumed": summary.units_consumed,
+                    "error": summary.err,
+                    "logs": summary.logs,
+                })
+            );
+        } else {
+            print_dry_run(&summary, output_type);
+        }
+        if let Some((index, threshold)) = verify {
+            let (status, output) = summary
+                .output
+                .as_ref()
+                .ok_or("simulation returned no account data to verify")?;
+            report_verify(check_threshold(*status, output, index, threshold), json)?;
+        }
+        return Ok(());
+    }
+
+    let run = execute(
+        &client,
+        &retry,
+        &[cu_ix, exec_ix],
+        &payer,
+        &signers,
+        &vm_pubkey,
+        &layout,
+    )?;
+
+    if json {
+        println!(
+            "{}",
+            serde_json::to_string(&RunReport::new(&vm_pubkey, &run, output_type))?
+        );
     } else {
-        &[]
-    };
+        println!("Status: {}", run.status);
+        if run.output.is_empty() {
+            println!("Output: <empty>");
+        } else {
+            println!(
+                "{}",
+                OutputValues::decode(output_type, &run.output).describe()
+            );
+        }
+    }
+    if let Some((index, threshold)) = verify {
+        report_verify(
+            check_threshold(run.status, &run.output, index, threshold),
+            json,
+        )?;
+    }
+    Ok(())
+}
 
-    println!("Status: {}", status);
-    if output.is_empty() {
-        println!("Output: <empty>");
+fn print_dry_run(summary: &SimulationSummary, output_type: OutputType) {
+    println!("Dry run (simulated, not sent)");
+    for line in &summary.logs {
+        println!("  {}", line);
+    }
+    match summary.units_consumed {
+        Some(units) => println!("Compute units consumed: {}", units),
+        None => println!("Compute units consumed: <unknown>"),
+    }
+    if let Some(err) = &summary.err {
+        println!("Simulation error: {}", err);
+    }
+    match &summary.output {
+        Some((status, output)) => {
+            println!("Simulated status: {}", status);
+            if !output.is_empty() {
+                println!(
+                    "Simulated {}",
+                    OutputValues::decode(output_type, output).describe()
+                );
+            }
+        }
+        None => println!("Simulated status: <account data not returned>"),
+    }
+}
+
+/// Informational lines go to stderr under `--json`, so stdout carries only
+/// the JSON report.
+fn progress(json: bool, line: &str) {
+    if json {
+        eprintln!("{}", line);
     } else {
-        println!("Output (i32): {:?}", decode_i32(output));
+        println!("{}", line);
     }
+}
+
+/// Print a `--verify-threshold` pass (to stderr under `--json`, so stdout
+/// stays one JSON line); a failure becomes the error main exits with.
+fn report_verify(
+    result: Result<i32, String>,
+    json: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let value = result.map_err(|reason| format!("Verify: gatekeeper would reject: {}", reason))?;
+    progress(json, &format!("Verify: pass (value {})", value));
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn argv(args: &[&str]) -> Vec<String> {
+        std::iter::once("frostbite-run")
+            .chain(args.iter().copied())
+            .map(String::from)
+            .collect()
+    }
+
+    #[test]
+    fn parse_args_dry_run() {
+        let cli = parse_args(&argv(&[
+            "--manifest",
+            "m.toml",
+            "--dry-run",
+            "--instructions",
+            "1200",
+        ]))
+        .unwrap();
+        assert!(cli.dry_run);
+        assert_eq!(cli.manifest_path.as_deref(), Some("m.toml"));
+        assert_eq!(cli.instructions, 1200);
+
+        let cli = parse_args(&argv(&["--manifest", "m.toml"])).unwrap();
+        assert!(!cli.dry_run);
+        assert_eq!(cli.instructions, 50_000);
+    }
+
+    #[test]
+    fn parse_args_output_type() {
+        assert_eq!(parse_args(&argv(&[])).unwrap().output_type, OutputType::I32);
+        let cli = parse_args(&argv(&["--output-type", "f32"])).unwrap();
+        assert_eq!(cli.output_type, OutputType::F32);
+        assert!(parse_args(&argv(&["--output-type", "q16"])).is_err());
+    }
+
+    #[test]
+    fn parse_args_max_retries() {
+        assert_eq!(parse_args(&argv(&[])).unwrap().max_retries, None);
+        let cli = parse_args(&argv(&["--max-retries", "5"])).unwrap();
+        assert_eq!(cli.max_retries, Some(5));
+        assert!(parse_args(&argv(&["--max-retries", "-1"])).is_err());
+    }
+
+    #[test]
+    fn parse_args_auto_instructions() {
+        let cli = parse_args(&argv(&["--auto-instructions", "200000"])).unwrap();
+        assert_eq!(cli.auto_instructions, Some(200_000));
+        assert!(parse_args(&argv(&["--auto-instructions", "0"])).is_err());
+    }
+
+    #[test]
+    fn with_budget_patches_only_the_budget() {
+        let ix = Instruction {
+            program_id: Pubkey::default(),
+            accounts: Vec::new(),
+            data: vec![EXECUTE_OP, 1, 0, 0, 0, 0, 0, 0, 0, 0xEE],
+        };
+        let patched = with_budget(&ix, 1, 0x0102_0304);
+        assert_eq!(patched.data[0], EXECUTE_OP);
+        assert_eq!(patched.data[1..9], 0x0102_0304u64.to_le_bytes());
+        assert_eq!(patched.data[9], 0xEE);
+    }
+
+    #[test]
+    fn parse_args_rejects_dry_run_with_side_effects() {
+        assert!(parse_args(&argv(&["--dry-run", "--input", "in.bin"])).is_err());
+        assert!(parse_args(&argv(&["--dry-run", "--bench", "3"])).is_err());
+        assert!(parse_args(&argv(&["--instructions", "lots"])).is_err());
+        assert!(parse_args(&argv(&["--dry-run", "--input-hex", "00"])).is_err());
+        let err = parse_args(&argv(&["--input", "in.bin", "--dry-run"])).unwrap_err();
+        assert_eq!(err.to_string(), DRY_RUN_WITH_INPUT);
+        assert!(parse_args(&argv(&["--input-hex", "00", "--dry-run"])).is_err());
+    }
+
+    #[test]
+    fn parse_manifest_list_pairs() {
+        let text = "\
+# model, accounts
+a/model.toml a/accounts.toml
+/abs/m.toml,/abs/acc.toml   # trailing comment
+
+  b.toml ,  b-accounts.toml
+";
+        let pairs = parse_manifest_list(text, Path::new("lists")).unwrap();
+        assert_eq!(
+            pairs,
+            vec![
+                (
+                    PathBuf::from("lists/a/model.toml"),
+                    PathBuf::from("lists/a/accounts.toml")
+                ),
+                (PathBuf::from("/abs/m.toml"), PathBuf::from("/abs/acc.toml")),
+                (
+                    PathBuf::from("lists/b.toml"),
+                    PathBuf::from("lists/b-accounts.toml")
+                ),
+            ]
+        );
+
+        let err =
+            parse_manifest_list("ok.toml ok-acc.toml\nonly-one.toml\n", Path::new("")).unwrap_err();
+        assert!(err.contains("line 2"), "{err}");
+        assert!(parse_manifest_list("a b c\n", Path::new("")).is_err());
+        assert!(parse_manifest_list("# nothing\n\n", Path::new("")).is_err());
+    }
+
+    #[test]
+    fn parse_args_manifest_list() {
+        let cli = parse_args(&argv(&["--manifest-list", "vms.txt", "--concurrency", "8"])).unwrap();
+        assert_eq!(cli.manifest_list.as_deref(), Some("vms.txt"));
+        assert_eq!(cli.concurrency, 8);
+        assert_eq!(
+            parse_args(&argv(&[])).unwrap().concurrency,
+            DEFAULT_CONCURRENCY
+        );
+
+        assert!(parse_args(&argv(&["--concurrency", "0"])).is_err());
+        assert!(parse_args(&argv(&["--manifest-list", "l", "--manifest", "m"])).is_err());
+        assert!(parse_args(&argv(&["--manifest-list", "l", "--dry-run"])).is_err());
+        assert!(parse_args(&argv(&["--manifest-list", "l", "--input-hex", "00"])).is_err());
+    }
+
+    fn ms(values: &[u64]) -> Vec<Duration> {
+        values.iter().map(|&v| Duration::from_millis(v)).collect()
+    }
+
+    fn stats_ms(values: &[u64]) -> (u128, u128, u128, u128) {
+        let stats = bench_stats(&ms(values)).unwrap();
+        (
+            stats.min.as_millis(),
+            stats.median.as_millis(),
+            stats.max.as_millis(),
+            stats.total.as_millis(),
+        )
+    }
+
+    #[test]
+    fn bench_stats_odd_count_takes_the_middle_sample() {
+        assert_eq!(stats_ms(&[30, 10, 20]), (10, 20, 30, 60));
+    }
+
+    #[test]
+    fn bench_stats_even_count_averages_the_middle_pair() {
+        assert_eq!(stats_ms(&[40, 10, 30, 20]), (10, 25, 40, 100));
+    }
+
+    #[test]
+    fn bench_stats_single_sample_is_min_median_and_max() {
+        assert_eq!(stats_ms(&[7]), (7, 7, 7, 7));
+    }
+
+    #[test]
+    fn bench_stats_of_no_samples_is_none() {
+        assert!(bench_stats(&[]).is_none());
+    }
+
+    #[test]
+    fn parse_args_verify_threshold() {
+        let cli = parse_args(&argv(&["--verify-threshold", "-5", "--verify-index", "2"])).unwrap();
+        assert_eq!(cli.verify_threshold, Some(-5));
+        assert_eq!(cli.verify_index, Some(2));
+
+        assert!(parse_args(&argv(&["--verify-index", "1"])).is_err());
+        assert!(parse_args(&argv(&["--verify-index", "-1", "--verify-threshold", "0"])).is_err());
+        assert!(parse_args(&argv(&["--verify-threshold", "0", "--bench", "2"])).is_err());
+    }
+
+    #[test]
+    fn parse_args_input_hex_and_fbh1() {
+        let cli = parse_args(&argv(&[
+            "--input-hex",
+            "0102",
+            "--fbh1",
+            "--fbh1-crc",
+            "--schema-hash",
+            "0xDEADBEEF",
+        ]))
+        .unwrap();
+        assert_eq!(cli.input_hex.as_deref(), Some("0102"));
+        assert!(cli.fbh1 && cli.fbh1_crc);
+        assert_eq!(cli.schema_hash, Some(0xDEAD_BEEF));
+
+        assert!(parse_args(&argv(&["--input", "a.bin", "--input-hex", "00"])).is_err());
+        assert!(parse_args(&argv(&["--fbh1"])).is_err());
+        assert!(parse_args(&argv(&["--input", "a.bin", "--fbh1-crc"])).is_err());
+        assert!(parse_args(&argv(&["--schema-hash", "0x1_0000_0000"])).is_err());
+    }
+}
@@ -8,6 +8,7 @@ from pathlib import Path
 from unittest.mock import Mock, patch
 
 from cauldron.accounts import Segment
+from cauldron.helpers import build_control_block, parse_control_block
 from cauldron.cli import (
     _accounts_segment_metas,
     _apply_accounts_env,
@@ -1429,6 +1430,16 @@ class CliPdaHardeningTests(unittest.TestCase):
                 rc = _cmd_accounts_show(args)
         self.assertEqual(rc, 0)
 
+    def test_control_block_round_trips_vm_seed(self) -> None:
+        seed = 0xDEAD_BEEF_0123_4567
+        ctrl = build_control_block(64, 0x1000, 12, 0x2000, 0, vm_seed=seed)
+        self.assertEqual(struct.unpack_from("<Q", ctrl, 56)[0], seed)
+        fields = parse_control_block(ctrl, 0)
+        self.assertEqual(fields["vm_seed"], seed)
+        self.assertEqual(fields["input_len"], 12)
+        with self.assertRaises(ValueError):
+            build_control_block(64, 0, 0, 0, 0, vm_seed=1 << 64)
+
 
 if __name__ == "__main__":
     unittest.main()
@@ -0,0 +1,65 @@
+import unittest
+from pathlib import Path
+
+from cauldron.guest import GuestConfig, render_config
+from cauldron.schema import _fnv1a32
+
+FIXTURES = Path(__file__).parent / "fixtures"
+
+# rust_tools/src/guest_config.rs renders the same configs and compares against
+# the same files, so a change on either side has to update both.
+_BASE = dict(
+    control_offset=0,
+    input_max=4096,
+    output_max=256,
+    scratch_min=262144,
+    reserved_tail=32,
+    stack_guard=0x4000,
+    stack_ptr=245728,
+    expected_schema_id=1,
+    expected_schema_hash=0x1234ABCD,
+    weights_seg=1,
+    weights_offset=0,
+    weights_data_offset=0,
+)
+
+# Includes a quote, a backslash and a non-printable byte to cover escaping.
+PARITY_CANONICAL = b'{"type":"vector","note":"a\\b"}\x01'
+
+
+class GuestConfigParityTests(unittest.TestCase):
+    def test_two_tower(self) -> None:
+        cfg = GuestConfig(
+            template="two_tower",
+            output_dim=1,
+            input_dim_a=8,
+            input_dim_b=4,
+            embed_dim=16,
+            w1_scale_q16=65536,
+            w2_scale_q16=32768,
+            has_bias=True,
+            dot_shift=16,
+            embed_a_offset=0x3000,
+            embed_b_offset=0x3040,
+            schema_canonical=PARITY_CANONICAL,
+            **{**_BASE, "expected_schema_hash": _fnv1a32(PARITY_CANONICAL)},
+        )
+        expected = (FIXTURES / "guest_config_two_tower.txt").read_text()
+        self.assertEqual(render_config(cfg), expected)
+
+    def test_tree(self) -> None:
+        cfg = GuestConfig(
+            template="tree",
+            input_dim=10,
+            output_dim=1,
+            tree_count=4,
+            tree_node_count=15,
+            tree_stride=16,
+            **_BASE,
+        )
+        expected = (FIXTURES / "guest_config_tree.txt").read_text()
+        self.assertEqual(render_config(cfg), expected)
+
+
+if __name__ == "__main__":
+    unittest.main()